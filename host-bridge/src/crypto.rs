@@ -1,4 +1,5 @@
-use anyhow::Result;
+use aes_gcm::{aead::Aead, Aes256Gcm, KeyInit, Nonce};
+use anyhow::{anyhow, Result};
 use argon2::{
     password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
     Argon2,
@@ -625,6 +626,44 @@ impl CryptoBridge {
             })),
         }
     }
+
+    /// Encrypt `plaintext` with AES-256-GCM under a fresh random nonce, as
+    /// `_crypto_encrypt_aes` does. `key` is raw key material: shorter input
+    /// is zero-padded to 32 bytes, longer input is truncated. Returns
+    /// `(nonce, ciphertext)`; the 16-byte tag is the tail of the ciphertext.
+    pub fn encrypt_aes(&self, key: &[u8], plaintext: &[u8]) -> Result<([u8; 12], Vec<u8>)> {
+        let cipher = Aes256Gcm::new(&aes_key(key).into());
+        let mut nonce = [0u8; 12];
+        OsRng.try_fill_bytes(&mut nonce)?;
+        let ciphertext = cipher
+            .encrypt(Nonce::from_slice(&nonce), plaintext)
+            .map_err(|e| anyhow!("AES-GCM encryption failed: {}", e))?;
+        Ok((nonce, ciphertext))
+    }
+
+    /// Decrypt and authenticate AES-256-GCM `ciphertext` (tag appended), as
+    /// `_crypto_decrypt_aes` does. Fails on a wrong key, a malformed nonce,
+    /// or any tampering.
+    pub fn decrypt_aes(&self, key: &[u8], nonce: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>> {
+        if nonce.len() != 12 {
+            return Err(anyhow!(
+                "AES-GCM nonce must be 12 bytes, got {}",
+                nonce.len()
+            ));
+        }
+        let cipher = Aes256Gcm::new(&aes_key(key).into());
+        cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| anyhow!("AES-GCM authentication failed"))
+    }
+}
+
+/// Zero-pad or truncate raw key material to an AES-256 key
+fn aes_key(raw: &[u8]) -> [u8; 32] {
+    let mut key = [0u8; 32];
+    let len = raw.len().min(32);
+    key[..len].copy_from_slice(&raw[..len]);
+    key
 }
 
 impl Default for CryptoBridge {
@@ -1323,6 +1362,22 @@ mod tests {
         assert_eq!(result["err"]["code"], "AUTH_ERROR");
     }
 
+    #[test]
+    fn test_aes_gcm_round_trip_and_tamper() {
+        let bridge = CryptoBridge::new();
+        let (nonce, mut ciphertext) = bridge.encrypt_aes(b"secret", b"hello").unwrap();
+        assert_eq!(
+            bridge.decrypt_aes(b"secret", &nonce, &ciphertext).unwrap(),
+            b"hello"
+        );
+
+        assert!(bridge.decrypt_aes(b"other", &nonce, &ciphertext).is_err());
+
+        ciphertext[0] ^= 1;
+        assert!(bridge.decrypt_aes(b"secret", &nonce, &ciphertext).is_err());
+        assert!(bridge.decrypt_aes(b"secret", &nonce[..8], &ciphertext).is_err());
+    }
+
     #[tokio::test]
     async fn test_unknown_function() {
         let mut bridge = CryptoBridge::new();
@@ -14,8 +14,17 @@ pub struct FsBridge {
     sandbox_root: Option<PathBuf>,
     /// Maximum file size for read/write operations (default: 100MB)
     max_file_size: u64,
+    /// Directory `temp_file` creates files in, resolved like any other path
+    /// (default: "tmp", relative to the sandbox root)
+    temp_dir: String,
 }
 
+/// Directory `temp_file` uses unless `FsBridge::with_temp_dir` sets another
+pub const DEFAULT_TEMP_DIR: &str = "tmp";
+
+/// Longest suffix `temp_file` accepts
+const MAX_TEMP_SUFFIX_LEN: usize = 32;
+
 // Request structures
 #[derive(Debug, Serialize, Deserialize)]
 struct ReadRequest {
@@ -69,6 +78,12 @@ struct StatRequest {
     path: String,
 }
 
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct TempFileRequest {
+    #[serde(default)]
+    suffix: Option<String>,
+}
+
 // Response structures
 #[derive(Debug, Serialize, Deserialize)]
 struct FileEntry {
@@ -108,6 +123,7 @@ impl FsBridge {
         Self {
             sandbox_root: None,
             max_file_size: 100 * 1024 * 1024, // 100MB
+            temp_dir: DEFAULT_TEMP_DIR.to_string(),
         }
     }
 
@@ -116,6 +132,7 @@ impl FsBridge {
         Self {
             sandbox_root: Some(sandbox_root),
             max_file_size: 100 * 1024 * 1024,
+            temp_dir: DEFAULT_TEMP_DIR.to_string(),
         }
     }
 
@@ -125,6 +142,13 @@ impl FsBridge {
         self
     }
 
+    /// Set the directory `temp_file` creates files in. Relative paths are
+    /// resolved against the sandbox root, and the directory must lie inside it
+    pub fn with_temp_dir(mut self, temp_dir: impl Into<String>) -> Self {
+        self.temp_dir = temp_dir.into();
+        self
+    }
+
     /// Check if filesystem operations are allowed on this platform
     pub fn is_platform_allowed(&self) -> bool {
         cfg!(any(
@@ -158,6 +182,7 @@ impl FsBridge {
             "mkdir" => self.mkdir(params).await,
             "list" => self.list(params).await,
             "stat" => self.stat(params).await,
+            "temp_file" => self.temp_file(params).await,
             _ => Ok(json!({
                 "ok": false,
                 "err": {
@@ -992,6 +1017,116 @@ impl FsBridge {
         }))
     }
 
+    /// Create an empty file with a unique random name in the temp directory
+    /// Args: {"suffix": ".png"} (optional)
+    /// Returns: {"ok": true, "data": "/sandbox/tmp/tmp-<random>.png"}
+    async fn temp_file(&self, params: Value) -> Result<Value> {
+        // Parse request; no params at all means no suffix
+        let request: TempFileRequest = if params.is_null() {
+            TempFileRequest::default()
+        } else {
+            match serde_json::from_value(params) {
+                Ok(req) => req,
+                Err(_) => {
+                    return Ok(json!({
+                        "ok": false,
+                        "err": {
+                            "code": "VALIDATION_ERROR",
+                            "message": "Invalid request format: expected object with optional 'suffix' field",
+                            "details": {}
+                        }
+                    }));
+                }
+            }
+        };
+
+        // The suffix becomes part of the file name, so it cannot name a directory
+        let suffix = request.suffix.unwrap_or_default();
+        if suffix.len() > MAX_TEMP_SUFFIX_LEN
+            || !suffix
+                .chars()
+                .all(|ch| ch.is_ascii_alphanumeric() || ch == '.' || ch == '-' || ch == '_')
+        {
+            return Ok(json!({
+                "ok": false,
+                "err": {
+                    "code": "VALIDATION_ERROR",
+                    "message": format!(
+                        "Invalid suffix: use at most {} letters, digits, '.', '-' or '_'",
+                        MAX_TEMP_SUFFIX_LEN
+                    ),
+                    "details": {"suffix": suffix}
+                }
+            }));
+        }
+
+        // Validate and resolve the temp directory
+        let dir = match self.validate_and_resolve_path(&self.temp_dir) {
+            Ok(p) => p,
+            Err(e) => {
+                return Ok(json!({
+                    "ok": false,
+                    "err": {
+                        "code": e.code,
+                        "message": e.message,
+                        "details": {"path": self.temp_dir}
+                    }
+                }));
+            }
+        };
+
+        if let Err(e) = fs::create_dir_all(&dir).await {
+            return Ok(json!({
+                "ok": false,
+                "err": {
+                    "code": "FS_ERROR",
+                    "message": format!("Failed to create temp directory: {}", e),
+                    "details": {"path": self.temp_dir}
+                }
+            }));
+        }
+
+        // `create_new` fails if the name is taken, so two callers can never
+        // end up with the same file; retry with a fresh name on a collision
+        for _ in 0..8 {
+            let name = format!("tmp-{}{}", uuid::Uuid::new_v4().simple(), suffix);
+            let path = dir.join(name);
+            match fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)
+                .await
+            {
+                Ok(_) => {
+                    return Ok(json!({
+                        "ok": true,
+                        "data": path.to_string_lossy()
+                    }));
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+                Err(e) => {
+                    return Ok(json!({
+                        "ok": false,
+                        "err": {
+                            "code": "FS_ERROR",
+                            "message": format!("Failed to create temp file: {}", e),
+                            "details": {"path": self.temp_dir}
+                        }
+                    }));
+                }
+            }
+        }
+
+        Ok(json!({
+            "ok": false,
+            "err": {
+                "code": "FS_ERROR",
+                "message": "Failed to find an unused temp file name",
+                "details": {"path": self.temp_dir}
+            }
+        }))
+    }
+
     // Helper methods
 
     /// Validate path and prevent directory traversal attacks
@@ -1737,6 +1872,56 @@ mod tests {
         assert_eq!(result["err"]["code"], "NOT_FOUND");
     }
 
+    #[tokio::test]
+    async fn test_temp_file_creates_distinct_files() {
+        let (temp, bridge) = create_test_sandbox().await;
+
+        let first = bridge.call("temp_file", Value::Null).await.unwrap();
+        let second = bridge
+            .call("temp_file", json!({"suffix": ".png"}))
+            .await
+            .unwrap();
+        assert_eq!(first["ok"], true, "{}", first);
+        assert_eq!(second["ok"], true, "{}", second);
+
+        let first = PathBuf::from(first["data"].as_str().unwrap());
+        let second = PathBuf::from(second["data"].as_str().unwrap());
+        assert_ne!(first, second);
+        let temp_dir = temp.path().canonicalize().unwrap().join(DEFAULT_TEMP_DIR);
+        for path in [&first, &second] {
+            assert!(path.is_file(), "{}", path.display());
+            assert_eq!(std::fs::metadata(path).unwrap().len(), 0);
+            assert_eq!(path.parent(), Some(temp_dir.as_path()));
+        }
+        assert!(second.to_string_lossy().ends_with(".png"));
+
+        // The returned path works with the other fs functions
+        let result = bridge
+            .call(
+                "write",
+                json!({"path": first.to_string_lossy(), "content": "scratch"}),
+            )
+            .await
+            .unwrap();
+        assert_eq!(result["ok"], true, "{}", result);
+    }
+
+    #[tokio::test]
+    async fn test_temp_file_rejects_bad_suffix_and_dir_outside_sandbox() {
+        let (_temp, bridge) = create_test_sandbox().await;
+
+        let result = bridge
+            .call("temp_file", json!({"suffix": "/../x"}))
+            .await
+            .unwrap();
+        assert_eq!(result["err"]["code"], "VALIDATION_ERROR");
+
+        let outside = TempDir::new().unwrap();
+        let bridge = bridge.with_temp_dir(outside.path().to_string_lossy());
+        let result = bridge.call("temp_file", json!({})).await.unwrap();
+        assert_eq!(result["err"]["code"], "PERMISSION_DENIED");
+    }
+
     #[tokio::test]
     async fn test_unknown_function() {
         let bridge = FsBridge::new();
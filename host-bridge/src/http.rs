@@ -3,12 +3,38 @@ use reqwest::{redirect::Policy, Client, Method};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use std::collections::HashMap;
-use std::time::Duration;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::warn;
 use url::Url;
 
+/// Consecutive failures after which a host's circuit opens by default
+pub const DEFAULT_CIRCUIT_THRESHOLD: u32 = 5;
+
+/// How long an open circuit fails calls before letting one through, by default
+pub const DEFAULT_CIRCUIT_COOLDOWN: Duration = Duration::from_secs(5);
+
 /// HTTP bridge providing outbound HTTP request capabilities
 pub struct HttpBridge {
     client: Client,
+    /// Consecutive failures per host (see `set_circuit_breaker`)
+    circuits: Mutex<HashMap<String, HostCircuit>>,
+    /// Failures that open a host's circuit (0 = never)
+    circuit_threshold: u32,
+    /// How long an open circuit fails calls before letting one through
+    circuit_cooldown: Duration,
+}
+
+/// Failure tracking for one `host:port`. A transport error or 5xx response
+/// counts as a failure and any other response resets the count. Once the
+/// count reaches the threshold the circuit opens: requests to the host fail
+/// at once without connecting until the cooldown has passed, then one
+/// request is let through and its outcome closes or reopens the circuit.
+#[derive(Debug, Default)]
+struct HostCircuit {
+    consecutive_failures: u32,
+    /// When the circuit last opened or a probe was last let through
+    opened_at: Option<Instant>,
 }
 
 /// Request parameters for host:http.request
@@ -69,12 +95,36 @@ impl HttpBridge {
             .build()
             .expect("Failed to build HTTP client");
 
-        Self { client }
+        Self::with_client(client)
     }
 
     /// Create a new HttpBridge with custom client configuration
     pub fn with_client(client: Client) -> Self {
-        Self { client }
+        Self {
+            client,
+            circuits: Mutex::new(HashMap::new()),
+            circuit_threshold: DEFAULT_CIRCUIT_THRESHOLD,
+            circuit_cooldown: DEFAULT_CIRCUIT_COOLDOWN,
+        }
+    }
+
+    /// Failures that open a host's circuit (0 = never)
+    pub fn circuit_threshold(&self) -> u32 {
+        self.circuit_threshold
+    }
+
+    /// How long an open circuit fails requests before letting one through
+    pub fn circuit_cooldown(&self) -> Duration {
+        self.circuit_cooldown
+    }
+
+    /// Open a host's circuit after `threshold` consecutive failures (0
+    /// disables the breaker) and keep it open for `cooldown`. Resets the
+    /// failures recorded so far.
+    pub fn set_circuit_breaker(&mut self, threshold: u32, cooldown: Duration) {
+        self.circuit_threshold = threshold;
+        self.circuit_cooldown = cooldown;
+        self.circuits.lock().unwrap().clear();
     }
 
     /// Main call dispatcher for the HTTP bridge
@@ -93,10 +143,97 @@ impl HttpBridge {
         }
     }
 
-    /// Execute an HTTP request
+    /// Execute an HTTP request, failing fast while the target host's circuit
+    /// is open
     /// Args: {"method": "GET", "url": "https://api.example.com", "headers": {}, "body": null, "timeout": 30000}
     /// Returns: {"ok": true, "data": {"status": 200, "headers": {}, "body": "...", "url": "..."}}
     async fn request(&self, params: Value) -> Result<Value> {
+        // Private addresses are refused before connecting, so they never
+        // count as failures of the host
+        let host = params
+            .get("url")
+            .and_then(|u| u.as_str())
+            .and_then(|u| Url::parse(u).ok())
+            .filter(|_| self.circuit_threshold > 0)
+            .and_then(|url| {
+                let host = url.host_str()?.to_ascii_lowercase();
+                let bare = host.trim_start_matches('[').trim_end_matches(']');
+                if Self::is_private_ip(bare) {
+                    return None;
+                }
+                Some(format!("{}:{}", host, url.port_or_known_default()?))
+            });
+        let Some(host) = host else {
+            return self.send(params).await;
+        };
+        if let Some(response) = self.check_circuit(&host) {
+            return Ok(response);
+        }
+        let result = self.send(params).await;
+        if let Ok(response) = &result {
+            self.record_outcome(&host, response);
+        }
+        result
+    }
+
+    /// The response to fail a request to `host` with while its circuit is
+    /// open. Once the cooldown has passed, lets this request through as a
+    /// probe and keeps failing others for another cooldown.
+    fn check_circuit(&self, host: &str) -> Option<Value> {
+        let mut circuits = self.circuits.lock().unwrap();
+        let circuit = circuits.get_mut(host)?;
+        let opened_at = circuit.opened_at?;
+        if opened_at.elapsed() >= self.circuit_cooldown {
+            circuit.opened_at = Some(Instant::now());
+            return None;
+        }
+        Some(json!({
+            "ok": false,
+            "err": {
+                "code": "NETWORK_FAIL",
+                "message": format!(
+                    "{} unavailable after {} consecutive failures; retrying within {}ms",
+                    host,
+                    circuit.consecutive_failures,
+                    self.circuit_cooldown.as_millis()
+                ),
+                "details": { "host": host, "circuit_open": true }
+            }
+        }))
+    }
+
+    /// Count a network failure or 5xx response towards opening `host`'s
+    /// circuit; any other response closes it.
+    fn record_outcome(&self, host: &str, response: &Value) {
+        let failed = match response["ok"].as_bool() {
+            Some(true) => response["data"]["status"].as_u64().unwrap_or(0) >= 500,
+            _ => matches!(
+                response["err"]["code"].as_str(),
+                Some("NETWORK_FAIL" | "TIMEOUT")
+            ),
+        };
+        let mut circuits = self.circuits.lock().unwrap();
+        if !failed {
+            circuits.remove(host);
+            return;
+        }
+        let circuit = circuits.entry(host.to_string()).or_default();
+        circuit.consecutive_failures += 1;
+        if circuit.consecutive_failures >= self.circuit_threshold {
+            if circuit.opened_at.is_none() {
+                warn!(
+                    "{} consecutive failures from {}; failing requests to it fast for {}ms",
+                    circuit.consecutive_failures,
+                    host,
+                    self.circuit_cooldown.as_millis()
+                );
+            }
+            circuit.opened_at = Some(Instant::now());
+        }
+    }
+
+    /// Send an HTTP request, following redirects as asked
+    async fn send(&self, params: Value) -> Result<Value> {
         // Parse request parameters
         let req: HttpRequest = match serde_json::from_value(params.clone()) {
             Ok(req) => req,
@@ -566,6 +703,8 @@ impl Default for HttpBridge {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
     use tokio;
 
     #[tokio::test]
@@ -938,6 +1077,82 @@ mod tests {
         }
     }
 
+    /// Serve `503` to every connection on a loopback port, counting them.
+    fn spawn_failing_upstream() -> (std::net::SocketAddr, Arc<AtomicUsize>) {
+        use std::io::{Read, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let connections = Arc::new(AtomicUsize::new(0));
+        let counter = connections.clone();
+        std::thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                counter.fetch_add(1, Ordering::SeqCst);
+                let mut buf = [0; 4096];
+                let _ = stream.read(&mut buf);
+                let _ = stream.write_all(
+                    b"HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                );
+            }
+        });
+        (addr, connections)
+    }
+
+    #[tokio::test]
+    async fn test_circuit_opens_after_repeated_failures() {
+        let (addr, connections) = spawn_failing_upstream();
+        // A public-looking name resolved to the mock, past the private IP check
+        let client = Client::builder()
+            .resolve("flaky.test", addr)
+            .redirect(Policy::none())
+            .build()
+            .unwrap();
+        let mut bridge = HttpBridge::with_client(client);
+        bridge.set_circuit_breaker(3, Duration::from_secs(60));
+        let params =
+            json!({ "method": "GET", "url": format!("http://flaky.test:{}/", addr.port()) });
+
+        for _ in 0..3 {
+            let result = bridge.call("request", params.clone()).await.unwrap();
+            assert_eq!(result["data"]["status"], 503, "{}", result);
+        }
+        for _ in 0..5 {
+            let result = bridge.call("request", params.clone()).await.unwrap();
+            assert_eq!(result["ok"], false);
+            assert_eq!(result["err"]["code"], "NETWORK_FAIL");
+            assert_eq!(result["err"]["details"]["circuit_open"], true);
+        }
+        assert_eq!(connections.load(Ordering::SeqCst), 3);
+
+        // After the cooldown one request goes through again
+        bridge.set_circuit_breaker(3, Duration::ZERO);
+        let result = bridge.call("request", params.clone()).await.unwrap();
+        assert_eq!(result["data"]["status"], 503, "{}", result);
+        assert_eq!(connections.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    async fn test_circuit_breaker_can_be_disabled() {
+        let (addr, connections) = spawn_failing_upstream();
+        let client = Client::builder()
+            .resolve("flaky.test", addr)
+            .build()
+            .unwrap();
+        let mut bridge = HttpBridge::with_client(client);
+        bridge.set_circuit_breaker(0, Duration::from_secs(60));
+        let params =
+            json!({ "method": "GET", "url": format!("http://flaky.test:{}/", addr.port()) });
+
+        for _ in 0..DEFAULT_CIRCUIT_THRESHOLD + 2 {
+            let result = bridge.call("request", params.clone()).await.unwrap();
+            assert_eq!(result["data"]["status"], 503, "{}", result);
+        }
+        assert_eq!(
+            connections.load(Ordering::SeqCst),
+            DEFAULT_CIRCUIT_THRESHOLD as usize + 2
+        );
+    }
+
     #[tokio::test]
     async fn test_unknown_function() {
         let mut bridge = HttpBridge::new();
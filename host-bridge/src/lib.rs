@@ -13,11 +13,18 @@ mod time;
 pub mod wasm_linker;
 
 pub use crypto::CryptoBridge;
-pub use db::{DbBridge, DbConfig, DbQuery, DbResult};
+pub use db::{
+    redact_url_credentials, DbBridge, DbConfig, DbQuery, DbResult, MaxRowsBehavior, PoolStats,
+    REDACTED_PASSWORD, SUPPORTED_DB_SCHEMES,
+};
+#[cfg(feature = "postgres")]
+pub use db::{register_pg_type_decoder, PgTypeDecoder};
 pub use env::EnvBridge;
 pub use error::{BridgeError as WasmBridgeError, BridgeResult};
 pub use fs::FsBridge;
-pub use http::{HttpBridge, HttpRequest, HttpResponse};
+pub use http::{
+    HttpBridge, HttpRequest, HttpResponse, DEFAULT_CIRCUIT_COOLDOWN, DEFAULT_CIRCUIT_THRESHOLD,
+};
 pub use log::{LogBridge, LogConfig, LogEntry, LogLevel};
 pub use sys::SysBridge;
 pub use time::TimeBridge;
@@ -29,16 +36,21 @@ pub use wasm_linker::{
     // Helper functions
     read_string_from_caller,
     register_all_functions,
+    register_all_functions_with,
     write_bytes_to_caller,
     write_string_to_caller,
     AuthContext,
+    HostFunctionRegistrar,
+    PrintOutput,
     RequestContext,
     SharedDbBridge,
     WasmMemory,
     // Core types and trait
     WasmState,
     WasmStateCore,
+    REENTRY_HEADER,
     STRING_LENGTH_PREFIX_SIZE,
+    WASM_PRINT_TARGET,
 };
 
 /// Standard envelope for all bridge responses
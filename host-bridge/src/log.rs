@@ -1,11 +1,12 @@
 use anyhow::Result;
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
-use serde_json::{json, Value};
-use std::sync::RwLock;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock, RwLock};
 
 /// Log levels supported by the bridge
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "UPPERCASE")]
 pub enum LogLevel {
     Debug,
@@ -154,6 +155,7 @@ impl LogBridge {
             "info" => self.log_bridge_call(LogLevel::Info, params),
             "warn" => self.log_bridge_call(LogLevel::Warn, params),
             "error" => self.log_bridge_call(LogLevel::Error, params),
+            "write" => self.write_call(params),
             _ => Ok(json!({
                 "ok": false,
                 "err": {
@@ -165,6 +167,53 @@ impl LogBridge {
         }
     }
 
+    /// Handle `log.write` — the level is chosen by the caller at runtime.
+    ///
+    /// Params: `{"level": "info", "message": "...", "fields": {...}}`.
+    /// `fields` is optional and must be a JSON object; it becomes the entry's
+    /// `data`, and each key is recorded as its own field on the `tracing` event.
+    fn write_call(&self, params: Value) -> Result<Value> {
+        let level_str = params.get("level").and_then(|v| v.as_str()).unwrap_or("");
+        let level = match LogLevel::from_str(level_str) {
+            Some(level) => level,
+            None => {
+                return Ok(json!({
+                    "ok": false,
+                    "err": {
+                        "code": "VALIDATION_ERROR",
+                        "message": format!("Invalid log level '{}': expected debug, info, warn or error", level_str),
+                        "details": {
+                            "level": level_str
+                        }
+                    }
+                }));
+            }
+        };
+
+        let fields = match params.get("fields") {
+            None | Some(Value::Null) => None,
+            Some(Value::Object(map)) if map.is_empty() => None,
+            Some(fields @ Value::Object(_)) => Some(fields.clone()),
+            Some(_) => {
+                return Ok(json!({
+                    "ok": false,
+                    "err": {
+                        "code": "VALIDATION_ERROR",
+                        "message": "Log fields must be a JSON object",
+                        "details": {}
+                    }
+                }));
+            }
+        };
+
+        let message = params
+            .get("message")
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_string();
+        self.log_bridge_call(level, json!({ "message": message, "data": fields }))
+    }
+
     /// Internal method to handle log calls from bridge
     fn log_bridge_call(&self, level: LogLevel, params: Value) -> Result<Value> {
         // Parse request - support both old format (string) and new format (object)
@@ -311,36 +360,30 @@ impl LogBridge {
             }
         }
 
-        // Also output to tracing for integration with other Rust logging
-        match level {
-            LogLevel::Debug => {
-                if let Some(data) = &entry.data {
-                    tracing::debug!(message = %entry.message, data = %data, "host:log.debug");
-                } else {
-                    tracing::debug!(message = %entry.message, "host:log.debug");
+        // Also output to tracing for integration with other Rust logging.
+        // Object data is flattened so each key is its own tracing field.
+        match &entry.data {
+            Some(Value::Object(fields)) if emit_with_fields(level, &entry.message, fields) => {}
+            Some(data) => match level {
+                LogLevel::Debug => {
+                    tracing::debug!(message = %entry.message, data = %data, "host:log.debug")
                 }
-            }
-            LogLevel::Info => {
-                if let Some(data) = &entry.data {
-                    tracing::info!(message = %entry.message, data = %data, "host:log.info");
-                } else {
-                    tracing::info!(message = %entry.message, "host:log.info");
+                LogLevel::Info => {
+                    tracing::info!(message = %entry.message, data = %data, "host:log.info")
                 }
-            }
-            LogLevel::Warn => {
-                if let Some(data) = &entry.data {
-                    tracing::warn!(message = %entry.message, data = %data, "host:log.warn");
-                } else {
-                    tracing::warn!(message = %entry.message, "host:log.warn");
+                LogLevel::Warn => {
+                    tracing::warn!(message = %entry.message, data = %data, "host:log.warn")
                 }
-            }
-            LogLevel::Error => {
-                if let Some(data) = &entry.data {
-                    tracing::error!(message = %entry.message, data = %data, "host:log.error");
-                } else {
-                    tracing::error!(message = %entry.message, "host:log.error");
+                LogLevel::Error => {
+                    tracing::error!(message = %entry.message, data = %data, "host:log.error")
                 }
-            }
+            },
+            None => match level {
+                LogLevel::Debug => tracing::debug!(message = %entry.message, "host:log.debug"),
+                LogLevel::Info => tracing::info!(message = %entry.message, "host:log.info"),
+                LogLevel::Warn => tracing::warn!(message = %entry.message, "host:log.warn"),
+                LogLevel::Error => tracing::error!(message = %entry.message, "host:log.error"),
+            },
         }
     }
 
@@ -393,6 +436,169 @@ impl Default for LogBridge {
     }
 }
 
+/// Upper bound on distinct (level, field names) shapes that get their own
+/// callsite; past it, object data falls back to a single `data` field.
+const MAX_FIELD_CALLSITES: usize = 1024;
+
+/// Runtime-built callsite for events whose field names come from the guest.
+/// `tracing` needs `'static` metadata, so each distinct shape is leaked once
+/// and cached in [`field_callsite`].
+struct FieldsCallsite {
+    metadata: OnceLock<tracing::Metadata<'static>>,
+}
+
+impl tracing::callsite::Callsite for FieldsCallsite {
+    fn set_interest(&self, _interest: tracing::subscriber::Interest) {}
+
+    fn metadata(&self) -> &tracing::Metadata<'_> {
+        self.metadata.get().expect("callsite metadata is set before registration")
+    }
+}
+
+/// The callsite for `level` events carrying `message` plus `names`, or
+/// `None` once [`MAX_FIELD_CALLSITES`] shapes exist.
+fn field_callsite(level: LogLevel, names: Vec<String>) -> Option<&'static FieldsCallsite> {
+    type Callsites = HashMap<(LogLevel, Vec<String>), &'static FieldsCallsite>;
+    static CALLSITES: OnceLock<Mutex<Callsites>> = OnceLock::new();
+    let mut callsites = CALLSITES
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    if let Some(callsite) = callsites.get(&(level, names.clone())) {
+        return Some(callsite);
+    }
+    if callsites.len() >= MAX_FIELD_CALLSITES {
+        return None;
+    }
+
+    let field_names: Vec<&'static str> = std::iter::once("message")
+        .chain(names.iter().map(|name| {
+            // `message` is the log text; a guest field of that name is renamed
+            let name = if name == "message" { "fields.message" } else { name.as_str() };
+            &*Box::leak(name.to_string().into_boxed_str())
+        }))
+        .collect();
+    let (event_name, tracing_level) = match level {
+        LogLevel::Debug => ("host:log.debug", tracing::Level::DEBUG),
+        LogLevel::Info => ("host:log.info", tracing::Level::INFO),
+        LogLevel::Warn => ("host:log.warn", tracing::Level::WARN),
+        LogLevel::Error => ("host:log.error", tracing::Level::ERROR),
+    };
+    let callsite: &'static FieldsCallsite = Box::leak(Box::new(FieldsCallsite {
+        metadata: OnceLock::new(),
+    }));
+    let _ = callsite.metadata.set(tracing::Metadata::new(
+        event_name,
+        module_path!(),
+        tracing_level,
+        Some(file!()),
+        Some(line!()),
+        Some(module_path!()),
+        tracing::field::FieldSet::new(
+            Box::leak(field_names.into_boxed_slice()),
+            tracing::callsite::Identifier(callsite),
+        ),
+        tracing::metadata::Kind::EVENT,
+    ));
+    tracing::callsite::register(callsite);
+    callsites.insert((level, names), callsite);
+    Some(callsite)
+}
+
+/// A JSON value as the closest native `tracing` value, so subscribers see
+/// numbers and booleans rather than their string form.
+fn field_value(value: &Value) -> Box<dyn tracing::Value + '_> {
+    match value {
+        Value::String(s) => Box::new(s.as_str()),
+        Value::Bool(b) => Box::new(*b),
+        Value::Number(n) => match (n.as_i64(), n.as_u64()) {
+            (Some(i), _) => Box::new(i),
+            (None, Some(u)) => Box::new(u),
+            _ => Box::new(n.as_f64().unwrap_or_default()),
+        },
+        other => Box::new(tracing::field::display(other)),
+    }
+}
+
+/// Emit `message` with each entry of `fields` as its own tracing field.
+/// Returns `false` if no callsite is available for this shape.
+fn emit_with_fields(level: LogLevel, message: &str, fields: &Map<String, Value>) -> bool {
+    let Some(callsite) = field_callsite(level, fields.keys().cloned().collect()) else {
+        return false;
+    };
+    let metadata = tracing::callsite::Callsite::metadata(callsite);
+    if tracing::level_filters::LevelFilter::current() < *metadata.level() {
+        return true;
+    }
+
+    let message: Box<dyn tracing::Value + '_> = Box::new(message);
+    let values: Vec<Box<dyn tracing::Value + '_>> = std::iter::once(message)
+        .chain(fields.values().map(field_value))
+        .collect();
+    let values: Vec<Option<&dyn tracing::Value>> =
+        values.iter().map(|value| Some(value.as_ref())).collect();
+    let value_set = metadata.fields().value_set_all(&values);
+    tracing::dispatcher::get_default(|dispatch| {
+        if dispatch.enabled(metadata) {
+            dispatch.event(&tracing::Event::new(metadata, &value_set));
+        }
+    });
+    true
+}
+
+/// Event captured by [`capture_tracing_events`]: level, target and each
+/// field rendered to a string.
+#[cfg(test)]
+pub(crate) type CapturedEvent = (
+    tracing::Level,
+    &'static str,
+    std::collections::HashMap<String, String>,
+);
+
+/// Run `f` under a thread-local subscriber that records every `tracing`
+/// event it emits. Used to assert what the log bridge hands to `tracing`.
+#[cfg(test)]
+pub(crate) fn capture_tracing_events(f: impl FnOnce()) -> Vec<CapturedEvent> {
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+    use tracing::field::{Field, Visit};
+    use tracing_subscriber::layer::{Context, SubscriberExt};
+    use tracing_subscriber::Layer;
+
+    struct FieldVisitor<'a>(&'a mut HashMap<String, String>);
+
+    impl Visit for FieldVisitor<'_> {
+        fn record_str(&mut self, field: &Field, value: &str) {
+            self.0.insert(field.name().to_string(), value.to_string());
+        }
+
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            self.0
+                .insert(field.name().to_string(), format!("{:?}", value));
+        }
+    }
+
+    struct CaptureLayer(Arc<Mutex<Vec<CapturedEvent>>>);
+
+    impl<S: tracing::Subscriber> Layer<S> for CaptureLayer {
+        fn on_event(&self, event: &tracing::Event<'_>, _ctx: Context<'_, S>) {
+            let mut fields = HashMap::new();
+            event.record(&mut FieldVisitor(&mut fields));
+            self.0.lock().unwrap().push((
+                *event.metadata().level(),
+                event.metadata().target(),
+                fields,
+            ));
+        }
+    }
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let subscriber = tracing_subscriber::registry().with(CaptureLayer(events.clone()));
+    tracing::subscriber::with_default(subscriber, f);
+    let captured = events.lock().unwrap().clone();
+    captured
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -770,6 +976,84 @@ mod tests {
         assert_eq!(result["ok"], true);
     }
 
+    #[test]
+    fn test_write_call_preserves_level_and_fields() {
+        let bridge = LogBridge::new();
+        let mut result = Value::Null;
+        let events = capture_tracing_events(|| {
+            result = bridge
+                .call(
+                    "write",
+                    json!({
+                        "level": "warn",
+                        "message": "Quota nearly exhausted",
+                        "fields": {"user_id": 42, "remaining": 3}
+                    }),
+                )
+                .unwrap();
+        });
+        assert_eq!(result["ok"], true);
+
+        let (level, _, fields) = events
+            .iter()
+            .find(|(_, _, f)| {
+                f.get("message").map(String::as_str) == Some("Quota nearly exhausted")
+            })
+            .expect("log.write event not captured");
+        assert_eq!(*level, tracing::Level::WARN);
+        assert_eq!(fields["user_id"], "42");
+        assert_eq!(fields["remaining"], "3");
+        assert!(!fields.contains_key("data"));
+    }
+
+    #[test]
+    fn test_write_call_each_level() {
+        let bridge = LogBridge::new();
+        for (name, expected) in [
+            ("debug", tracing::Level::DEBUG),
+            ("INFO", tracing::Level::INFO),
+            ("warning", tracing::Level::WARN),
+            ("error", tracing::Level::ERROR),
+        ] {
+            let events = capture_tracing_events(|| {
+                let result = bridge
+                    .call("write", json!({"level": name, "message": "level check"}))
+                    .unwrap();
+                assert_eq!(result["ok"], true);
+            });
+            assert_eq!(events.len(), 1, "level {}", name);
+            assert_eq!(events[0].0, expected, "level {}", name);
+            assert!(!events[0].2.contains_key("data"));
+        }
+    }
+
+    #[test]
+    fn test_write_call_rejects_invalid_level() {
+        let bridge = LogBridge::new();
+        let events = capture_tracing_events(|| {
+            let result = bridge
+                .call("write", json!({"level": "verbose", "message": "nope"}))
+                .unwrap();
+            assert_eq!(result["ok"], false);
+            assert_eq!(result["err"]["code"], "VALIDATION_ERROR");
+            assert_eq!(result["err"]["details"]["level"], "verbose");
+        });
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn test_write_call_rejects_non_object_fields() {
+        let bridge = LogBridge::new();
+        let result = bridge
+            .call(
+                "write",
+                json!({"level": "info", "message": "hi", "fields": [1, 2]}),
+            )
+            .unwrap();
+        assert_eq!(result["ok"], false);
+        assert_eq!(result["err"]["code"], "VALIDATION_ERROR");
+    }
+
     #[test]
     fn test_max_message_size_boundary() {
         let bridge = LogBridge::new();
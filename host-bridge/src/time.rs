@@ -340,7 +340,10 @@ impl TimeBridge {
     }
 
     /// Format DateTime to string using specified format
-    fn format_datetime(datetime: &DateTime<FixedOffset>, format: &str) -> Result<String> {
+    pub(crate) fn format_datetime(
+        datetime: &DateTime<FixedOffset>,
+        format: &str,
+    ) -> Result<String> {
         match format.to_uppercase().as_str() {
             "ISO8601" => Ok(datetime.to_rfc3339_opts(chrono::SecondsFormat::Millis, false)),
             "RFC2822" => Ok(datetime.to_rfc2822()),
@@ -358,7 +361,7 @@ impl TimeBridge {
     }
 
     /// Parse date string to Unix timestamp (milliseconds)
-    fn parse_datetime(date_string: &str, format: &str, timezone: &str) -> Result<i64> {
+    pub(crate) fn parse_datetime(date_string: &str, format: &str, timezone: &str) -> Result<i64> {
         // Handle standard formats
         let naive = match format.to_uppercase().as_str() {
             "ISO8601" | "RFC3339" => {
@@ -414,7 +417,7 @@ impl TimeBridge {
     }
 
     /// Parse timezone offset string (e.g., "+0200", "-0530")
-    fn parse_timezone_offset(tz_str: &str) -> Result<FixedOffset> {
+    pub(crate) fn parse_timezone_offset(tz_str: &str) -> Result<FixedOffset> {
         let sign = if tz_str.starts_with('+') { 1 } else { -1 };
         let tz_str = &tz_str[1..]; // Remove sign
 
@@ -1,16 +1,45 @@
 //! Console I/O Host Functions
 //!
 //! Provides print, input, and console logging functions for WASM modules.
-//! These require host access to stdout/stdin.
+//! These require host access to stdout/stdin. `_log_write` emits structured
+//! logs through `LogBridge` at a level chosen by the module.
+//!
+//! The `print*` functions write to stdout unless the host's
+//! `WasmStateCore::print_output` routes them through `tracing`.
 //!
 //! All functions are generic over `WasmStateCore` to work with any runtime.
 
 use super::helpers::{read_raw_string, read_string_from_caller, write_string_to_caller};
-use super::state::WasmStateCore;
+use super::state::{PrintOutput, WasmStateCore, WASM_PRINT_TARGET};
 use crate::error::BridgeResult;
-use tracing::info;
+use crate::LogBridge;
+use serde_json::{json, Value};
+use std::io::Write;
+use tracing::{info, warn, Level};
 use wasmtime::{Caller, Linker};
 
+/// Write `text` from a `print*` function to `output`. Each call is its own
+/// `tracing` event, without the newline `printl` adds on stdout.
+fn emit_print(output: PrintOutput, text: &str, newline: bool) {
+    match output {
+        PrintOutput::RawStdout => {
+            let mut stdout = std::io::stdout().lock();
+            let _ = if newline {
+                writeln!(stdout, "{}", text)
+            } else {
+                write!(stdout, "{}", text)
+            };
+        }
+        PrintOutput::Tracing(level) => match level {
+            Level::ERROR => tracing::error!(target: WASM_PRINT_TARGET, "{}", text),
+            Level::WARN => tracing::warn!(target: WASM_PRINT_TARGET, "{}", text),
+            Level::INFO => tracing::info!(target: WASM_PRINT_TARGET, "{}", text),
+            Level::DEBUG => tracing::debug!(target: WASM_PRINT_TARGET, "{}", text),
+            Level::TRACE => tracing::trace!(target: WASM_PRINT_TARGET, "{}", text),
+        },
+    }
+}
+
 /// Register all console/IO functions with the linker
 pub fn register_functions<S: WasmStateCore>(linker: &mut Linker<S>) -> BridgeResult<()> {
     // =========================================
@@ -23,7 +52,7 @@ pub fn register_functions<S: WasmStateCore>(linker: &mut Linker<S>) -> BridgeRes
         "print",
         |mut caller: Caller<'_, S>, ptr: i32, len: i32| {
             if let Some(s) = read_raw_string(&mut caller, ptr, len) {
-                print!("{}", s);
+                emit_print(caller.data().print_output(), &s, false);
             }
         },
     )?;
@@ -34,7 +63,7 @@ pub fn register_functions<S: WasmStateCore>(linker: &mut Linker<S>) -> BridgeRes
         "printl",
         |mut caller: Caller<'_, S>, ptr: i32, len: i32| {
             if let Some(s) = read_raw_string(&mut caller, ptr, len) {
-                println!("{}", s);
+                emit_print(caller.data().print_output(), &s, true);
             }
         },
     )?;
@@ -45,25 +74,34 @@ pub fn register_functions<S: WasmStateCore>(linker: &mut Linker<S>) -> BridgeRes
         "print_string",
         |mut caller: Caller<'_, S>, ptr: i32, len: i32| {
             if let Some(s) = read_raw_string(&mut caller, ptr, len) {
-                print!("{}", s);
+                emit_print(caller.data().print_output(), &s, false);
             }
         },
     )?;
 
     // print_integer - Print integer (i64 per spec)
-    linker.func_wrap("env", "print_integer", |_: Caller<'_, S>, value: i64| {
-        print!("{}", value);
-    })?;
+    linker.func_wrap(
+        "env",
+        "print_integer",
+        |caller: Caller<'_, S>, value: i64| {
+            emit_print(caller.data().print_output(), &value.to_string(), false);
+        },
+    )?;
 
     // print_float - Print float
-    linker.func_wrap("env", "print_float", |_: Caller<'_, S>, value: f64| {
-        print!("{}", value);
+    linker.func_wrap("env", "print_float", |caller: Caller<'_, S>, value: f64| {
+        emit_print(caller.data().print_output(), &value.to_string(), false);
     })?;
 
     // print_boolean - Print boolean
-    linker.func_wrap("env", "print_boolean", |_: Caller<'_, S>, value: i32| {
-        print!("{}", if value != 0 { "true" } else { "false" });
-    })?;
+    linker.func_wrap(
+        "env",
+        "print_boolean",
+        |caller: Caller<'_, S>, value: i32| {
+            let text = if value != 0 { "true" } else { "false" };
+            emit_print(caller.data().print_output(), text, false);
+        },
+    )?;
 
     // =========================================
     // CONSOLE LOGGING FUNCTIONS
@@ -139,6 +177,75 @@ pub fn register_functions<S: WasmStateCore>(linker: &mut Linker<S>) -> BridgeRes
         },
     )?;
 
+    // =========================================
+    // STRUCTURED LOGGING
+    // =========================================
+
+    // _log_write - Log at a runtime-chosen level with structured fields
+    // Args: level_ptr, level_len, message_ptr, message_len, fields_ptr, fields_len
+    // `level` is debug/info/warn/error; `fields` is a JSON object string or empty.
+    // Returns: 0 on success, -1 on an invalid level or fields (error is recorded)
+    linker.func_wrap(
+        "env",
+        "_log_write",
+        |mut caller: Caller<'_, S>,
+         level_ptr: i32,
+         level_len: i32,
+         message_ptr: i32,
+         message_len: i32,
+         fields_ptr: i32,
+         fields_len: i32|
+         -> i32 {
+            let level = read_raw_string(&mut caller, level_ptr, level_len).unwrap_or_default();
+            let message =
+                read_raw_string(&mut caller, message_ptr, message_len).unwrap_or_default();
+            let fields_json =
+                read_raw_string(&mut caller, fields_ptr, fields_len).unwrap_or_default();
+
+            let fields: Value = if fields_json.trim().is_empty() {
+                Value::Null
+            } else {
+                match serde_json::from_str(&fields_json) {
+                    Ok(v) => v,
+                    Err(e) => {
+                        let msg = format!("_log_write: invalid fields JSON: {}", e);
+                        warn!("{}", msg);
+                        caller.data_mut().set_error(msg);
+                        return -1;
+                    }
+                }
+            };
+
+            let result = LogBridge::new().call(
+                "write",
+                json!({
+                    "level": level,
+                    "message": message,
+                    "fields": fields
+                }),
+            );
+
+            match result {
+                Ok(v) if v["ok"] == true => 0,
+                Ok(v) => {
+                    let msg = format!(
+                        "_log_write: {}",
+                        v["err"]["message"].as_str().unwrap_or("unknown error")
+                    );
+                    warn!("{}", msg);
+                    caller.data_mut().set_error(msg);
+                    -1
+                }
+                Err(e) => {
+                    let msg = format!("_log_write: {}", e);
+                    warn!("{}", msg);
+                    caller.data_mut().set_error(msg);
+                    -1
+                }
+            }
+        },
+    )?;
+
     // =========================================
     // INPUT FUNCTIONS
     // =========================================
@@ -201,5 +308,128 @@ pub fn register_functions<S: WasmStateCore>(linker: &mut Linker<S>) -> BridgeRes
 
 #[cfg(test)]
 mod tests {
-    // Tests would require WASM runtime setup
+    use crate::log::capture_tracing_events;
+    use crate::wasm_linker::{create_linker, PrintOutput, WasmState, WASM_PRINT_TARGET};
+    use wasmtime::{Engine, Module, Store};
+
+    // Data segments hold the level, message and fields strings; `log` passes
+    // (ptr, len) pairs for each to `_log_write`.
+    const WAT: &str = r#"
+        (module
+          (import "env" "_log_write"
+            (func $log_write (param i32 i32 i32 i32 i32 i32) (result i32)))
+          (import "env" "print" (func $print (param i32 i32)))
+          (import "env" "printl" (func $printl (param i32 i32)))
+          (import "env" "print_integer" (func $print_integer (param i64)))
+          (memory (export "memory") 1)
+          (data (i32.const 16) "error")
+          (data (i32.const 32) "payment failed")
+          (data (i32.const 64) "{\"order\":7,\"retry\":true}")
+          (data (i32.const 128) "loud")
+          (data (i32.const 144) "[1,2]")
+          (data (i32.const 160) "hello from wasm")
+          (func (export "log_ok") (result i32)
+            (call $log_write
+              (i32.const 16) (i32.const 5)
+              (i32.const 32) (i32.const 14)
+              (i32.const 64) (i32.const 24)))
+          (func (export "log_bad_level") (result i32)
+            (call $log_write
+              (i32.const 128) (i32.const 4)
+              (i32.const 32) (i32.const 14)
+              (i32.const 0) (i32.const 0)))
+          (func (export "log_bad_fields") (result i32)
+            (call $log_write
+              (i32.const 16) (i32.const 5)
+              (i32.const 32) (i32.const 14)
+              (i32.const 144) (i32.const 5)))
+          (func (export "print_hello") (result i32)
+            (call $print (i32.const 160) (i32.const 5))
+            (call $printl (i32.const 160) (i32.const 15))
+            (call $print_integer (i64.const 42))
+            (i32.const 0)))
+    "#;
+
+    fn call_export(name: &str) -> (i32, Option<String>) {
+        call_export_with(name, WasmState::default())
+    }
+
+    fn call_export_with(name: &str, state: WasmState) -> (i32, Option<String>) {
+        let engine = Engine::default();
+        let linker = create_linker(&engine).expect("create linker");
+        let module = Module::new(&engine, WAT).expect("compile test module");
+        let mut store = Store::new(&engine, state);
+        let instance = linker
+            .instantiate(&mut store, &module)
+            .expect("instantiate");
+        let func = instance
+            .get_typed_func::<(), i32>(&mut store, name)
+            .expect("export missing");
+        let rc = func.call(&mut store, ()).expect("call trapped");
+        (rc, store.data().last_error.clone())
+    }
+
+    #[test]
+    fn test_log_write_emits_event_with_level_and_fields() {
+        let mut rc = -1;
+        let events = capture_tracing_events(|| {
+            rc = call_export("log_ok").0;
+        });
+        assert_eq!(rc, 0);
+
+        let (level, _, fields) = events
+            .iter()
+            .find(|(_, _, f)| f.get("message").map(String::as_str) == Some("payment failed"))
+            .expect("_log_write event not captured");
+        assert_eq!(*level, tracing::Level::ERROR);
+        assert_eq!(fields["order"], "7");
+        assert_eq!(fields["retry"], "true");
+        assert!(!fields.contains_key("data"));
+    }
+
+    #[test]
+    fn test_print_functions_emit_wasm_events_when_routed_through_tracing() {
+        let state = WasmState {
+            print_output: PrintOutput::Tracing(tracing::Level::INFO),
+            ..WasmState::default()
+        };
+        let events = capture_tracing_events(|| {
+            call_export_with("print_hello", state);
+        });
+        let printed: Vec<(&tracing::Level, &str)> = events
+            .iter()
+            .filter(|(_, target, _)| *target == WASM_PRINT_TARGET)
+            .map(|(level, _, fields)| (level, fields["message"].as_str()))
+            .collect();
+        assert_eq!(
+            printed,
+            [
+                (&tracing::Level::INFO, "hello"),
+                (&tracing::Level::INFO, "hello from wasm"),
+                (&tracing::Level::INFO, "42"),
+            ]
+        );
+
+        // Raw stdout mode emits no events
+        let events = capture_tracing_events(|| {
+            call_export("print_hello");
+        });
+        assert!(events
+            .iter()
+            .all(|(_, target, _)| *target != WASM_PRINT_TARGET));
+    }
+
+    #[test]
+    fn test_log_write_rejects_invalid_level() {
+        let (rc, err) = call_export("log_bad_level");
+        assert_eq!(rc, -1);
+        assert!(err.unwrap().contains("Invalid log level 'loud'"));
+    }
+
+    #[test]
+    fn test_log_write_rejects_non_object_fields() {
+        let (rc, err) = call_export("log_bad_fields");
+        assert_eq!(rc, -1);
+        assert!(err.unwrap().contains("must be a JSON object"));
+    }
 }
@@ -8,18 +8,25 @@
 //! - _crypto_hash_sha256: SHA-256 hash
 //! - _crypto_hash_sha512: SHA-512 hash
 //! - _crypto_hmac: HMAC digest
+//! - hash_data: SHA-256, SHA-512 or BLAKE3 of raw bytes, for checksums and
+//!   cache keys (not passwords)
 //! - _jwt_sign: Sign JWT token
 //! - _jwt_verify: Verify JWT token
 //! - _jwt_decode: Decode JWT without verification
+//! - base64_encode / base64_decode: Standard base64 of raw bytes
+//! - base64_url_encode / base64_url_decode: URL-safe base64 of raw bytes
 //!
 //! All functions are generic over `WasmStateCore` to work with any runtime.
 
-use super::helpers::{read_raw_string, write_string_to_caller};
+use super::helpers::{
+    read_raw_bytes, read_raw_string, write_bytes_to_caller, write_string_to_caller,
+};
 use super::state::WasmStateCore;
 use crate::error::BridgeResult;
 use crate::CryptoBridge;
-use aes_gcm::{aead::Aead, Aes256Gcm, Nonce};
-use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use base64::engine::general_purpose::{GeneralPurpose, GeneralPurposeConfig, STANDARD as BASE64};
+use base64::engine::DecodePaddingMode;
+use base64::{alphabet, Engine};
 use hmac::{Hmac, Mac};
 use md5::{Digest as Md5Digest, Md5};
 use rand::RngCore;
@@ -28,6 +35,26 @@ use sha2::{Sha256, Sha512};
 use tracing::{debug, error};
 use wasmtime::{Caller, Linker};
 
+/// URL-safe base64 for `base64_url_*`: encodes without padding, as JWTs and
+/// URLs use it, and decodes with or without
+const BASE64_URL: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::URL_SAFE,
+    GeneralPurposeConfig::new()
+        .with_encode_padding(false)
+        .with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+/// Lowercase hex digest of `data` with `algorithm` (`sha256`, `sha512` or
+/// `blake3`, case-insensitive), or None for any other algorithm
+fn hash_data(algorithm: &str, data: &[u8]) -> Option<String> {
+    match algorithm.trim().to_ascii_lowercase().as_str() {
+        "sha256" | "sha-256" => Some(hex::encode(Sha256::digest(data))),
+        "sha512" | "sha-512" => Some(hex::encode(Sha512::digest(data))),
+        "blake3" => Some(blake3::hash(data).to_hex().to_string()),
+        _ => None,
+    }
+}
+
 /// Register all crypto and JWT functions with the linker
 pub fn register_functions<S: WasmStateCore>(linker: &mut Linker<S>) -> BridgeResult<()> {
     // =========================================
@@ -50,6 +77,11 @@ pub fn register_functions<S: WasmStateCore>(linker: &mut Linker<S>) -> BridgeRes
             };
 
             debug!("_crypto_hash_password: hashing password");
+            caller.data().audit(
+                "crypto",
+                "hash_password",
+                &json!({ "password": password, "algorithm": "bcrypt" }),
+            );
 
             let result = tokio::task::block_in_place(|| {
                 tokio::runtime::Handle::current().block_on(async {
@@ -115,6 +147,11 @@ pub fn register_functions<S: WasmStateCore>(linker: &mut Linker<S>) -> BridgeRes
             };
 
             debug!("_crypto_verify_password: verifying password");
+            caller.data().audit(
+                "crypto",
+                "verify_password",
+                &json!({ "password": password, "hash": hash }),
+            );
 
             let result = tokio::task::block_in_place(|| {
                 tokio::runtime::Handle::current().block_on(async {
@@ -292,6 +329,29 @@ pub fn register_functions<S: WasmStateCore>(linker: &mut Linker<S>) -> BridgeRes
         },
     )?;
 
+    // hash_data(algo_ptr, algo_len, data_ptr, data_len) -> ptr
+    // Hex digest of the raw bytes with `sha256`, `sha512` or `blake3`; empty
+    // for any other algorithm. Too fast for passwords: use
+    // _crypto_hash_password for those
+    linker.func_wrap(
+        "env",
+        "hash_data",
+        |mut caller: Caller<'_, S>,
+         algo_ptr: i32,
+         algo_len: i32,
+         data_ptr: i32,
+         data_len: i32|
+         -> i32 {
+            let algorithm = read_raw_string(&mut caller, algo_ptr, algo_len).unwrap_or_default();
+            let data = read_raw_bytes(&mut caller, data_ptr, data_len).unwrap_or_default();
+            let digest = hash_data(&algorithm, &data).unwrap_or_else(|| {
+                error!("hash_data: unsupported algorithm '{}'", algorithm);
+                String::new()
+            });
+            write_string_to_caller(&mut caller, &digest)
+        },
+    )?;
+
     // _crypto_hmac - Compute HMAC digest
     // Args: data_ptr, data_len, key_ptr, key_len, algo_ptr, algo_len
     // Returns: pointer to hex digest string (length-prefixed)
@@ -580,6 +640,52 @@ pub fn register_functions<S: WasmStateCore>(linker: &mut Linker<S>) -> BridgeRes
         },
     )?;
 
+    // base64_encode(ptr, len) / base64_url_encode(ptr, len) -> ptr
+    // Encode raw bytes; the result is a length-prefixed string
+    linker.func_wrap(
+        "env",
+        "base64_encode",
+        |mut caller: Caller<'_, S>, p: i32, l: i32| -> i32 {
+            let bytes = read_raw_bytes(&mut caller, p, l).unwrap_or_default();
+            write_string_to_caller(&mut caller, &BASE64.encode(bytes))
+        },
+    )?;
+    linker.func_wrap(
+        "env",
+        "base64_url_encode",
+        |mut caller: Caller<'_, S>, p: i32, l: i32| -> i32 {
+            let bytes = read_raw_bytes(&mut caller, p, l).unwrap_or_default();
+            write_string_to_caller(&mut caller, &BASE64_URL.encode(bytes))
+        },
+    )?;
+
+    // base64_decode(ptr, len) / base64_url_decode(ptr, len) -> ptr
+    // Decode to raw bytes, length-prefixed; empty on invalid input
+    linker.func_wrap(
+        "env",
+        "base64_decode",
+        |mut caller: Caller<'_, S>, p: i32, l: i32| -> i32 {
+            let input = read_raw_bytes(&mut caller, p, l).unwrap_or_default();
+            let bytes = BASE64.decode(input).unwrap_or_else(|e| {
+                error!("base64_decode: invalid base64: {}", e);
+                Vec::new()
+            });
+            write_bytes_to_caller(&mut caller, &bytes)
+        },
+    )?;
+    linker.func_wrap(
+        "env",
+        "base64_url_decode",
+        |mut caller: Caller<'_, S>, p: i32, l: i32| -> i32 {
+            let input = read_raw_bytes(&mut caller, p, l).unwrap_or_default();
+            let bytes = BASE64_URL.decode(input).unwrap_or_else(|e| {
+                error!("base64_url_decode: invalid base64: {}", e);
+                Vec::new()
+            });
+            write_bytes_to_caller(&mut caller, &bytes)
+        },
+    )?;
+
     // _crypto_encrypt_aes(key, plaintext) -> ptr (JSON {iv, tag, data} each base64)
     // Key is taken as raw key material; if shorter than 32 bytes, padded with zeros;
     // if longer, truncated. AES-256-GCM gives a 16-byte tag bundled with the ciphertext.
@@ -590,24 +696,14 @@ pub fn register_functions<S: WasmStateCore>(linker: &mut Linker<S>) -> BridgeRes
             let key = read_raw_string(&mut caller, kp, kl).unwrap_or_default();
             let plaintext = read_raw_string(&mut caller, pp, pl).unwrap_or_default();
 
-            let mut key_buf = [0u8; 32];
-            let kb = key.as_bytes();
-            let copy_len = kb.len().min(32);
-            key_buf[..copy_len].copy_from_slice(&kb[..copy_len]);
-
-            let cipher =
-                <Aes256Gcm as aes_gcm::KeyInit>::new_from_slice(&key_buf).expect("32-byte key");
-            let mut iv_buf = [0u8; 12];
-            rand::thread_rng().fill_bytes(&mut iv_buf);
-            let nonce = Nonce::from_slice(&iv_buf);
-
-            let ciphertext = match cipher.encrypt(nonce, plaintext.as_bytes()) {
-                Ok(c) => c,
-                Err(e) => {
-                    error!("_crypto_encrypt_aes: {}", e);
-                    return write_string_to_caller(&mut caller, "");
-                }
-            };
+            let (iv_buf, ciphertext) =
+                match CryptoBridge::new().encrypt_aes(key.as_bytes(), plaintext.as_bytes()) {
+                    Ok(c) => c,
+                    Err(e) => {
+                        error!("_crypto_encrypt_aes: {}", e);
+                        return write_string_to_caller(&mut caller, "");
+                    }
+                };
             // GCM tags are the last 16 bytes of the output
             let split = ciphertext.len().saturating_sub(16);
             let (data, tag) = ciphertext.split_at(split);
@@ -654,15 +750,7 @@ pub fn register_functions<S: WasmStateCore>(linker: &mut Linker<S>) -> BridgeRes
             let mut ciphertext = data;
             ciphertext.extend_from_slice(&tag);
 
-            let mut key_buf = [0u8; 32];
-            let kb = key.as_bytes();
-            let copy_len = kb.len().min(32);
-            key_buf[..copy_len].copy_from_slice(&kb[..copy_len]);
-            let cipher =
-                <Aes256Gcm as aes_gcm::KeyInit>::new_from_slice(&key_buf).expect("32-byte key");
-            let nonce = Nonce::from_slice(&iv);
-
-            match cipher.decrypt(nonce, ciphertext.as_ref()) {
+            match CryptoBridge::new().decrypt_aes(key.as_bytes(), &iv, &ciphertext) {
                 Ok(plain) => {
                     let s = String::from_utf8(plain).unwrap_or_default();
                     write_string_to_caller(&mut caller, &s)
@@ -679,4 +767,151 @@ pub fn register_functions<S: WasmStateCore>(linker: &mut Linker<S>) -> BridgeRes
 mod tests {
     // Crypto tests are covered by the spec compliance test in mod.rs
     // and by crypto.rs unit tests
+
+    use crate::wasm_linker::{create_linker, WasmState};
+    use wasmtime::{Engine, Module, Store};
+
+    // `run(name_ptr, name_len, len)` calls the `name` function on the `len`
+    // input bytes at offset 1024 and returns the result pointer.
+    const WAT: &str = r#"
+        (module
+          (import "env" "base64_encode" (func $encode (param i32 i32) (result i32)))
+          (import "env" "base64_decode" (func $decode (param i32 i32) (result i32)))
+          (import "env" "base64_url_encode" (func $url_encode (param i32 i32) (result i32)))
+          (import "env" "base64_url_decode" (func $url_decode (param i32 i32) (result i32)))
+          (import "env" "hash_data" (func $hash (param i32 i32 i32 i32) (result i32)))
+          (memory (export "memory") 2)
+          (data (i32.const 512) "sha256sha512blake3md5")
+          (global $heap (mut i32) (i32.const 65536))
+          (global (export "__heap_ptr") (mut i32) (i32.const 65536))
+          (func (export "malloc") (param $size i32) (result i32)
+            (local $ptr i32)
+            (local.set $ptr (global.get $heap))
+            (global.set $heap
+              (i32.and
+                (i32.add (i32.add (global.get $heap) (local.get $size)) (i32.const 7))
+                (i32.const -8)))
+            (global.set 1 (global.get $heap))
+            (local.get $ptr))
+          (func (export "encode") (param i32) (result i32)
+            (call $encode (i32.const 1024) (local.get 0)))
+          (func (export "decode") (param i32) (result i32)
+            (call $decode (i32.const 1024) (local.get 0)))
+          (func (export "url_encode") (param i32) (result i32)
+            (call $url_encode (i32.const 1024) (local.get 0)))
+          (func (export "url_decode") (param i32) (result i32)
+            (call $url_decode (i32.const 1024) (local.get 0)))
+          (func (export "sha256") (param i32) (result i32)
+            (call $hash (i32.const 512) (i32.const 6) (i32.const 1024) (local.get 0)))
+          (func (export "sha512") (param i32) (result i32)
+            (call $hash (i32.const 518) (i32.const 6) (i32.const 1024) (local.get 0)))
+          (func (export "blake3") (param i32) (result i32)
+            (call $hash (i32.const 524) (i32.const 6) (i32.const 1024) (local.get 0)))
+          (func (export "md5") (param i32) (result i32)
+            (call $hash (i32.const 530) (i32.const 3) (i32.const 1024) (local.get 0))))
+    "#;
+
+    struct Fixture {
+        store: Store<WasmState>,
+        instance: wasmtime::Instance,
+    }
+
+    impl Fixture {
+        fn new() -> Self {
+            let engine = Engine::default();
+            let linker = create_linker(&engine).expect("create linker");
+            let module = Module::new(&engine, WAT).expect("compile test module");
+            let mut store = Store::new(&engine, WasmState::default());
+            let instance = linker
+                .instantiate(&mut store, &module)
+                .expect("instantiate");
+            Self { store, instance }
+        }
+
+        /// Copy `input` into memory, call `export` on it and read back the
+        /// length-prefixed result.
+        fn call(&mut self, export: &str, input: &[u8]) -> Vec<u8> {
+            let memory = self.instance.get_memory(&mut self.store, "memory").unwrap();
+            memory.write(&mut self.store, 1024, input).unwrap();
+            let func = self
+                .instance
+                .get_typed_func::<i32, i32>(&mut self.store, export)
+                .unwrap();
+            let ptr = func.call(&mut self.store, input.len() as i32).unwrap() as usize;
+            let data = memory.data(&self.store);
+            let len = u32::from_le_bytes(data[ptr..ptr + 4].try_into().unwrap()) as usize;
+            data[ptr + 4..ptr + 4 + len].to_vec()
+        }
+    }
+
+    #[test]
+    fn test_base64_round_trips_binary_data_through_both_alphabets() {
+        let mut fixture = Fixture::new();
+        let binary: Vec<u8> = (0..=255u8).rev().chain([0xfb, 0xff, 0x00]).collect();
+
+        let standard = fixture.call("encode", &binary);
+        assert!(standard.contains(&b'+') || standard.contains(&b'/'));
+        assert_eq!(fixture.call("decode", &standard), binary);
+
+        let url = fixture.call("url_encode", &binary);
+        assert!(!url.iter().any(|b| matches!(b, b'+' | b'/' | b'=')));
+        assert_eq!(fixture.call("url_decode", &url), binary);
+
+        assert_eq!(fixture.call("encode", b"\xff\xfe"), b"//4=");
+        assert_eq!(fixture.call("url_encode", b"\xff\xfe"), b"__4");
+        assert_eq!(fixture.call("url_decode", b"__4="), b"\xff\xfe");
+    }
+
+    #[test]
+    fn test_hash_data_matches_known_vectors() {
+        let mut fixture = Fixture::new();
+        let mut hash = |algorithm: &str, input: &[u8]| {
+            String::from_utf8(fixture.call(algorithm, input)).unwrap()
+        };
+
+        assert_eq!(
+            hash("sha256", b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            hash("sha256", b""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            hash("sha512", b"abc"),
+            "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a\
+             2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f"
+        );
+        assert_eq!(
+            hash("blake3", b""),
+            "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262"
+        );
+        assert_eq!(
+            hash("blake3", b"abc"),
+            "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85"
+        );
+        assert!(hash("md5", b"abc").is_empty());
+    }
+
+    #[test]
+    fn test_hash_data_hashes_bytes_that_are_not_utf8() {
+        use sha2::Digest;
+
+        let mut fixture = Fixture::new();
+        let binary = [0xff, 0x00, 0xfe];
+        let digest = fixture.call("sha256", &binary);
+        assert_eq!(digest, hex::encode(sha2::Sha256::digest(binary)).as_bytes());
+        assert_eq!(
+            super::hash_data("BLAKE3", &binary).as_deref(),
+            Some(blake3::hash(&binary).to_hex().as_str())
+        );
+    }
+
+    #[test]
+    fn test_base64_decode_of_invalid_input_is_empty() {
+        let mut fixture = Fixture::new();
+        assert!(fixture.call("decode", b"not base64!").is_empty());
+        assert!(fixture.call("decode", b"__4=").is_empty());
+        assert!(fixture.call("url_decode", b"//4=").is_empty());
+    }
 }
@@ -2,8 +2,15 @@
 //!
 //! Provides database operations for WASM modules:
 //! - _db_query: Execute SELECT queries
+//! - _db_query_one, _db_query_first: SELECT a single row (or null)
+//! - _db_count: Row count as a plain integer
+//! - _db_exists: Whether any row of a table matches a filter
+//! - _db_insert: INSERT built from a JSON object of column values
+//! - _db_update, _db_delete: UPDATE/DELETE scoped by an equality filter
+//! - _db_explain: Query plan, optionally with EXPLAIN ANALYZE
 //! - _db_execute: Execute INSERT/UPDATE/DELETE
 //! - _db_begin, _db_commit, _db_rollback: Transaction management
+//! - _db_transaction: Run a batch of statements in one transaction
 //! - _db_configure: Configure connection pool from JSON
 //! - _db_paginate: Offset-based paginated query
 //! - _db_cursor_page: Cursor-based paginated query
@@ -11,6 +18,7 @@
 //! - _db_migration_status: List applied + pending migrations
 //! - _db_rollback_migration: Rollback a specific migration
 //! - _db_run_migrations: Apply all pending migrations
+//! - _db_migrate: Apply numbered `.sql` migration files from a directory
 //! - _db_valid_field: Runtime ORDER BY safety check
 //!
 //! All functions are generic over `WasmStateCore` to work with any runtime.
@@ -20,6 +28,8 @@ use super::state::WasmStateCore;
 use crate::error::BridgeResult;
 use serde_json::json;
 use std::cell::RefCell;
+use std::future::Future;
+use std::time::Instant;
 use tracing::{debug, error};
 use wasmtime::{Caller, Linker};
 
@@ -104,6 +114,142 @@ fn is_insert(sql: &str) -> bool {
         || sql.trim_start().to_uppercase().starts_with("REPLACE")
 }
 
+/// Block on a database bridge call, adding the time it takes to the
+/// request's database total through `WasmStateCore::record_db_time`.
+fn block_on_db<S: WasmStateCore, T>(
+    caller: &mut Caller<'_, S>,
+    call: impl Future<Output = T>,
+) -> T {
+    let start = Instant::now();
+    let result = tokio::task::block_in_place(|| tokio::runtime::Handle::current().block_on(call));
+    caller.data_mut().record_db_time(start.elapsed());
+    result
+}
+
+/// Shared body of `_db_query_one` / `_db_query_first`: dispatch `function`
+/// with the SQL and JSON params read from WASM memory and return a pointer to
+/// the JSON result.
+fn single_row_query<S: WasmStateCore>(
+    caller: &mut Caller<'_, S>,
+    name: &str,
+    function: &str,
+    sql_ptr: i32,
+    sql_len: i32,
+    params_ptr: i32,
+    params_len: i32,
+) -> i32 {
+    let sql = match read_raw_string(caller, sql_ptr, sql_len) {
+        Some(s) => s,
+        None => {
+            error!("{}: Failed to read SQL string", name);
+            return write_string_to_caller(
+                caller,
+                r#"{"ok":false,"err":{"code":"MEMORY_ERROR","message":"Failed to read SQL"}}"#,
+            );
+        }
+    };
+    let params: Vec<serde_json::Value> = if params_len > 0 {
+        read_raw_string(caller, params_ptr, params_len)
+            .and_then(|p| serde_json::from_str(&p).ok())
+            .unwrap_or_default()
+    } else {
+        Vec::new()
+    };
+    debug!("{}: SQL='{}', params={:?}", name, sql, params);
+
+    let db_bridge = match caller.data().db_bridge() {
+        Some(db) => db,
+        None => {
+            return write_string_to_caller(
+                caller,
+                r#"{"ok":false,"err":{"code":"NO_DB","message":"No database configured"}}"#,
+            );
+        }
+    };
+
+    let result = block_on_db(caller, async {
+        let mut bridge = db_bridge.write().await;
+        bridge
+            .call(function, json!({ "sql": sql, "params": params }))
+            .await
+    });
+
+    let result_str = match result {
+        Ok(v) => v.to_string(),
+        Err(e) => {
+            error!("{}: Query failed: {}", name, e);
+            json!({ "ok": false, "err": { "code": "DB_ERROR", "message": e.to_string() } })
+                .to_string()
+        }
+    };
+    write_string_to_caller(caller, &result_str)
+}
+
+/// Shared body of `_db_update` / `_db_delete`: dispatch `function` with the
+/// JSON request read from WASM memory and return a pointer to the JSON result.
+fn scoped_write<S: WasmStateCore>(
+    caller: &mut Caller<'_, S>,
+    name: &str,
+    function: &str,
+    request_ptr: i32,
+    request_len: i32,
+) -> i32 {
+    let request: serde_json::Value = match read_raw_string(caller, request_ptr, request_len)
+        .and_then(|r| serde_json::from_str(&r).ok())
+    {
+        Some(r) => r,
+        None => {
+            error!("{}: Failed to read request JSON", name);
+            return write_string_to_caller(
+                caller,
+                &json!({
+                    "ok": false,
+                    "err": {
+                        "code": "VALIDATION_ERROR",
+                        "message": format!("Invalid {} request", function)
+                    }
+                })
+                .to_string(),
+            );
+        }
+    };
+    debug!("{}: request={}", name, request);
+    caller.data().audit(
+        "db",
+        function,
+        &json!({
+            "table": request.get("table"),
+            "where": request.get("where"),
+            "allow_full_table": request.get("allow_full_table"),
+        }),
+    );
+
+    let db_bridge = match caller.data().db_bridge() {
+        Some(db) => db,
+        None => {
+            return write_string_to_caller(
+                caller,
+                r#"{"ok":false,"err":{"code":"NO_DB","message":"No database configured"}}"#,
+            );
+        }
+    };
+
+    let result = block_on_db(caller, async {
+        let mut bridge = db_bridge.write().await;
+        bridge.call(function, request).await
+    });
+
+    let result_str = match result {
+        Ok(v) => v.to_string(),
+        Err(e) => {
+            error!("{}: Statement failed: {}", name, e);
+            json!({ "ok": false, "err": { "code": "DB_ERROR", "message": e.to_string() } })
+                .to_string()
+        }
+    };
+    write_string_to_caller(caller, &result_str)
+}
+
 /// Register all database functions with the linker
 pub fn register_functions<S: WasmStateCore>(linker: &mut Linker<S>) -> BridgeResult<()> {
     // =========================================
@@ -181,20 +327,25 @@ pub fn register_functions<S: WasmStateCore>(linker: &mut Linker<S>) -> BridgeRes
             } else {
                 "query"
             };
+            if method == "execute" {
+                caller.data().audit(
+                    "db",
+                    "execute",
+                    &json!({ "sql": sql, "param_count": params.len() }),
+                );
+            }
 
-            let result = tokio::task::block_in_place(|| {
-                tokio::runtime::Handle::current().block_on(async {
-                    let mut bridge = db_bridge.write().await;
-                    bridge
-                        .call(
-                            method,
-                            json!({
-                                "sql": sql,
-                                "params": params
-                            }),
-                        )
-                        .await
-                })
+            let result = block_on_db(&mut caller, async {
+                let mut bridge = db_bridge.write().await;
+                bridge
+                    .call(
+                        method,
+                        json!({
+                            "sql": sql,
+                            "params": params
+                        }),
+                    )
+                    .await
             });
 
             // Cache last_insert_id from successful INSERT/REPLACE so a follow-up
@@ -238,6 +389,370 @@ pub fn register_functions<S: WasmStateCore>(linker: &mut Linker<S>) -> BridgeRes
         },
     )?;
 
+    // _db_query_one / _db_query_first - SELECT a single row
+    // Args: sql_ptr, sql_len, params_ptr, params_len (JSON array of params)
+    // Returns: pointer to JSON `{"ok":true,"data":<row>|null}`. `_db_query_one`
+    // fails with MULTIPLE_ROWS when more than one row matches;
+    // `_db_query_first` returns the first.
+    for (name, function) in [
+        ("_db_query_one", "query_one"),
+        ("_db_query_first", "query_first"),
+    ] {
+        linker.func_wrap(
+            "env",
+            name,
+            move |mut caller: Caller<'_, S>,
+                  sql_ptr: i32,
+                  sql_len: i32,
+                  params_ptr: i32,
+                  params_len: i32|
+                  -> i32 {
+                single_row_query(
+                    &mut caller,
+                    name,
+                    function,
+                    sql_ptr,
+                    sql_len,
+                    params_ptr,
+                    params_len,
+                )
+            },
+        )?;
+    }
+
+    // _db_count - Count rows as a plain integer
+    // Args: request_ptr, request_len (JSON `{"table":..,"where":..,"params":[..]}`
+    //       or `{"sql":"SELECT COUNT(...) ...","params":[..]}`)
+    // Returns: pointer to JSON `{"ok":true,"data":{"count":<i64>}}`
+    linker.func_wrap(
+        "env",
+        "_db_count",
+        |mut caller: Caller<'_, S>, request_ptr: i32, request_len: i32| -> i32 {
+            let request: serde_json::Value = match read_raw_string(&mut caller, request_ptr, request_len)
+                .and_then(|r| serde_json::from_str(&r).ok())
+            {
+                Some(r) => r,
+                None => {
+                    error!("_db_count: Failed to read request JSON");
+                    return write_string_to_caller(
+                        &mut caller,
+                        r#"{"ok":false,"err":{"code":"VALIDATION_ERROR","message":"Invalid count request"}}"#,
+                    );
+                }
+            };
+            debug!("_db_count: request={}", request);
+
+            let db_bridge = match caller.data().db_bridge() {
+                Some(db) => db,
+                None => {
+                    return write_string_to_caller(
+                        &mut caller,
+                        r#"{"ok":false,"err":{"code":"NO_DB","message":"No database configured"}}"#,
+                    );
+                }
+            };
+
+            let result = block_on_db(&mut caller, async {
+                let mut bridge = db_bridge.write().await;
+                bridge.call("count", request).await
+            });
+
+            let result_str = match result {
+                Ok(v) => v.to_string(),
+                Err(e) => {
+                    error!("_db_count: Count failed: {}", e);
+                    json!({ "ok": false, "err": { "code": "DB_ERROR", "message": e.to_string() } })
+                        .to_string()
+                }
+            };
+            write_string_to_caller(&mut caller, &result_str)
+        },
+    )?;
+
+    // _db_exists - Check whether any row matches
+    // Args: request_ptr, request_len (JSON `{"table":..,"where":..,"params":[..]}`)
+    // Returns: pointer to JSON `{"ok":true,"data":true|false}`
+    linker.func_wrap(
+        "env",
+        "_db_exists",
+        |mut caller: Caller<'_, S>, request_ptr: i32, request_len: i32| -> i32 {
+            let request: serde_json::Value = match read_raw_string(&mut caller, request_ptr, request_len)
+                .and_then(|r| serde_json::from_str(&r).ok())
+            {
+                Some(r) => r,
+                None => {
+                    error!("_db_exists: Failed to read request JSON");
+                    return write_string_to_caller(
+                        &mut caller,
+                        r#"{"ok":false,"err":{"code":"VALIDATION_ERROR","message":"Invalid exists request"}}"#,
+                    );
+                }
+            };
+            debug!("_db_exists: request={}", request);
+
+            let db_bridge = match caller.data().db_bridge() {
+                Some(db) => db,
+                None => {
+                    return write_string_to_caller(
+                        &mut caller,
+                        r#"{"ok":false,"err":{"code":"NO_DB","message":"No database configured"}}"#,
+                    );
+                }
+            };
+
+            let result = block_on_db(&mut caller, async {
+                let mut bridge = db_bridge.write().await;
+                bridge.call("exists", request).await
+            });
+
+            let result_str = match result {
+                Ok(v) => v.to_string(),
+                Err(e) => {
+                    error!("_db_exists: Query failed: {}", e);
+                    json!({ "ok": false, "err": { "code": "DB_ERROR", "message": e.to_string() } })
+                        .to_string()
+                }
+            };
+            write_string_to_caller(&mut caller, &result_str)
+        },
+    )?;
+
+    // _db_activity - List the queries other sessions are running
+    // Args: none
+    // Returns: pointer to JSON `{"ok":true,"data":[{"pid":..,"username":..,
+    //          "db":..,"state":..,"query":..,"duration_ms":..},..]}`
+    // Requires `admin_functions` in the database config
+    linker.func_wrap("env", "_db_activity", |mut caller: Caller<'_, S>| -> i32 {
+        let db_bridge = match caller.data().db_bridge() {
+            Some(db) => db,
+            None => {
+                return write_string_to_caller(
+                    &mut caller,
+                    r#"{"ok":false,"err":{"code":"NO_DB","message":"No database configured"}}"#,
+                );
+            }
+        };
+
+        let result = block_on_db(&mut caller, async {
+            let mut bridge = db_bridge.write().await;
+            bridge.call("activity", json!({})).await
+        });
+
+        let result_str = match result {
+            Ok(v) => v.to_string(),
+            Err(e) => {
+                error!("_db_activity: Query failed: {}", e);
+                json!({ "ok": false, "err": { "code": "DB_ERROR", "message": e.to_string() } })
+                    .to_string()
+            }
+        };
+        write_string_to_caller(&mut caller, &result_str)
+    })?;
+
+    // _db_kill - Cancel the query a database session is running
+    // Args: pid (i64, from `_db_activity`)
+    // Returns: pointer to JSON `{"ok":true,"data":{"pid":..,"cancelled":true|false}}`
+    // Requires `admin_functions` in the database config
+    linker.func_wrap(
+        "env",
+        "_db_kill",
+        |mut caller: Caller<'_, S>, pid: i64| -> i32 {
+            debug!("_db_kill: pid={}", pid);
+            let db_bridge = match caller.data().db_bridge() {
+                Some(db) => db,
+                None => {
+                    return write_string_to_caller(
+                        &mut caller,
+                        r#"{"ok":false,"err":{"code":"NO_DB","message":"No database configured"}}"#,
+                    );
+                }
+            };
+
+            let result = block_on_db(&mut caller, async {
+                let mut bridge = db_bridge.write().await;
+                bridge.call("kill", json!({ "pid": pid })).await
+            });
+
+            let result_str = match result {
+                Ok(v) => v.to_string(),
+                Err(e) => {
+                    error!("_db_kill: Query failed: {}", e);
+                    json!({ "ok": false, "err": { "code": "DB_ERROR", "message": e.to_string() } })
+                        .to_string()
+                }
+            };
+            write_string_to_caller(&mut caller, &result_str)
+        },
+    )?;
+
+    // _db_insert - Insert one row with every value bound as a parameter
+    // Args: request_ptr, request_len (JSON `{"table":..,"values":{col:val,..},
+    //       "returning":"id"|[..]}`)
+    // Returns: pointer to JSON `{"ok":true,"data":{"affected_rows":1,
+    //          "last_insert_id":..}}`, with `"row":{..}` instead of
+    //          `last_insert_id` when `returning` is given
+    linker.func_wrap(
+        "env",
+        "_db_insert",
+        |mut caller: Caller<'_, S>, request_ptr: i32, request_len: i32| -> i32 {
+            let request: serde_json::Value = match read_raw_string(&mut caller, request_ptr, request_len)
+                .and_then(|r| serde_json::from_str(&r).ok())
+            {
+                Some(r) => r,
+                None => {
+                    error!("_db_insert: Failed to read request JSON");
+                    return write_string_to_caller(
+                        &mut caller,
+                        r#"{"ok":false,"err":{"code":"VALIDATION_ERROR","message":"Invalid insert request"}}"#,
+                    );
+                }
+            };
+            debug!("_db_insert: request={}", request);
+            caller.data().audit(
+                "db",
+                "insert",
+                &json!({
+                    "table": request.get("table"),
+                    "columns": request
+                        .get("values")
+                        .and_then(|v| v.as_object())
+                        .map(|values| values.keys().collect::<Vec<_>>()),
+                }),
+            );
+
+            let db_bridge = match caller.data().db_bridge() {
+                Some(db) => db,
+                None => {
+                    return write_string_to_caller(
+                        &mut caller,
+                        r#"{"ok":false,"err":{"code":"NO_DB","message":"No database configured"}}"#,
+                    );
+                }
+            };
+
+            let result = block_on_db(&mut caller, async {
+                let mut bridge = db_bridge.write().await;
+                bridge.call("insert", request).await
+            });
+
+            let result_str = match result {
+                Ok(v) => {
+                    // Same follow-up `SELECT LAST_INSERT_ID()` support as `_db_execute`.
+                    if let Some(id) = v["data"]["last_insert_id"].as_i64() {
+                        caller.data_mut().set_last_insert_id(Some(id));
+                    }
+                    v.to_string()
+                }
+                Err(e) => {
+                    error!("_db_insert: Insert failed: {}", e);
+                    json!({ "ok": false, "err": { "code": "DB_ERROR", "message": e.to_string() } })
+                        .to_string()
+                }
+            };
+            write_string_to_caller(&mut caller, &result_str)
+        },
+    )?;
+
+    // _db_update / _db_delete - UPDATE or DELETE with a parameterized WHERE
+    // Args: request_ptr, request_len (JSON `{"table":..,"set":{..},"where":{..},
+    //       "allow_full_table":false}`; `set` is for `_db_update` only)
+    // Returns: pointer to JSON `{"ok":true,"data":{"affected_rows":N}}`. An
+    // empty `where` is a VALIDATION_ERROR unless `allow_full_table` is true.
+    for (name, function) in [("_db_update", "update"), ("_db_delete", "delete")] {
+        linker.func_wrap(
+            "env",
+            name,
+            move |mut caller: Caller<'_, S>, request_ptr: i32, request_len: i32| -> i32 {
+                scoped_write(&mut caller, name, function, request_ptr, request_len)
+            },
+        )?;
+    }
+
+    // _db_explain - Query plan for a statement
+    // Args: sql_ptr, sql_len, params_ptr, params_len (JSON array of params),
+    //       options_ptr, options_len (JSON `{"analyze":bool,"rollback":bool}`)
+    // Returns: pointer to JSON `{"ok":true,"data":{"driver":..,"plan":[..]}}`.
+    // With `analyze` the statement really runs; writes additionally need
+    // `rollback` so the plan is measured inside a transaction that is undone.
+    linker.func_wrap(
+        "env",
+        "_db_explain",
+        |mut caller: Caller<'_, S>,
+         sql_ptr: i32,
+         sql_len: i32,
+         params_ptr: i32,
+         params_len: i32,
+         options_ptr: i32,
+         options_len: i32|
+         -> i32 {
+            let sql = match read_raw_string(&mut caller, sql_ptr, sql_len) {
+                Some(s) => s,
+                None => {
+                    error!("_db_explain: Failed to read SQL string");
+                    return write_string_to_caller(
+                        &mut caller,
+                        r#"{"ok":false,"err":{"code":"MEMORY_ERROR","message":"Failed to read SQL"}}"#,
+                    );
+                }
+            };
+            let params: Vec<serde_json::Value> = if params_len > 0 {
+                read_raw_string(&mut caller, params_ptr, params_len)
+                    .and_then(|p| serde_json::from_str(&p).ok())
+                    .unwrap_or_default()
+            } else {
+                Vec::new()
+            };
+            let options: serde_json::Value = if options_len > 0 {
+                read_raw_string(&mut caller, options_ptr, options_len)
+                    .and_then(|o| serde_json::from_str(&o).ok())
+                    .unwrap_or_default()
+            } else {
+                serde_json::Value::Null
+            };
+            let analyze = options["analyze"].as_bool().unwrap_or(false);
+            let rollback = options["rollback"].as_bool().unwrap_or(false);
+            debug!(
+                "_db_explain: SQL='{}', analyze={}, rollback={}",
+                sql, analyze, rollback
+            );
+
+            let db_bridge = match caller.data().db_bridge() {
+                Some(db) => db,
+                None => {
+                    return write_string_to_caller(
+                        &mut caller,
+                        r#"{"ok":false,"err":{"code":"NO_DB","message":"No database configured"}}"#,
+                    );
+                }
+            };
+
+            let result = block_on_db(&mut caller, async {
+                let mut bridge = db_bridge.write().await;
+                bridge
+                    .call(
+                        "explain",
+                        json!({
+                            "sql": sql,
+                            "params": params,
+                            "analyze": analyze,
+                            "rollback": rollback,
+                        }),
+                    )
+                    .await
+            });
+
+            let result_str = match result {
+                Ok(v) => v.to_string(),
+                Err(e) => {
+                    error!("_db_explain: Explain failed: {}", e);
+                    json!({ "ok": false, "err": { "code": "DB_ERROR", "message": e.to_string() } })
+                        .to_string()
+                }
+            };
+            write_string_to_caller(&mut caller, &result_str)
+        },
+    )?;
+
     // =========================================
     // DATABASE EXECUTE
     // =========================================
@@ -273,6 +788,11 @@ pub fn register_functions<S: WasmStateCore>(linker: &mut Linker<S>) -> BridgeRes
 
             let params: Vec<serde_json::Value> =
                 serde_json::from_str(&params_json).unwrap_or_default();
+            caller.data().audit(
+                "db",
+                "execute",
+                &json!({ "sql": sql, "param_count": params.len() }),
+            );
 
             let db_bridge = match caller.data().db_bridge() {
                 Some(db) => db,
@@ -282,19 +802,17 @@ pub fn register_functions<S: WasmStateCore>(linker: &mut Linker<S>) -> BridgeRes
                 }
             };
 
-            let result = tokio::task::block_in_place(|| {
-                tokio::runtime::Handle::current().block_on(async {
-                    let mut bridge = db_bridge.write().await;
-                    bridge
-                        .call(
-                            "execute",
-                            json!({
-                                "sql": sql,
-                                "params": params
-                            }),
-                        )
-                        .await
-                })
+            let result = block_on_db(&mut caller, async {
+                let mut bridge = db_bridge.write().await;
+                bridge
+                    .call(
+                        "execute",
+                        json!({
+                            "sql": sql,
+                            "params": params
+                        }),
+                    )
+                    .await
             });
 
             match result {
@@ -345,11 +863,9 @@ pub fn register_functions<S: WasmStateCore>(linker: &mut Linker<S>) -> BridgeRes
             }
         };
 
-        let result = tokio::task::block_in_place(|| {
-            tokio::runtime::Handle::current().block_on(async {
-                let mut bridge = db_bridge.write().await;
-                bridge.call("transaction_begin", json!({})).await
-            })
+        let result = block_on_db(&mut caller, async {
+            let mut bridge = db_bridge.write().await;
+            bridge.call("transaction_begin", json!({})).await
         });
 
         match result {
@@ -387,13 +903,11 @@ pub fn register_functions<S: WasmStateCore>(linker: &mut Linker<S>) -> BridgeRes
             None => return 0,
         };
 
-        let result = tokio::task::block_in_place(|| {
-            tokio::runtime::Handle::current().block_on(async {
-                let mut bridge = db_bridge.write().await;
-                bridge
-                    .call("transaction_commit", json!({ "tx_id": tx_id }))
-                    .await
-            })
+        let result = block_on_db(&mut caller, async {
+            let mut bridge = db_bridge.write().await;
+            bridge
+                .call("transaction_commit", json!({ "tx_id": tx_id }))
+                .await
         });
 
         match result {
@@ -426,13 +940,11 @@ pub fn register_functions<S: WasmStateCore>(linker: &mut Linker<S>) -> BridgeRes
             None => return 0,
         };
 
-        let result = tokio::task::block_in_place(|| {
-            tokio::runtime::Handle::current().block_on(async {
-                let mut bridge = db_bridge.write().await;
-                bridge
-                    .call("transaction_rollback", json!({ "tx_id": tx_id }))
-                    .await
-            })
+        let result = block_on_db(&mut caller, async {
+            let mut bridge = db_bridge.write().await;
+            bridge
+                .call("transaction_rollback", json!({ "tx_id": tx_id }))
+                .await
         });
 
         match result {
@@ -451,6 +963,55 @@ pub fn register_functions<S: WasmStateCore>(linker: &mut Linker<S>) -> BridgeRes
         }
     })?;
 
+    // _db_transaction - Run statements in order in one transaction, committing
+    // when all succeed and rolling back when any fails
+    // Args: request_ptr, request_len (JSON `{"operations":[{"sql":..,"params":[..]},..]}`)
+    // Returns: pointer to JSON `{"ok":true,"data":{"affected_rows":[<n>,..]}}`
+    linker.func_wrap(
+        "env",
+        "_db_transaction",
+        |mut caller: Caller<'_, S>, request_ptr: i32, request_len: i32| -> i32 {
+            let request: serde_json::Value = match read_raw_string(&mut caller, request_ptr, request_len)
+                .and_then(|r| serde_json::from_str(&r).ok())
+            {
+                Some(r) => r,
+                None => {
+                    error!("_db_transaction: Failed to read request JSON");
+                    return write_string_to_caller(
+                        &mut caller,
+                        r#"{"ok":false,"err":{"code":"VALIDATION_ERROR","message":"Invalid transaction request"}}"#,
+                    );
+                }
+            };
+            debug!("_db_transaction: request={}", request);
+
+            let db_bridge = match caller.data().db_bridge() {
+                Some(db) => db,
+                None => {
+                    return write_string_to_caller(
+                        &mut caller,
+                        r#"{"ok":false,"err":{"code":"NO_DB","message":"No database configured"}}"#,
+                    );
+                }
+            };
+
+            let result = block_on_db(&mut caller, async {
+                let mut bridge = db_bridge.write().await;
+                bridge.call("transaction", request).await
+            });
+
+            let result_str = match result {
+                Ok(v) => v.to_string(),
+                Err(e) => {
+                    error!("_db_transaction: Transaction failed: {}", e);
+                    json!({ "ok": false, "err": { "code": "DB_ERROR", "message": e.to_string() } })
+                        .to_string()
+                }
+            };
+            write_string_to_caller(&mut caller, &result_str)
+        },
+    )?;
+
     // =========================================
     // MIGRATION REGISTRATION
     // =========================================
@@ -491,20 +1052,18 @@ pub fn register_functions<S: WasmStateCore>(linker: &mut Linker<S>) -> BridgeRes
                 }
             };
 
-            let result = tokio::task::block_in_place(|| {
-                tokio::runtime::Handle::current().block_on(async {
-                    let mut bridge = db_bridge.write().await;
-                    bridge
-                        .call(
-                            "register_migration",
-                            serde_json::json!({
-                                "name": name,
-                                "up_sql": up_sql,
-                                "down_sql": down_sql
-                            }),
-                        )
-                        .await
-                })
+            let result = block_on_db(&mut caller, async {
+                let mut bridge = db_bridge.write().await;
+                bridge
+                    .call(
+                        "register_migration",
+                        serde_json::json!({
+                            "name": name,
+                            "up_sql": up_sql,
+                            "down_sql": down_sql
+                        }),
+                    )
+                    .await
             });
 
             match result {
@@ -555,11 +1114,9 @@ pub fn register_functions<S: WasmStateCore>(linker: &mut Linker<S>) -> BridgeRes
                 }
             };
 
-            let result = tokio::task::block_in_place(|| {
-                tokio::runtime::Handle::current().block_on(async {
-                    let mut bridge = db_bridge.write().await;
-                    bridge.configure_from_json(&config_json).await
-                })
+            let result = block_on_db(&mut caller, async {
+                let mut bridge = db_bridge.write().await;
+                bridge.configure_from_json(&config_json).await
             });
 
             match result {
@@ -629,21 +1186,19 @@ pub fn register_functions<S: WasmStateCore>(linker: &mut Linker<S>) -> BridgeRes
             let where_val: serde_json::Value =
                 serde_json::from_str(&where_json).unwrap_or(serde_json::json!({}));
 
-            let result = tokio::task::block_in_place(|| {
-                tokio::runtime::Handle::current().block_on(async {
-                    let mut bridge = db_bridge.write().await;
-                    bridge
-                        .call(
-                            "paginate",
-                            serde_json::json!({
-                                "table": table,
-                                "where": where_val,
-                                "page": page,
-                                "per_page": per_page
-                            }),
-                        )
-                        .await
-                })
+            let result = block_on_db(&mut caller, async {
+                let mut bridge = db_bridge.write().await;
+                bridge
+                    .call(
+                        "paginate",
+                        serde_json::json!({
+                            "table": table,
+                            "where": where_val,
+                            "page": page,
+                            "per_page": per_page
+                        }),
+                    )
+                    .await
             });
 
             let result_str = match result {
@@ -730,22 +1285,20 @@ pub fn register_functions<S: WasmStateCore>(linker: &mut Linker<S>) -> BridgeRes
             let where_val: serde_json::Value =
                 serde_json::from_str(&where_json).unwrap_or(serde_json::json!({}));
 
-            let result = tokio::task::block_in_place(|| {
-                tokio::runtime::Handle::current().block_on(async {
-                    let mut bridge = db_bridge.write().await;
-                    bridge
-                        .call(
-                            "cursor_page",
-                            serde_json::json!({
-                                "table": table,
-                                "where": where_val,
-                                "per_page": per_page,
-                                "after": after,
-                                "by_field": by_field
-                            }),
-                        )
-                        .await
-                })
+            let result = block_on_db(&mut caller, async {
+                let mut bridge = db_bridge.write().await;
+                bridge
+                    .call(
+                        "cursor_page",
+                        serde_json::json!({
+                            "table": table,
+                            "where": where_val,
+                            "per_page": per_page,
+                            "after": after,
+                            "by_field": by_field
+                        }),
+                    )
+                    .await
             });
 
             let result_str = match result {
@@ -823,20 +1376,18 @@ pub fn register_functions<S: WasmStateCore>(linker: &mut Linker<S>) -> BridgeRes
                 ),
             };
 
-            let result = tokio::task::block_in_place(|| {
-                tokio::runtime::Handle::current().block_on(async {
-                    let mut bridge = db_bridge.write().await;
-                    bridge
-                        .call(
-                            "migration_diff",
-                            serde_json::json!({
-                                "table": table_opt,
-                                "declared": declared_val,
-                                "live": live_val
-                            }),
-                        )
-                        .await
-                })
+            let result = block_on_db(&mut caller, async {
+                let mut bridge = db_bridge.write().await;
+                bridge
+                    .call(
+                        "migration_diff",
+                        serde_json::json!({
+                            "table": table_opt,
+                            "declared": declared_val,
+                            "live": live_val
+                        }),
+                    )
+                    .await
             });
 
             let diff_sql = match result {
@@ -874,11 +1425,9 @@ pub fn register_functions<S: WasmStateCore>(linker: &mut Linker<S>) -> BridgeRes
                 }
             };
 
-            let result = tokio::task::block_in_place(|| {
-                tokio::runtime::Handle::current().block_on(async {
-                    let mut bridge = db_bridge.write().await;
-                    bridge.call("migration_status", serde_json::json!({})).await
-                })
+            let result = block_on_db(&mut caller, async {
+                let mut bridge = db_bridge.write().await;
+                bridge.call("migration_status", serde_json::json!({})).await
             });
 
             let result_str = match result {
@@ -929,13 +1478,11 @@ pub fn register_functions<S: WasmStateCore>(linker: &mut Linker<S>) -> BridgeRes
                 }
             };
 
-            let result = tokio::task::block_in_place(|| {
-                tokio::runtime::Handle::current().block_on(async {
-                    let mut bridge = db_bridge.write().await;
-                    bridge
-                        .call("rollback_migration", serde_json::json!({"name": name}))
-                        .await
-                })
+            let result = block_on_db(&mut caller, async {
+                let mut bridge = db_bridge.write().await;
+                bridge
+                    .call("rollback_migration", serde_json::json!({"name": name}))
+                    .await
             });
 
             match result {
@@ -965,7 +1512,7 @@ pub fn register_functions<S: WasmStateCore>(linker: &mut Linker<S>) -> BridgeRes
     linker.func_wrap(
         "env",
         "_db_run_migrations",
-        |caller: Caller<'_, S>| -> i32 {
+        |mut caller: Caller<'_, S>| -> i32 {
             let db_bridge = match caller.data().db_bridge() {
                 Some(db) => db,
                 None => {
@@ -974,11 +1521,9 @@ pub fn register_functions<S: WasmStateCore>(linker: &mut Linker<S>) -> BridgeRes
                 }
             };
 
-            let result = tokio::task::block_in_place(|| {
-                tokio::runtime::Handle::current().block_on(async {
-                    let mut bridge = db_bridge.write().await;
-                    bridge.call("run_migrations", serde_json::json!({})).await
-                })
+            let result = block_on_db(&mut caller, async {
+                let mut bridge = db_bridge.write().await;
+                bridge.call("run_migrations", serde_json::json!({})).await
             });
 
             match result {
@@ -1001,6 +1546,61 @@ pub fn register_functions<S: WasmStateCore>(linker: &mut Linker<S>) -> BridgeRes
         },
     )?;
 
+    // =========================================
+    // FILE MIGRATIONS — _db_migrate
+    // =========================================
+
+    // _db_migrate - Apply the pending numbered `.sql` files in a directory.
+    // Args: dir_ptr, dir_len (relative to the working directory, which it
+    //       must stay inside)
+    // Returns: pointer to JSON `{"ok":true,"data":{"applied":["001_x.sql",...]}}`
+    linker.func_wrap(
+        "env",
+        "_db_migrate",
+        |mut caller: Caller<'_, S>, dir_ptr: i32, dir_len: i32| -> i32 {
+            let dir = match read_raw_string(&mut caller, dir_ptr, dir_len) {
+                Some(s) => s,
+                None => {
+                    error!("_db_migrate: Failed to read directory string");
+                    return write_string_to_caller(
+                        &mut caller,
+                        r#"{"ok":false,"err":{"code":"MEMORY_ERROR","message":"Failed to read directory"}}"#,
+                    );
+                }
+            };
+
+            let db_bridge = match caller.data().db_bridge() {
+                Some(db) => db,
+                None => {
+                    return write_string_to_caller(
+                        &mut caller,
+                        r#"{"ok":false,"err":{"code":"NO_DB","message":"No database configured"}}"#,
+                    );
+                }
+            };
+
+            let result = block_on_db(&mut caller, async {
+                let mut bridge = db_bridge.write().await;
+                bridge.call("migrate", json!({ "dir": dir })).await
+            });
+
+            let result_str = match result {
+                Ok(v) => {
+                    if !v.get("ok").and_then(|o| o.as_bool()).unwrap_or(false) {
+                        error!("_db_migrate: Failed: {:?}", v.get("err"));
+                    }
+                    v.to_string()
+                }
+                Err(e) => {
+                    error!("_db_migrate: Error: {}", e);
+                    json!({ "ok": false, "err": { "code": "DB_ERROR", "message": e.to_string() } })
+                        .to_string()
+                }
+            };
+            write_string_to_caller(&mut caller, &result_str)
+        },
+    )?;
+
     // =========================================
     // FIELD VALIDATION — _db_valid_field
     // =========================================
@@ -1042,16 +1642,14 @@ pub fn register_functions<S: WasmStateCore>(linker: &mut Linker<S>) -> BridgeRes
                 }
             };
 
-            let result = tokio::task::block_in_place(|| {
-                tokio::runtime::Handle::current().block_on(async {
-                    let mut bridge = db_bridge.write().await;
-                    bridge
-                        .call(
-                            "valid_field",
-                            serde_json::json!({"table": table, "field": field}),
-                        )
-                        .await
-                })
+            let result = block_on_db(&mut caller, async {
+                let mut bridge = db_bridge.write().await;
+                bridge
+                    .call(
+                        "valid_field",
+                        serde_json::json!({"table": table, "field": field}),
+                    )
+                    .await
             });
 
             match result {
@@ -1115,11 +1713,9 @@ pub fn register_functions<S: WasmStateCore>(linker: &mut Linker<S>) -> BridgeRes
                 || sql_upper.starts_with("DROP") || sql_upper.starts_with("ALTER")
                 || sql_upper.starts_with("TRUNCATE") || sql_upper.starts_with("REPLACE")
             { "execute" } else { "query" };
-            let result = tokio::task::block_in_place(|| {
-                tokio::runtime::Handle::current().block_on(async {
-                    let mut bridge = db_bridge.write().await;
-                    bridge.call(method, json!({"sql": sql, "params": params})).await
-                })
+            let result = block_on_db(&mut caller, async {
+                let mut bridge = db_bridge.write().await;
+                bridge.call(method, json!({"sql": sql, "params": params})).await
             });
             let s = match result {
                 Ok(v) => v.to_string(),
@@ -1158,13 +1754,11 @@ pub fn register_functions<S: WasmStateCore>(linker: &mut Linker<S>) -> BridgeRes
             };
             let params: Vec<serde_json::Value> =
                 serde_json::from_str(&params_json).unwrap_or_default();
-            let result = tokio::task::block_in_place(|| {
-                tokio::runtime::Handle::current().block_on(async {
-                    let mut bridge = db_bridge.write().await;
-                    bridge
-                        .call("execute", json!({"sql": sql, "params": params}))
-                        .await
-                })
+            let result = block_on_db(&mut caller, async {
+                let mut bridge = db_bridge.write().await;
+                bridge
+                    .call("execute", json!({"sql": sql, "params": params}))
+                    .await
             });
             let affected: i32 = match result {
                 Ok(v) => v
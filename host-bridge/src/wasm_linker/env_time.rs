@@ -3,13 +3,21 @@
 //! Provides environment and time operations for WASM modules:
 //! - _env_get: Get environment variable value
 //! - _time_now: Get current Unix timestamp in seconds
+//! - time_parse / time_format / time_now_local: strftime-style parsing and
+//!   formatting
+//!
+//! Time functions read the current time from `WasmStateCore::now`, so a host
+//! can pin it for deterministic tests, and format in
+//! `WasmStateCore::default_timezone` unless the call names a zone.
 //!
 //! All functions are generic over `WasmStateCore` to work with any runtime.
 
 use super::helpers::{read_raw_string, write_string_to_caller};
 use super::state::WasmStateCore;
 use crate::error::BridgeResult;
-use chrono::{DateTime, Datelike, Local, TimeZone, Timelike, Utc};
+use crate::time::TimeBridge;
+use chrono::{DateTime, Datelike, Local, NaiveDate, TimeZone, Timelike, Utc};
+use chrono_tz::Tz;
 use serde_json::json;
 use std::time::{SystemTime, UNIX_EPOCH};
 use tracing::{debug, error};
@@ -29,6 +37,55 @@ fn is_sensitive_env_name(name: &str) -> bool {
     SENSITIVE_SUBSTRINGS.iter().any(|p| upper.contains(p))
 }
 
+fn epoch_secs(t: SystemTime) -> i64 {
+    t.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+fn epoch_millis(t: SystemTime) -> i64 {
+    t.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+/// Epoch millis of `input` read with `format`: a strftime pattern or one of
+/// RFC3339, ISO8601 and RFC2822. Without an offset in the pattern the time
+/// is taken as UTC, and a date-only pattern means midnight.
+fn parse_with_format(format: &str, input: &str) -> Option<i64> {
+    // Patterns with an offset first: parsing as a naive time ignores it.
+    if let Ok(dt) = DateTime::parse_from_str(input, format) {
+        return Some(dt.timestamp_millis());
+    }
+    if let Ok(millis) = TimeBridge::parse_datetime(input, format, "UTC") {
+        return Some(millis);
+    }
+    let date = NaiveDate::parse_from_str(input, format).ok()?;
+    Some(
+        Utc.from_utc_datetime(&date.and_hms_opt(0, 0, 0)?)
+            .timestamp_millis(),
+    )
+}
+
+/// `epoch_ms` formatted with `format` (as for `parse_with_format`) in
+/// `zone`: empty for `default`, "UTC" or "Z" for UTC, an IANA name such as
+/// "America/New_York", or a fixed "+HHMM", "+HH:MM" or "+HH" (or with "-")
+/// offset.
+fn format_in_zone(format: &str, epoch_ms: i64, zone: &str, default: Tz) -> Option<String> {
+    let dt = Utc.timestamp_millis_opt(epoch_ms).single()?;
+    let local = match zone.trim() {
+        "" => dt.with_timezone(&default).fixed_offset(),
+        "Z" | "z" => dt.fixed_offset(),
+        z if z.eq_ignore_ascii_case("UTC") => dt.fixed_offset(),
+        z if z.starts_with(['+', '-']) => {
+            let offset = TimeBridge::parse_timezone_offset(&z.replace(':', "")).ok()?;
+            dt.with_timezone(&offset)
+        }
+        z => dt.with_timezone(&z.parse::<Tz>().ok()?).fixed_offset(),
+    };
+    TimeBridge::format_datetime(&local, format).ok()
+}
+
 /// Register environment and time functions with the linker
 pub fn register_functions<S: WasmStateCore>(linker: &mut Linker<S>) -> BridgeResult<()> {
     // =========================================
@@ -89,11 +146,8 @@ pub fn register_functions<S: WasmStateCore>(linker: &mut Linker<S>) -> BridgeRes
     // _time_now - Get current Unix timestamp in seconds
     // Args: none
     // Returns: i64 timestamp (seconds since epoch)
-    linker.func_wrap("env", "_time_now", |_caller: Caller<'_, S>| -> i64 {
-        SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .map(|d| d.as_secs() as i64)
-            .unwrap_or(0)
+    linker.func_wrap("env", "_time_now", |caller: Caller<'_, S>| -> i64 {
+        epoch_secs(caller.data().now())
     })?;
 
     // =========================================
@@ -157,24 +211,19 @@ pub fn register_functions<S: WasmStateCore>(linker: &mut Linker<S>) -> BridgeRes
     // =========================================
 
     // _time_epoch_ms() -> i64
-    linker.func_wrap("env", "_time_epoch_ms", |_: Caller<'_, S>| -> i64 {
-        SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .map(|d| d.as_millis() as i64)
-            .unwrap_or(0)
+    linker.func_wrap("env", "_time_epoch_ms", |caller: Caller<'_, S>| -> i64 {
+        epoch_millis(caller.data().now())
     })?;
 
     // _time_epoch_sec() -> i64
-    linker.func_wrap("env", "_time_epoch_sec", |_: Caller<'_, S>| -> i64 {
-        SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .map(|d| d.as_secs() as i64)
-            .unwrap_or(0)
+    linker.func_wrap("env", "_time_epoch_sec", |caller: Caller<'_, S>| -> i64 {
+        epoch_secs(caller.data().now())
     })?;
 
     // _time_iso() -> ptr — current time as ISO 8601 UTC
     linker.func_wrap("env", "_time_iso", |mut caller: Caller<'_, S>| -> i32 {
-        let s = Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+        let now = DateTime::<Utc>::from(caller.data().now());
+        let s = now.to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
         write_string_to_caller(&mut caller, &s)
     })?;
 
@@ -271,34 +320,35 @@ pub fn register_functions<S: WasmStateCore>(linker: &mut Linker<S>) -> BridgeRes
     )?;
 
     // _time_timezone_offset() -> i32 minutes (positive = west of UTC, matches JS Date#getTimezoneOffset)
-    linker.func_wrap("env", "_time_timezone_offset", |_: Caller<'_, S>| -> i32 {
-        let local_off_secs = Local::now().offset().local_minus_utc();
-        -(local_off_secs / 60)
-    })?;
+    linker.func_wrap(
+        "env",
+        "_time_timezone_offset",
+        |caller: Caller<'_, S>| -> i32 {
+            let now = DateTime::<Utc>::from(caller.data().now());
+            let local_off_secs = now.with_timezone(&Local).offset().local_minus_utc();
+            -(local_off_secs / 60)
+        },
+    )?;
 
     // _time_is_past(epoch_ms) -> boolean
-    linker.func_wrap("env", "_time_is_past", |_: Caller<'_, S>, e: i64| -> i32 {
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .map(|d| d.as_millis() as i64)
-            .unwrap_or(0);
-        if e < now {
-            1
-        } else {
-            0
-        }
-    })?;
+    linker.func_wrap(
+        "env",
+        "_time_is_past",
+        |caller: Caller<'_, S>, e: i64| -> i32 {
+            if e < epoch_millis(caller.data().now()) {
+                1
+            } else {
+                0
+            }
+        },
+    )?;
 
     // _time_is_future(epoch_ms) -> boolean
     linker.func_wrap(
         "env",
         "_time_is_future",
-        |_: Caller<'_, S>, e: i64| -> i32 {
-            let now = SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .map(|d| d.as_millis() as i64)
-                .unwrap_or(0);
-            if e > now {
+        |caller: Caller<'_, S>, e: i64| -> i32 {
+            if e > epoch_millis(caller.data().now()) {
                 1
             } else {
                 0
@@ -306,6 +356,62 @@ pub fn register_functions<S: WasmStateCore>(linker: &mut Linker<S>) -> BridgeRes
         },
     )?;
 
+    // time_parse(format, input) -> i64 epoch_ms (-1 on invalid)
+    linker.func_wrap(
+        "env",
+        "time_parse",
+        |mut caller: Caller<'_, S>, fp: i32, fl: i32, ip: i32, il: i32| -> i64 {
+            let format = read_raw_string(&mut caller, fp, fl).unwrap_or_default();
+            let input = read_raw_string(&mut caller, ip, il).unwrap_or_default();
+            parse_with_format(&format, &input).unwrap_or_else(|| {
+                debug!("time_parse: {:?} does not match format {:?}", input, format);
+                -1
+            })
+        },
+    )?;
+
+    // time_format(format, epoch_ms, zone) -> ptr (empty on invalid format
+    // or zone; an empty zone formats in the host's default timezone)
+    linker.func_wrap(
+        "env",
+        "time_format",
+        |mut caller: Caller<'_, S>, fp: i32, fl: i32, epoch_ms: i64, zp: i32, zl: i32| -> i32 {
+            let format = read_raw_string(&mut caller, fp, fl).unwrap_or_default();
+            let zone = read_raw_string(&mut caller, zp, zl).unwrap_or_default();
+            let default = caller.data().default_timezone();
+            let formatted =
+                format_in_zone(&format, epoch_ms, &zone, default).unwrap_or_else(|| {
+                    debug!(
+                        "time_format: cannot format {} with {:?} in zone {:?}",
+                        epoch_ms, format, zone
+                    );
+                    String::new()
+                });
+            write_string_to_caller(&mut caller, &formatted)
+        },
+    )?;
+
+    // time_now_local(format, zone) -> ptr: the current time as time_format
+    // would render it
+    linker.func_wrap(
+        "env",
+        "time_now_local",
+        |mut caller: Caller<'_, S>, fp: i32, fl: i32, zp: i32, zl: i32| -> i32 {
+            let format = read_raw_string(&mut caller, fp, fl).unwrap_or_default();
+            let zone = read_raw_string(&mut caller, zp, zl).unwrap_or_default();
+            let now = epoch_millis(caller.data().now());
+            let default = caller.data().default_timezone();
+            let formatted = format_in_zone(&format, now, &zone, default).unwrap_or_else(|| {
+                debug!(
+                    "time_now_local: cannot format with {:?} in zone {:?}",
+                    format, zone
+                );
+                String::new()
+            });
+            write_string_to_caller(&mut caller, &formatted)
+        },
+    )?;
+
     // _time_sleep(ms) -> void  (sync sleep per spec; docs note divergence in HOST_BRIDGE.md)
     linker.func_wrap("env", "_time_sleep", |_: Caller<'_, S>, ms: i32| {
         let ms = ms.max(0) as u64;
@@ -314,3 +420,208 @@ pub fn register_functions<S: WasmStateCore>(linker: &mut Linker<S>) -> BridgeRes
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::wasm_linker::{create_linker, WasmState};
+    use wasmtime::{Engine, Instance, Module, Store};
+
+    // `parse` and `format` forward to the imports, with pointers to strings
+    // the test wrote into memory.
+    const WAT: &str = r#"
+        (module
+          (import "env" "time_parse" (func $parse (param i32 i32 i32 i32) (result i64)))
+          (import "env" "time_format" (func $format (param i32 i32 i64 i32 i32) (result i32)))
+          (import "env" "time_now_local" (func $now_local (param i32 i32 i32 i32) (result i32)))
+          (memory (export "memory") 2)
+          (global $heap (mut i32) (i32.const 65536))
+          (global (export "__heap_ptr") (mut i32) (i32.const 65536))
+          (func (export "malloc") (param $size i32) (result i32)
+            (local $ptr i32)
+            (local.set $ptr (global.get $heap))
+            (global.set $heap
+              (i32.and
+                (i32.add (i32.add (global.get $heap) (local.get $size)) (i32.const 7))
+                (i32.const -8)))
+            (global.set 1 (global.get $heap))
+            (local.get $ptr))
+          (func (export "parse") (param i32 i32 i32 i32) (result i64)
+            (call $parse (local.get 0) (local.get 1) (local.get 2) (local.get 3)))
+          (func (export "format") (param i32 i32 i64 i32 i32) (result i32)
+            (call $format
+              (local.get 0) (local.get 1) (local.get 2) (local.get 3) (local.get 4)))
+          (func (export "now_local") (param i32 i32 i32 i32) (result i32)
+            (call $now_local (local.get 0) (local.get 1) (local.get 2) (local.get 3))))
+    "#;
+
+    struct Fixture {
+        store: Store<WasmState>,
+        instance: Instance,
+    }
+
+    impl Fixture {
+        fn new() -> Self {
+            Self::with_state(WasmState::default())
+        }
+
+        fn with_state(state: WasmState) -> Self {
+            let engine = Engine::default();
+            let linker = create_linker(&engine).expect("create linker");
+            let module = Module::new(&engine, WAT).expect("compile test module");
+            let mut store = Store::new(&engine, state);
+            let instance = linker
+                .instantiate(&mut store, &module)
+                .expect("instantiate");
+            Self { store, instance }
+        }
+
+        /// Write `s` at `offset` and return its (ptr, len).
+        fn put(&mut self, offset: usize, s: &str) -> (i32, i32) {
+            let memory = self.instance.get_memory(&mut self.store, "memory").unwrap();
+            memory.write(&mut self.store, offset, s.as_bytes()).unwrap();
+            (offset as i32, s.len() as i32)
+        }
+
+        fn parse(&mut self, format: &str, input: &str) -> i64 {
+            let (fp, fl) = self.put(1024, format);
+            let (ip, il) = self.put(2048, input);
+            self.instance
+                .get_typed_func::<(i32, i32, i32, i32), i64>(&mut self.store, "parse")
+                .unwrap()
+                .call(&mut self.store, (fp, fl, ip, il))
+                .unwrap()
+        }
+
+        fn format(&mut self, format: &str, epoch_ms: i64, offset: &str) -> String {
+            let (fp, fl) = self.put(1024, format);
+            let (op, ol) = self.put(2048, offset);
+            let ptr = self
+                .instance
+                .get_typed_func::<(i32, i32, i64, i32, i32), i32>(&mut self.store, "format")
+                .unwrap()
+                .call(&mut self.store, (fp, fl, epoch_ms, op, ol))
+                .unwrap();
+            self.read(ptr)
+        }
+
+        fn now_local(&mut self, format: &str, zone: &str) -> String {
+            let (fp, fl) = self.put(1024, format);
+            let (zp, zl) = self.put(2048, zone);
+            let ptr = self
+                .instance
+                .get_typed_func::<(i32, i32, i32, i32), i32>(&mut self.store, "now_local")
+                .unwrap()
+                .call(&mut self.store, (fp, fl, zp, zl))
+                .unwrap();
+            self.read(ptr)
+        }
+
+        /// Read the length-prefixed string at `ptr`.
+        fn read(&mut self, ptr: i32) -> String {
+            let ptr = ptr as usize;
+            let memory = self.instance.get_memory(&mut self.store, "memory").unwrap();
+            let data = memory.data(&self.store);
+            let len = u32::from_le_bytes(data[ptr..ptr + 4].try_into().unwrap()) as usize;
+            String::from_utf8(data[ptr + 4..ptr + 4 + len].to_vec()).unwrap()
+        }
+    }
+
+    #[test]
+    fn time_parse_reads_rfc3339_and_strftime_patterns() {
+        let mut fx = Fixture::new();
+        assert_eq!(
+            fx.parse("RFC3339", "2024-03-01T12:30:00.250+02:00"),
+            1_709_289_000_250
+        );
+        assert_eq!(
+            fx.parse("%Y-%m-%d %H:%M %z", "2024-03-01 12:30 +0200"),
+            1_709_289_000_000
+        );
+        assert_eq!(fx.parse("%Y-%m-%d", "2024-03-01"), 1_709_251_200_000);
+        assert_eq!(fx.parse("%Y-%m-%d", "March 1st"), -1);
+        assert_eq!(fx.parse("RFC3339", "2024-03-01"), -1);
+    }
+
+    #[test]
+    fn time_format_renders_a_known_epoch_in_utc_or_at_an_offset() {
+        let mut fx = Fixture::new();
+        let epoch_ms = 1_700_000_000_000;
+        assert_eq!(
+            fx.format("%Y-%m-%d %H:%M:%S", epoch_ms, ""),
+            "2023-11-14 22:13:20"
+        );
+        assert_eq!(
+            fx.format("RFC3339", epoch_ms, "UTC"),
+            "2023-11-14T22:13:20+00:00"
+        );
+        assert_eq!(
+            fx.format("%Y-%m-%d %H:%M %z", epoch_ms, "+05:30"),
+            "2023-11-15 03:43 +0530"
+        );
+        assert_eq!(fx.format("%Y-%", epoch_ms, ""), "");
+        assert_eq!(fx.format("%Y", epoch_ms, "+5 hours"), "");
+    }
+
+    #[test]
+    fn time_format_in_a_named_zone_applies_its_offset_across_dst() {
+        let mut fx = Fixture::new();
+        let pattern = "%Y-%m-%d %H:%M:%S %z";
+        // Winter: New York is five hours behind UTC
+        assert_eq!(
+            fx.format(pattern, 1_700_000_000_000, "UTC"),
+            "2023-11-14 22:13:20 +0000"
+        );
+        assert_eq!(
+            fx.format(pattern, 1_700_000_000_000, "America/New_York"),
+            "2023-11-14 17:13:20 -0500"
+        );
+        // Clocks jump from 02:00 EST to 03:00 EDT at 07:00 UTC on 2024-03-10
+        assert_eq!(
+            fx.format(pattern, 1_710_053_999_000, "America/New_York"),
+            "2024-03-10 01:59:59 -0500"
+        );
+        assert_eq!(
+            fx.format(pattern, 1_710_054_000_000, "America/New_York"),
+            "2024-03-10 03:00:00 -0400"
+        );
+        // and fall back from 02:00 EDT to 01:00 EST at 06:00 UTC on 2024-11-03
+        assert_eq!(
+            fx.format(pattern, 1_730_613_599_000, "America/New_York"),
+            "2024-11-03 01:59:59 -0400"
+        );
+        assert_eq!(
+            fx.format(pattern, 1_730_613_600_000, "America/New_York"),
+            "2024-11-03 01:00:00 -0500"
+        );
+        assert_eq!(fx.format(pattern, 1_700_000_000_000, "Mars/Olympus"), "");
+    }
+
+    #[test]
+    fn default_timezone_applies_when_the_call_names_no_zone() {
+        let mut fx = Fixture::with_state(WasmState {
+            default_timezone: chrono_tz::America::New_York,
+            ..WasmState::default()
+        });
+        let pattern = "%H:%M %z";
+        assert_eq!(fx.format(pattern, 1_700_000_000_000, ""), "17:13 -0500");
+        assert_eq!(fx.format(pattern, 1_700_000_000_000, "UTC"), "22:13 +0000");
+        assert_eq!(
+            fx.format(pattern, 1_700_000_000_000, "Asia/Tokyo"),
+            "07:13 +0900"
+        );
+        assert_eq!(
+            fx.format(pattern, 1_700_000_000_000, "+01:00"),
+            "23:13 +0100"
+        );
+
+        // Kolkata has no DST, so its offset does not depend on today's date
+        assert_eq!(fx.now_local("%z", "Asia/Kolkata"), "+0530");
+        assert_eq!(fx.now_local("%z", "Z"), "+0000");
+        let default_offset = fx.now_local("%z", "");
+        assert!(
+            ["-0500", "-0400"].contains(&default_offset.as_str()),
+            "{}",
+            default_offset
+        );
+    }
+}
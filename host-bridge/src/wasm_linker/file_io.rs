@@ -678,6 +678,11 @@ pub fn register_functions<S: WasmStateCore>(linker: &mut Linker<S>) -> BridgeRes
                 target.display(),
                 payload.len()
             );
+            caller.data().audit(
+                "fs",
+                "write_bytes",
+                &serde_json::json!({ "path": path, "bytes": payload.len() }),
+            );
 
             match atomic_write_bytes(&target, &payload) {
                 Ok(()) => FS_WRITE_OK,
@@ -806,6 +811,9 @@ pub fn register_functions<S: WasmStateCore>(linker: &mut Linker<S>) -> BridgeRes
                     return 0;
                 }
             };
+            caller
+                .data()
+                .audit("fs", "delete", &serde_json::json!({ "path": path }));
 
             match fs::remove_file(&target) {
                 Ok(()) => {
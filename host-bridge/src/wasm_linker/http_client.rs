@@ -3,18 +3,23 @@
 //! Provides HTTP client operations for WASM modules:
 //! - http_get, http_post, http_put, http_patch, http_delete
 //! - Various helper functions for headers, JSON, forms, etc.
+//! - http_set_circuit_threshold, http_set_circuit_cooldown: tune the
+//!   per-host circuit breaker of this thread's `HttpBridge`
 //!
 //! All functions are generic over `WasmStateCore` to work with any runtime.
 
 use super::helpers::{read_raw_string, write_string_to_caller};
 use super::state::WasmStateCore;
 use crate::error::BridgeResult;
-use crate::HttpBridge;
+use crate::{HttpBridge, DEFAULT_CIRCUIT_COOLDOWN, DEFAULT_CIRCUIT_THRESHOLD};
 use serde_json::json;
 use std::cell::RefCell;
+use std::net::{IpAddr, SocketAddr};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::RwLock;
 use tracing::{debug, error};
+use url::Url;
 use wasmtime::{Caller, Linker};
 
 /// Per-thread HTTP client configuration set by http_set_* functions
@@ -114,6 +119,145 @@ fn store_last_response(result: &serde_json::Value) {
     });
 }
 
+/// Apply new circuit breaker settings to this thread's HTTP bridge. Requests
+/// only hold the bridge lock inside `send_request`, so it is free here.
+fn configure_circuit_breaker(update: impl FnOnce(u32, Duration) -> (u32, Duration)) {
+    HTTP_BRIDGE.with(|bridge| match bridge.try_write() {
+        Ok(mut bridge) => {
+            let (threshold, cooldown) =
+                update(bridge.circuit_threshold(), bridge.circuit_cooldown());
+            bridge.set_circuit_breaker(threshold, cooldown);
+        }
+        Err(_) => error!("HTTP bridge busy; circuit breaker settings not applied"),
+    });
+}
+
+/// Header carrying `WasmStateCore::reentry_token` on outbound requests to
+/// `WasmStateCore::server_addr`, so the server can refuse requests its own
+/// handlers make to it
+pub const REENTRY_HEADER: &str = "x-clean-reentry";
+
+/// Whether a request to `url` is delivered to `server`: the proxy the
+/// environment routes it through, or else its own host, resolves to that
+/// address. A server bound to the unspecified address matches targets on
+/// its port that resolve to loopback or to one of the host's interface
+/// addresses.
+///
+/// Only targets on the server's port are resolved, so requests to other
+/// services never wait on DNS here.
+async fn targets_server(url: &str, server: SocketAddr) -> bool {
+    let Ok(url) = Url::parse(url) else {
+        return false;
+    };
+    let target = env_proxy(&url, |name| std::env::var(name).ok()).unwrap_or(url);
+    let (Some(host), Some(port)) = (target.host_str(), target.port_or_known_default()) else {
+        return false;
+    };
+    if port != server.port() {
+        return false;
+    }
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let Ok(mut addrs) = tokio::net::lookup_host((host, port)).await else {
+        return false;
+    };
+    let local: Vec<IpAddr> = if server.ip().is_unspecified() {
+        if_addrs::get_if_addrs()
+            .map(|interfaces| interfaces.iter().map(|i| i.ip()).collect())
+            .unwrap_or_default()
+    } else {
+        Vec::new()
+    };
+    addrs.any(|addr| {
+        addr.ip() == server.ip()
+            || (server.ip().is_unspecified()
+                && (addr.ip().is_loopback() || local.contains(&addr.ip())))
+    })
+}
+
+/// The proxy `http_proxy` / `https_proxy` / `all_proxy` (either case) route
+/// `url` through, unless `no_proxy` exempts its host, mirroring reqwest's
+/// system proxy settings. `var` reads an environment variable.
+fn env_proxy(url: &Url, var: impl Fn(&str) -> Option<String>) -> Option<Url> {
+    let lookup = |name: &str| {
+        var(name)
+            .or_else(|| var(&name.to_ascii_uppercase()))
+            .filter(|value| !value.is_empty())
+    };
+    let host = url.host_str()?;
+    if let Some(no_proxy) = lookup("no_proxy") {
+        let exempt = no_proxy.split(',').map(str::trim).any(|entry| {
+            let domain = entry.trim_start_matches('.');
+            entry == "*"
+                || (!domain.is_empty()
+                    && (host == domain || host.ends_with(&format!(".{}", domain))))
+        });
+        if exempt {
+            return None;
+        }
+    }
+    let proxy = lookup(&format!("{}_proxy", url.scheme())).or_else(|| lookup("all_proxy"))?;
+    Url::parse(&proxy)
+        .ok()
+        .filter(|proxy| proxy.has_host())
+        .or_else(|| Url::parse(&format!("http://{}", proxy)).ok())
+}
+
+/// Send `params` through this thread's HTTP bridge, blocking the calling
+/// WASM thread until the response arrives.
+///
+/// Blocking is only safe on a multi-threaded runtime, where
+/// `block_in_place` hands this worker's other tasks to another thread; on a
+/// current-thread runtime the request fails instead of deadlocking.
+///
+/// That replacement thread comes from the runtime's blocking pool, so every
+/// request in flight here holds a blocking thread until it returns. With
+/// the pool at its `max_blocking_threads` limit, replacements queue and the
+/// runtime is left with fewer workers for everything else; the server sizes
+/// the pool with `--max-blocking-threads`.
+///
+/// Outside a Tokio runtime (a plain thread, a test) there is nothing to
+/// block on, so the request fails with an error rather than panicking. The
+/// last response is cleared first, so after a failed request
+/// `http_get_response_code` reports 0 instead of an earlier status.
+///
+/// The `REENTRY_HEADER` token is only added to requests that reach the
+/// server's own address, so it never leaks to third-party hosts.
+fn send_request<S: WasmStateCore>(
+    caller: &Caller<'_, S>,
+    mut params: serde_json::Value,
+) -> anyhow::Result<serde_json::Value> {
+    HTTP_LAST_RESPONSE.with(|last| *last.borrow_mut() = HttpLastResponse::default());
+    let handle = tokio::runtime::Handle::try_current()
+        .map_err(|_| anyhow::anyhow!("HTTP requests need a Tokio runtime"))?;
+    if handle.runtime_flavor() != tokio::runtime::RuntimeFlavor::MultiThread {
+        anyhow::bail!("HTTP requests from WASM need a multi-threaded Tokio runtime");
+    }
+    let reentry = caller
+        .data()
+        .reentry_token()
+        .zip(caller.data().server_addr())
+        .map(|(token, server)| (token.to_string(), server));
+    HTTP_BRIDGE.with(|bridge| {
+        let bridge = bridge.clone();
+        tokio::task::block_in_place(|| {
+            handle.block_on(async {
+                if let Some((token, server)) = reentry {
+                    let url = params.get("url").and_then(|u| u.as_str()).unwrap_or("");
+                    if targets_server(url, server).await {
+                        if params.get("headers").is_none_or(|h| h.is_null()) {
+                            params["headers"] = json!({});
+                        }
+                        if let Some(headers) = params["headers"].as_object_mut() {
+                            headers.insert(REENTRY_HEADER.to_string(), json!(token));
+                        }
+                    }
+                }
+                bridge.write().await.call("request", params).await
+            })
+        })
+    })
+}
+
 /// Register all HTTP client functions with the linker
 pub fn register_functions<S: WasmStateCore>(linker: &mut Linker<S>) -> BridgeResult<()> {
     // =========================================
@@ -138,15 +282,7 @@ pub fn register_functions<S: WasmStateCore>(linker: &mut Linker<S>) -> BridgeRes
             let max_redirects = get_max_redirects();
             let headers = build_request_headers(None);
 
-            let result = HTTP_BRIDGE.with(|bridge| {
-                let bridge = bridge.clone();
-                tokio::task::block_in_place(|| {
-                    tokio::runtime::Handle::current().block_on(async {
-                        let mut b = bridge.write().await;
-                        b.call("request", json!({ "method": "GET", "url": url, "headers": headers, "timeout": timeout, "max_redirects": max_redirects })).await
-                    })
-                })
-            });
+            let result = send_request(&caller, json!({ "method": "GET", "url": url, "headers": headers, "timeout": timeout, "max_redirects": max_redirects }));
 
             match result {
                 Ok(v) => {
@@ -191,15 +327,7 @@ pub fn register_functions<S: WasmStateCore>(linker: &mut Linker<S>) -> BridgeRes
             let max_redirects = get_max_redirects();
             let headers = build_request_headers(None);
 
-            let result = HTTP_BRIDGE.with(|bridge| {
-                let bridge = bridge.clone();
-                tokio::task::block_in_place(|| {
-                    tokio::runtime::Handle::current().block_on(async {
-                        let mut b = bridge.write().await;
-                        b.call("request", json!({ "method": "POST", "url": url, "body": body, "headers": headers, "timeout": timeout, "max_redirects": max_redirects })).await
-                    })
-                })
-            });
+            let result = send_request(&caller, json!({ "method": "POST", "url": url, "body": body, "headers": headers, "timeout": timeout, "max_redirects": max_redirects }));
 
             match result {
                 Ok(v) => {
@@ -244,15 +372,7 @@ pub fn register_functions<S: WasmStateCore>(linker: &mut Linker<S>) -> BridgeRes
             let max_redirects = get_max_redirects();
             let headers = build_request_headers(None);
 
-            let result = HTTP_BRIDGE.with(|bridge| {
-                let bridge = bridge.clone();
-                tokio::task::block_in_place(|| {
-                    tokio::runtime::Handle::current().block_on(async {
-                        let mut b = bridge.write().await;
-                        b.call("request", json!({ "method": "PUT", "url": url, "body": body, "headers": headers, "timeout": timeout, "max_redirects": max_redirects })).await
-                    })
-                })
-            });
+            let result = send_request(&caller, json!({ "method": "PUT", "url": url, "body": body, "headers": headers, "timeout": timeout, "max_redirects": max_redirects }));
 
             match result {
                 Ok(v) => {
@@ -297,15 +417,7 @@ pub fn register_functions<S: WasmStateCore>(linker: &mut Linker<S>) -> BridgeRes
             let max_redirects = get_max_redirects();
             let headers = build_request_headers(None);
 
-            let result = HTTP_BRIDGE.with(|bridge| {
-                let bridge = bridge.clone();
-                tokio::task::block_in_place(|| {
-                    tokio::runtime::Handle::current().block_on(async {
-                        let mut b = bridge.write().await;
-                        b.call("request", json!({ "method": "PATCH", "url": url, "body": body, "headers": headers, "timeout": timeout, "max_redirects": max_redirects })).await
-                    })
-                })
-            });
+            let result = send_request(&caller, json!({ "method": "PATCH", "url": url, "body": body, "headers": headers, "timeout": timeout, "max_redirects": max_redirects }));
 
             match result {
                 Ok(v) => {
@@ -343,15 +455,7 @@ pub fn register_functions<S: WasmStateCore>(linker: &mut Linker<S>) -> BridgeRes
             let max_redirects = get_max_redirects();
             let headers = build_request_headers(None);
 
-            let result = HTTP_BRIDGE.with(|bridge| {
-                let bridge = bridge.clone();
-                tokio::task::block_in_place(|| {
-                    tokio::runtime::Handle::current().block_on(async {
-                        let mut b = bridge.write().await;
-                        b.call("request", json!({ "method": "DELETE", "url": url, "headers": headers, "timeout": timeout, "max_redirects": max_redirects })).await
-                    })
-                })
-            });
+            let result = send_request(&caller, json!({ "method": "DELETE", "url": url, "headers": headers, "timeout": timeout, "max_redirects": max_redirects }));
 
             match result {
                 Ok(v) => {
@@ -389,15 +493,7 @@ pub fn register_functions<S: WasmStateCore>(linker: &mut Linker<S>) -> BridgeRes
             let max_redirects = get_max_redirects();
             let req_headers = build_request_headers(None);
 
-            let result = HTTP_BRIDGE.with(|bridge| {
-                let bridge = bridge.clone();
-                tokio::task::block_in_place(|| {
-                    tokio::runtime::Handle::current().block_on(async {
-                        let mut b = bridge.write().await;
-                        b.call("request", json!({ "method": "HEAD", "url": url, "headers": req_headers, "timeout": timeout, "max_redirects": max_redirects })).await
-                    })
-                })
-            });
+            let result = send_request(&caller, json!({ "method": "HEAD", "url": url, "headers": req_headers, "timeout": timeout, "max_redirects": max_redirects }));
 
             match result {
                 Ok(v) => {
@@ -436,15 +532,7 @@ pub fn register_functions<S: WasmStateCore>(linker: &mut Linker<S>) -> BridgeRes
             let max_redirects = get_max_redirects();
             let req_headers = build_request_headers(None);
 
-            let result = HTTP_BRIDGE.with(|bridge| {
-                let bridge = bridge.clone();
-                tokio::task::block_in_place(|| {
-                    tokio::runtime::Handle::current().block_on(async {
-                        let mut b = bridge.write().await;
-                        b.call("request", json!({ "method": "OPTIONS", "url": url, "headers": req_headers, "timeout": timeout, "max_redirects": max_redirects })).await
-                    })
-                })
-            });
+            let result = send_request(&caller, json!({ "method": "OPTIONS", "url": url, "headers": req_headers, "timeout": timeout, "max_redirects": max_redirects }));
 
             match result {
                 Ok(v) => {
@@ -490,26 +578,17 @@ pub fn register_functions<S: WasmStateCore>(linker: &mut Linker<S>) -> BridgeRes
             let headers =
                 build_request_headers(Some(json!({ "Content-Type": "application/json" })));
 
-            let result = HTTP_BRIDGE.with(|bridge| {
-                let bridge = bridge.clone();
-                tokio::task::block_in_place(|| {
-                    tokio::runtime::Handle::current().block_on(async {
-                        let mut b = bridge.write().await;
-                        b.call(
-                            "request",
-                            json!({
-                                "method": "POST",
-                                "url": url,
-                                "body": json_body,
-                                "headers": headers,
-                                "timeout": timeout,
-                                "max_redirects": max_redirects
-                            }),
-                        )
-                        .await
-                    })
-                })
-            });
+            let result = send_request(
+                &caller,
+                json!({
+                    "method": "POST",
+                    "url": url,
+                    "body": json_body,
+                    "headers": headers,
+                    "timeout": timeout,
+                    "max_redirects": max_redirects
+                }),
+            );
 
             match result {
                 Ok(v) => {
@@ -576,6 +655,39 @@ pub fn register_functions<S: WasmStateCore>(linker: &mut Linker<S>) -> BridgeRes
         },
     )?;
 
+    // http_set_circuit_threshold - Consecutive failures (network errors or
+    // 5xx responses) that open a host's circuit; 0 disables the breaker and
+    // a negative value restores the default
+    linker.func_wrap(
+        "env",
+        "http_set_circuit_threshold",
+        |_: Caller<'_, S>, threshold: i32| {
+            let threshold = if threshold < 0 {
+                DEFAULT_CIRCUIT_THRESHOLD
+            } else {
+                threshold as u32
+            };
+            debug!("http_set_circuit_threshold: {}", threshold);
+            configure_circuit_breaker(|_, cooldown| (threshold, cooldown));
+        },
+    )?;
+
+    // http_set_circuit_cooldown - How long an open circuit fails requests
+    // before letting one through; a negative value restores the default
+    linker.func_wrap(
+        "env",
+        "http_set_circuit_cooldown",
+        |_: Caller<'_, S>, cooldown_ms: i32| {
+            let cooldown = if cooldown_ms < 0 {
+                DEFAULT_CIRCUIT_COOLDOWN
+            } else {
+                Duration::from_millis(cooldown_ms as u64)
+            };
+            debug!("http_set_circuit_cooldown: {}ms", cooldown.as_millis());
+            configure_circuit_breaker(|threshold, _| (threshold, cooldown));
+        },
+    )?;
+
     // http_enable_cookies - Store cookies flag in per-thread config
     linker.func_wrap(
         "env",
@@ -757,15 +869,7 @@ pub fn register_functions<S: WasmStateCore>(linker: &mut Linker<S>) -> BridgeRes
             let max_redirects = get_max_redirects();
             let merged_headers = build_request_headers(Some(parsed));
 
-            let result = HTTP_BRIDGE.with(|bridge| {
-                let bridge = bridge.clone();
-                tokio::task::block_in_place(|| {
-                    tokio::runtime::Handle::current().block_on(async {
-                        let mut b = bridge.write().await;
-                        b.call("request", json!({ "method": "GET", "url": url, "headers": merged_headers, "timeout": timeout, "max_redirects": max_redirects })).await
-                    })
-                })
-            });
+            let result = send_request(&caller, json!({ "method": "GET", "url": url, "headers": merged_headers, "timeout": timeout, "max_redirects": max_redirects }));
 
             match result {
                 Ok(v) => {
@@ -809,15 +913,7 @@ pub fn register_functions<S: WasmStateCore>(linker: &mut Linker<S>) -> BridgeRes
             let max_redirects = get_max_redirects();
             let merged_headers = build_request_headers(Some(parsed));
 
-            let result = HTTP_BRIDGE.with(|bridge| {
-                let bridge = bridge.clone();
-                tokio::task::block_in_place(|| {
-                    tokio::runtime::Handle::current().block_on(async {
-                        let mut b = bridge.write().await;
-                        b.call("request", json!({ "method": "POST", "url": url, "body": body, "headers": merged_headers, "timeout": timeout, "max_redirects": max_redirects })).await
-                    })
-                })
-            });
+            let result = send_request(&caller, json!({ "method": "POST", "url": url, "body": body, "headers": merged_headers, "timeout": timeout, "max_redirects": max_redirects }));
 
             match result {
                 Ok(v) => {
@@ -861,15 +957,7 @@ pub fn register_functions<S: WasmStateCore>(linker: &mut Linker<S>) -> BridgeRes
             let max_redirects = get_max_redirects();
             let merged_headers = build_request_headers(Some(parsed));
 
-            let result = HTTP_BRIDGE.with(|bridge| {
-                let bridge = bridge.clone();
-                tokio::task::block_in_place(|| {
-                    tokio::runtime::Handle::current().block_on(async {
-                        let mut b = bridge.write().await;
-                        b.call("request", json!({ "method": "PUT", "url": url, "body": body, "headers": merged_headers, "timeout": timeout, "max_redirects": max_redirects })).await
-                    })
-                })
-            });
+            let result = send_request(&caller, json!({ "method": "PUT", "url": url, "body": body, "headers": merged_headers, "timeout": timeout, "max_redirects": max_redirects }));
 
             match result {
                 Ok(v) => {
@@ -913,15 +1001,7 @@ pub fn register_functions<S: WasmStateCore>(linker: &mut Linker<S>) -> BridgeRes
             let max_redirects = get_max_redirects();
             let merged_headers = build_request_headers(Some(parsed));
 
-            let result = HTTP_BRIDGE.with(|bridge| {
-                let bridge = bridge.clone();
-                tokio::task::block_in_place(|| {
-                    tokio::runtime::Handle::current().block_on(async {
-                        let mut b = bridge.write().await;
-                        b.call("request", json!({ "method": "PATCH", "url": url, "body": body, "headers": merged_headers, "timeout": timeout, "max_redirects": max_redirects })).await
-                    })
-                })
-            });
+            let result = send_request(&caller, json!({ "method": "PATCH", "url": url, "body": body, "headers": merged_headers, "timeout": timeout, "max_redirects": max_redirects }));
 
             match result {
                 Ok(v) => {
@@ -962,15 +1042,7 @@ pub fn register_functions<S: WasmStateCore>(linker: &mut Linker<S>) -> BridgeRes
             let max_redirects = get_max_redirects();
             let merged_headers = build_request_headers(Some(parsed));
 
-            let result = HTTP_BRIDGE.with(|bridge| {
-                let bridge = bridge.clone();
-                tokio::task::block_in_place(|| {
-                    tokio::runtime::Handle::current().block_on(async {
-                        let mut b = bridge.write().await;
-                        b.call("request", json!({ "method": "DELETE", "url": url, "headers": merged_headers, "timeout": timeout, "max_redirects": max_redirects })).await
-                    })
-                })
-            });
+            let result = send_request(&caller, json!({ "method": "DELETE", "url": url, "headers": merged_headers, "timeout": timeout, "max_redirects": max_redirects }));
 
             match result {
                 Ok(v) => {
@@ -1009,26 +1081,17 @@ pub fn register_functions<S: WasmStateCore>(linker: &mut Linker<S>) -> BridgeRes
             let headers =
                 build_request_headers(Some(json!({ "Content-Type": "application/json" })));
 
-            let result = HTTP_BRIDGE.with(|bridge| {
-                let bridge = bridge.clone();
-                tokio::task::block_in_place(|| {
-                    tokio::runtime::Handle::current().block_on(async {
-                        let mut b = bridge.write().await;
-                        b.call(
-                            "request",
-                            json!({
-                                "method": "PUT",
-                                "url": url,
-                                "body": json_body,
-                                "headers": headers,
-                                "timeout": timeout,
-                                "max_redirects": max_redirects
-                            }),
-                        )
-                        .await
-                    })
-                })
-            });
+            let result = send_request(
+                &caller,
+                json!({
+                    "method": "PUT",
+                    "url": url,
+                    "body": json_body,
+                    "headers": headers,
+                    "timeout": timeout,
+                    "max_redirects": max_redirects
+                }),
+            );
 
             match result {
                 Ok(v) => {
@@ -1068,26 +1131,17 @@ pub fn register_functions<S: WasmStateCore>(linker: &mut Linker<S>) -> BridgeRes
             let headers =
                 build_request_headers(Some(json!({ "Content-Type": "application/json" })));
 
-            let result = HTTP_BRIDGE.with(|bridge| {
-                let bridge = bridge.clone();
-                tokio::task::block_in_place(|| {
-                    tokio::runtime::Handle::current().block_on(async {
-                        let mut b = bridge.write().await;
-                        b.call(
-                            "request",
-                            json!({
-                                "method": "PATCH",
-                                "url": url,
-                                "body": json_body,
-                                "headers": headers,
-                                "timeout": timeout,
-                                "max_redirects": max_redirects
-                            }),
-                        )
-                        .await
-                    })
-                })
-            });
+            let result = send_request(
+                &caller,
+                json!({
+                    "method": "PATCH",
+                    "url": url,
+                    "body": json_body,
+                    "headers": headers,
+                    "timeout": timeout,
+                    "max_redirects": max_redirects
+                }),
+            );
 
             match result {
                 Ok(v) => {
@@ -1128,26 +1182,17 @@ pub fn register_functions<S: WasmStateCore>(linker: &mut Linker<S>) -> BridgeRes
                 json!({ "Content-Type": "application/x-www-form-urlencoded" }),
             ));
 
-            let result = HTTP_BRIDGE.with(|bridge| {
-                let bridge = bridge.clone();
-                tokio::task::block_in_place(|| {
-                    tokio::runtime::Handle::current().block_on(async {
-                        let mut b = bridge.write().await;
-                        b.call(
-                            "request",
-                            json!({
-                                "method": "POST",
-                                "url": url,
-                                "body": form_body,
-                                "headers": headers,
-                                "timeout": timeout,
-                                "max_redirects": max_redirects
-                            }),
-                        )
-                        .await
-                    })
-                })
-            });
+            let result = send_request(
+                &caller,
+                json!({
+                    "method": "POST",
+                    "url": url,
+                    "body": form_body,
+                    "headers": headers,
+                    "timeout": timeout,
+                    "max_redirects": max_redirects
+                }),
+            );
 
             match result {
                 Ok(v) => {
@@ -1172,5 +1217,123 @@ pub fn register_functions<S: WasmStateCore>(linker: &mut Linker<S>) -> BridgeRes
 
 #[cfg(test)]
 mod tests {
-    // HTTP client tests would require mocking or real network
+    use super::{env_proxy, targets_server};
+    use crate::wasm_linker::{create_linker, WasmState};
+    use std::net::SocketAddr;
+    use url::Url;
+    use wasmtime::{Engine, Module, Store};
+
+    // `get` requests the URL at offset 1024 (length given) and returns the
+    // result pointer; `status` returns the last response code.
+    const WAT: &str = r#"
+        (module
+          (import "env" "http_get" (func $get (param i32 i32) (result i32)))
+          (import "env" "http_get_response_code" (func $code (result i32)))
+          (memory (export "memory") 2)
+          (data (i32.const 1024) "http://127.0.0.1:9/")
+          (global $heap (mut i32) (i32.const 65536))
+          (global (export "__heap_ptr") (mut i32) (i32.const 65536))
+          (func (export "malloc") (param $size i32) (result i32)
+            (local $ptr i32)
+            (local.set $ptr (global.get $heap))
+            (global.set $heap
+              (i32.and
+                (i32.add (i32.add (global.get $heap) (local.get $size)) (i32.const 7))
+                (i32.const -8)))
+            (global.set 1 (global.get $heap))
+            (local.get $ptr))
+          (func (export "get") (result i32)
+            (call $get (i32.const 1024) (i32.const 19)))
+          (func (export "status") (result i32)
+            (call $code)))
+    "#;
+
+    /// Call `http_get` and return the body it wrote plus the response code
+    fn get_without_network() -> (String, i32) {
+        let engine = Engine::default();
+        let linker = create_linker(&engine).expect("create linker");
+        let module = Module::new(&engine, WAT).expect("compile test module");
+        let mut store = Store::new(&engine, WasmState::default());
+        let instance = linker
+            .instantiate(&mut store, &module)
+            .expect("instantiate");
+
+        let get = instance
+            .get_typed_func::<(), i32>(&mut store, "get")
+            .unwrap();
+        let ptr = get.call(&mut store, ()).expect("http_get should not trap") as usize;
+        let memory = instance.get_memory(&mut store, "memory").unwrap();
+        let data = memory.data(&store);
+        let len = u32::from_le_bytes(data[ptr..ptr + 4].try_into().unwrap()) as usize;
+        let body = String::from_utf8(data[ptr + 4..ptr + 4 + len].to_vec()).unwrap();
+
+        let status = instance
+            .get_typed_func::<(), i32>(&mut store, "status")
+            .unwrap();
+        (body, status.call(&mut store, ()).unwrap())
+    }
+
+    #[test]
+    fn request_outside_a_runtime_fails_without_panicking() {
+        assert!(tokio::runtime::Handle::try_current().is_err());
+        assert_eq!(get_without_network(), (String::new(), 0));
+    }
+
+    #[test]
+    fn request_on_a_current_thread_runtime_fails_without_panicking() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let result = runtime.block_on(async { get_without_network() });
+        assert_eq!(result, (String::new(), 0));
+    }
+
+    #[tokio::test]
+    async fn targets_server_matches_only_the_bound_address() {
+        let server = "127.0.0.1:8080".parse().unwrap();
+        assert!(targets_server("http://127.0.0.1:8080/loop", server).await);
+        assert!(!targets_server("http://127.0.0.1:8081/loop", server).await);
+        assert!(!targets_server("http://192.0.2.1:8080/loop", server).await);
+        assert!(!targets_server("not a url", server).await);
+    }
+
+    #[tokio::test]
+    async fn targets_server_bound_to_unspecified_matches_local_addresses() {
+        let unspecified = "0.0.0.0:8080".parse().unwrap();
+        assert!(targets_server("http://127.0.0.1:8080/", unspecified).await);
+        assert!(targets_server("http://localhost:8080/", unspecified).await);
+        for interface in if_addrs::get_if_addrs().expect("list interfaces") {
+            let url = format!("http://{}/", SocketAddr::new(interface.ip(), 8080));
+            assert!(targets_server(&url, unspecified).await, "{}", url);
+        }
+        assert!(!targets_server("http://203.0.113.1:8080/", unspecified).await);
+        assert!(!targets_server("http://127.0.0.1:8081/", unspecified).await);
+    }
+
+    #[test]
+    fn env_proxy_follows_scheme_and_no_proxy() {
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                vars.iter()
+                    .find(|(key, _)| *key == name)
+                    .map(|(_, value)| value.to_string())
+            }
+        };
+        let url = Url::parse("http://api.example.com/x").unwrap();
+
+        let proxy = env_proxy(&url, env(&[("http_proxy", "http://127.0.0.1:3128")]));
+        assert_eq!(proxy.unwrap().as_str(), "http://127.0.0.1:3128/");
+        let proxy = env_proxy(&url, env(&[("ALL_PROXY", "127.0.0.1:3128")]));
+        assert_eq!(proxy.unwrap().port(), Some(3128));
+        assert!(env_proxy(&url, env(&[("https_proxy", "http://127.0.0.1:3128")])).is_none());
+        assert!(env_proxy(
+            &url,
+            env(&[
+                ("http_proxy", "http://127.0.0.1:3128"),
+                ("no_proxy", "localhost,.example.com")
+            ])
+        )
+        .is_none());
+    }
 }
@@ -11,7 +11,7 @@
 //! ## Host Function Categories
 //!
 //! ### Platform I/O (requires host access)
-//! - Console: print, printl, input, input_*
+//! - Console: print, printl, input, input_*, _log_write
 //! - File: file_read, file_write, file_exists, file_delete, file_append
 //! - HTTP Client: http_get, http_post, etc.
 //! - Database: _db_query, _db_execute, etc.
@@ -42,6 +42,7 @@ mod state;
 mod string_ops;
 
 pub use array_funcs::reset_array_store;
+pub use http_client::REENTRY_HEADER;
 pub use list_funcs::reset_list_store;
 // NOTE: HTTP Server functions (Layer 3) are NOT in host-bridge.
 // They are server-specific and implemented in clean-server/src/bridge.rs.
@@ -53,8 +54,8 @@ pub use helpers::{
     write_bytes_to_caller, write_string_to_caller, STRING_LENGTH_PREFIX_SIZE,
 };
 pub use state::{
-    AuthContext, HttpResponseBuilder, RequestContext, SharedDbBridge, WasmMemory, WasmState,
-    WasmStateCore,
+    AuthContext, HttpResponseBuilder, PrintOutput, RequestContext, SharedDbBridge, WasmMemory,
+    WasmState, WasmStateCore, WASM_PRINT_TARGET,
 };
 
 use crate::error::BridgeResult;
@@ -152,6 +153,46 @@ pub fn register_all_functions<S: WasmStateCore>(linker: &mut Linker<S>) -> Bridg
     Ok(())
 }
 
+/// A caller-supplied registration step, run after the built-in host functions.
+///
+/// Registrars receive the same linker the built-ins were added to and add
+/// their own functions with `func_wrap` (or `register_bridge_fn!`). The
+/// closure is generic over the runtime's state type `S`; inside a host
+/// function, `caller.data()` yields `&S`, so anything reachable through
+/// `WasmStateCore` (memory allocator, error slot, database bridge) is
+/// available to the custom function.
+pub type HostFunctionRegistrar<S> = Box<dyn FnOnce(&mut Linker<S>) -> BridgeResult<()> + Send>;
+
+/// Register all bridge host functions, then the caller's extensions.
+///
+/// This lets embedders add host functions without forking the crate. `S`
+/// must implement `WasmStateCore`, the same bound every built-in function
+/// relies on. Extensions run in order after the built-ins and their dot
+/// aliases, so a name that collides with a built-in fails with a
+/// "defined twice" error instead of silently replacing it.
+///
+/// # Example
+///
+/// ```ignore
+/// use host_bridge::wasm_linker::{register_all_functions_with, HostFunctionRegistrar, WasmState};
+///
+/// let myext: HostFunctionRegistrar<WasmState> = Box::new(|linker| {
+///     linker.func_wrap("env", "myext", |_caller: Caller<'_, WasmState>, x: i32| x * 2)?;
+///     Ok(())
+/// });
+/// register_all_functions_with(&mut linker, vec![myext])?;
+/// ```
+pub fn register_all_functions_with<S: WasmStateCore>(
+    linker: &mut Linker<S>,
+    extensions: impl IntoIterator<Item = HostFunctionRegistrar<S>>,
+) -> BridgeResult<()> {
+    register_all_functions(linker)?;
+    for register in extensions {
+        register(linker)?;
+    }
+    Ok(())
+}
+
 /// Register dot-notation aliases for all `_namespace_fn` bridge functions.
 ///
 /// The Clean Language compiler (0.30.120+) generates WASM imports in both
@@ -166,12 +207,22 @@ fn register_dot_aliases<S: WasmStateCore>(linker: &mut Linker<S>) -> BridgeResul
         // HTML interpolation helpers (string_ops module)
         ("_html_escape", "html.escape"),
         ("_html_raw", "html.raw"),
+        // Structured logging (console module)
+        ("_log_write", "log.write"),
         // Database (database module)
         ("_db_query", "db.query"),
+        ("_db_query_one", "db.query_one"),
+        ("_db_query_first", "db.query_first"),
+        ("_db_count", "db.count"),
+        ("_db_exists", "db.exists"),
+        ("_db_activity", "db.activity"),
+        ("_db_kill", "db.kill"),
+        ("_db_explain", "db.explain"),
         ("_db_execute", "db.execute"),
         ("_db_begin", "db.begin"),
         ("_db_commit", "db.commit"),
         ("_db_rollback", "db.rollback"),
+        ("_db_transaction", "db.transaction"),
         ("_db_register_migration", "db.register_migration"),
         ("_db_configure", "db.configure"),
         ("_db_paginate", "db.paginate"),
@@ -180,6 +231,7 @@ fn register_dot_aliases<S: WasmStateCore>(linker: &mut Linker<S>) -> BridgeResul
         ("_db_migration_status", "db.migration_status"),
         ("_db_rollback_migration", "db.rollback_migration"),
         ("_db_run_migrations", "db.run_migrations"),
+        ("_db_migrate", "db.migrate"),
         ("_db_valid_field", "db.valid_field"),
         // Crypto (crypto_funcs module)
         ("_crypto_hash_password", "crypto.hash_password"),
@@ -268,6 +320,54 @@ mod tests {
         assert!(linker.is_ok());
     }
 
+    #[test]
+    fn test_register_all_functions_with_extension() {
+        use wasmtime::Caller;
+
+        let engine = Engine::default();
+        let mut linker: Linker<WasmState> = Linker::new(&engine);
+        let myext: HostFunctionRegistrar<WasmState> = Box::new(|linker| {
+            linker.func_wrap("env", "myext", |_: Caller<'_, WasmState>, x: i32| -> i32 {
+                x * 3
+            })?;
+            Ok(())
+        });
+        register_all_functions_with(&mut linker, vec![myext]).expect("register with extension");
+
+        // The module imports the custom function next to a built-in one.
+        let wat = r#"
+            (module
+              (import "env" "myext" (func $myext (param i32) (result i32)))
+              (import "env" "math_sqrt" (func $sqrt (param f64) (result f64)))
+              (func (export "run") (result i32)
+                (i32.add
+                  (call $myext (i32.const 14))
+                  (i32.trunc_f64_s (call $sqrt (f64.const 16))))))
+        "#;
+        let module = Module::new(&engine, wat).expect("compile test module");
+        let mut store = Store::new(&engine, WasmState::default());
+        let instance = linker
+            .instantiate(&mut store, &module)
+            .expect("instantiate against extended linker");
+        let run = instance
+            .get_typed_func::<(), i32>(&mut store, "run")
+            .unwrap();
+        assert_eq!(run.call(&mut store, ()).unwrap(), 46);
+    }
+
+    #[test]
+    fn test_extension_cannot_shadow_builtin() {
+        use wasmtime::Caller;
+
+        let engine = Engine::default();
+        let mut linker: Linker<WasmState> = Linker::new(&engine);
+        let shadow: HostFunctionRegistrar<WasmState> = Box::new(|linker| {
+            linker.func_wrap("env", "math_sqrt", |_: Caller<'_, WasmState>, x: f64| x)?;
+            Ok(())
+        });
+        assert!(register_all_functions_with(&mut linker, vec![shadow]).is_err());
+    }
+
     // --- Registry TOML types ---
 
     #[derive(serde::Deserialize)]
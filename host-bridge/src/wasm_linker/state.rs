@@ -11,6 +11,38 @@ use tokio::sync::RwLock as TokioRwLock;
 /// Shared database bridge type
 pub type SharedDbBridge = Arc<TokioRwLock<DbBridge>>;
 
+/// `tracing` target of events from the `print*` functions when they are
+/// routed through `tracing`
+pub const WASM_PRINT_TARGET: &str = "wasm";
+
+/// Where the `print*` functions send a module's output
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PrintOutput {
+    /// Straight to stdout, as written
+    #[default]
+    RawStdout,
+    /// One `tracing` event per call under the `wasm` target, so the
+    /// subscriber filters and formats it with the host's own logs
+    Tracing(tracing::Level),
+}
+
+impl std::str::FromStr for PrintOutput {
+    type Err = String;
+
+    /// "raw" (or "stdout"), or a level name such as "info"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "raw" | "stdout" => Ok(PrintOutput::RawStdout),
+            level => level.parse().map(PrintOutput::Tracing).map_err(|_| {
+                format!(
+                    "unknown print output {:?}: expected raw or a log level (trace, debug, info, warn, error)",
+                    s
+                )
+            }),
+        }
+    }
+}
+
 // ============================================================================
 // CORE TRAIT - Implement this to use host-bridge functions with any state
 // ============================================================================
@@ -80,6 +112,20 @@ pub trait WasmStateCore: Send + 'static {
         // Default implementation does nothing
     }
 
+    /// Record a security-sensitive bridge call (db writes, file writes and
+    /// deletes, password hashing) in the host's audit trail, if it keeps one.
+    /// `params` summarizes the arguments; the host redacts secrets in it
+    /// before writing.
+    fn audit(&self, _namespace: &str, _function: &str, _params: &serde_json::Value) {
+        // Default implementation does nothing
+    }
+
+    /// Add the time a database bridge call took to the current request's
+    /// total, for hosts that report per-request timings.
+    fn record_db_time(&mut self, _elapsed: std::time::Duration) {
+        // Default implementation does nothing
+    }
+
     // =========================================
     // HTTP SERVER METHODS (optional, for server runtimes)
     // =========================================
@@ -118,6 +164,37 @@ pub trait WasmStateCore: Send + 'static {
     fn http_response_mut(&mut self) -> Option<&mut HttpResponseBuilder> {
         None
     }
+
+    /// Token sent in the `x-clean-reentry` header of outbound `http_*`
+    /// requests made while handling a request, so a server can recognise
+    /// and refuse calls its own handlers make back into it.
+    fn reentry_token(&self) -> Option<&str> {
+        None
+    }
+
+    /// Address the server handling the request listens on. The reentry
+    /// token is only sent to requests that reach it.
+    fn server_addr(&self) -> Option<std::net::SocketAddr> {
+        None
+    }
+
+    /// Current time as read by the `_time_*` bridges. Hosts can pin it to
+    /// a fixed instant so time-dependent handlers behave the same in tests.
+    fn now(&self) -> std::time::SystemTime {
+        std::time::SystemTime::now()
+    }
+
+    /// Zone `time_format` and `time_now_local` use when the call names
+    /// none. Hosts can set a regional default instead of UTC.
+    fn default_timezone(&self) -> chrono_tz::Tz {
+        chrono_tz::Tz::UTC
+    }
+
+    /// Where `print`, `printl` and the other `print_*` stdout functions
+    /// write. Hosts can route them through `tracing` instead of stdout.
+    fn print_output(&self) -> PrintOutput {
+        PrintOutput::RawStdout
+    }
 }
 
 /// Memory manager for WASM instance (bump allocator)
@@ -355,6 +432,10 @@ pub struct WasmState {
     /// Cached last_insert_id from the most recent INSERT in this state.
     /// See `WasmStateCore::last_insert_id` for the rationale.
     pub last_insert_id: Option<i64>,
+    /// Where the `print*` functions write (see `WasmStateCore::print_output`)
+    pub print_output: PrintOutput,
+    /// See `WasmStateCore::default_timezone`
+    pub default_timezone: chrono_tz::Tz,
 }
 
 /// Router interface for HTTP server integration
@@ -385,6 +466,8 @@ impl WasmState {
             router: None,
             current_tx_id: None,
             last_insert_id: None,
+            print_output: PrintOutput::RawStdout,
+            default_timezone: chrono_tz::Tz::UTC,
         }
     }
 
@@ -401,6 +484,8 @@ impl WasmState {
             router: None,
             current_tx_id: None,
             last_insert_id: None,
+            print_output: PrintOutput::RawStdout,
+            default_timezone: chrono_tz::Tz::UTC,
         }
     }
 
@@ -498,6 +583,14 @@ impl WasmStateCore for WasmState {
     fn set_last_insert_id(&mut self, id: Option<i64>) {
         self.last_insert_id = id;
     }
+
+    fn print_output(&self) -> PrintOutput {
+        self.print_output
+    }
+
+    fn default_timezone(&self) -> chrono_tz::Tz {
+        self.default_timezone
+    }
 }
 
 #[cfg(test)]
@@ -44,11 +44,56 @@ TIER2_FILES=(
   req_body_bytes_bridge_test.rs
   crypto_sha256_bytes_bridge_test.rs
   dev_snapshot_bridge_test.rs
+  test_server_test.rs
+  res_binary_bridge_test.rs
+  default_content_type_test.rs
+  req_client_ip_bridge_test.rs
+  route_schema_test.rs
+  res_render_bridge_test.rs
+  audit_log_test.rs
+  memory_limit_test.rs
+  module_cache_test.rs
+  mount_test.rs
+  auto_options_test.rs
+  idempotency_test.rs
+  response_cache_test.rs
+  request_context_store_test.rs
+  extension_method_test.rs
+  route_timeout_test.rs
+  task_spawn_test.rs
+  malformed_encoding_test.rs
+  content_type_allowlist_test.rs
+  bearer_auth_test.rs
+  role_hierarchy_test.rs
+  auth_permissions_test.rs
+  server_timing_test.rs
+  req_file_size_test.rs
+  req_input_test.rs
+  server_shutdown_test.rs
+  unix_socket_test.rs
+  ipv6_test.rs
+  handler_reentry_test.rs
+  max_routes_test.rs
+  clock_test.rs
+  default_response_headers_test.rs
+  sighup_reload_test.rs
+  req_raw_query_test.rs
+  instantiation_timeout_test.rs
+  spa_fallback_test.rs
+  db_reload_test.rs
+  runtime_threads_test.rs
+  request_coalescing_test.rs
+  cors_credentials_test.rs
+  req_route_pattern_test.rs
+  default_timezone_test.rs
+  res_json_bridge_test.rs
+  cookie_session_test.rs
 )
 
 TIER3_FILES=(
   host_functions_test.rs
   server_smoke_test.rs
+  self_check_test.rs
 )
 
 # ---------------------------------------------------------------------------
@@ -165,7 +210,7 @@ check_p3() {
     awk -v F="$file" '
       # Detect a test attribute on the current line; remember it and its line
       # number so we can attribute the following fn to it.
-      /^[[:space:]]*#\[(tokio::)?test\]/ { pending_test=1; test_line=NR; next }
+      /^[[:space:]]*#\[(tokio::)?test(\(.*\))?\]/ { pending_test=1; test_line=NR; next }
       # Start of an fn immediately after a test attribute.
       pending_test && /^[[:space:]]*(async[[:space:]]+)?fn[[:space:]]+[A-Za-z0-9_]+/ {
         in_test=1; pending_test=0; brace_depth=0; found_assert=0; fn_line=NR
@@ -263,7 +308,7 @@ check_p6() {
   while IFS= read -r f; do
     [[ -z "$f" ]] && continue
     base="$(basename "$f")"
-    if grep -qE '^[[:space:]]*#\[(tokio::)?test\]' "$f" 2>/dev/null; then continue; fi
+    if grep -qE '^[[:space:]]*#\[(tokio::)?test(\(.*\))?\]' "$f" 2>/dev/null; then continue; fi
     if allowlist_contains "P6" "tests/$base"; then continue; fi
     record "P6 tests/$base — no #[test] or #[tokio::test] function found"
   done < <(find tests -maxdepth 1 -type f -name '*.rs' 2>/dev/null || true)
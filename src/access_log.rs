@@ -0,0 +1,106 @@
+//! One log line per HTTP request, optionally sampled.
+//!
+//! Lines are emitted at `info` under the `clean_server::access` target.
+//! `ServerConfig::access_log_sample_rate` below 1.0 logs only that fraction
+//! of successful (non-4xx/5xx) requests; failed requests are always logged.
+
+use std::cell::Cell;
+use std::time::Duration;
+
+use axum::http::{Method, StatusCode};
+use tracing::info;
+
+use crate::error::{RuntimeError, RuntimeResult};
+
+/// Tracing target of access log lines
+pub const ACCESS_LOG_TARGET: &str = "clean_server::access";
+
+thread_local! {
+    static RNG_STATE: Cell<u64> = Cell::new(seed());
+}
+
+/// Per-thread PRNG seed; only needs to differ between threads and runs.
+fn seed() -> u64 {
+    use std::hash::{BuildHasher, RandomState};
+    RandomState::new().hash_one(std::thread::current().id()) | 1
+}
+
+/// Uniform sample in [0, 1) from a thread-local xorshift64* generator.
+fn next_unit() -> f64 {
+    RNG_STATE.with(|state| {
+        let mut x = state.get();
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        state.set(x);
+        (x.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 11) as f64 / (1u64 << 53) as f64
+    })
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct AccessLog {
+    sample_rate: f64,
+}
+
+impl Default for AccessLog {
+    fn default() -> Self {
+        Self { sample_rate: 1.0 }
+    }
+}
+
+impl AccessLog {
+    /// Access log keeping `sample_rate` (0.0–1.0) of successful requests.
+    pub fn new(sample_rate: f64) -> RuntimeResult<Self> {
+        if !(0.0..=1.0).contains(&sample_rate) {
+            return Err(RuntimeError::config(format!(
+                "Access log sample rate must be between 0.0 and 1.0, got {}",
+                sample_rate
+            )));
+        }
+        Ok(Self { sample_rate })
+    }
+
+    fn should_log(&self, status: StatusCode) -> bool {
+        status.is_client_error()
+            || status.is_server_error()
+            || self.sample_rate >= 1.0
+            || (self.sample_rate > 0.0 && next_unit() < self.sample_rate)
+    }
+
+    /// Log a finished request, unless sampled out.
+    pub fn record(&self, method: &Method, path: &str, status: StatusCode, elapsed: Duration) {
+        if self.should_log(status) {
+            info!(
+                target: ACCESS_LOG_TARGET,
+                "{} {} {} {}ms",
+                method,
+                path,
+                status.as_u16(),
+                elapsed.as_millis()
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sampling_keeps_roughly_the_configured_fraction() {
+        let log = AccessLog::new(0.25).unwrap();
+        let kept = (0..10_000)
+            .filter(|_| log.should_log(StatusCode::OK))
+            .count();
+        assert!((2_000..3_000).contains(&kept), "kept {}", kept);
+        assert!(log.should_log(StatusCode::NOT_FOUND));
+        assert!(log.should_log(StatusCode::INTERNAL_SERVER_ERROR));
+    }
+
+    #[test]
+    fn rejects_out_of_range_rates() {
+        assert!(AccessLog::new(-0.1).is_err());
+        assert!(AccessLog::new(1.5).is_err());
+        assert!(AccessLog::new(f64::NAN).is_err());
+    }
+}
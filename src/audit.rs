@@ -0,0 +1,202 @@
+//! Audit trail of security-sensitive bridge calls.
+//!
+//! When `ServerConfig::audit_log` is set, bridges that write data or touch
+//! credentials report through `WasmStateCore::audit`, and calls whose
+//! `namespace.function` is in the configured operation set are appended to
+//! the file as JSON lines:
+//!
+//! ```json
+//! {"ts":"2026-01-01T00:00:00Z","request_id":"…","namespace":"db","function":"execute","params":{…}}
+//! ```
+//!
+//! Values under secret-looking keys (passwords, tokens, hashes, session ids,
+//! …) are replaced with `"[REDACTED]"` before the entry is written.
+
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+use serde_json::{Map, Value, json};
+use tracing::warn;
+
+use crate::error::{RuntimeError, RuntimeResult};
+
+/// Operations audited when no explicit set is configured
+pub const DEFAULT_AUDIT_OPERATIONS: &[&str] = &[
+    "db.execute",
+    "fs.write_bytes",
+    "fs.delete",
+    "crypto.hash_password",
+    "crypto.verify_password",
+    "auth.set_session",
+    "auth.clear_session",
+    "auth.create_reset_token",
+    "auth.consume_reset_token",
+    "session.create",
+    "session.destroy",
+];
+
+/// Placeholder written in place of a redacted value
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Key fragments whose values are never written to the audit log
+const SECRET_KEY_FRAGMENTS: &[&str] = &[
+    "password",
+    "passwd",
+    "secret",
+    "token",
+    "hash",
+    "session",
+    "cookie",
+    "authorization",
+    "api_key",
+    "apikey",
+    "private_key",
+    "credential",
+];
+
+pub struct AuditLog {
+    file: Mutex<std::fs::File>,
+    operations: HashSet<String>,
+}
+
+pub type SharedAuditLog = Arc<AuditLog>;
+
+impl AuditLog {
+    /// Open `path` for appending. `operations` lists `namespace.function`
+    /// names to record (`namespace.*` matches a whole namespace); empty
+    /// selects [`DEFAULT_AUDIT_OPERATIONS`].
+    pub fn open(path: &Path, operations: &[String]) -> RuntimeResult<Self> {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| {
+                RuntimeError::config(format!("Cannot open audit log {}: {}", path.display(), e))
+            })?;
+        let operations = if operations.is_empty() {
+            DEFAULT_AUDIT_OPERATIONS
+                .iter()
+                .map(|op| op.to_string())
+                .collect()
+        } else {
+            operations.iter().cloned().collect()
+        };
+        Ok(Self {
+            file: Mutex::new(file),
+            operations,
+        })
+    }
+
+    pub fn is_audited(&self, namespace: &str, function: &str) -> bool {
+        self.operations
+            .contains(&format!("{}.{}", namespace, function))
+            || self.operations.contains(&format!("{}.*", namespace))
+    }
+
+    /// Append an entry for `namespace.function` if it is audited.
+    pub fn record(
+        &self,
+        namespace: &str,
+        function: &str,
+        params: &Value,
+        request_id: Option<&str>,
+    ) {
+        if !self.is_audited(namespace, function) {
+            return;
+        }
+        let entry = json!({
+            "ts": chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+            "request_id": request_id,
+            "namespace": namespace,
+            "function": function,
+            "params": redact(params),
+        });
+        let mut file = self.file.lock();
+        if let Err(e) = writeln!(file, "{}", entry) {
+            warn!(
+                "Failed to write audit entry for {}.{}: {}",
+                namespace, function, e
+            );
+        }
+    }
+}
+
+/// Copy of `value` with the values of secret-looking keys replaced by
+/// [`REDACTED`], at any depth.
+pub fn redact(value: &Value) -> Value {
+    match value {
+        Value::Object(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(key, v)| {
+                    let v = if is_secret_key(key) {
+                        Value::String(REDACTED.to_string())
+                    } else {
+                        redact(v)
+                    };
+                    (key.clone(), v)
+                })
+                .collect::<Map<_, _>>(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().map(redact).collect()),
+        other => other.clone(),
+    }
+}
+
+pub(crate) fn is_secret_key(key: &str) -> bool {
+    let key = key.to_ascii_lowercase();
+    SECRET_KEY_FRAGMENTS
+        .iter()
+        .any(|fragment| key.contains(fragment))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redacts_secret_keys_at_any_depth() {
+        let redacted = redact(&json!({
+            "user": "ada",
+            "password": "hunter2",
+            "nested": [{"API_KEY": "k", "session_id": "s", "count": 2}],
+        }));
+        assert_eq!(
+            redacted,
+            json!({
+                "user": "ada",
+                "password": REDACTED,
+                "nested": [{"API_KEY": REDACTED, "session_id": REDACTED, "count": 2}],
+            })
+        );
+    }
+
+    #[test]
+    fn records_only_configured_operations() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.log");
+        let log = AuditLog::open(&path, &["db.*".to_string()]).unwrap();
+
+        log.record(
+            "db",
+            "execute",
+            &json!({"sql": "DELETE FROM t"}),
+            Some("r1"),
+        );
+        log.record("fs", "delete", &json!({"path": "a.txt"}), Some("r1"));
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<Value> = contents
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0]["namespace"], "db");
+        assert_eq!(lines[0]["function"], "execute");
+        assert_eq!(lines[0]["request_id"], "r1");
+        assert_eq!(lines[0]["params"]["sql"], "DELETE FROM t");
+    }
+}
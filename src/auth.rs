@@ -0,0 +1,375 @@
+//! Bearer token verification.
+//!
+//! Requests carrying `Authorization: Bearer <token>` are authenticated by the
+//! configured `AuthProvider` (`ServerConfig.auth`) before dispatch; the
+//! resulting `AuthContext` is what `_auth_require_auth`, `_auth_get_session`
+//! and protected routes see. Without a provider, bearer tokens are ignored
+//! and only session cookies authenticate.
+//!
+//! `RoleHierarchy` (`ServerConfig.role_hierarchy`) decides whether a user's
+//! role satisfies the role a route or `_auth_require_role` asks for; `can`
+//! decides `_auth_can` from the user's own permissions and those registered
+//! for their role.
+
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+use serde::Deserialize;
+use tracing::debug;
+
+use crate::error::{RuntimeError, RuntimeResult};
+use crate::session::SharedSessionStore;
+use crate::wasm::{AuthContext, RolesStore};
+
+/// Role given to a token that names none
+pub const DEFAULT_ROLE: &str = "user";
+
+/// Verifies bearer tokens.
+pub trait AuthProvider: Send + Sync {
+    /// The identity `token` proves, or None if it is invalid or expired.
+    fn verify(&self, token: &str) -> Option<AuthContext>;
+}
+
+pub type SharedAuthProvider = Arc<dyn AuthProvider>;
+
+/// Which provider verifies bearer tokens (`ServerConfig::auth`)
+#[derive(Clone, PartialEq, Eq)]
+pub enum AuthConfig {
+    /// HS256 JWTs signed with this secret
+    JwtSecret(String),
+    /// RS256/RS384/RS512 or ES256/ES384 JWTs, verified with the PEM public
+    /// key in this file
+    JwtPublicKey(PathBuf),
+    /// The token is a session id from the server's session store
+    Session,
+}
+
+impl fmt::Debug for AuthConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuthConfig::JwtSecret(_) => f.write_str("JwtSecret(<redacted>)"),
+            AuthConfig::JwtPublicKey(path) => f.debug_tuple("JwtPublicKey").field(path).finish(),
+            AuthConfig::Session => f.write_str("Session"),
+        }
+    }
+}
+
+impl AuthConfig {
+    /// Build the provider, reading the public key file if there is one.
+    pub fn provider(&self, sessions: &SharedSessionStore) -> RuntimeResult<SharedAuthProvider> {
+        Ok(match self {
+            AuthConfig::JwtSecret(secret) => Arc::new(JwtAuthProvider::hs256(secret.as_bytes())),
+            AuthConfig::JwtPublicKey(path) => {
+                let pem = std::fs::read(path).map_err(|e| {
+                    RuntimeError::config(format!(
+                        "Cannot read JWT public key {}: {}",
+                        path.display(),
+                        e
+                    ))
+                })?;
+                Arc::new(JwtAuthProvider::from_public_key_pem(&pem)?)
+            }
+            AuthConfig::Session => Arc::new(SessionAuthProvider::new(sessions.clone())),
+        })
+    }
+}
+
+/// Ordered roles, highest first (`ServerConfig::role_hierarchy`). A role
+/// satisfies a requirement at or below it; roles outside the hierarchy only
+/// satisfy themselves. With no hierarchy configured, `admin` satisfies every
+/// requirement.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RoleHierarchy {
+    roles: Vec<String>,
+}
+
+pub type SharedRoleHierarchy = Arc<RoleHierarchy>;
+
+impl RoleHierarchy {
+    pub fn new(roles: Vec<String>) -> Self {
+        Self { roles }
+    }
+
+    /// Whether a user with `role` meets a `required` role.
+    pub fn satisfies(&self, role: &str, required: &str) -> bool {
+        if role == required {
+            return true;
+        }
+        if self.roles.is_empty() {
+            return role == "admin";
+        }
+        let rank = |name: &str| self.roles.iter().position(|r| r == name);
+        matches!((rank(role), rank(required)), (Some(have), Some(need)) if have <= need)
+    }
+}
+
+/// Whether `auth` holds `permission`: granted directly (`*` grants
+/// everything), or registered with `_roles_register` for the user's role or
+/// a role below it in `hierarchy`.
+pub fn can(
+    auth: &AuthContext,
+    permission: &str,
+    roles: &RolesStore,
+    hierarchy: &RoleHierarchy,
+) -> bool {
+    if auth.permissions.iter().any(|p| p == "*" || p == permission) {
+        return true;
+    }
+    roles
+        .roles
+        .keys()
+        .any(|role| hierarchy.satisfies(&auth.role, role) && roles.has_permission(role, permission))
+}
+
+/// The `permissions` array of a session's claims JSON, or none.
+pub fn permissions_from_claims(claims: &str) -> Vec<String> {
+    #[derive(Deserialize)]
+    struct SessionClaims {
+        #[serde(default)]
+        permissions: Vec<String>,
+    }
+    serde_json::from_str::<SessionClaims>(claims)
+        .map(|c| c.permissions)
+        .unwrap_or_default()
+}
+
+/// Claims read from a verified JWT. `exp` is required and checked by
+/// `jsonwebtoken`.
+#[derive(Debug, Deserialize)]
+struct Claims {
+    /// User id, as a number or a numeric string
+    sub: serde_json::Value,
+    #[serde(default)]
+    role: Option<String>,
+    /// Session id, if the issuer ties tokens to one
+    #[serde(default)]
+    sid: Option<String>,
+    #[serde(default)]
+    permissions: Vec<String>,
+}
+
+/// Verifies JWTs: signature, algorithm, `exp` and `nbf`.
+pub struct JwtAuthProvider {
+    key: DecodingKey,
+    validation: Validation,
+}
+
+impl JwtAuthProvider {
+    /// Tokens signed with HS256 and `secret`.
+    pub fn hs256(secret: &[u8]) -> Self {
+        Self::new(DecodingKey::from_secret(secret), &[Algorithm::HS256])
+    }
+
+    /// Tokens signed with the private half of a PEM-encoded RSA or EC
+    /// public key.
+    pub fn from_public_key_pem(pem: &[u8]) -> RuntimeResult<Self> {
+        if let Ok(key) = DecodingKey::from_rsa_pem(pem) {
+            return Ok(Self::new(
+                key,
+                &[Algorithm::RS256, Algorithm::RS384, Algorithm::RS512],
+            ));
+        }
+        DecodingKey::from_ec_pem(pem)
+            .map(|key| Self::new(key, &[Algorithm::ES256, Algorithm::ES384]))
+            .map_err(|e| RuntimeError::config(format!("Invalid JWT public key: {}", e)))
+    }
+
+    fn new(key: DecodingKey, algorithms: &[Algorithm]) -> Self {
+        let mut validation = Validation::new(algorithms[0]);
+        validation.algorithms = algorithms.to_vec();
+        validation.validate_nbf = true;
+        Self { key, validation }
+    }
+}
+
+impl AuthProvider for JwtAuthProvider {
+    fn verify(&self, token: &str) -> Option<AuthContext> {
+        let claims = match jsonwebtoken::decode::<Claims>(token, &self.key, &self.validation) {
+            Ok(data) => data.claims,
+            Err(e) => {
+                debug!("Rejected bearer token: {}", e);
+                return None;
+            }
+        };
+        let user_id = match &claims.sub {
+            serde_json::Value::Number(n) => n.as_i64().and_then(|n| i32::try_from(n).ok()),
+            serde_json::Value::String(s) => s.parse().ok(),
+            _ => None,
+        };
+        let Some(user_id) = user_id else {
+            debug!("Rejected bearer token: sub is not a user id");
+            return None;
+        };
+        Some(AuthContext {
+            user_id,
+            role: claims.role.unwrap_or_else(|| DEFAULT_ROLE.to_string()),
+            session_id: claims.sid,
+            permissions: claims.permissions,
+        })
+    }
+}
+
+/// Treats the token as a session id and looks it up in the session store.
+pub struct SessionAuthProvider {
+    sessions: SharedSessionStore,
+}
+
+impl SessionAuthProvider {
+    pub fn new(sessions: SharedSessionStore) -> Self {
+        Self { sessions }
+    }
+}
+
+impl AuthProvider for SessionAuthProvider {
+    fn verify(&self, token: &str) -> Option<AuthContext> {
+        let session = self
+            .sessions
+            .write()
+            .expect("store lock poisoned")
+            .get(token)?;
+        Some(AuthContext {
+            user_id: session.user_id,
+            role: session.role,
+            session_id: Some(session.session_id),
+            permissions: permissions_from_claims(&session.claims),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonwebtoken::{EncodingKey, Header};
+
+    fn token(claims: serde_json::Value, secret: &[u8]) -> String {
+        jsonwebtoken::encode(
+            &Header::default(),
+            &claims,
+            &EncodingKey::from_secret(secret),
+        )
+        .unwrap()
+    }
+
+    fn in_an_hour() -> i64 {
+        chrono::Utc::now().timestamp() + 3600
+    }
+
+    #[test]
+    fn jwt_provider_reads_identity_from_claims() {
+        let provider = JwtAuthProvider::hs256(b"secret");
+
+        let auth = provider
+            .verify(&token(
+                serde_json::json!({ "sub": "42", "role": "admin", "exp": in_an_hour() }),
+                b"secret",
+            ))
+            .expect("valid token");
+        assert_eq!(auth.user_id, 42);
+        assert_eq!(auth.role, "admin");
+
+        let auth = provider
+            .verify(&token(
+                serde_json::json!({ "sub": 7, "exp": in_an_hour() }),
+                b"secret",
+            ))
+            .expect("valid token");
+        assert_eq!(auth.role, DEFAULT_ROLE);
+    }
+
+    #[test]
+    fn jwt_provider_rejects_bad_tokens() {
+        let provider = JwtAuthProvider::hs256(b"secret");
+        let expired = chrono::Utc::now().timestamp() - 3600;
+
+        for token in [
+            token(
+                serde_json::json!({ "sub": 1, "exp": in_an_hour() }),
+                b"other",
+            ),
+            token(serde_json::json!({ "sub": 1, "exp": expired }), b"secret"),
+            token(serde_json::json!({ "sub": 1 }), b"secret"),
+            token(
+                serde_json::json!({ "sub": "ada", "exp": in_an_hour() }),
+                b"secret",
+            ),
+            "not-a-jwt".to_string(),
+        ] {
+            assert!(provider.verify(&token).is_none(), "{}", token);
+        }
+    }
+
+    #[test]
+    fn higher_roles_inherit_lower_requirements() {
+        let hierarchy = RoleHierarchy::new(
+            ["owner", "admin", "editor", "viewer"]
+                .map(String::from)
+                .to_vec(),
+        );
+        assert!(hierarchy.satisfies("editor", "viewer"));
+        assert!(hierarchy.satisfies("editor", "editor"));
+        assert!(!hierarchy.satisfies("editor", "admin"));
+        assert!(hierarchy.satisfies("owner", "admin"));
+        assert!(!hierarchy.satisfies("guest", "viewer"));
+        assert!(!hierarchy.satisfies("admin", "billing"));
+        assert!(hierarchy.satisfies("billing", "billing"));
+    }
+
+    #[test]
+    fn without_a_hierarchy_admin_satisfies_everything() {
+        let hierarchy = RoleHierarchy::default();
+        assert!(hierarchy.satisfies("admin", "editor"));
+        assert!(hierarchy.satisfies("editor", "editor"));
+        assert!(!hierarchy.satisfies("editor", "viewer"));
+    }
+
+    fn user(role: &str, permissions: &[&str]) -> AuthContext {
+        AuthContext {
+            user_id: 1,
+            role: role.to_string(),
+            session_id: None,
+            permissions: permissions.iter().map(|p| p.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn can_checks_explicit_then_role_permissions() {
+        let mut roles = RolesStore::new();
+        assert!(roles.register(r#"{"editor": ["posts.write"], "viewer": ["posts.read"]}"#));
+        let flat = RoleHierarchy::default();
+
+        let auth = user("member", &["billing.read"]);
+        assert!(can(&auth, "billing.read", &roles, &flat));
+        assert!(!can(&auth, "posts.read", &roles, &flat));
+
+        assert!(can(&user("member", &["*"]), "anything", &roles, &flat));
+        assert!(can(&user("editor", &[]), "posts.write", &roles, &flat));
+        assert!(!can(&user("editor", &[]), "posts.read", &roles, &flat));
+
+        let hierarchy = RoleHierarchy::new(["editor", "viewer"].map(String::from).to_vec());
+        assert!(can(&user("editor", &[]), "posts.read", &roles, &hierarchy));
+        assert!(!can(
+            &user("viewer", &[]),
+            "posts.write",
+            &roles,
+            &hierarchy
+        ));
+    }
+
+    #[test]
+    fn permissions_come_from_session_claims() {
+        assert_eq!(
+            permissions_from_claims(r#"{"permissions": ["a", "b"], "email": "x"}"#),
+            vec!["a", "b"]
+        );
+        assert!(permissions_from_claims("{}").is_empty());
+        assert!(permissions_from_claims("not json").is_empty());
+    }
+
+    #[test]
+    fn debug_output_hides_the_secret() {
+        let config = AuthConfig::JwtSecret("hunter2".to_string());
+        assert!(!format!("{:?}", config).contains("hunter2"));
+    }
+}
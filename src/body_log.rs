@@ -0,0 +1,153 @@
+//! Request and response bodies in the debug log
+//! (`ServerConfig.debug_body_logging`).
+//!
+//! Bodies are logged at `debug` under the `clean_server::body` target. Values
+//! of secret-looking fields in JSON and urlencoded bodies are replaced by
+//! [`REDACTED`] first (the same keys the audit log hides), then the text is
+//! cut to [`MAX_LOGGED_BODY_BYTES`]. Streamed responses are not buffered and
+//! so are not logged.
+
+use axum::body::{Body, HttpBody};
+use axum::http::{HeaderMap, Method, header};
+use axum::response::Response;
+use tracing::debug;
+
+use crate::audit::{REDACTED, is_secret_key, redact};
+
+/// Tracing target of body log lines
+pub const BODY_LOG_TARGET: &str = "clean_server::body";
+
+/// Longest body text written to one log line
+pub const MAX_LOGGED_BODY_BYTES: usize = 2048;
+
+/// Log a request body.
+pub fn log_request(method: &Method, path: &str, headers: &HeaderMap, body: &[u8]) {
+    debug!(
+        target: BODY_LOG_TARGET,
+        "request {} {} body: {}",
+        method,
+        path,
+        render(body, content_type(headers))
+    );
+}
+
+/// Log the body of `response`, which is buffered to read it unless it is
+/// streamed.
+pub async fn log_response(method: &Method, path: &str, response: Response) -> Response {
+    if response.body().size_hint().exact().is_none() {
+        debug!(
+            target: BODY_LOG_TARGET,
+            "response {} {} body: <streamed>", method, path
+        );
+        return response;
+    }
+    let (parts, body) = response.into_parts();
+    let bytes = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            debug!(
+                target: BODY_LOG_TARGET,
+                "response {} {} body unreadable: {}", method, path, e
+            );
+            return Response::from_parts(parts, Body::empty());
+        }
+    };
+    debug!(
+        target: BODY_LOG_TARGET,
+        "response {} {} {} body: {}",
+        method,
+        path,
+        parts.status.as_u16(),
+        render(&bytes, content_type(&parts.headers))
+    );
+    Response::from_parts(parts, Body::from(bytes))
+}
+
+fn content_type(headers: &HeaderMap) -> &str {
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default()
+}
+
+/// Body text with secrets redacted, cut to `MAX_LOGGED_BODY_BYTES`.
+fn render(body: &[u8], content_type: &str) -> String {
+    if body.is_empty() {
+        return "<empty>".to_string();
+    }
+    let media_type = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    let text = if media_type == "application/x-www-form-urlencoded" {
+        redact_form(body)
+    } else if let Ok(json) = serde_json::from_slice::<serde_json::Value>(body) {
+        redact(&json).to_string()
+    } else {
+        match std::str::from_utf8(body) {
+            Ok(text) => text.to_string(),
+            Err(_) => return format!("<{} bytes of binary data>", body.len()),
+        }
+    };
+    truncate(text)
+}
+
+fn redact_form(body: &[u8]) -> String {
+    let mut out = url::form_urlencoded::Serializer::new(String::new());
+    for (key, value) in url::form_urlencoded::parse(body) {
+        let value = if is_secret_key(&key) {
+            REDACTED.into()
+        } else {
+            value
+        };
+        out.append_pair(&key, &value);
+    }
+    out.finish()
+}
+
+fn truncate(mut text: String) -> String {
+    if text.len() <= MAX_LOGGED_BODY_BYTES {
+        return text;
+    }
+    let total = text.len();
+    let mut end = MAX_LOGGED_BODY_BYTES;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    text.truncate(end);
+    format!("{}... ({} bytes total)", text, total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn secret_fields_are_redacted() {
+        let json = render(
+            br#"{"user":"ada","password":"hunter2","nested":{"api_token":"t"}}"#,
+            "application/json",
+        );
+        assert_eq!(
+            json,
+            r#"{"nested":{"api_token":"[REDACTED]"},"password":"[REDACTED]","user":"ada"}"#
+        );
+
+        let form = render(
+            b"user=ada&client_secret=s3cr3t",
+            "application/x-www-form-urlencoded; charset=utf-8",
+        );
+        assert_eq!(form, "user=ada&client_secret=%5BREDACTED%5D");
+    }
+
+    #[test]
+    fn long_bodies_are_truncated_on_a_char_boundary() {
+        let body = "é".repeat(MAX_LOGGED_BODY_BYTES);
+        let logged = render(body.as_bytes(), "text/plain");
+        assert!(logged.starts_with(&"é".repeat(MAX_LOGGED_BODY_BYTES / 2)));
+        assert!(logged.ends_with(&format!("... ({} bytes total)", body.len())));
+        assert_eq!(render(&[0xff, 0xfe], ""), "<2 bytes of binary data>");
+    }
+}
@@ -14,7 +14,7 @@
 //!
 //! ## Server-Specific Functions (defined here)
 //! - HTTP server (_http_listen, _http_route, _http_route_protected, _http_serve_static)
-//! - Request context (_req_param, _req_query, _req_body, _req_header, _req_method, _req_path, _req_cookie)
+//! - Request context (_req_param, _req_query, _req_raw_query, _req_body, _req_header, _req_method, _req_path, _req_cookie)
 //! - Response manipulation (_res_set_header, _res_redirect)
 //! - Session management (_session_store, _session_get, _session_delete, _session_exists, _session_set_csrf, _session_get_csrf, _http_set_cookie)
 //! - Session auth (_auth_get_session, _auth_require_auth, _auth_require_role, _auth_can, _auth_has_any_role)
@@ -27,7 +27,9 @@ use crate::session::parse_cookies;
 use crate::wasm::{
     IslandEntry, McpBridgeState, McpPendingRequest, McpTransport, TestResponse, WasmState,
 };
-use host_bridge::{read_raw_string, read_string_from_caller, write_string_to_caller};
+use host_bridge::{
+    WasmStateCore, read_raw_string, read_string_from_caller, write_string_to_caller,
+};
 use tracing::{debug, error, info, warn};
 use wasmtime::{Caller, Engine, Linker};
 
@@ -99,6 +101,7 @@ pub fn create_linker(engine: &Engine) -> RuntimeResult<Linker<WasmState>> {
     register_email_functions(&mut linker)?;
     register_jobs_functions(&mut linker)?;
     register_locale_functions(&mut linker)?;
+    register_validation_functions(&mut linker)?;
     crate::bridge_canvas_stubs::register_canvas_stubs(&mut linker)?;
     crate::bridge_ui_stubs::register_ui_stubs(&mut linker)?;
     crate::bridge_browser_stubs::register_browser_stubs(&mut linker)?;
@@ -145,6 +148,18 @@ fn current_session_id(state: &WasmState) -> Option<String> {
         })
 }
 
+/// Client IP for `_req_client_ip` / `_req_ip`, as resolved by the server from
+/// the socket peer and trusted-proxy headers; `"unknown"` outside an HTTP
+/// request.
+fn request_client_ip(state: &WasmState) -> String {
+    state
+        .request_context
+        .as_ref()
+        .and_then(|ctx| ctx.client.as_ref())
+        .map(|client| client.ip.clone())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
 /// Format a JSON error body in the canonical `{ok:false, err:{code, message}}` shape.
 fn err_body(code: &str, message: &str) -> String {
     serde_json::json!({
@@ -197,7 +212,7 @@ fn register_http_server_functions(linker: &mut Linker<WasmState>) -> RuntimeResu
                     method_str, path, handler_name
                 );
 
-                let method = match HttpMethod::parse(&method_str) {
+                let method = match HttpMethod::register_extension(&method_str) {
                     Ok(m) => m,
                     Err(e) => {
                         error!("Invalid HTTP method '{}': {}", method_str, e);
@@ -211,7 +226,8 @@ fn register_http_server_functions(linker: &mut Linker<WasmState>) -> RuntimeResu
                 if let Err(e) =
                     router.register(method, path.clone(), handler_name, false, None, false)
                 {
-                    error!("Failed to register route {} {}: {}", method_str, path, e);
+                    // The router logs hitting its route limit once itself
+                    debug!("Failed to register route {} {}: {}", method_str, path, e);
                     return -1; // Error
                 }
                 0 // Success
@@ -247,7 +263,7 @@ fn register_http_server_functions(linker: &mut Linker<WasmState>) -> RuntimeResu
             method_str, from_path, to_path, status_code
         );
 
-        let method = match HttpMethod::parse(&method_str) {
+        let method = match HttpMethod::register_extension(&method_str) {
             Ok(m) => m,
             Err(e) => {
                 error!("Invalid HTTP method '{}': {}", method_str, e);
@@ -257,7 +273,118 @@ fn register_http_server_functions(linker: &mut Linker<WasmState>) -> RuntimeResu
 
         let router = caller.data().router.clone();
         if let Err(e) = router.register_redirect(method, from_path.clone(), to_path, status_code) {
-            error!("Failed to register redirect route {}: {}", from_path, e);
+            debug!("Failed to register redirect route {}: {}", from_path, e);
+            return -1;
+        }
+        0
+    });
+
+    // _http_route_schema - Register a route whose JSON request body must satisfy a
+    // JSON Schema; the server answers 422 without invoking the handler otherwise.
+    // Signature: (method_ptr, method_len, path_ptr, path_len, handler_ptr, handler_len, schema_ptr, schema_len) -> i32
+    // The schema is compiled once here; an invalid or unsupported schema fails
+    // registration (-1) instead of being skipped at request time.
+    register_bridge_fn!(linker, "_http_route_schema", |mut caller: Caller<
+        '_,
+        WasmState,
+    >,
+                                                       method_ptr: i32,
+                                                       method_len: i32,
+                                                       path_ptr: i32,
+                                                       path_len: i32,
+                                                       handler_ptr: i32,
+                                                       handler_len: i32,
+                                                       schema_ptr: i32,
+                                                       schema_len: i32|
+     -> i32 {
+        let method_str = read_raw_string(&mut caller, method_ptr, method_len)
+            .unwrap_or_else(|| "GET".to_string());
+        let path =
+            read_raw_string(&mut caller, path_ptr, path_len).unwrap_or_else(|| "/".to_string());
+        let handler_name = read_raw_string(&mut caller, handler_ptr, handler_len)
+            .unwrap_or_else(|| "__route_handler_0".to_string());
+        let schema_json = read_raw_string(&mut caller, schema_ptr, schema_len).unwrap_or_default();
+
+        debug!(
+            "_http_route_schema: method={}, path={}, handler={}",
+            method_str, path, handler_name
+        );
+
+        let method = match HttpMethod::register_extension(&method_str) {
+            Ok(m) => m,
+            Err(e) => {
+                error!("Invalid HTTP method '{}': {}", method_str, e);
+                return -1;
+            }
+        };
+        let schema = match crate::json_schema::JsonSchema::parse(&schema_json) {
+            Ok(schema) => std::sync::Arc::new(schema),
+            Err(e) => {
+                error!("Invalid request schema for {} {}: {}", method_str, path, e);
+                return -1;
+            }
+        };
+
+        let router = caller.data().router.clone();
+        if let Err(e) = router
+            .register(method, path.clone(), handler_name, false, None, false)
+            .and_then(|()| router.set_request_schema(method, &path, schema))
+        {
+            debug!("Failed to register route {} {}: {}", method_str, path, e);
+            return -1;
+        }
+        0
+    });
+
+    // _http_route_timeout - Register a route with its own handler timeout,
+    // overriding the server-wide `handler_timeout_ms` for that route.
+    // Signature: (method_ptr, method_len, path_ptr, path_len, handler_ptr, handler_len, timeout_ms) -> i32
+    // A timeout of 0 or less fails registration (-1).
+    register_bridge_fn!(linker, "_http_route_timeout", |mut caller: Caller<
+        '_,
+        WasmState,
+    >,
+                                                        method_ptr: i32,
+                                                        method_len: i32,
+                                                        path_ptr: i32,
+                                                        path_len: i32,
+                                                        handler_ptr: i32,
+                                                        handler_len: i32,
+                                                        timeout_ms: i32|
+     -> i32 {
+        let method_str = read_raw_string(&mut caller, method_ptr, method_len)
+            .unwrap_or_else(|| "GET".to_string());
+        let path =
+            read_raw_string(&mut caller, path_ptr, path_len).unwrap_or_else(|| "/".to_string());
+        let handler_name = read_raw_string(&mut caller, handler_ptr, handler_len)
+            .unwrap_or_else(|| "__route_handler_0".to_string());
+
+        debug!(
+            "_http_route_timeout: method={}, path={}, handler={}, timeout_ms={}",
+            method_str, path, handler_name, timeout_ms
+        );
+
+        let method = match HttpMethod::register_extension(&method_str) {
+            Ok(m) => m,
+            Err(e) => {
+                error!("Invalid HTTP method '{}': {}", method_str, e);
+                return -1;
+            }
+        };
+        if timeout_ms <= 0 {
+            error!(
+                "Invalid timeout {} ms for {} {}: must be positive",
+                timeout_ms, method_str, path
+            );
+            return -1;
+        }
+
+        let router = caller.data().router.clone();
+        if let Err(e) = router
+            .register(method, path.clone(), handler_name, false, None, false)
+            .and_then(|()| router.set_route_timeout(method, &path, timeout_ms as u64))
+        {
+            debug!("Failed to register route {} {}: {}", method_str, path, e);
             return -1;
         }
         0
@@ -294,7 +421,7 @@ fn register_http_server_functions(linker: &mut Linker<WasmState>) -> RuntimeResu
                     method_str, path, handler_name, required_role
                 );
 
-                let method = match HttpMethod::parse(&method_str) {
+                let method = match HttpMethod::register_extension(&method_str) {
                     Ok(m) => m,
                     Err(e) => {
                         error!("Invalid HTTP method '{}': {}", method_str, e);
@@ -313,7 +440,7 @@ fn register_http_server_functions(linker: &mut Linker<WasmState>) -> RuntimeResu
                     required_role,
                     false,
                 ) {
-                    error!(
+                    debug!(
                         "Failed to register protected route {} {}: {}",
                         method_str, path, e
                     );
@@ -409,7 +536,7 @@ fn register_http_server_functions(linker: &mut Linker<WasmState>) -> RuntimeResu
             None,
             true,
         ) {
-            error!(
+            debug!(
                 "_http_sse_route: Failed to register SSE route {}: {}",
                 path, e
             );
@@ -423,7 +550,7 @@ fn register_http_server_functions(linker: &mut Linker<WasmState>) -> RuntimeResu
 
 /// Register `server:` block runtime-config bridges:
 /// `_http_listen_on`, `_cors_configure`, `_rate_limit_configure`,
-/// `_http_set_global_error_handler`. These write to the shared
+/// `_http_set_global_error_handler`, `_http_middleware`. These write to the shared
 /// `SharedRuntimeConfig` that the server reads after WASM init to build the
 /// axum router.
 fn register_server_config_functions(linker: &mut Linker<WasmState>) -> RuntimeResult<()> {
@@ -567,6 +694,29 @@ fn register_server_config_functions(linker: &mut Linker<WasmState>) -> RuntimeRe
         }
     );
 
+    // _http_middleware - Register a WASM export to run before every route
+    // handler, in the handler's own instance, so anything it stores with
+    // `_ctx_set` is visible to the handler. Middleware runs in registration
+    // order. Returns 0 on success, -1 if the export name is empty.
+    register_bridge_fn!(linker, "_http_middleware", |mut caller: Caller<
+        '_,
+        WasmState,
+    >,
+                                                     name_ptr: i32,
+                                                     name_len: i32|
+     -> i32 {
+        let name = match read_raw_string(&mut caller, name_ptr, name_len) {
+            Some(s) if !s.trim().is_empty() => s,
+            _ => {
+                error!("_http_middleware: handler name is empty");
+                return -1;
+            }
+        };
+        info!("_http_middleware: registered middleware '{}'", name);
+        caller.data().runtime_config.write().middleware.push(name);
+        0
+    });
+
     Ok(())
 }
 
@@ -691,6 +841,60 @@ fn register_request_context_functions(linker: &mut Linker<WasmState>) -> Runtime
     // `register_dot_aliases` at end-of-registration (compiler >= 0.30.120
     // emits both forms).
 
+    // _req_body_len - Byte length of the request body, from the same source
+    // as _req_body_bytes. Lets a handler size a buffer or check
+    // Content-Length without copying the body into WASM memory.
+    linker
+        .func_wrap(
+            "env",
+            "_req_body_len",
+            |caller: Caller<'_, WasmState>| -> i32 {
+                let len = match caller.data().request_context.as_ref() {
+                    Some(ctx) => match &ctx.body_bytes {
+                        Some(b) => b.len(),
+                        None => ctx.body.len(),
+                    },
+                    None => 0,
+                };
+                i32::try_from(len).unwrap_or(i32::MAX)
+            },
+        )
+        .map_err(|e| RuntimeError::wasm(format!("Failed to define _req_body_len: {}", e)))?;
+
+    // _req_file_size - Byte length of the file uploaded in the
+    // multipart/form-data part named `name`, or -1 when the request carries
+    // no such file. Nothing is copied into WASM memory, so a handler can
+    // enforce a quota before fetching the file contents.
+    linker
+        .func_wrap(
+            "env",
+            "_req_file_size",
+            |mut caller: Caller<'_, WasmState>, name_ptr: i32, name_len: i32| -> i32 {
+                let Some(name) = read_raw_string(&mut caller, name_ptr, name_len) else {
+                    return -1;
+                };
+                let Some(ctx) = caller.data().request_context.as_ref() else {
+                    return -1;
+                };
+                let content_type = ctx
+                    .headers
+                    .iter()
+                    .find(|(k, _)| k.eq_ignore_ascii_case("content-type"))
+                    .map(|(_, v)| v.as_str())
+                    .unwrap_or_default();
+                let body = match &ctx.body_bytes {
+                    Some(b) => b.as_slice(),
+                    None => ctx.body.as_bytes(),
+                };
+                let size = crate::multipart::find_file(content_type, body, &name)
+                    .map(|part| i32::try_from(part.data.len()).unwrap_or(i32::MAX))
+                    .unwrap_or(-1);
+                debug!("_req_file_size({}): {}", name, size);
+                size
+            },
+        )
+        .map_err(|e| RuntimeError::wasm(format!("Failed to define _req_file_size: {}", e)))?;
+
     // _req_body_sha256_hex - Server-computed SHA-256 (lowercase hex, 64 chars)
     // of the raw pre-parse request body. Byte source matches _req_body_bytes:
     // ctx.body_bytes when raw middleware buffered them, else UTF-8 of ctx.body.
@@ -771,6 +975,36 @@ fn register_request_context_functions(linker: &mut Linker<WasmState>) -> Runtime
         )
         .map_err(|e| RuntimeError::wasm(format!("Failed to define _req_body_field: {}", e)))?;
 
+    // _req_input - Value named `name` from the first request source that has
+    // one, checked in `ServerConfig.input_precedence` order (default: path
+    // params, query, body fields). Returns "" when no source has it.
+    linker
+        .func_wrap(
+            "env",
+            "_req_input",
+            |mut caller: Caller<'_, WasmState>, name_ptr: i32, name_len: i32| -> i32 {
+                let name = match read_raw_string(&mut caller, name_ptr, name_len) {
+                    Some(s) => s,
+                    None => return write_string_to_caller(&mut caller, ""),
+                };
+
+                let value = {
+                    let state = caller.data();
+                    state
+                        .request_context
+                        .as_ref()
+                        .and_then(|ctx| {
+                            crate::request_input::lookup(ctx, &name, &state.input_precedence)
+                        })
+                        .unwrap_or_default()
+                };
+
+                debug!("_req_input({}): {}", name, value);
+                write_string_to_caller(&mut caller, &value)
+            },
+        )
+        .map_err(|e| RuntimeError::wasm(format!("Failed to define _req_input: {}", e)))?;
+
     // _req_param_int - Get a path parameter as integer
     linker
         .func_wrap(
@@ -868,6 +1102,43 @@ fn register_request_context_functions(linker: &mut Linker<WasmState>) -> Runtime
         )
         .map_err(|e| RuntimeError::wasm(format!("Failed to define _req_path: {}", e)))?;
 
+    // _req_route_pattern - Get the pattern of the matched route, e.g.
+    // `/users/:id` for a request to `/users/42` (empty outside a route)
+    linker
+        .func_wrap(
+            "env",
+            "_req_route_pattern",
+            |mut caller: Caller<'_, WasmState>| -> i32 {
+                let pattern = caller
+                    .data()
+                    .request_context
+                    .as_ref()
+                    .and_then(|ctx| ctx.route_pattern.clone())
+                    .unwrap_or_default();
+
+                write_string_to_caller(&mut caller, &pattern)
+            },
+        )
+        .map_err(|e| RuntimeError::wasm(format!("Failed to define _req_route_pattern: {}", e)))?;
+
+    // _req_raw_query - Get the query string exactly as received
+    linker
+        .func_wrap(
+            "env",
+            "_req_raw_query",
+            |mut caller: Caller<'_, WasmState>| -> i32 {
+                let raw_query = caller
+                    .data()
+                    .request_context
+                    .as_ref()
+                    .map(|ctx| ctx.raw_query.clone())
+                    .unwrap_or_default();
+
+                write_string_to_caller(&mut caller, &raw_query)
+            },
+        )
+        .map_err(|e| RuntimeError::wasm(format!("Failed to define _req_raw_query: {}", e)))?;
+
     // _req_cookie - Get a cookie value by name
     linker
         .func_wrap(
@@ -959,36 +1230,13 @@ fn register_request_context_functions(linker: &mut Linker<WasmState>) -> Runtime
         )
         .map_err(|e| RuntimeError::wasm(format!("Failed to define _req_form: {}", e)))?;
 
-    // _req_ip - Get client IP address
+    // _req_ip - Get client IP address (legacy name of _req_client_ip)
     linker
         .func_wrap(
             "env",
             "_req_ip",
             |mut caller: Caller<'_, WasmState>| -> i32 {
-                let ip = {
-                    let state = caller.data();
-                    state
-                        .request_context
-                        .as_ref()
-                        .and_then(|ctx| {
-                            // Try X-Forwarded-For first (can be comma-separated list)
-                            ctx.headers
-                                .iter()
-                                .find(|(k, _)| k.to_lowercase() == "x-forwarded-for")
-                                .and_then(|(_, v)| {
-                                    v.split(',').next().map(|s| s.trim().to_string())
-                                })
-                                .or_else(|| {
-                                    // Try X-Real-IP
-                                    ctx.headers
-                                        .iter()
-                                        .find(|(k, _)| k.to_lowercase() == "x-real-ip")
-                                        .map(|(_, v)| v.clone())
-                                })
-                        })
-                        .unwrap_or_else(|| "unknown".to_string())
-                };
-
+                let ip = request_client_ip(caller.data());
                 debug!("_req_ip: {}", ip);
                 write_string_to_caller(&mut caller, &ip)
             },
@@ -999,6 +1247,34 @@ fn register_request_context_functions(linker: &mut Linker<WasmState>) -> Runtime
     // PHASE 3 REQUEST CONTEXT EXTRAS
     // =========================================
 
+    // _req_client_ip() -> ptr (socket peer, or the X-Forwarded-For client
+    // when the peer is a trusted proxy)
+    register_bridge_fn!(linker, "_req_client_ip", |mut caller: Caller<
+        '_,
+        WasmState,
+    >|
+     -> i32 {
+        let ip = request_client_ip(caller.data());
+        write_string_to_caller(&mut caller, &ip)
+    });
+
+    // _req_scheme() -> ptr ("http" or "https"; X-Forwarded-Proto is honored
+    // only from a trusted proxy)
+    register_bridge_fn!(linker, "_req_scheme", |mut caller: Caller<
+        '_,
+        WasmState,
+    >|
+     -> i32 {
+        let scheme = caller
+            .data()
+            .request_context
+            .as_ref()
+            .and_then(|ctx| ctx.client.as_ref())
+            .map(|client| client.scheme.clone())
+            .unwrap_or_else(|| "http".to_string());
+        write_string_to_caller(&mut caller, &scheme)
+    });
+
     // _req_params() -> ptr (JSON map of path params)
     register_bridge_fn!(linker, "_req_params", |mut caller: Caller<
         '_,
@@ -1235,6 +1511,75 @@ fn register_request_context_functions(linker: &mut Linker<WasmState>) -> Runtime
         if has { 1 } else { 0 }
     });
 
+    // _ctx_set(key, value) - Store a value in the request-scoped context
+    // store, replacing any earlier value for the key. Returns 0 on success,
+    // -1 if either string can't be read.
+    register_bridge_fn!(linker, "_ctx_set", |mut caller: Caller<'_, WasmState>,
+                                             key_ptr: i32,
+                                             key_len: i32,
+                                             val_ptr: i32,
+                                             val_len: i32|
+     -> i32 {
+        let (Some(key), Some(value)) = (
+            read_raw_string(&mut caller, key_ptr, key_len),
+            read_raw_string(&mut caller, val_ptr, val_len),
+        ) else {
+            error!("_ctx_set: failed to read key or value");
+            return -1;
+        };
+        caller.data_mut().context_store.insert(key, value);
+        0
+    });
+
+    // _ctx_get(key) -> string - Value stored by `_ctx_set` earlier in this
+    // request (e.g. by middleware), or "" if the key was never set.
+    register_bridge_fn!(linker, "_ctx_get", |mut caller: Caller<'_, WasmState>,
+                                             key_ptr: i32,
+                                             key_len: i32|
+     -> i32 {
+        let value = read_raw_string(&mut caller, key_ptr, key_len)
+            .and_then(|key| caller.data().context_store.get(&key).cloned())
+            .unwrap_or_default();
+        write_string_to_caller(&mut caller, &value)
+    });
+
+    // _task_spawn(handler, payload_json) - Queue the exported `handler` to run
+    // as a detached task once the response has been sent, in its own
+    // instance with `payload_json` as its request body (`_req_body`).
+    // Returns 0 when queued, -1 if the strings can't be read or the module
+    // has no such export.
+    register_bridge_fn!(linker, "_task_spawn", |mut caller: Caller<
+        '_,
+        WasmState,
+    >,
+                                                handler_ptr: i32,
+                                                handler_len: i32,
+                                                payload_ptr: i32,
+                                                payload_len: i32|
+     -> i32 {
+        let (Some(handler), Some(payload)) = (
+            read_raw_string(&mut caller, handler_ptr, handler_len),
+            read_raw_string(&mut caller, payload_ptr, payload_len),
+        ) else {
+            error!("_task_spawn: failed to read handler or payload");
+            return -1;
+        };
+        if !matches!(caller.get_export(&handler), Some(wasmtime::Extern::Func(_))) {
+            error!("_task_spawn: no exported function '{}'", handler);
+            return -1;
+        }
+        debug!(
+            "_task_spawn: handler={}, payload_len={}",
+            handler,
+            payload.len()
+        );
+        caller
+            .data_mut()
+            .spawned_tasks
+            .push(crate::tasks::SpawnedTask { handler, payload });
+        0
+    });
+
     Ok(())
 }
 
@@ -1717,7 +2062,8 @@ fn register_session_auth_functions(linker: &mut Linker<WasmState>) -> RuntimeRes
                         serde_json::json!({
                             "user_id": auth.user_id,
                             "role": auth.role,
-                            "session_id": auth.session_id
+                            "session_id": auth.session_id,
+                            "permissions": auth.permissions
                         })
                         .to_string()
                     } else {
@@ -1754,16 +2100,15 @@ fn register_session_auth_functions(linker: &mut Linker<WasmState>) -> RuntimeRes
                 };
 
                 let state = caller.data();
-                if let Some(auth) = &state.auth_context {
-                    if auth.role == required_role { 1 } else { 0 }
-                } else {
-                    0
+                match &state.auth_context {
+                    Some(auth) if state.role_hierarchy.satisfies(&auth.role, &required_role) => 1,
+                    _ => 0,
                 }
             },
         )
         .map_err(|e| RuntimeError::wasm(format!("Failed to define _auth_require_role: {}", e)))?;
 
-    // _auth_can - Check if user has permission (role-based)
+    // _auth_can - Check if user has a permission, granted directly or through their role
     linker
         .func_wrap(
             "env",
@@ -1776,15 +2121,16 @@ fn register_session_auth_functions(linker: &mut Linker<WasmState>) -> RuntimeRes
                 };
 
                 let state = caller.data();
-                if let Some(auth) = &state.auth_context {
-                    // Simple role-based check: admin can do anything
-                    if auth.role == "admin" || auth.role == permission {
-                        1
-                    } else {
-                        0
+                match &state.auth_context {
+                    Some(auth) => {
+                        let roles = state.roles_store.read().expect("roles store lock poisoned");
+                        if crate::auth::can(auth, &permission, &roles, &state.role_hierarchy) {
+                            1
+                        } else {
+                            0
+                        }
                     }
-                } else {
-                    0
+                    None => 0,
                 }
             },
         )
@@ -1804,10 +2150,15 @@ fn register_session_auth_functions(linker: &mut Linker<WasmState>) -> RuntimeRes
                 let roles: Vec<String> = serde_json::from_str(&roles_json).unwrap_or_default();
 
                 let state = caller.data();
-                if let Some(auth) = &state.auth_context {
-                    if roles.contains(&auth.role) { 1 } else { 0 }
-                } else {
-                    0
+                match &state.auth_context {
+                    Some(auth)
+                        if roles
+                            .iter()
+                            .any(|role| state.role_hierarchy.satisfies(&auth.role, role)) =>
+                    {
+                        1
+                    }
+                    _ => 0,
                 }
             },
         )
@@ -1851,6 +2202,11 @@ fn register_session_auth_functions(linker: &mut Linker<WasmState>) -> RuntimeRes
                     .unwrap_or_else(|| "{}".to_string());
 
                 info!("_auth_set_session: user_id={}, role={}", user_id, role);
+                caller.data().audit(
+                    "auth",
+                    "set_session",
+                    &serde_json::json!({ "user_id": user_id, "role": role }),
+                );
 
                 let session_store = caller.data().session_store.clone();
 
@@ -1858,6 +2214,13 @@ fn register_session_auth_functions(linker: &mut Linker<WasmState>) -> RuntimeRes
                     let mut store = session_store.write().expect("session store lock poisoned");
                     store.create(user_id, &role, &claims)
                 };
+                let session = match session {
+                    Ok(session) => session,
+                    Err(e) => {
+                        error!("_auth_set_session: {}", e);
+                        return 0;
+                    }
+                };
 
                 let session_id = session.session_id.clone();
 
@@ -1867,9 +2230,12 @@ fn register_session_auth_functions(linker: &mut Linker<WasmState>) -> RuntimeRes
                 };
 
                 caller.data_mut().pending_set_cookie = Some(set_cookie);
-                caller
-                    .data_mut()
-                    .set_auth_from_session(user_id, role, session_id);
+                caller.data_mut().set_auth_from_session(
+                    user_id,
+                    role,
+                    session_id,
+                    crate::auth::permissions_from_claims(&claims),
+                );
 
                 1
             },
@@ -1917,6 +2283,11 @@ fn register_session_auth_functions(linker: &mut Linker<WasmState>) -> RuntimeRes
                 };
 
                 info!("_auth_clear_session: Clearing session {}", session_id);
+                caller.data().audit(
+                    "auth",
+                    "clear_session",
+                    &serde_json::json!({ "session_id": session_id }),
+                );
 
                 let session_store = caller.data().session_store.clone();
 
@@ -2082,9 +2453,15 @@ fn register_session_auth_functions(linker: &mut Linker<WasmState>) -> RuntimeRes
             None => return 0,
         };
         let roles: Vec<String> = serde_json::from_str(&roles_json).unwrap_or_default();
-        match &caller.data().auth_context {
-            Some(a) if a.role == "admin" => 1,
-            Some(a) if roles.iter().any(|r| r == &a.role) => 1,
+        let state = caller.data();
+        match &state.auth_context {
+            Some(a)
+                if roles
+                    .iter()
+                    .any(|r| state.role_hierarchy.satisfies(&a.role, r)) =>
+            {
+                1
+            }
             _ => 0,
         }
     });
@@ -2109,21 +2486,34 @@ fn register_session_auth_functions(linker: &mut Linker<WasmState>) -> RuntimeRes
         let role = read_raw_string(&mut caller, rp, rl).unwrap_or_else(|| "user".to_string());
         let claims = read_raw_string(&mut caller, cp, cl).unwrap_or_else(|| "{}".to_string());
         let user_id: i32 = user_id_str.trim().parse().unwrap_or(0);
+        caller.data().audit(
+            "session",
+            "create",
+            &serde_json::json!({ "user_id": user_id, "role": role }),
+        );
 
         let session_store = caller.data().session_store.clone();
         let session = {
             let mut store = session_store.write().expect("session store lock poisoned");
             store.create(user_id, &role, &claims)
         };
+        let session = match session {
+            Ok(session) => session,
+            Err(e) => {
+                error!("_session_create: {}", e);
+                return write_string_to_caller(&mut caller, "");
+            }
+        };
         let sid = session.session_id.clone();
         let cookie = {
             let store = session_store.read().expect("session store lock poisoned");
             store.format_cookie(&sid)
         };
         caller.data_mut().pending_set_cookie = Some(cookie);
+        let permissions = crate::auth::permissions_from_claims(&claims);
         caller
             .data_mut()
-            .set_auth_from_session(user_id, role, sid.clone());
+            .set_auth_from_session(user_id, role, sid.clone(), permissions);
         write_string_to_caller(&mut caller, &sid)
     });
 
@@ -2145,12 +2535,24 @@ fn register_session_auth_functions(linker: &mut Linker<WasmState>) -> RuntimeRes
         let claims = read_raw_string(&mut caller, cp, cl).unwrap_or_else(|| "{}".to_string());
         let user_id: i32 = user_id_str.trim().parse().unwrap_or(0);
         let ttl = if ttl > 0 { ttl as u64 } else { 0 };
+        caller.data().audit(
+            "session",
+            "create",
+            &serde_json::json!({ "user_id": user_id, "role": role, "ttl_seconds": ttl }),
+        );
 
         let session_store = caller.data().session_store.clone();
         let session = {
             let mut store = session_store.write().expect("session store lock poisoned");
             store.create(user_id, &role, &claims)
         };
+        let session = match session {
+            Ok(session) => session,
+            Err(e) => {
+                error!("_session_create_with_ttl: {}", e);
+                return write_string_to_caller(&mut caller, "");
+            }
+        };
         let sid = session.session_id.clone();
         // Take the default cookie format and substitute the Max-Age with the requested ttl.
         let base = {
@@ -2163,9 +2565,10 @@ fn register_session_auth_functions(linker: &mut Linker<WasmState>) -> RuntimeRes
             base
         };
         caller.data_mut().pending_set_cookie = Some(cookie);
+        let permissions = crate::auth::permissions_from_claims(&claims);
         caller
             .data_mut()
-            .set_auth_from_session(user_id, role, sid.clone());
+            .set_auth_from_session(user_id, role, sid.clone(), permissions);
         write_string_to_caller(&mut caller, &sid)
     });
 
@@ -2179,6 +2582,11 @@ fn register_session_auth_functions(linker: &mut Linker<WasmState>) -> RuntimeRes
             Some(s) => s,
             None => return 0,
         };
+        caller.data().audit(
+            "session",
+            "destroy",
+            &serde_json::json!({ "session_id": sid }),
+        );
         let session_store = caller.data().session_store.clone();
         let removed = {
             let mut store = session_store.write().expect("session store lock poisoned");
@@ -2395,6 +2803,11 @@ fn register_session_auth_functions(linker: &mut Linker<WasmState>) -> RuntimeRes
 
         use sha2::Digest;
         let token_hash = hex::encode(sha2::Sha256::digest(token.as_bytes()));
+        caller.data().audit(
+            "auth",
+            "create_reset_token",
+            &serde_json::json!({ "user_id": user_id_i32, "ttl_seconds": ttl_seconds }),
+        );
 
         let ttl = std::time::Duration::from_secs(ttl_seconds as u64);
         let session_store = caller.data().session_store.clone();
@@ -2448,6 +2861,11 @@ fn register_session_auth_functions(linker: &mut Linker<WasmState>) -> RuntimeRes
             let mut store = session_store.write().expect("session store lock poisoned");
             store.consume_reset_token(&token_hash)
         };
+        caller.data().audit(
+            "auth",
+            "consume_reset_token",
+            &serde_json::json!({ "token": token, "user_id": user_id }),
+        );
         match user_id {
             Some(id) => id as i64,
             None => 0,
@@ -2823,6 +3241,10 @@ fn register_response_functions(linker: &mut Linker<WasmState>) -> RuntimeResult<
 
     // _res_json - Set JSON response (sets body + Content-Type header)
     // Args: json_ptr, json_len
+    // Returns: void (matches function-registry.toml). A payload that does not
+    // parse as JSON turns the response into a 500 labelled text/plain, so
+    // clients are never sent invalid JSON as application/json (the server
+    // would otherwise infer it from a leading `{` or `[`).
     linker
         .func_wrap(
             "env",
@@ -2830,6 +3252,16 @@ fn register_response_functions(linker: &mut Linker<WasmState>) -> RuntimeResult<
             |mut caller: Caller<'_, WasmState>, json_ptr: i32, json_len: i32| {
                 let json_body =
                     read_raw_string(&mut caller, json_ptr, json_len).unwrap_or_default();
+                if let Err(e) = serde_json::from_str::<serde_json::Value>(&json_body) {
+                    error!("_res_json: payload is not valid JSON: {}", e);
+                    let state = caller.data_mut();
+                    state.set_status(500);
+                    state.add_header(
+                        "Content-Type".to_string(),
+                        "text/plain; charset=utf-8".to_string(),
+                    );
+                    return;
+                }
                 debug!("_res_json: {} bytes", json_body.len());
                 caller
                     .data_mut()
@@ -2903,6 +3335,64 @@ fn register_response_functions(linker: &mut Linker<WasmState>) -> RuntimeResult<
         }
     );
 
+    // _res_set_binary - Send the handler's return value as raw bytes
+    // Args: none
+    // Returns: void
+    // The length-prefixed return value is sent without UTF-8 validation and
+    // defaults to Content-Type: application/octet-stream.
+    register_bridge_fn!(linker, "_res_set_binary", |mut caller: Caller<
+        '_,
+        WasmState,
+    >| {
+        debug!("_res_set_binary: handler output is binary");
+        caller.data_mut().set_binary();
+    });
+
+    // _res_render - Render a template from the configured templates directory
+    // Args: name_ptr, name_len, data_ptr, data_len (data is a JSON object)
+    // Returns: pointer to the rendered HTML, which the handler returns as its
+    // body; Content-Type is set to text/html. On failure (missing template,
+    // invalid JSON) the response becomes a 500 with a JSON error body.
+    register_bridge_fn!(linker, "_res_render", |mut caller: Caller<
+        '_,
+        WasmState,
+    >,
+                                                name_ptr: i32,
+                                                name_len: i32,
+                                                data_ptr: i32,
+                                                data_len: i32|
+     -> i32 {
+        let name = read_raw_string(&mut caller, name_ptr, name_len).unwrap_or_default();
+        let data_json = read_raw_string(&mut caller, data_ptr, data_len).unwrap_or_default();
+
+        let rendered = if data_json.trim().is_empty() {
+            Ok(serde_json::Value::Object(Default::default()))
+        } else {
+            serde_json::from_str(&data_json)
+                .map_err(|e| RuntimeError::config(format!("Invalid template data: {}", e)))
+        }
+        .and_then(|data| caller.data().templates.render(&name, &data));
+
+        let (content_type, body) = match rendered {
+            Ok(html) => {
+                debug!("_res_render: {} ({} bytes)", name, html.len());
+                ("text/html; charset=utf-8", html)
+            }
+            Err(e) => {
+                error!("_res_render: {}", e);
+                caller.data_mut().set_status(500);
+                (
+                    "application/json",
+                    err_body("TEMPLATE_ERROR", &e.to_string()),
+                )
+            }
+        };
+        let state = caller.data_mut();
+        state.add_header("Content-Type".to_string(), content_type.to_string());
+        state.set_body(body.clone());
+        write_string_to_caller(&mut caller, &body)
+    });
+
     // =========================================
     // PHASE 3 RESPONSE EXTRAS
     // =========================================
@@ -3131,7 +3621,8 @@ fn write_boxed_any_string(caller: &mut Caller<'_, WasmState>, s: &str) -> i32 {
     box_ptr
 }
 
-/// Register JSON encode/decode/query functions (_json_encode, _json_decode, _json_get)
+/// Register JSON encode/decode/query functions (_json_encode, _json_decode,
+/// _json_get, json_minify, json_canonicalize)
 fn register_json_functions(linker: &mut Linker<WasmState>) -> RuntimeResult<()> {
     // _json_encode - Serialize value to JSON string
     linker
@@ -3283,9 +3774,92 @@ fn register_json_functions(linker: &mut Linker<WasmState>) -> RuntimeResult<()>
         )
         .map_err(|e| RuntimeError::wasm(format!("Failed to define _json_get: {}", e)))?;
 
+    // json_minify(ptr, len) -> ptr: the JSON without insignificant
+    // whitespace, key order kept; empty on invalid JSON
+    linker
+        .func_wrap(
+            "env",
+            "json_minify",
+            |mut caller: Caller<'_, WasmState>, json_ptr: i32, json_len: i32| -> i32 {
+                let json = read_raw_string(&mut caller, json_ptr, json_len).unwrap_or_default();
+                let minified = minify_json(&json).unwrap_or_else(|| {
+                    debug!("json_minify: invalid JSON");
+                    String::new()
+                });
+                write_string_to_caller(&mut caller, &minified)
+            },
+        )
+        .map_err(|e| RuntimeError::wasm(format!("Failed to define json_minify: {}", e)))?;
+
+    // json_canonicalize(ptr, len) -> ptr: minified with object keys sorted,
+    // for signing; empty on invalid JSON
+    linker
+        .func_wrap(
+            "env",
+            "json_canonicalize",
+            |mut caller: Caller<'_, WasmState>, json_ptr: i32, json_len: i32| -> i32 {
+                let json = read_raw_string(&mut caller, json_ptr, json_len).unwrap_or_default();
+                let canonical = canonicalize_json(&json).unwrap_or_else(|| {
+                    debug!("json_canonicalize: invalid JSON");
+                    String::new()
+                });
+                write_string_to_caller(&mut caller, &canonical)
+            },
+        )
+        .map_err(|e| RuntimeError::wasm(format!("Failed to define json_canonicalize: {}", e)))?;
+
     Ok(())
 }
 
+/// `json` with the whitespace between tokens removed, or None if it is not
+/// valid JSON. Strings, numbers and key order are left exactly as written.
+fn minify_json(json: &str) -> Option<String> {
+    serde_json::from_str::<serde::de::IgnoredAny>(json).ok()?;
+    let mut out = String::with_capacity(json.len());
+    let (mut in_string, mut escaped) = (false, false);
+    for ch in json.chars() {
+        if in_string {
+            out.push(ch);
+            if escaped {
+                escaped = false;
+            } else if ch == '\\' {
+                escaped = true;
+            } else if ch == '"' {
+                in_string = false;
+            }
+        } else if !matches!(ch, ' ' | '\t' | '\n' | '\r') {
+            in_string = ch == '"';
+            out.push(ch);
+        }
+    }
+    Some(out)
+}
+
+/// `json` re-serialized without whitespace and with every object's keys in
+/// byte order, or None if it is not valid JSON.
+fn canonicalize_json(json: &str) -> Option<String> {
+    fn sort_keys(value: serde_json::Value) -> serde_json::Value {
+        match value {
+            serde_json::Value::Object(fields) => {
+                let mut fields: Vec<_> = fields.into_iter().collect();
+                fields.sort_by(|(a, _), (b, _)| a.cmp(b));
+                serde_json::Value::Object(
+                    fields
+                        .into_iter()
+                        .map(|(key, v)| (key, sort_keys(v)))
+                        .collect(),
+                )
+            }
+            serde_json::Value::Array(items) => {
+                serde_json::Value::Array(items.into_iter().map(sort_keys).collect())
+            }
+            other => other,
+        }
+    }
+    let value: serde_json::Value = serde_json::from_str(json).ok()?;
+    Some(sort_keys(value).to_string())
+}
+
 /// Register island component functions (_island_register)
 fn register_islands_functions(linker: &mut Linker<WasmState>) -> RuntimeResult<()> {
     // _island_register - Register an island component for client-side hydration
@@ -3641,7 +4215,7 @@ fn register_ui_functions(linker: &mut Linker<WasmState>) -> RuntimeResult<()> {
 /// produce an empty string. To emit a literal brace in output, escape with
 /// `{{` (renders as `{`) or `}}` (renders as `}`). Malformed placeholders
 /// (empty key, newline inside braces) are preserved as literals.
-fn substitute_template(template: &str, data: &serde_json::Value) -> String {
+pub(crate) fn substitute_template(template: &str, data: &serde_json::Value) -> String {
     // `{`, `}`, `\n`, `\r` are all ASCII, so byte-level scanning at char
     // boundaries is safe inside a `&str`: every ASCII byte is its own char.
     let bytes = template.as_bytes();
@@ -4996,10 +5570,10 @@ fn register_test_functions(linker: &mut Linker<WasmState>) -> RuntimeResult<()>
         };
 
         // Look up route and extract path params
-        let (handler_name, path_params) = {
+        let (handler_name, route_pattern, path_params) = {
             let state = caller.data();
             match state.router.find(http_method, &clean_path) {
-                Some((handler, params)) => (handler.handler_name.clone(), params),
+                Some((handler, params)) => (handler.handler_name.clone(), handler.path, params),
                 None => {
                     debug!("_test_http_request: no route for {} {}", method, clean_path);
                     return -1;
@@ -5049,6 +5623,9 @@ fn register_test_functions(linker: &mut Linker<WasmState>) -> RuntimeResult<()>
                 body,
                 params: path_params,
                 query,
+                raw_query: query_str,
+                client: None,
+                route_pattern: Some(route_pattern),
             });
             state.pending_status = None;
             state.pending_body = None;
@@ -5625,7 +6202,7 @@ fn register_websocket_functions(linker: &mut Linker<WasmState>) -> RuntimeResult
                 // handler can detect it as a WebSocket route.
                 let router = caller.data().router.clone();
                 if let Err(e) = router.register_ws(path.clone(), on_connect.clone()) {
-                    error!(
+                    debug!(
                         "_http_ws_route: Failed to register WS route {}: {}",
                         path, e
                     );
@@ -6590,6 +7167,105 @@ fn register_locale_functions(linker: &mut Linker<WasmState>) -> RuntimeResult<()
     Ok(())
 }
 
+/// Register form input checks (validate_email, normalize_url)
+fn register_validation_functions(linker: &mut Linker<WasmState>) -> RuntimeResult<()> {
+    // validate_email(ptr, len) -> 1 if the string is a plausible address, else 0
+    linker
+        .func_wrap(
+            "env",
+            "validate_email",
+            |mut caller: Caller<'_, WasmState>, ptr: i32, len: i32| -> i32 {
+                let email = read_raw_string(&mut caller, ptr, len).unwrap_or_default();
+                is_valid_email(&email) as i32
+            },
+        )
+        .map_err(|e| RuntimeError::wasm(format!("Failed to define validate_email: {}", e)))?;
+
+    // normalize_url(ptr, len) -> ptr: the URL with a lowercased host and no
+    // default port; empty when it is not an absolute URL with a host
+    linker
+        .func_wrap(
+            "env",
+            "normalize_url",
+            |mut caller: Caller<'_, WasmState>, ptr: i32, len: i32| -> i32 {
+                let raw = read_raw_string(&mut caller, ptr, len).unwrap_or_default();
+                let normalized = normalize_url(&raw).unwrap_or_else(|| {
+                    debug!("normalize_url: invalid URL");
+                    String::new()
+                });
+                write_string_to_caller(&mut caller, &normalized)
+            },
+        )
+        .map_err(|e| RuntimeError::wasm(format!("Failed to define normalize_url: {}", e)))?;
+
+    Ok(())
+}
+
+/// Whether `email` looks like `local@domain`: a dot-atom local part of at
+/// most 64 bytes and a domain of at least two hostname labels. Quoted local
+/// parts and IP-literal domains are rejected, as forms rarely want them.
+fn is_valid_email(email: &str) -> bool {
+    const LOCAL_SPECIALS: &str = "!#$%&'*+/=?^_`{|}~-";
+
+    let Some((local, domain)) = email.rsplit_once('@') else {
+        return false;
+    };
+    if email.len() > 254 || local.is_empty() || local.len() > 64 {
+        return false;
+    }
+    let local_ok = local.split('.').all(|atom| {
+        !atom.is_empty()
+            && atom
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || LOCAL_SPECIALS.contains(c))
+    });
+    if !local_ok || domain.len() > 253 {
+        return false;
+    }
+    let labels: Vec<&str> = domain.split('.').collect();
+    labels.len() >= 2
+        && labels.iter().all(|label| {
+            (1..=63).contains(&label.len())
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        })
+        && labels
+            .last()
+            .is_some_and(|tld| !tld.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// `raw` parsed as an absolute URL with a host, with the host lowercased and
+/// a port equal to the scheme's default dropped, or None if it is not one.
+fn normalize_url(raw: &str) -> Option<String> {
+    let mut url = url::Url::parse(raw.trim()).ok()?;
+    // Special schemes (http, https, ws, ...) already come back with a
+    // lowercased host and no default port; other schemes keep both as written
+    let host = url.host_str()?.to_ascii_lowercase();
+    if host.is_empty() {
+        return None;
+    }
+    url.set_host(Some(&host)).ok()?;
+    if url.port().is_some() && url.port() == default_port(url.scheme()) {
+        url.set_port(None).ok()?;
+    }
+    Some(url.to_string())
+}
+
+/// Default port of schemes the `url` crate does not know one for
+fn default_port(scheme: &str) -> Option<u16> {
+    match scheme {
+        "ssh" | "sftp" => Some(22),
+        "postgres" | "postgresql" => Some(5432),
+        "mysql" => Some(3306),
+        "redis" => Some(6379),
+        "smtp" => Some(25),
+        "ldap" => Some(389),
+        "ldaps" => Some(636),
+        _ => None,
+    }
+}
+
 /// Register dot-notation aliases for all Layer 3 `_namespace_fn` bridge functions.
 ///
 /// The Clean Language compiler (0.30.120+) generates WASM imports in both
@@ -6610,14 +7286,19 @@ fn register_dot_aliases(linker: &mut Linker<WasmState>) -> RuntimeResult<()> {
         ("_req_param", "req.param"),
         ("_req_param_int", "req.param_int"),
         ("_req_query", "req.query"),
+        ("_req_raw_query", "req.raw_query"),
         ("_req_body", "req.body"),
         ("_req_body_bytes", "req.body_bytes"),
+        ("_req_body_len", "req.body_len"),
+        ("_req_file_size", "req.file_size"),
         ("_req_body_sha256_hex", "req.body_sha256_hex"),
         ("_req_body_field", "req.body_field"),
+        ("_req_input", "req.input"),
         ("_req_header", "req.header"),
         ("_req_headers", "req.headers"),
         ("_req_method", "req.method"),
         ("_req_path", "req.path"),
+        ("_req_route_pattern", "req.route_pattern"),
         ("_req_cookie", "req.cookie"),
         ("_req_form", "req.form"),
         ("_req_ip", "req.ip"),
@@ -6654,7 +7335,7 @@ fn register_dot_aliases(linker: &mut Linker<WasmState>) -> RuntimeResult<()> {
         ("_res_json", "res.json"),
         ("_http_set_cache", "http.set_cache"),
         ("_http_no_cache", "http.no_cache"),
-        // _res_download, _email_configure, _email_send, _email_last_error aliases are
+        // _res_download, _res_set_binary, _email_configure, _email_send, _email_last_error aliases are
         // derived automatically by the register_bridge_fn! macro.
         ("_json_encode", "json.encode"),
         ("_json_decode", "json.decode"),
@@ -6777,6 +7458,92 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn json_minify_strips_whitespace_only() {
+        let json = "{ \"b\" : [1, 2.50 ,\n\t\"x y\\\" z\"],\r\n \"a\": {} }";
+        let minified = minify_json(json).unwrap();
+        assert_eq!(minified, r#"{"b":[1,2.50,"x y\" z"],"a":{}}"#);
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&minified).unwrap(),
+            serde_json::from_str::<serde_json::Value>(json).unwrap()
+        );
+        assert_eq!(minify_json("{\"a\": }"), None);
+        assert_eq!(minify_json(""), None);
+    }
+
+    #[test]
+    fn json_canonicalize_sorts_keys_at_every_depth() {
+        let canonical =
+            canonicalize_json(r#"{ "z": 1, "a": { "y": [ { "d": 4, "c": 3 } ], "b": null } }"#);
+        assert_eq!(
+            canonical.as_deref(),
+            Some(r#"{"a":{"b":null,"y":[{"c":3,"d":4}]},"z":1}"#)
+        );
+        assert_eq!(canonicalize_json("[1,"), None);
+    }
+
+    #[test]
+    fn validate_email_accepts_dot_atoms_at_hostnames() {
+        for email in [
+            "user@example.com",
+            "first.last+tag@mail.example.co.uk",
+            "o'brien_99@sub-domain.example.org",
+        ] {
+            assert!(is_valid_email(email), "{email}");
+        }
+        for email in [
+            "",
+            "plain",
+            "@example.com",
+            "user@",
+            "user@localhost",
+            "user@@example.com",
+            ".user@example.com",
+            "us..er@example.com",
+            "user.@example.com",
+            "us er@example.com",
+            "user@-example.com",
+            "user@example..com",
+            "user@192.168.0.1",
+            &format!("{}@example.com", "a".repeat(65)),
+        ] {
+            assert!(!is_valid_email(email), "{email}");
+        }
+    }
+
+    #[test]
+    fn normalize_url_lowercases_host_and_drops_default_ports() {
+        let cases = [
+            (
+                "HTTP://Example.COM:80/a/../b?q=1#f",
+                "http://example.com/b?q=1#f",
+            ),
+            ("https://EXAMPLE.com:443", "https://example.com/"),
+            ("https://example.com:8443/x", "https://example.com:8443/x"),
+            ("  https://example.com/Path  ", "https://example.com/Path"),
+            (
+                "postgres://User@DB.Internal:5432/app",
+                "postgres://User@db.internal/app",
+            ),
+            (
+                "ssh://Git.Example.com:2222/repo",
+                "ssh://git.example.com:2222/repo",
+            ),
+        ];
+        for (raw, expected) in cases {
+            assert_eq!(normalize_url(raw).as_deref(), Some(expected), "{raw}");
+        }
+        for raw in [
+            "",
+            "example.com",
+            "/relative/path",
+            "mailto:user@example.com",
+            "http://",
+        ] {
+            assert_eq!(normalize_url(raw), None, "{raw}");
+        }
+    }
+
     #[test]
     fn test_json_get_path_logic() {
         // Tests the path traversal logic used by _json_get, including numeric array indices
@@ -0,0 +1,31 @@
+//! Source of the current time for the `_time_*` bridges.
+//!
+//! Servers read the system clock. Tests can pin it with `Clock::Fixed` so
+//! handlers that depend on the time return the same output on every run.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Where `_time_now` and the other time bridges get the current time
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Clock {
+    /// The host's wall clock
+    #[default]
+    System,
+    /// Always the given instant
+    Fixed(SystemTime),
+}
+
+impl Clock {
+    /// A clock fixed at `secs` seconds after the Unix epoch.
+    pub fn fixed_at_unix_secs(secs: u64) -> Self {
+        Clock::Fixed(UNIX_EPOCH + Duration::from_secs(secs))
+    }
+
+    /// The current time according to this clock.
+    pub fn now(&self) -> SystemTime {
+        match self {
+            Clock::System => SystemTime::now(),
+            Clock::Fixed(instant) => *instant,
+        }
+    }
+}
@@ -0,0 +1,230 @@
+//! Single-flight coalescing of identical concurrent GET requests.
+//!
+//! Enabled by `ServerConfig::coalesce_requests`. While a GET is running its
+//! WASM handler, further GETs with the same key (path, query and the
+//! `Authorization`, `Cookie`, `Accept`, `Accept-Encoding` and
+//! `Accept-Language` headers) wait for it and get a copy of its response
+//! instead of running the handler themselves, so a burst of identical
+//! requests costs one handler call. Waiting requests are marked with
+//! `X-Coalesced: true`. Streamed responses and responses setting cookies
+//! are not shared; the waiters then run the handler on their own, as they
+//! do when the first request is dropped before it finishes.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use axum::{
+    body::{Body, Bytes},
+    extract::{Request, State},
+    http::{HeaderMap, HeaderName, HeaderValue, Method, StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use parking_lot::Mutex;
+use tokio::sync::watch;
+use tracing::{debug, warn};
+
+/// Header added to responses shared from another request
+pub const COALESCED_HEADER: &str = "x-coalesced";
+
+/// Request headers that can change the response, so they are part of the key
+const KEY_HEADERS: [HeaderName; 5] = [
+    header::AUTHORIZATION,
+    header::COOKIE,
+    header::ACCEPT,
+    header::ACCEPT_ENCODING,
+    header::ACCEPT_LANGUAGE,
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct FlightKey {
+    path_and_query: String,
+    headers: Vec<Option<HeaderValue>>,
+}
+
+impl FlightKey {
+    fn new(req: &Request) -> Self {
+        Self {
+            path_and_query: req
+                .uri()
+                .path_and_query()
+                .map(|pq| pq.as_str().to_string())
+                .unwrap_or_else(|| req.uri().path().to_string()),
+            headers: KEY_HEADERS
+                .iter()
+                .map(|name| req.headers().get(name).cloned())
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct SharedResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+/// What waiters see: `None` until the leader finishes, then its response, or
+/// `Some(None)` when it cannot be shared.
+type Outcome = Option<Option<Arc<SharedResponse>>>;
+
+#[derive(Debug, Default)]
+pub struct RequestCoalescer {
+    in_flight: Mutex<HashMap<FlightKey, watch::Receiver<Outcome>>>,
+}
+
+pub type SharedRequestCoalescer = Arc<RequestCoalescer>;
+
+impl RequestCoalescer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Join the request running for `key`, or register this one as running
+    /// it, in which case the returned flight must be completed.
+    fn join(self: &Arc<Self>, key: FlightKey) -> Result<watch::Receiver<Outcome>, Flight> {
+        let mut in_flight = self.in_flight.lock();
+        if let Some(receiver) = in_flight.get(&key) {
+            return Ok(receiver.clone());
+        }
+        let (sender, receiver) = watch::channel(None);
+        in_flight.insert(key.clone(), receiver);
+        Err(Flight {
+            coalescer: Arc::clone(self),
+            key,
+            sender,
+        })
+    }
+}
+
+/// The request running the handler for a key. Dropping it, finished or
+/// not, lets the next identical request start a new flight.
+#[derive(Debug)]
+struct Flight {
+    coalescer: SharedRequestCoalescer,
+    key: FlightKey,
+    sender: watch::Sender<Outcome>,
+}
+
+impl Flight {
+    fn finish(self, response: Option<Arc<SharedResponse>>) {
+        self.sender.send_replace(Some(response));
+    }
+}
+
+impl Drop for Flight {
+    fn drop(&mut self) {
+        self.coalescer.in_flight.lock().remove(&self.key);
+    }
+}
+
+fn shared_copy(shared: &SharedResponse) -> Response {
+    let mut response = Response::new(Body::from(shared.body.clone()));
+    *response.status_mut() = shared.status;
+    *response.headers_mut() = shared.headers.clone();
+    response
+        .headers_mut()
+        .insert(COALESCED_HEADER, HeaderValue::from_static("true"));
+    response
+}
+
+/// axum middleware letting identical concurrent GETs share one response.
+pub async fn coalesce_middleware(
+    State(coalescer): State<SharedRequestCoalescer>,
+    req: Request,
+    next: Next,
+) -> Response {
+    // WebSocket upgrades are GETs too, but each needs its own connection.
+    if req.method() != Method::GET || req.headers().contains_key(header::UPGRADE) {
+        return next.run(req).await;
+    }
+    let key = FlightKey::new(&req);
+
+    let flight = match coalescer.join(key) {
+        Ok(mut receiver) => {
+            let outcome = receiver
+                .wait_for(Option::is_some)
+                .await
+                .ok()
+                .and_then(|o| o.clone());
+            if let Some(Some(shared)) = outcome {
+                debug!("Sharing in-flight response for {}", req.uri());
+                return shared_copy(&shared);
+            }
+            // The leader's response could not be shared: run our own
+            return next.run(req).await;
+        }
+        Err(flight) => flight,
+    };
+
+    let response = next.run(req).await;
+    let is_stream = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|ct| ct.starts_with("text/event-stream"));
+    if is_stream || response.headers().contains_key(header::SET_COOKIE) {
+        flight.finish(None);
+        return response;
+    }
+
+    let (parts, body) = response.into_parts();
+    let body = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(body) => body,
+        Err(e) => {
+            warn!("Failed to buffer response for request coalescing: {}", e);
+            flight.finish(None);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    flight.finish(Some(Arc::new(SharedResponse {
+        status: parts.status,
+        headers: parts.headers.clone(),
+        body: body.clone(),
+    })));
+    Response::from_parts(parts, Body::from(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(req: Request) -> FlightKey {
+        FlightKey::new(&req)
+    }
+
+    fn get(uri: &str) -> axum::http::request::Builder {
+        Request::builder().method(Method::GET).uri(uri)
+    }
+
+    #[test]
+    fn key_covers_query_and_identity_headers() {
+        let plain = key(get("/a?x=1").body(Body::empty()).unwrap());
+        assert_eq!(plain, key(get("/a?x=1").body(Body::empty()).unwrap()));
+        assert_ne!(plain, key(get("/a?x=2").body(Body::empty()).unwrap()));
+        let authorized = get("/a?x=1")
+            .header(header::AUTHORIZATION, "Bearer t")
+            .body(Body::empty())
+            .unwrap();
+        assert_ne!(plain, key(authorized));
+        let traced = get("/a?x=1")
+            .header("x-request-id", "abc")
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(plain, key(traced));
+    }
+
+    #[test]
+    fn dropped_flight_frees_the_key() {
+        let coalescer = Arc::new(RequestCoalescer::new());
+        let key = key(get("/a").body(Body::empty()).unwrap());
+        let flight = coalescer
+            .join(key.clone())
+            .expect_err("first request leads");
+        let waiter = coalescer.join(key.clone()).expect("second request waits");
+        drop(flight);
+        assert!(waiter.has_changed().is_err(), "waiter sees the leader gone");
+        assert!(coalescer.join(key).is_err(), "next request leads again");
+    }
+}
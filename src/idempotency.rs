@@ -0,0 +1,361 @@
+//! Idempotency-key response replay and axum middleware.
+//!
+//! Enabled by `ServerConfig::idempotency_header` (usually `Idempotency-Key`).
+//! The first successful (2xx) response to a non-GET/HEAD request carrying
+//! the header is cached under (method, path, key, credentials) for
+//! `ServerConfig::idempotency_ttl_secs`; repeats within that time get the
+//! cached status, headers and body back without the WASM handler running
+//! again, marked with `Idempotent-Replayed: true`. Failed responses are not
+//! cached, so the client can retry them.
+//!
+//! Keys are scoped to the caller's `Authorization` and `Cookie` headers, so
+//! one client cannot replay another's response by reusing its key. While
+//! the first request for a key runs, the key is reserved: duplicates wait
+//! for it and then replay its response, or run themselves if it failed. At
+//! most `ServerConfig::idempotency_max_entries` keys are kept; when full,
+//! the oldest response is evicted, and requests arriving while every slot
+//! is reserved run without replay protection.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::{
+    body::{Body, Bytes},
+    extract::{Request, State},
+    http::{HeaderMap, HeaderName, HeaderValue, Method, StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use parking_lot::Mutex;
+use tokio::sync::watch;
+use tracing::{debug, warn};
+
+/// Header added to replayed responses
+pub const REPLAYED_HEADER: &str = "idempotent-replayed";
+
+/// Request headers identifying the caller a key belongs to
+const CREDENTIAL_HEADERS: [HeaderName; 2] = [header::AUTHORIZATION, header::COOKIE];
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct EntryKey {
+    method: Method,
+    path: String,
+    key: String,
+    credentials: Vec<Option<HeaderValue>>,
+}
+
+#[derive(Debug)]
+struct CachedResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+    stored_at: Instant,
+}
+
+#[derive(Debug)]
+enum Entry {
+    /// The first request for the key is still running; the receiver's
+    /// sender is dropped when it finishes
+    Pending(watch::Receiver<()>),
+    Done(CachedResponse),
+}
+
+/// Outcome of looking up a key in `IdempotencyStore::claim`
+enum Claim {
+    /// A cached response to send back
+    Replay(Response),
+    /// Another request holds the key; wait for it to finish, then retry
+    Wait(watch::Receiver<()>),
+    /// This request holds the key and runs the handler
+    Reserved(Reservation),
+    /// The store is full of reservations; run without one
+    Full,
+}
+
+#[derive(Debug)]
+pub struct IdempotencyStore {
+    header: HeaderName,
+    ttl: Duration,
+    max_entries: usize,
+    entries: Mutex<HashMap<EntryKey, Entry>>,
+}
+
+impl IdempotencyStore {
+    pub fn new(header: HeaderName, ttl: Duration, max_entries: usize) -> Self {
+        Self {
+            header,
+            ttl,
+            max_entries,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn is_fresh(&self, cached: &CachedResponse) -> bool {
+        cached.stored_at.elapsed() < self.ttl
+    }
+
+    /// Replay the response cached for `key`, wait for the request holding
+    /// it, or reserve it for this request.
+    fn claim(self: &Arc<Self>, key: &EntryKey) -> Claim {
+        let mut entries = self.entries.lock();
+        match entries.get(key) {
+            Some(Entry::Done(cached)) if self.is_fresh(cached) => {
+                return Claim::Replay(replay(cached));
+            }
+            Some(Entry::Pending(done)) => return Claim::Wait(done.clone()),
+            _ => {}
+        }
+        if entries.len() >= self.max_entries && !entries.contains_key(key) {
+            entries.retain(|_, entry| match entry {
+                Entry::Done(cached) => self.is_fresh(cached),
+                Entry::Pending(_) => true,
+            });
+            if entries.len() >= self.max_entries {
+                let oldest = entries
+                    .iter()
+                    .filter_map(|(key, entry)| match entry {
+                        Entry::Done(cached) => Some((key, cached.stored_at)),
+                        Entry::Pending(_) => None,
+                    })
+                    .min_by_key(|(_, stored_at)| *stored_at)
+                    .map(|(key, _)| key.clone());
+                match oldest {
+                    Some(oldest) => {
+                        entries.remove(&oldest);
+                    }
+                    None => return Claim::Full,
+                }
+            }
+        }
+        let (done, pending) = watch::channel(());
+        entries.insert(key.clone(), Entry::Pending(pending));
+        Claim::Reserved(Reservation {
+            store: self.clone(),
+            key: Some(key.clone()),
+            _done: done,
+        })
+    }
+}
+
+/// The cached response, marked as replayed
+fn replay(cached: &CachedResponse) -> Response {
+    let mut response = Response::new(Body::from(cached.body.clone()));
+    *response.status_mut() = cached.status;
+    *response.headers_mut() = cached.headers.clone();
+    response
+        .headers_mut()
+        .insert(REPLAYED_HEADER, HeaderValue::from_static("true"));
+    response
+}
+
+/// A key held by the request running its handler. Dropping it without
+/// `complete` (failed response, cancelled request) releases the key so a
+/// waiting duplicate can run instead.
+struct Reservation {
+    store: Arc<IdempotencyStore>,
+    key: Option<EntryKey>,
+    /// Dropped last, waking the duplicates waiting on the key
+    _done: watch::Sender<()>,
+}
+
+impl Reservation {
+    /// Cache the response for the reserved key.
+    fn complete(mut self, status: StatusCode, headers: HeaderMap, body: Bytes) {
+        if let Some(key) = self.key.take() {
+            self.store.entries.lock().insert(
+                key,
+                Entry::Done(CachedResponse {
+                    status,
+                    headers,
+                    body,
+                    stored_at: Instant::now(),
+                }),
+            );
+        }
+    }
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        if let Some(key) = self.key.take() {
+            self.store.entries.lock().remove(&key);
+        }
+    }
+}
+
+pub type SharedIdempotencyStore = Arc<IdempotencyStore>;
+
+/// axum middleware replaying cached responses for repeated idempotency keys.
+pub async fn idempotency_middleware(
+    State(store): State<SharedIdempotencyStore>,
+    req: Request,
+    next: Next,
+) -> Response {
+    if matches!(*req.method(), Method::GET | Method::HEAD) {
+        return next.run(req).await;
+    }
+    let Some(key) = req
+        .headers()
+        .get(&store.header)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|k| !k.is_empty())
+    else {
+        return next.run(req).await;
+    };
+    let key = EntryKey {
+        method: req.method().clone(),
+        path: req.uri().path().to_string(),
+        key: key.to_string(),
+        credentials: CREDENTIAL_HEADERS
+            .iter()
+            .map(|name| req.headers().get(name).cloned())
+            .collect(),
+    };
+
+    let reservation = loop {
+        match store.claim(&key) {
+            Claim::Replay(response) => {
+                debug!(
+                    "Replaying cached response for {} {} (idempotency key {})",
+                    key.method, key.path, key.key
+                );
+                return response;
+            }
+            Claim::Wait(mut done) => {
+                // Errors once the request holding the key finishes
+                let _ = done.changed().await;
+            }
+            Claim::Reserved(reservation) => break Some(reservation),
+            Claim::Full => {
+                warn!(
+                    "Idempotency store full; running {} {} without replay protection",
+                    key.method, key.path
+                );
+                break None;
+            }
+        }
+    };
+
+    let response = next.run(req).await;
+    let is_stream = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|ct| ct.starts_with("text/event-stream"));
+    let Some(reservation) = reservation.filter(|_| response.status().is_success() && !is_stream)
+    else {
+        return response;
+    };
+
+    let (parts, body) = response.into_parts();
+    let body = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(body) => body,
+        Err(e) => {
+            warn!("Failed to buffer response for idempotency cache: {}", e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    reservation.complete(parts.status, parts.headers.clone(), body.clone());
+    Response::from_parts(parts, Body::from(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_store(ttl: Duration, max_entries: usize) -> Arc<IdempotencyStore> {
+        Arc::new(IdempotencyStore::new(
+            HeaderName::from_static("idempotency-key"),
+            ttl,
+            max_entries,
+        ))
+    }
+
+    fn key(k: &str) -> EntryKey {
+        EntryKey {
+            method: Method::POST,
+            path: "/pay".to_string(),
+            key: k.to_string(),
+            credentials: vec![None, None],
+        }
+    }
+
+    fn reserve(store: &Arc<IdempotencyStore>, key: &EntryKey) -> Reservation {
+        match store.claim(key) {
+            Claim::Reserved(reservation) => reservation,
+            _ => panic!("expected to reserve {:?}", key.key),
+        }
+    }
+
+    fn complete(store: &Arc<IdempotencyStore>, key: &EntryKey) {
+        reserve(store, key).complete(
+            StatusCode::CREATED,
+            HeaderMap::new(),
+            Bytes::from_static(b"done"),
+        );
+    }
+
+    #[test]
+    fn replays_until_ttl_expires() {
+        let store = new_store(Duration::from_millis(50), 10);
+        complete(&store, &key("a"));
+
+        let Claim::Replay(replayed) = store.claim(&key("a")) else {
+            panic!("expected a replay");
+        };
+        assert_eq!(replayed.status(), StatusCode::CREATED);
+        assert_eq!(replayed.headers()[REPLAYED_HEADER], "true");
+
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(matches!(store.claim(&key("a")), Claim::Reserved(_)));
+    }
+
+    #[test]
+    fn keys_are_scoped_to_credentials() {
+        let store = new_store(Duration::from_secs(60), 10);
+        complete(&store, &key("a"));
+
+        let mut other = key("a");
+        other.credentials[0] = Some(HeaderValue::from_static("Bearer other"));
+        assert!(matches!(store.claim(&other), Claim::Reserved(_)));
+    }
+
+    #[tokio::test]
+    async fn duplicate_waits_for_the_reserved_key() {
+        let store = new_store(Duration::from_secs(60), 10);
+        let reservation = reserve(&store, &key("a"));
+        let Claim::Wait(mut done) = store.claim(&key("a")) else {
+            panic!("expected to wait");
+        };
+
+        // A failed first request releases the key for the duplicate
+        drop(reservation);
+        assert!(done.changed().await.is_err());
+        let reservation = reserve(&store, &key("a"));
+        let Claim::Wait(mut done) = store.claim(&key("a")) else {
+            panic!("expected to wait");
+        };
+        reservation.complete(StatusCode::OK, HeaderMap::new(), Bytes::new());
+        assert!(done.changed().await.is_err());
+        assert!(matches!(store.claim(&key("a")), Claim::Replay(_)));
+    }
+
+    #[test]
+    fn evicts_oldest_response_when_full() {
+        let store = new_store(Duration::from_secs(60), 2);
+        complete(&store, &key("a"));
+        std::thread::sleep(Duration::from_millis(1));
+        complete(&store, &key("b"));
+        complete(&store, &key("c"));
+        assert!(matches!(store.claim(&key("b")), Claim::Replay(_)));
+        assert!(matches!(store.claim(&key("c")), Claim::Replay(_)));
+        assert_eq!(store.entries.lock().len(), 2);
+
+        // Reservations are never evicted
+        let store = new_store(Duration::from_secs(60), 1);
+        let _held = reserve(&store, &key("a"));
+        assert!(matches!(store.claim(&key("b")), Claim::Full));
+    }
+}
@@ -0,0 +1,264 @@
+//! Client address resolution, IP allowlist/denylist filtering and axum
+//! middleware.
+//!
+//! Configured from `ServerConfig::ip_allow` / `ip_deny`. The client address is
+//! the TCP peer, unless the peer is one of `trusted_proxies`, in which case it
+//! is the rightmost `X-Forwarded-For` entry not itself a trusted proxy.
+//! Disallowed clients get 403 before any WASM handler runs.
+
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+
+use axum::body::Body;
+use axum::extract::{ConnectInfo, Request, State};
+use axum::http::{HeaderMap, StatusCode, header};
+use axum::middleware::Next;
+use axum::response::Response;
+use ipnet::IpNet;
+use tracing::warn;
+
+/// Parse a CIDR network, accepting a bare address as a single-host network
+/// (`10.0.0.1` → `10.0.0.1/32`, `::1` → `::1/128`).
+pub fn parse_net(s: &str) -> Result<IpNet, String> {
+    let s = s.trim();
+    s.parse::<IpNet>()
+        .or_else(|_| s.parse::<IpAddr>().map(IpNet::from))
+        .map_err(|_| format!("invalid IP address or CIDR network: {:?}", s))
+}
+
+/// Proxies whose `X-Forwarded-For` / `X-Forwarded-Proto` headers are believed.
+///
+/// From any other peer the headers are ignored, so a client cannot spoof its
+/// address or scheme by sending them directly.
+#[derive(Debug, Clone, Default)]
+pub struct TrustedProxies {
+    nets: Vec<IpNet>,
+}
+
+impl TrustedProxies {
+    pub fn new(nets: Vec<IpNet>) -> Self {
+        Self { nets }
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        let ip = canonical(ip);
+        self.nets.iter().any(|net| net.contains(&ip))
+    }
+
+    /// Resolve the client address for a connection from `peer`.
+    ///
+    /// `X-Forwarded-For` is only consulted when `peer` is trusted, and is
+    /// walked right to left so a client cannot spoof its address by
+    /// prepending entries.
+    pub fn client_ip(&self, peer: IpAddr, headers: &HeaderMap) -> IpAddr {
+        let mut client = canonical(peer);
+        if !self.contains(client) {
+            return client;
+        }
+        let forwarded = headers
+            .get_all("x-forwarded-for")
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .collect::<Vec<_>>();
+        for entry in forwarded.into_iter().rev() {
+            let Ok(ip) = entry.trim().parse::<IpAddr>() else {
+                // An unparsable hop ends the trusted chain.
+                break;
+            };
+            client = canonical(ip);
+            if !self.contains(client) {
+                break;
+            }
+        }
+        client
+    }
+
+    /// Resolve the request scheme (`"http"` or `"https"`) for a connection
+    /// from `peer`.
+    ///
+    /// Uses the last `X-Forwarded-Proto` value, as set by the trusted peer
+    /// itself, when `peer` is trusted; otherwise the connection's own scheme.
+    pub fn scheme(&self, peer: IpAddr, headers: &HeaderMap) -> &'static str {
+        const CONNECTION_SCHEME: &str = "http";
+        if !self.contains(peer) {
+            return CONNECTION_SCHEME;
+        }
+        let forwarded = headers
+            .get_all("x-forwarded-proto")
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .next_back()
+            .map(str::trim);
+        match forwarded {
+            Some(proto) if proto.eq_ignore_ascii_case("https") => "https",
+            Some(proto) if proto.eq_ignore_ascii_case("http") => "http",
+            _ => CONNECTION_SCHEME,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct IpFilter {
+    allow: Vec<IpNet>,
+    deny: Vec<IpNet>,
+    trusted_proxies: TrustedProxies,
+}
+
+impl IpFilter {
+    pub fn new(allow: Vec<IpNet>, deny: Vec<IpNet>, trusted_proxies: TrustedProxies) -> Self {
+        Self {
+            allow,
+            deny,
+            trusted_proxies,
+        }
+    }
+
+    /// Whether any allow or deny rule is configured
+    pub fn is_active(&self) -> bool {
+        !self.allow.is_empty() || !self.deny.is_empty()
+    }
+
+    /// Deny rules win; a non-empty allow list admits only matching addresses.
+    pub fn is_allowed(&self, ip: IpAddr) -> bool {
+        let ip = canonical(ip);
+        if self.deny.iter().any(|net| net.contains(&ip)) {
+            return false;
+        }
+        self.allow.is_empty() || self.allow.iter().any(|net| net.contains(&ip))
+    }
+
+    /// Client address for a connection from `peer`; see
+    /// [`TrustedProxies::client_ip`].
+    pub fn client_ip(&self, peer: IpAddr, headers: &HeaderMap) -> IpAddr {
+        self.trusted_proxies.client_ip(peer, headers)
+    }
+}
+
+/// Treat IPv4-mapped IPv6 peers (`::ffff:a.b.c.d`, seen on dual-stack
+/// listeners) as their IPv4 address so IPv4 rules match them.
+fn canonical(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => v6
+            .to_ipv4_mapped()
+            .map(IpAddr::V4)
+            .unwrap_or(IpAddr::V6(v6)),
+        v4 => v4,
+    }
+}
+
+pub type SharedIpFilter = Arc<IpFilter>;
+
+/// axum middleware that answers 403 for clients rejected by `SharedIpFilter`.
+///
+/// Requests without a `ConnectInfo<SocketAddr>` extension have no known peer
+/// and are rejected whenever an allow list is configured.
+pub async fn ip_filter_middleware(
+    State(filter): State<SharedIpFilter>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let peer = req
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+    let allowed = match peer {
+        Some(peer) => filter.is_allowed(filter.client_ip(peer, req.headers())),
+        None => filter.allow.is_empty(),
+    };
+    if allowed {
+        return next.run(req).await;
+    }
+    warn!(
+        "Rejecting {} {} from {:?}: client IP not allowed",
+        req.method(),
+        req.uri().path(),
+        peer.map(|peer| filter.client_ip(peer, req.headers()))
+    );
+    Response::builder()
+        .status(StatusCode::FORBIDDEN)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(r#"{"ok":false,"error":"forbidden"}"#))
+        .expect("ip-filter response builder")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn nets(list: &[&str]) -> Vec<IpNet> {
+        list.iter().map(|s| parse_net(s).unwrap()).collect()
+    }
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parse_net_accepts_bare_addresses_and_cidrs() {
+        assert_eq!(parse_net("10.0.0.1").unwrap().to_string(), "10.0.0.1/32");
+        assert_eq!(parse_net("::1").unwrap().to_string(), "::1/128");
+        assert_eq!(parse_net(" 10.0.0.0/8 ").unwrap().to_string(), "10.0.0.0/8");
+        assert!(parse_net("10.0.0.0/33").is_err());
+        assert!(parse_net("localhost").is_err());
+    }
+
+    #[test]
+    fn deny_wins_over_allow_and_allow_list_is_exclusive() {
+        let filter = IpFilter::new(
+            nets(&["10.0.0.0/8"]),
+            nets(&["10.1.0.0/16"]),
+            TrustedProxies::default(),
+        );
+        assert!(filter.is_allowed(ip("10.2.3.4")));
+        assert!(!filter.is_allowed(ip("10.1.2.3")));
+        assert!(!filter.is_allowed(ip("192.168.1.1")));
+        assert!(filter.is_allowed(ip("::ffff:10.2.3.4")));
+
+        let deny_only = IpFilter::new(vec![], nets(&["127.0.0.1"]), TrustedProxies::default());
+        assert!(!deny_only.is_allowed(ip("127.0.0.1")));
+        assert!(deny_only.is_allowed(ip("127.0.0.2")));
+    }
+
+    #[test]
+    fn forwarded_for_is_only_trusted_from_known_proxies() {
+        let proxies = TrustedProxies::new(nets(&["10.0.0.0/8"]));
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-forwarded-for",
+            HeaderValue::from_static("1.1.1.1, 203.0.113.7, 10.0.0.2"),
+        );
+
+        // Untrusted peer: header ignored.
+        assert_eq!(
+            proxies.client_ip(ip("198.51.100.1"), &headers),
+            ip("198.51.100.1")
+        );
+        // Trusted peer: rightmost untrusted hop, not the spoofable leftmost one.
+        assert_eq!(
+            proxies.client_ip(ip("10.0.0.1"), &headers),
+            ip("203.0.113.7")
+        );
+        // Trusted peer without the header: the peer itself.
+        assert_eq!(
+            proxies.client_ip(ip("10.0.0.1"), &HeaderMap::new()),
+            ip("10.0.0.1")
+        );
+    }
+
+    #[test]
+    fn forwarded_proto_is_only_trusted_from_known_proxies() {
+        let proxies = TrustedProxies::new(nets(&["10.0.0.1"]));
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-proto", HeaderValue::from_static("http, HTTPS"));
+
+        assert_eq!(proxies.scheme(ip("198.51.100.1"), &headers), "http");
+        assert_eq!(proxies.scheme(ip("10.0.0.1"), &headers), "https");
+        assert_eq!(proxies.scheme(ip("10.0.0.1"), &HeaderMap::new()), "http");
+
+        headers.insert("x-forwarded-proto", HeaderValue::from_static("gopher"));
+        assert_eq!(proxies.scheme(ip("10.0.0.1"), &headers), "http");
+    }
+}
@@ -273,7 +273,54 @@ pub struct CronSchedule {
 /// are deleted at startup.
 const JOBS_RETENTION_DAYS: u64 = 7;
 
+/// Pool job records are written through to
+#[cfg(feature = "sqlite")]
+pub type JobsPool = sqlx::SqlitePool;
+
+/// Built without the `sqlite` feature there is nothing to persist jobs to.
+/// The type has no values, so `JobsStore::sqlite_pool` is always `None`
+/// and the stand-ins in `no_persistence` are never called.
+#[cfg(not(feature = "sqlite"))]
+#[derive(Clone)]
+pub enum JobsPool {}
+
+#[cfg(not(feature = "sqlite"))]
+use no_persistence::*;
+
+#[cfg(not(feature = "sqlite"))]
+mod no_persistence {
+    use super::{JobRecord, JobsPool, SharedJobsState};
+
+    pub(super) async fn ensure_jobs_table(pool: &JobsPool) -> anyhow::Result<()> {
+        match *pool {}
+    }
+
+    pub(super) async fn db_insert_job(pool: &JobsPool, _: &JobRecord) -> anyhow::Result<()> {
+        match *pool {}
+    }
+
+    pub(super) async fn db_update_job(pool: &JobsPool, _: &JobRecord) -> anyhow::Result<()> {
+        match *pool {}
+    }
+
+    pub(super) async fn db_query_job_by_id(
+        pool: &JobsPool,
+        _: &str,
+    ) -> anyhow::Result<Option<(String, Option<String>, Option<String>)>> {
+        match *pool {}
+    }
+
+    pub(super) async fn db_cleanup_old_jobs(pool: &JobsPool, _: u64) -> anyhow::Result<u64> {
+        match *pool {}
+    }
+
+    pub(super) async fn recover_from_disk(_: &SharedJobsState, pool: &JobsPool) {
+        match *pool {}
+    }
+}
+
 /// Ensure the `__clean_jobs` table and index exist.
+#[cfg(feature = "sqlite")]
 pub async fn ensure_jobs_table(pool: &sqlx::SqlitePool) -> anyhow::Result<()> {
     sqlx::query(
         "CREATE TABLE IF NOT EXISTS __clean_jobs (
@@ -305,6 +352,7 @@ pub async fn ensure_jobs_table(pool: &sqlx::SqlitePool) -> anyhow::Result<()> {
 }
 
 /// Persist a freshly created `JobRecord` to SQLite (INSERT).
+#[cfg(feature = "sqlite")]
 async fn db_insert_job(pool: &sqlx::SqlitePool, r: &JobRecord) -> anyhow::Result<()> {
     sqlx::query(
         "INSERT INTO __clean_jobs
@@ -328,6 +376,7 @@ async fn db_insert_job(pool: &sqlx::SqlitePool, r: &JobRecord) -> anyhow::Result
 /// Write the mutable fields of a `JobRecord` back to SQLite (UPDATE).
 ///
 /// Called after every state transition (Pending→Running, Running→Succeeded, etc.).
+#[cfg(feature = "sqlite")]
 async fn db_update_job(pool: &sqlx::SqlitePool, r: &JobRecord) -> anyhow::Result<()> {
     sqlx::query(
         "UPDATE __clean_jobs
@@ -359,6 +408,7 @@ async fn db_update_job(pool: &sqlx::SqlitePool, r: &JobRecord) -> anyhow::Result
 /// Query a single row from `__clean_jobs` by id without loading it into memory.
 ///
 /// Returns `(status_str, result_json_opt, error_message_opt)` or `None` when not found.
+#[cfg(feature = "sqlite")]
 async fn db_query_job_by_id(
     pool: &sqlx::SqlitePool,
     job_id: &str,
@@ -377,6 +427,7 @@ async fn db_query_job_by_id(
 /// Targets `succeeded`, `failed`, and `cancelled` rows whose `finished_at_ms`
 /// is older than the retention window.  Rows with NULL `finished_at_ms` are
 /// never deleted here (they are still active).
+#[cfg(feature = "sqlite")]
 async fn db_cleanup_old_jobs(pool: &sqlx::SqlitePool, retention_days: u64) -> anyhow::Result<u64> {
     let cutoff = now_ms().saturating_sub(retention_days * 86_400_000);
     let result = sqlx::query(
@@ -407,7 +458,7 @@ pub struct JobsStore {
     pub schedules: HashMap<String, CronSchedule>,
     /// Optional SQLite pool for write-through persistence.
     /// `None` when the server is running without a SQLite database.
-    pub sqlite_pool: Option<sqlx::SqlitePool>,
+    pub sqlite_pool: Option<JobsPool>,
 }
 
 impl JobsStore {
@@ -422,7 +473,7 @@ impl JobsStore {
 
     /// Wire in a SQLite pool for persistence.  Called once during server startup,
     /// before the worker loop is started.
-    pub fn set_sqlite_pool(&mut self, pool: sqlx::SqlitePool) {
+    pub fn set_sqlite_pool(&mut self, pool: JobsPool) {
         self.sqlite_pool = Some(pool);
     }
 }
@@ -494,6 +545,7 @@ pub async fn init_persistence(state: &SharedJobsState, retention_days: u64) {
 ///
 /// `running` rows are reset to `pending` (the handler that was executing
 /// was cut short by the process kill and must be retried).
+#[cfg(feature = "sqlite")]
 async fn recover_from_disk(state: &SharedJobsState, pool: &sqlx::SqlitePool) {
     let rows = sqlx::query_as::<
         _,
@@ -606,11 +658,12 @@ async fn recover_from_disk(state: &SharedJobsState, pool: &sqlx::SqlitePool) {
 /// Calls the same internal recovery logic as the startup path, but accepts the
 /// pool as a direct parameter so tests can pass an in-memory pool without
 /// attaching it to the `JobsStore`.
-pub async fn recover_from_disk_with_pool(state: &SharedJobsState, pool: &sqlx::SqlitePool) {
+pub async fn recover_from_disk_with_pool(state: &SharedJobsState, pool: &JobsPool) {
     recover_from_disk(state, pool).await;
 }
 
 /// Helper to make the borrow-checker happy around the conditional pool flush.
+#[cfg(feature = "sqlite")]
 #[inline(always)]
 fn was_running_reset_needed(reset_count: usize) -> bool {
     reset_count > 0
@@ -1046,9 +1099,10 @@ pub fn start_worker_loop(
     state: SharedJobsState,
     wasm: Arc<WasmInstance>,
     db_bridge: Option<crate::wasm::SharedDbBridge>,
-) {
+) -> tokio::task::JoinHandle<()> {
     tokio::spawn(async move {
         // Wire in the SQLite pool from the DbBridge (if one is configured).
+        #[cfg(feature = "sqlite")]
         if let Some(bridge) = db_bridge {
             let bridge_read = bridge.read().await;
             if let Some(pool) = bridge_read.get_sqlite_pool().await {
@@ -1058,6 +1112,8 @@ pub fn start_worker_loop(
                 debug!("jobs: DbBridge is not SQLite — persistence disabled");
             }
         }
+        #[cfg(not(feature = "sqlite"))]
+        let _ = db_bridge;
 
         // Initialise schema, run cleanup, and recover pending/running records.
         init_persistence(&state, JOBS_RETENTION_DAYS).await;
@@ -1168,6 +1224,9 @@ pub fn start_worker_loop(
                                                             params: std::collections::HashMap::new(
                                                             ),
                                                             query: std::collections::HashMap::new(),
+                                                            raw_query: String::new(),
+                                                            client: None,
+                                                            route_pattern: None,
                                                         };
                                                         let handler_result = wasm_clone
                                                             .call_handler_job(
@@ -1216,7 +1275,7 @@ pub fn start_worker_loop(
                 }
             }
         }
-    });
+    })
 }
 
 /// The bundled outcome of a single job handler invocation.
@@ -1322,7 +1381,7 @@ async fn apply_outcome(
 ///
 /// Eviction is safe because `job_status` and `job_result` fall back to the DB
 /// when the id is absent from the cache.
-async fn persist_and_evict(state: &SharedJobsState, pool: &sqlx::SqlitePool, record: JobRecord) {
+async fn persist_and_evict(state: &SharedJobsState, pool: &JobsPool, record: JobRecord) {
     if let Err(e) = db_update_job(pool, &record).await {
         warn!(
             "jobs: failed to persist terminal status for {}: {}",
@@ -1421,7 +1480,10 @@ async fn apply_failure_with_retry_override(
 ///
 /// Cron schedules are declarative — they are re-registered on every server
 /// start via `_schedule_cron` calls.  No persistence is needed.
-pub fn start_cron_scheduler(state: SharedJobsState, wasm: Arc<WasmInstance>) {
+pub fn start_cron_scheduler(
+    state: SharedJobsState,
+    wasm: Arc<WasmInstance>,
+) -> tokio::task::JoinHandle<()> {
     tokio::spawn(async move {
         info!("Cron scheduler monitor started");
 
@@ -1516,6 +1578,9 @@ pub fn start_cron_scheduler(state: SharedJobsState, wasm: Arc<WasmInstance>) {
                                 body_bytes: None,
                                 params: std::collections::HashMap::new(),
                                 query: std::collections::HashMap::new(),
+                                raw_query: String::new(),
+                                client: None,
+                                route_pattern: None,
                             };
                             wasm_fire.call_handler_job(&h_name, req, None)
                         })
@@ -1536,7 +1601,7 @@ pub fn start_cron_scheduler(state: SharedJobsState, wasm: Arc<WasmInstance>) {
                 });
             }
         }
-    });
+    })
 }
 
 // ---------------------------------------------------------------------------
@@ -0,0 +1,215 @@
+//! JSON Schema validation for route request bodies.
+//!
+//! Schemas attached with `_http_route_schema` are compiled once at
+//! registration into a [`jsonschema::Validator`], which the route keeps and
+//! checks each request body against before the handler runs. The draft is
+//! taken from the schema's `$schema` (2020-12 when absent). Remote `$ref`s
+//! are not resolved; references must point inside the schema document.
+
+use serde::Serialize;
+use serde_json::Value;
+
+use jsonschema::error::ValidationErrorKind;
+
+use crate::error::{RuntimeError, RuntimeResult};
+
+/// One failed constraint
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ValidationError {
+    /// JSON Pointer to the offending value (`""` for the document root)
+    pub path: String,
+    pub message: String,
+}
+
+/// A compiled JSON Schema
+#[derive(Debug, Clone)]
+pub struct JsonSchema {
+    validator: jsonschema::Validator,
+    /// The document it was compiled from, for publishing (e.g. in OpenAPI)
+    source: Value,
+}
+
+impl JsonSchema {
+    /// Compile a schema document, rejecting schemas that are not valid
+    /// under their draft's meta-schema or reference unknown locations.
+    pub fn compile(schema: &Value) -> RuntimeResult<Self> {
+        let validator = jsonschema::validator_for(schema)
+            .map_err(|e| RuntimeError::config(format!("Invalid JSON Schema: {}", e)))?;
+        Ok(Self {
+            validator,
+            source: schema.clone(),
+        })
+    }
+
+    /// Parse and compile a schema from JSON text.
+    pub fn parse(schema_json: &str) -> RuntimeResult<Self> {
+        let schema: Value = serde_json::from_str(schema_json)
+            .map_err(|e| RuntimeError::config(format!("Invalid JSON Schema: {}", e)))?;
+        Self::compile(&schema)
+    }
+
+    /// Validate `instance`, returning every failed constraint (empty when valid).
+    ///
+    /// `required` and `additionalProperties` failures point at the missing or
+    /// unexpected property rather than the object holding it, one error each.
+    pub fn validate(&self, instance: &Value) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+        for error in self.validator.iter_errors(instance) {
+            let at = error.instance_path();
+            match error.kind() {
+                ValidationErrorKind::Required {
+                    property: Value::String(name),
+                } => errors.push(ValidationError {
+                    path: at.join(name.as_str()).to_string(),
+                    message: error.to_string(),
+                }),
+                ValidationErrorKind::AdditionalProperties { unexpected } => {
+                    errors.extend(unexpected.iter().map(|name| ValidationError {
+                        path: at.join(name.as_str()).to_string(),
+                        message: format!(
+                            "Additional properties are not allowed ('{}' was unexpected)",
+                            name
+                        ),
+                    }))
+                }
+                _ => errors.push(ValidationError {
+                    path: at.to_string(),
+                    message: error.to_string(),
+                }),
+            }
+        }
+        errors
+    }
+
+    pub fn is_valid(&self, instance: &Value) -> bool {
+        self.validator.is_valid(instance)
+    }
+
+    /// The schema document as registered
+    pub fn as_value(&self) -> &Value {
+        &self.source
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn user_schema() -> JsonSchema {
+        JsonSchema::compile(&json!({
+            "type": "object",
+            "required": ["name", "age"],
+            "additionalProperties": false,
+            "properties": {
+                "name": { "type": "string", "minLength": 1, "maxLength": 20 },
+                "age": { "type": "integer", "minimum": 0 },
+                "role": { "enum": ["admin", "member"] },
+                "tags": { "type": "array", "items": { "type": "string" }, "uniqueItems": true }
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn valid_document_has_no_errors() {
+        let schema = user_schema();
+        assert!(schema.is_valid(&json!({"name": "Ada", "age": 36, "tags": ["a", "b"]})));
+        assert!(schema.is_valid(&json!({"name": "Ada", "age": 36.0, "role": "admin"})));
+        assert!(
+            schema
+                .validate(&json!({"name": "Ada", "age": 36}))
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn errors_point_at_offending_fields() {
+        let errors = user_schema().validate(&json!({
+            "name": "",
+            "age": -1,
+            "role": "owner",
+            "tags": ["a", 2, "a"],
+            "extra": true,
+            "more~/": 1
+        }));
+        let mut paths: Vec<&str> = errors.iter().map(|e| e.path.as_str()).collect();
+        paths.sort_unstable();
+        assert_eq!(
+            paths,
+            vec![
+                "/age",
+                "/extra",
+                "/more~0~1",
+                "/name",
+                "/role",
+                "/tags",
+                "/tags/1"
+            ],
+            "{:?}",
+            errors
+        );
+
+        let missing = user_schema().validate(&json!({}));
+        let mut paths: Vec<&str> = missing.iter().map(|e| e.path.as_str()).collect();
+        paths.sort_unstable();
+        assert_eq!(paths, vec!["/age", "/name"]);
+        assert!(
+            missing
+                .iter()
+                .all(|e| e.message.ends_with("is a required property"))
+        );
+
+        let wrong_type = user_schema().validate(&json!([1]));
+        assert_eq!(wrong_type.len(), 1);
+        assert_eq!(wrong_type[0].path, "");
+        assert_eq!(wrong_type[0].message, r#"[1] is not of type "object""#);
+    }
+
+    #[test]
+    fn combinators_refs_and_boolean_schemas() {
+        let schema = JsonSchema::compile(&json!({
+            "anyOf": [{ "type": "string" }, { "type": "number", "multipleOf": 5 }],
+            "not": { "const": "forbidden" }
+        }))
+        .unwrap();
+        assert!(schema.is_valid(&json!("ok")));
+        assert!(schema.is_valid(&json!(15)));
+        assert!(!schema.is_valid(&json!(7)));
+        assert!(!schema.is_valid(&json!("forbidden")));
+
+        let with_ref = JsonSchema::compile(&json!({
+            "$defs": { "id": { "type": "string", "pattern": "^u[0-9]+$" } },
+            "properties": { "id": { "$ref": "#/$defs/id" } }
+        }))
+        .unwrap();
+        assert!(with_ref.is_valid(&json!({ "id": "u42" })));
+        assert_eq!(with_ref.validate(&json!({ "id": "42" }))[0].path, "/id");
+
+        assert!(
+            JsonSchema::compile(&json!(true))
+                .unwrap()
+                .is_valid(&json!(null))
+        );
+        assert!(
+            !JsonSchema::compile(&json!(false))
+                .unwrap()
+                .is_valid(&json!(null))
+        );
+    }
+
+    #[test]
+    fn compile_rejects_malformed_schemas() {
+        for schema in [
+            json!("object"),
+            json!({ "type": "text" }),
+            json!({ "required": "name" }),
+            json!({ "minLength": -1 }),
+            json!({ "properties": { "id": { "$ref": "#/$defs/missing" } } }),
+        ] {
+            assert!(JsonSchema::compile(&schema).is_err(), "{}", schema);
+        }
+        let err = JsonSchema::parse("{not json").unwrap_err();
+        assert!(err.to_string().contains("Invalid JSON Schema"), "{}", err);
+    }
+}
@@ -73,23 +73,47 @@
 //! - **db**: Database operations (_db_query, _db_execute)
 //! - **auth**: Authentication (_auth_verify, _auth_create_session)
 
+pub mod access_log;
+pub mod audit;
+pub mod auth;
+pub mod body_log;
 pub mod bridge;
 pub mod bridge_browser_stubs;
 pub mod bridge_canvas_stubs;
 pub mod bridge_ui_stubs;
 pub mod build_manifest;
+pub mod clock;
+pub mod coalesce;
 pub mod dev_capture;
 pub mod error;
 pub mod error_reporting;
+pub mod idempotency;
+pub mod ip_filter;
 pub mod jobs;
+pub mod json_schema;
 pub mod locale;
 pub mod memory;
+pub mod metrics;
+pub mod module_cache;
+pub mod mount;
+pub mod multipart;
+pub mod openapi;
 pub mod permissions;
 pub mod rate_limit;
+pub mod readiness;
+pub mod request_input;
+pub mod response_cache;
 pub mod router;
 pub mod runtime_config;
+pub mod self_check;
 pub mod server;
+pub mod server_timing;
 pub mod session;
+pub mod tasks;
+pub mod telemetry;
+pub mod templates;
+pub mod testing;
+pub mod url_decode;
 pub mod wasm;
 pub mod websocket;
 
@@ -99,8 +123,8 @@ pub use jobs::{SharedJobsState, create_shared_jobs_state};
 pub use router::{HttpMethod, RouteHandler, Router, SharedRouter};
 pub use server::{MemoryTier, ServerConfig, start_server};
 pub use session::{
-    SessionConfig, SessionData, SessionStore, SharedSessionStore, create_session_store,
-    parse_cookies,
+    CookieSessionStore, SessionConfig, SessionData, SessionStore, SharedSessionStore,
+    create_session_store, parse_cookies,
 };
 pub use wasm::{
     AuthContext, RequestContext, SharedDbBridge, SharedWasmInstance, WasmInstance, WasmState,
@@ -0,0 +1,272 @@
+//! Prometheus metrics registry and text exposition.
+//!
+//! Enabled by `ServerConfig.metrics_endpoint`. Request counters and latency
+//! histograms are updated by `server::handle_request`; database pool and
+//! session gauges are sampled when the endpoint is scraped. Serving the
+//! endpoint never enters WASM.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Arc;
+use std::time::Duration;
+
+use host_bridge::PoolStats;
+use parking_lot::Mutex;
+
+/// Upper bounds, in seconds, of the request latency histogram buckets.
+pub const LATENCY_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// Route label for requests that matched no registered route.
+///
+/// Using the raw path instead would let clients mint unbounded label values.
+pub const UNMATCHED_ROUTE: &str = "unmatched";
+
+/// Content type of the Prometheus text exposition format.
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct RequestKey {
+    method: String,
+    route: String,
+    status: u16,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct LatencyKey {
+    method: String,
+    route: String,
+}
+
+/// Cumulative histogram: `buckets[i]` counts observations `<= LATENCY_BUCKETS[i]`.
+#[derive(Debug)]
+struct Histogram {
+    buckets: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn new() -> Self {
+        Self {
+            buckets: vec![0; LATENCY_BUCKETS.len()],
+            sum: 0.0,
+            count: 0,
+        }
+    }
+
+    fn observe(&mut self, secs: f64) {
+        for (bucket, bound) in self.buckets.iter_mut().zip(LATENCY_BUCKETS) {
+            if secs <= *bound {
+                *bucket += 1;
+            }
+        }
+        self.sum += secs;
+        self.count += 1;
+    }
+}
+
+/// Gauges sampled at scrape time and passed to [`Metrics::render`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GaugeSnapshot {
+    /// Database pool state; `None` when no database is configured
+    pub db_pool: Option<PoolStats>,
+    /// Sessions currently held by the session store
+    pub active_sessions: usize,
+}
+
+/// In-process metrics registry
+#[derive(Debug, Default)]
+pub struct Metrics {
+    requests: Mutex<BTreeMap<RequestKey, u64>>,
+    latency: Mutex<BTreeMap<LatencyKey, Histogram>>,
+}
+
+/// Thread-safe metrics registry shared between the dispatch path and the endpoint
+pub type SharedMetrics = Arc<Metrics>;
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count one finished request and record its latency.
+    ///
+    /// `route` is the registered route pattern (e.g. `/users/:id`) or
+    /// [`UNMATCHED_ROUTE`].
+    pub fn record_request(&self, method: &str, route: &str, status: u16, elapsed: Duration) {
+        *self
+            .requests
+            .lock()
+            .entry(RequestKey {
+                method: method.to_string(),
+                route: route.to_string(),
+                status,
+            })
+            .or_insert(0) += 1;
+
+        self.latency
+            .lock()
+            .entry(LatencyKey {
+                method: method.to_string(),
+                route: route.to_string(),
+            })
+            .or_insert_with(Histogram::new)
+            .observe(elapsed.as_secs_f64());
+    }
+
+    /// Number of requests recorded for one method/route/status combination.
+    pub fn request_count(&self, method: &str, route: &str, status: u16) -> u64 {
+        self.requests
+            .lock()
+            .get(&RequestKey {
+                method: method.to_string(),
+                route: route.to_string(),
+                status,
+            })
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Render every metric in the Prometheus text exposition format.
+    pub fn render(&self, gauges: &GaugeSnapshot) -> String {
+        let mut out = String::new();
+
+        out.push_str(
+            "# HELP clean_http_requests_total HTTP requests handled, by method, route and status.\n",
+        );
+        out.push_str("# TYPE clean_http_requests_total counter\n");
+        for (key, count) in self.requests.lock().iter() {
+            let _ = writeln!(
+                out,
+                "clean_http_requests_total{{method=\"{}\",route=\"{}\",status=\"{}\"}} {}",
+                escape_label(&key.method),
+                escape_label(&key.route),
+                key.status,
+                count
+            );
+        }
+
+        out.push_str(
+            "# HELP clean_http_request_duration_seconds HTTP request latency, by method and route.\n",
+        );
+        out.push_str("# TYPE clean_http_request_duration_seconds histogram\n");
+        for (key, hist) in self.latency.lock().iter() {
+            let labels = format!(
+                "method=\"{}\",route=\"{}\"",
+                escape_label(&key.method),
+                escape_label(&key.route)
+            );
+            for (bound, count) in LATENCY_BUCKETS.iter().zip(&hist.buckets) {
+                let _ = writeln!(
+                    out,
+                    "clean_http_request_duration_seconds_bucket{{{},le=\"{}\"}} {}",
+                    labels, bound, count
+                );
+            }
+            let _ = writeln!(
+                out,
+                "clean_http_request_duration_seconds_bucket{{{},le=\"+Inf\"}} {}",
+                labels, hist.count
+            );
+            let _ = writeln!(
+                out,
+                "clean_http_request_duration_seconds_sum{{{}}} {}",
+                labels, hist.sum
+            );
+            let _ = writeln!(
+                out,
+                "clean_http_request_duration_seconds_count{{{}}} {}",
+                labels, hist.count
+            );
+        }
+
+        if let Some(pool) = gauges.db_pool {
+            out.push_str(
+                "# HELP clean_db_pool_connections Open database connections, idle or in use.\n",
+            );
+            out.push_str("# TYPE clean_db_pool_connections gauge\n");
+            let _ = writeln!(out, "clean_db_pool_connections {}", pool.size);
+            out.push_str(
+                "# HELP clean_db_pool_idle_connections Open database connections not in use.\n",
+            );
+            out.push_str("# TYPE clean_db_pool_idle_connections gauge\n");
+            let _ = writeln!(out, "clean_db_pool_idle_connections {}", pool.idle);
+        }
+
+        out.push_str("# HELP clean_sessions_active Sessions held by the session store.\n");
+        out.push_str("# TYPE clean_sessions_active gauge\n");
+        let _ = writeln!(out, "clean_sessions_active {}", gauges.active_sessions);
+
+        out
+    }
+}
+
+/// Escape a label value per the exposition format (`\`, `"` and newline).
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_request_counts_by_route_and_status() {
+        let metrics = Metrics::new();
+        metrics.record_request("GET", "/users/:id", 200, Duration::from_millis(3));
+        metrics.record_request("GET", "/users/:id", 200, Duration::from_millis(4));
+        metrics.record_request("GET", "/users/:id", 404, Duration::from_millis(1));
+
+        assert_eq!(metrics.request_count("GET", "/users/:id", 200), 2);
+        assert_eq!(metrics.request_count("GET", "/users/:id", 404), 1);
+        assert_eq!(metrics.request_count("POST", "/users/:id", 200), 0);
+    }
+
+    #[test]
+    fn test_render_histogram_is_cumulative() {
+        let metrics = Metrics::new();
+        metrics.record_request("GET", "/", 200, Duration::from_millis(2));
+        metrics.record_request("GET", "/", 200, Duration::from_millis(200));
+
+        let text = metrics.render(&GaugeSnapshot::default());
+        assert!(text.contains(
+            "clean_http_request_duration_seconds_bucket{method=\"GET\",route=\"/\",le=\"0.005\"} 1"
+        ));
+        assert!(text.contains(
+            "clean_http_request_duration_seconds_bucket{method=\"GET\",route=\"/\",le=\"0.25\"} 2"
+        ));
+        assert!(text.contains(
+            "clean_http_request_duration_seconds_bucket{method=\"GET\",route=\"/\",le=\"+Inf\"} 2"
+        ));
+        assert!(
+            text.contains(
+                "clean_http_request_duration_seconds_count{method=\"GET\",route=\"/\"} 2"
+            )
+        );
+    }
+
+    #[test]
+    fn test_render_gauges() {
+        let metrics = Metrics::new();
+        let text = metrics.render(&GaugeSnapshot {
+            db_pool: Some(PoolStats { size: 4, idle: 3 }),
+            active_sessions: 7,
+        });
+        assert!(text.contains("clean_db_pool_connections 4\n"));
+        assert!(text.contains("clean_db_pool_idle_connections 3\n"));
+        assert!(text.contains("clean_sessions_active 7\n"));
+
+        let text = metrics.render(&GaugeSnapshot::default());
+        assert!(!text.contains("clean_db_pool_connections"));
+    }
+
+    #[test]
+    fn test_label_values_are_escaped() {
+        assert_eq!(escape_label("a\"b\\c\nd"), "a\\\"b\\\\c\\nd");
+    }
+}
@@ -0,0 +1,154 @@
+//! On-disk cache of compiled WASM modules.
+//!
+//! Compiling a large module with Cranelift takes seconds. When
+//! `ServerConfig::module_cache_dir` is set, the compiled artifact is
+//! serialized to `<sha256 of the wasm>-<engine hash>.cwasm` in that directory
+//! and deserialized on the next start instead of recompiling. The engine hash
+//! covers the Wasmtime version and every compilation setting, so an upgrade
+//! or config change simply misses the cache. A cache file that fails to load
+//! is discarded and the module recompiled.
+//!
+//! Deserializing runs the cached machine code as-is: the directory must only
+//! be writable by the server itself.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+use tracing::{debug, info, warn};
+use wasmtime::{Engine, Module};
+
+/// File extension of cached compiled modules
+pub const CACHE_EXTENSION: &str = "cwasm";
+
+/// Where a loaded module came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModuleSource {
+    /// Compiled from the WASM bytes
+    Compiled,
+    /// Deserialized from the module cache
+    Cached,
+}
+
+/// Cache file for `wasm_bytes` compiled by `engine` inside `dir`
+pub fn cache_path(dir: &Path, engine: &Engine, wasm_bytes: &[u8]) -> PathBuf {
+    let mut hasher = DefaultHasher::new();
+    engine.precompile_compatibility_hash().hash(&mut hasher);
+    dir.join(format!(
+        "{}-{:016x}.{}",
+        hex::encode(Sha256::digest(wasm_bytes)),
+        hasher.finish(),
+        CACHE_EXTENSION
+    ))
+}
+
+/// Compile `wasm_bytes`, going through the cache in `cache_dir` when set.
+///
+/// Only compilation errors are returned; cache read and write failures are
+/// logged and fall back to compiling.
+pub fn load_or_compile(
+    engine: &Engine,
+    wasm_bytes: &[u8],
+    cache_dir: Option<&Path>,
+) -> wasmtime::Result<(Module, ModuleSource)> {
+    let Some(dir) = cache_dir else {
+        return Ok((Module::new(engine, wasm_bytes)?, ModuleSource::Compiled));
+    };
+    let path = cache_path(dir, engine, wasm_bytes);
+
+    if path.is_file() {
+        // SAFETY: files in the cache directory are only ever written by
+        // `store` below from `Module::serialize` output.
+        match unsafe { Module::deserialize_file(engine, &path) } {
+            Ok(module) => {
+                info!("Loaded compiled WASM module from cache {:?}", path);
+                return Ok((module, ModuleSource::Cached));
+            }
+            Err(e) => {
+                warn!("Discarding unusable module cache file {:?}: {}", path, e);
+                let _ = std::fs::remove_file(&path);
+            }
+        }
+    }
+
+    let module = Module::new(engine, wasm_bytes)?;
+    match store(&module, dir, &path) {
+        Ok(()) => debug!("Wrote compiled WASM module to cache {:?}", path),
+        Err(e) => warn!("Failed to write module cache file {:?}: {}", path, e),
+    }
+    Ok((module, ModuleSource::Compiled))
+}
+
+/// Write the serialized module next to `path` and rename it into place, so a
+/// concurrent start never reads a partial file.
+fn store(module: &Module, dir: &Path, path: &Path) -> std::io::Result<()> {
+    let bytes = module.serialize().map_err(std::io::Error::other)?;
+    std::fs::create_dir_all(dir)?;
+    let tmp = path.with_extension(format!("{}.tmp-{}", CACHE_EXTENSION, std::process::id()));
+    std::fs::write(&tmp, &bytes)?;
+    std::fs::rename(&tmp, path).inspect_err(|_| {
+        let _ = std::fs::remove_file(&tmp);
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WAT: &str = r#"(module (func (export "answer") (result i32) (i32.const 42)))"#;
+
+    fn answer(engine: &Engine, module: &Module) -> i32 {
+        let mut store = wasmtime::Store::new(engine, ());
+        let instance = wasmtime::Instance::new(&mut store, module, &[]).unwrap();
+        instance
+            .get_typed_func::<(), i32>(&mut store, "answer")
+            .unwrap()
+            .call(&mut store, ())
+            .unwrap()
+    }
+
+    #[test]
+    fn second_load_comes_from_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let engine = Engine::default();
+        let bytes = wat::parse_str(WAT).unwrap();
+
+        let (module, source) = load_or_compile(&engine, &bytes, Some(dir.path())).unwrap();
+        assert_eq!(source, ModuleSource::Compiled);
+        assert_eq!(answer(&engine, &module), 42);
+        assert!(cache_path(dir.path(), &engine, &bytes).is_file());
+
+        let (module, source) = load_or_compile(&engine, &bytes, Some(dir.path())).unwrap();
+        assert_eq!(source, ModuleSource::Cached);
+        assert_eq!(answer(&engine, &module), 42);
+    }
+
+    #[test]
+    fn corrupt_cache_file_is_replaced() {
+        let dir = tempfile::tempdir().unwrap();
+        let engine = Engine::default();
+        let bytes = wat::parse_str(WAT).unwrap();
+        let path = cache_path(dir.path(), &engine, &bytes);
+        std::fs::write(&path, b"not a compiled module").unwrap();
+
+        let (module, source) = load_or_compile(&engine, &bytes, Some(dir.path())).unwrap();
+        assert_eq!(source, ModuleSource::Compiled);
+        assert_eq!(answer(&engine, &module), 42);
+
+        let (_, source) = load_or_compile(&engine, &bytes, Some(dir.path())).unwrap();
+        assert_eq!(source, ModuleSource::Cached);
+    }
+
+    #[test]
+    fn different_modules_use_different_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let engine = Engine::default();
+        let a = wat::parse_str(WAT).unwrap();
+        let b = wat::parse_str("(module)").unwrap();
+        assert_ne!(
+            cache_path(dir.path(), &engine, &a),
+            cache_path(dir.path(), &engine, &b)
+        );
+    }
+}
@@ -0,0 +1,148 @@
+//! Serving several WASM modules from one server under path prefixes.
+//!
+//! Each `ServerConfig::mounts` entry is loaded like a standalone app, with
+//! its own router, instance and middleware. A request goes to the module
+//! whose prefix is the longest match for its path (`/api` matches `/api` and
+//! `/api/users`, not `/apiary`), with the prefix stripped before the module
+//! matches its own routes. Paths no prefix matches get 404.
+
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use axum::Router;
+use axum::extract::Request;
+use axum::http::{StatusCode, Uri};
+use axum::response::{IntoResponse, Response};
+use tower::ServiceExt;
+
+use crate::error::{RuntimeError, RuntimeResult};
+
+/// A WASM module served under a path prefix
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModuleMount {
+    /// Normalized prefix: leading `/`, no trailing `/` (except the root `/`)
+    pub prefix: String,
+    pub wasm_path: PathBuf,
+}
+
+impl ModuleMount {
+    pub fn new(prefix: &str, wasm_path: impl Into<PathBuf>) -> Self {
+        let trimmed = prefix.trim().trim_end_matches('/');
+        let prefix = if trimmed.starts_with('/') {
+            trimmed.to_string()
+        } else {
+            format!("/{}", trimmed)
+        };
+        Self {
+            prefix,
+            wasm_path: wasm_path.into(),
+        }
+    }
+}
+
+/// Parses `PREFIX=PATH`, as taken by `--mount`.
+impl FromStr for ModuleMount {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('=') {
+            Some((prefix, path)) if !path.is_empty() => Ok(Self::new(prefix, path)),
+            _ => Err(format!("invalid mount {:?}: expected PREFIX=PATH", s)),
+        }
+    }
+}
+
+/// Reject two mounts sharing a prefix.
+pub fn check_unique_prefixes(mounts: &[ModuleMount]) -> RuntimeResult<()> {
+    for (i, mount) in mounts.iter().enumerate() {
+        if mounts[..i].iter().any(|m| m.prefix == mount.prefix) {
+            return Err(RuntimeError::config(format!(
+                "Two WASM modules are mounted at {}",
+                mount.prefix
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// The part of `path` below `prefix`, or None when `prefix` does not cover
+/// `path`. Always starts with `/`.
+fn strip_prefix<'a>(prefix: &str, path: &'a str) -> Option<&'a str> {
+    if prefix == "/" {
+        return Some(path);
+    }
+    match path.strip_prefix(prefix)? {
+        "" => Some("/"),
+        rest if rest.starts_with('/') => Some(rest),
+        _ => None,
+    }
+}
+
+/// Route every request to the app mounted at the longest matching prefix.
+pub fn mount_router(mut apps: Vec<(String, Router)>) -> Router {
+    apps.sort_by_key(|(prefix, _)| std::cmp::Reverse(prefix.len()));
+    let apps = Arc::new(apps);
+    Router::new().fallback(move |req: Request| dispatch(apps.clone(), req))
+}
+
+async fn dispatch(apps: Arc<Vec<(String, Router)>>, mut req: Request) -> Response {
+    let path = req.uri().path();
+    let Some((app, stripped)) = apps
+        .iter()
+        .find_map(|(prefix, app)| strip_prefix(prefix, path).map(|rest| (app, rest)))
+    else {
+        return (StatusCode::NOT_FOUND, "Not Found").into_response();
+    };
+
+    let path_and_query = match req.uri().query() {
+        Some(query) => format!("{}?{}", stripped, query),
+        None => stripped.to_string(),
+    };
+    match Uri::builder().path_and_query(path_and_query).build() {
+        Ok(uri) => *req.uri_mut() = uri,
+        Err(_) => return (StatusCode::BAD_REQUEST, "Bad Request").into_response(),
+    }
+
+    match app.clone().oneshot(req).await {
+        Ok(response) => response,
+        Err(never) => match never {},
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_prefixes() {
+        assert_eq!(ModuleMount::new("api/", "a.wasm").prefix, "/api");
+        assert_eq!(ModuleMount::new("/admin", "a.wasm").prefix, "/admin");
+        assert_eq!(ModuleMount::new("/", "a.wasm").prefix, "/");
+    }
+
+    #[test]
+    fn parses_prefix_equals_path() {
+        let mount: ModuleMount = "/api=dist/api.wasm".parse().unwrap();
+        assert_eq!(mount, ModuleMount::new("/api", "dist/api.wasm"));
+        assert!("/api".parse::<ModuleMount>().is_err());
+        assert!("/api=".parse::<ModuleMount>().is_err());
+    }
+
+    #[test]
+    fn strips_only_whole_segments() {
+        assert_eq!(strip_prefix("/api", "/api"), Some("/"));
+        assert_eq!(strip_prefix("/api", "/api/users"), Some("/users"));
+        assert_eq!(strip_prefix("/api", "/apiary"), None);
+        assert_eq!(strip_prefix("/", "/anything"), Some("/anything"));
+    }
+
+    #[test]
+    fn rejects_duplicate_prefixes() {
+        let mounts = [
+            ModuleMount::new("/api", "a.wasm"),
+            ModuleMount::new("/api/", "b.wasm"),
+        ];
+        assert!(check_unique_prefixes(&mounts).is_err());
+    }
+}
@@ -0,0 +1,200 @@
+//! `multipart/form-data` request bodies (RFC 7578).
+//!
+//! Parts borrow from the buffered request body, so locating a part — or
+//! measuring an uploaded file — never copies its contents.
+
+/// One part of a `multipart/form-data` body
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Part<'a> {
+    /// Form field name from `Content-Disposition`
+    pub name: String,
+    /// Original file name; only file uploads carry one
+    pub filename: Option<String>,
+    /// The part's own `Content-Type`, if it sent one
+    pub content_type: Option<String>,
+    /// Part contents, without the trailing CRLF before the next boundary
+    pub data: &'a [u8],
+}
+
+/// The `boundary` parameter of a `multipart/form-data` Content-Type.
+pub fn boundary(content_type: &str) -> Option<String> {
+    let mut params = split_params(content_type).into_iter();
+    let media_type = params.next()?;
+    if !media_type
+        .trim()
+        .eq_ignore_ascii_case("multipart/form-data")
+    {
+        return None;
+    }
+    params
+        .filter_map(|param| parse_param(&param))
+        .find(|(key, _)| key.eq_ignore_ascii_case("boundary"))
+        .map(|(_, value)| value)
+        .filter(|boundary| !boundary.is_empty())
+}
+
+/// Parts of `body`, or None when `content_type` is not multipart/form-data
+/// or the body is malformed.
+pub fn parse<'a>(content_type: &str, body: &'a [u8]) -> Option<Vec<Part<'a>>> {
+    let delimiter = format!("--{}", boundary(content_type)?).into_bytes();
+    let next_delimiter = [b"\r\n".as_slice(), &delimiter].concat();
+
+    let mut pos = find(body, &delimiter, 0)? + delimiter.len();
+    let mut parts = Vec::new();
+    loop {
+        let rest = &body[pos..];
+        if rest.starts_with(b"--") {
+            return Some(parts);
+        }
+        if !rest.starts_with(b"\r\n") {
+            return None;
+        }
+        pos += 2;
+
+        let headers_end = find(body, b"\r\n\r\n", pos)?;
+        let headers = std::str::from_utf8(&body[pos..headers_end]).ok()?;
+        let data_start = headers_end + 4;
+        let data_end = find(body, &next_delimiter, data_start)?;
+
+        let mut part = Part {
+            name: String::new(),
+            filename: None,
+            content_type: None,
+            data: &body[data_start..data_end],
+        };
+        for line in headers.split("\r\n") {
+            let Some((name, value)) = line.split_once(':') else {
+                continue;
+            };
+            if name.trim().eq_ignore_ascii_case("content-disposition") {
+                for (key, value) in split_params(value).iter().filter_map(|p| parse_param(p)) {
+                    match key.to_ascii_lowercase().as_str() {
+                        "name" => part.name = value,
+                        "filename" => part.filename = Some(value),
+                        _ => {}
+                    }
+                }
+            } else if name.trim().eq_ignore_ascii_case("content-type") {
+                part.content_type = Some(value.trim().to_string());
+            }
+        }
+        parts.push(part);
+        pos = data_end + next_delimiter.len();
+    }
+}
+
+/// The uploaded file in the part named `name` — a part with a filename, so
+/// plain form fields of the same name are skipped.
+pub fn find_file<'a>(content_type: &str, body: &'a [u8], name: &str) -> Option<Part<'a>> {
+    parse(content_type, body)?
+        .into_iter()
+        .find(|part| part.name == name && part.filename.is_some())
+}
+
+fn find(haystack: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    haystack
+        .get(from..)?
+        .windows(needle.len())
+        .position(|window| window == needle)
+        .map(|i| i + from)
+}
+
+/// Split a header value on `;`, leaving separators inside quotes alone.
+fn split_params(value: &str) -> Vec<String> {
+    let mut params = vec![String::new()];
+    let mut quoted = false;
+    let mut escaped = false;
+    for c in value.chars() {
+        let current = params.last_mut().expect("params is never empty");
+        match c {
+            _ if escaped => {
+                current.push(c);
+                escaped = false;
+            }
+            '\\' if quoted => {
+                current.push(c);
+                escaped = true;
+            }
+            '"' => {
+                current.push(c);
+                quoted = !quoted;
+            }
+            ';' if !quoted => params.push(String::new()),
+            _ => current.push(c),
+        }
+    }
+    params
+}
+
+/// `key=value` or `key="quoted value"`
+fn parse_param(param: &str) -> Option<(String, String)> {
+    let (key, value) = param.split_once('=')?;
+    let value = value.trim();
+    let value = match value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+        Some(quoted) => quoted.replace("\\\"", "\"").replace("\\\\", "\\"),
+        None => value.to_string(),
+    };
+    Some((key.trim().to_string(), value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONTENT_TYPE: &str = "multipart/form-data; boundary=XyZ";
+
+    fn body() -> Vec<u8> {
+        let mut body = Vec::new();
+        body.extend_from_slice(b"--XyZ\r\nContent-Disposition: form-data; name=\"title\"\r\n\r\n");
+        body.extend_from_slice(b"Holiday\r\n");
+        body.extend_from_slice(
+            b"--XyZ\r\nContent-Disposition: form-data; name=\"photo\"; filename=\"a;b.png\"\r\n",
+        );
+        body.extend_from_slice(b"Content-Type: image/png\r\n\r\n");
+        body.extend_from_slice(&[0x89, b'P', b'N', b'G', b'\r', b'\n', 0x00]);
+        body.extend_from_slice(b"\r\n--XyZ--\r\n");
+        body
+    }
+
+    #[test]
+    fn boundary_is_read_from_the_content_type() {
+        assert_eq!(boundary(CONTENT_TYPE).as_deref(), Some("XyZ"));
+        assert_eq!(
+            boundary("Multipart/Form-Data; charset=utf-8; boundary=\"a b\"").as_deref(),
+            Some("a b")
+        );
+        assert_eq!(boundary("application/json; boundary=XyZ"), None);
+        assert_eq!(boundary("multipart/form-data"), None);
+    }
+
+    #[test]
+    fn parse_splits_fields_and_files() {
+        let body = body();
+        let parts = parse(CONTENT_TYPE, &body).unwrap();
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0].name, "title");
+        assert_eq!(parts[0].filename, None);
+        assert_eq!(parts[0].data, b"Holiday");
+        assert_eq!(parts[1].name, "photo");
+        assert_eq!(parts[1].filename.as_deref(), Some("a;b.png"));
+        assert_eq!(parts[1].content_type.as_deref(), Some("image/png"));
+        assert_eq!(parts[1].data, &[0x89, b'P', b'N', b'G', b'\r', b'\n', 0x00]);
+    }
+
+    #[test]
+    fn find_file_skips_plain_fields() {
+        let body = body();
+        assert_eq!(
+            find_file(CONTENT_TYPE, &body, "photo").unwrap().data.len(),
+            7
+        );
+        assert!(find_file(CONTENT_TYPE, &body, "title").is_none());
+        assert!(find_file(CONTENT_TYPE, &body, "missing").is_none());
+    }
+
+    #[test]
+    fn truncated_body_is_rejected() {
+        let body = body();
+        assert!(parse(CONTENT_TYPE, &body[..body.len() - 12]).is_none());
+    }
+}
@@ -0,0 +1,228 @@
+//! OpenAPI 3 document generated from the route registry.
+//!
+//! Served at `ServerConfig::openapi_endpoint`. Each handler route becomes an
+//! operation with its path parameters, the request body schema attached via
+//! `_http_route_schema`, and a session-cookie security requirement when the
+//! route is protected. Static redirects, WebSocket routes and routes for
+//! extension methods (e.g. PROPFIND) are omitted.
+
+use serde_json::{Map, Value, json};
+
+use crate::router::{HttpMethod, RouteHandler};
+
+/// OpenAPI version emitted in the `openapi` field
+pub const OPENAPI_VERSION: &str = "3.0.3";
+
+/// Name of the security scheme protected routes reference
+const SESSION_SCHEME: &str = "session";
+
+/// Build the OpenAPI document for `routes`.
+pub fn generate(routes: &[RouteHandler]) -> Value {
+    let mut routes: Vec<&RouteHandler> = routes
+        .iter()
+        .filter(|r| {
+            r.redirect_destination.is_none()
+                && !r.is_ws
+                && !matches!(r.method, HttpMethod::Other(_))
+        })
+        .collect();
+    routes.sort_by(|a, b| {
+        (a.path.as_str(), a.method.as_str()).cmp(&(b.path.as_str(), b.method.as_str()))
+    });
+
+    let mut paths = Map::new();
+    for route in routes {
+        let (template, params) = path_template(&route.path);
+        let item = paths
+            .entry(template)
+            .or_insert_with(|| Value::Object(Map::new()));
+        item[route.method.as_str().to_lowercase()] = operation(route, &params);
+    }
+
+    json!({
+        "openapi": OPENAPI_VERSION,
+        "info": {
+            "title": "Clean Server API",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "paths": paths,
+        "components": {
+            "securitySchemes": {
+                SESSION_SCHEME: {
+                    "type": "apiKey",
+                    "in": "cookie",
+                    "name": "session",
+                },
+            },
+        },
+    })
+}
+
+/// Convert an Express-style path (`/users/:id`) to an OpenAPI template
+/// (`/users/{id}`), returning the parameter names in order.
+fn path_template(path: &str) -> (String, Vec<String>) {
+    let mut params = Vec::new();
+    let template = path
+        .split('/')
+        .map(|segment| match segment.strip_prefix(':') {
+            Some(name) => {
+                params.push(name.to_string());
+                format!("{{{}}}", name)
+            }
+            None => segment.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("/");
+    (template, params)
+}
+
+fn operation(route: &RouteHandler, params: &[String]) -> Value {
+    let mut op = Map::new();
+    op.insert("operationId".into(), json!(route.handler_name));
+
+    if !params.is_empty() {
+        let parameters: Vec<Value> = params
+            .iter()
+            .map(|name| {
+                json!({
+                    "name": name,
+                    "in": "path",
+                    "required": true,
+                    "schema": { "type": "string" },
+                })
+            })
+            .collect();
+        op.insert("parameters".into(), json!(parameters));
+    }
+
+    let mut responses = Map::new();
+    if route.is_sse {
+        responses.insert(
+            "200".into(),
+            json!({
+                "description": "Server-sent event stream",
+                "content": { "text/event-stream": {} },
+            }),
+        );
+    } else {
+        responses.insert("200".into(), json!({ "description": "OK" }));
+    }
+
+    if let Some(schema) = &route.request_schema {
+        op.insert(
+            "requestBody".into(),
+            json!({
+                "required": true,
+                "content": { "application/json": { "schema": schema.as_value() } },
+            }),
+        );
+        responses.insert(
+            "422".into(),
+            json!({ "description": "Request body failed schema validation" }),
+        );
+    }
+
+    if route.protected {
+        op.insert("security".into(), json!([{ SESSION_SCHEME: [] }]));
+        responses.insert("401".into(), json!({ "description": "Not authenticated" }));
+        if let Some(role) = &route.required_role {
+            op.insert("x-required-role".into(), json!(role));
+            responses.insert(
+                "403".into(),
+                json!({ "description": "Missing required role" }),
+            );
+        }
+    }
+
+    op.insert("responses".into(), Value::Object(responses));
+    Value::Object(op)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json_schema::JsonSchema;
+    use crate::router::{HttpMethod, Router};
+    use std::sync::Arc;
+
+    #[test]
+    fn document_lists_routes_with_methods_params_and_schemas() {
+        let router = Router::new();
+        router
+            .register(
+                HttpMethod::GET,
+                "/users/:id/posts/:post_id".into(),
+                "show_post".into(),
+                false,
+                None,
+                false,
+            )
+            .unwrap();
+        router
+            .register(
+                HttpMethod::POST,
+                "/users".into(),
+                "create_user".into(),
+                true,
+                Some("admin".into()),
+                false,
+            )
+            .unwrap();
+        router
+            .set_request_schema(
+                HttpMethod::POST,
+                "/users",
+                Arc::new(JsonSchema::parse(r#"{"type":"object","required":["name"]}"#).unwrap()),
+            )
+            .unwrap();
+        router
+            .register(
+                HttpMethod::GET,
+                "/users".into(),
+                "list_users".into(),
+                false,
+                None,
+                false,
+            )
+            .unwrap();
+        router
+            .register_redirect(HttpMethod::GET, "/old".into(), "/users".into(), 301)
+            .unwrap();
+
+        let doc = generate(&router.all_routes());
+        assert_eq!(doc["openapi"], OPENAPI_VERSION);
+
+        let paths = doc["paths"].as_object().unwrap();
+        let mut keys: Vec<&str> = paths.keys().map(String::as_str).collect();
+        keys.sort_unstable();
+        assert_eq!(keys, vec!["/users", "/users/{id}/posts/{post_id}"]);
+
+        let show = &doc["paths"]["/users/{id}/posts/{post_id}"]["get"];
+        assert_eq!(show["operationId"], "show_post");
+        let names: Vec<&str> = show["parameters"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|p| {
+                assert_eq!(p["in"], "path");
+                assert_eq!(p["required"], true);
+                p["name"].as_str().unwrap()
+            })
+            .collect();
+        assert_eq!(names, vec!["id", "post_id"]);
+
+        let create = &doc["paths"]["/users"]["post"];
+        assert_eq!(
+            create["requestBody"]["content"]["application/json"]["schema"]["required"][0],
+            "name"
+        );
+        assert!(create["responses"]["422"].is_object());
+        assert_eq!(create["security"][0]["session"], json!([]));
+        assert_eq!(create["x-required-role"], "admin");
+
+        let list = &doc["paths"]["/users"]["get"];
+        assert_eq!(list["operationId"], "list_users");
+        assert!(list.get("parameters").is_none());
+        assert!(list.get("security").is_none());
+    }
+}
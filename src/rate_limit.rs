@@ -1,22 +1,26 @@
 //! Fixed-window rate limiter and axum middleware.
 //!
 //! Configured by `_rate_limit_configure` during WASM init. Strategy is either
-//! `ip` (key by `X-Forwarded-For`/`X-Real-IP`, falling back to a constant) or
-//! `user` (key by session cookie, falling back to ip).
+//! `ip` (key by client address, see `TrustedProxies::client_ip`) or `user`
+//! (key by session cookie, falling back to ip). Forwarded headers only count
+//! when the socket peer is a trusted proxy, so clients cannot pick their own
+//! bucket.
 
 use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
 use std::sync::Arc;
 use std::time::Instant;
 
 use axum::{
     body::Body,
-    extract::{Request, State},
+    extract::{ConnectInfo, Request, State},
     http::{HeaderMap, StatusCode, header},
     middleware::Next,
     response::Response,
 };
 use parking_lot::Mutex;
 
+use crate::ip_filter::TrustedProxies;
 use crate::runtime_config::{RateLimitConfig, RateLimitStrategy};
 
 #[derive(Debug)]
@@ -28,6 +32,7 @@ struct BucketState {
 #[derive(Debug)]
 pub struct RateLimiter {
     config: RateLimitConfig,
+    trusted_proxies: TrustedProxies,
     buckets: Mutex<HashMap<String, BucketState>>,
 }
 
@@ -35,10 +40,17 @@ impl RateLimiter {
     pub fn new(config: RateLimitConfig) -> Self {
         Self {
             config,
+            trusted_proxies: TrustedProxies::default(),
             buckets: Mutex::new(HashMap::new()),
         }
     }
 
+    /// Proxies whose `X-Forwarded-For` is believed when keying by IP.
+    pub fn with_trusted_proxies(mut self, trusted_proxies: TrustedProxies) -> Self {
+        self.trusted_proxies = trusted_proxies;
+        self
+    }
+
     pub fn config(&self) -> &RateLimitConfig {
         &self.config
     }
@@ -66,16 +78,9 @@ impl RateLimiter {
 
 pub type SharedRateLimiter = Arc<RateLimiter>;
 
-/// Derive the rate-limit key from request headers using the configured strategy.
-fn extract_key(strategy: RateLimitStrategy, headers: &HeaderMap) -> String {
-    fn header(headers: &HeaderMap, name: &str) -> Option<String> {
-        headers
-            .get(name)
-            .and_then(|v| v.to_str().ok())
-            .map(|s| s.split(',').next().unwrap_or(s).trim().to_string())
-            .filter(|s| !s.is_empty())
-    }
-
+/// Derive the rate-limit key using the configured strategy. `client` is the
+/// resolved client address, `None` when the connection has no IP peer.
+fn extract_key(strategy: RateLimitStrategy, headers: &HeaderMap, client: Option<IpAddr>) -> String {
     fn session_from_cookie(headers: &HeaderMap) -> Option<String> {
         let cookie_header = headers.get(header::COOKIE)?.to_str().ok()?;
         for part in cookie_header.split(';') {
@@ -92,11 +97,9 @@ fn extract_key(strategy: RateLimitStrategy, headers: &HeaderMap) -> String {
         None
     }
 
-    let ip_key = || {
-        let ip = header(headers, "x-forwarded-for")
-            .or_else(|| header(headers, "x-real-ip"))
-            .unwrap_or_else(|| "unknown".to_string());
-        format!("ip:{}", ip)
+    let ip_key = || match client {
+        Some(ip) => format!("ip:{}", ip),
+        None => "ip:unknown".to_string(),
     };
 
     match strategy {
@@ -111,7 +114,11 @@ pub async fn rate_limit_middleware(
     req: Request,
     next: Next,
 ) -> Response {
-    let key = extract_key(limiter.config().strategy, req.headers());
+    let client = req
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(peer)| limiter.trusted_proxies.client_ip(peer.ip(), req.headers()));
+    let key = extract_key(limiter.config().strategy, req.headers(), client);
     if limiter.allow(&key) {
         return next.run(req).await;
     }
@@ -127,3 +134,60 @@ pub async fn rate_limit_middleware(
         .body(Body::from(body))
         .expect("rate-limit response builder")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn forwarded(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", HeaderValue::from_str(value).unwrap());
+        headers.insert("x-real-ip", HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    fn client(proxies: &[&str], peer: &str, headers: &HeaderMap) -> Option<IpAddr> {
+        let nets = proxies.iter().map(|net| net.parse().unwrap()).collect();
+        Some(TrustedProxies::new(nets).client_ip(peer.parse().unwrap(), headers))
+    }
+
+    #[test]
+    fn test_ip_key_ignores_forwarded_headers_from_untrusted_peer() {
+        let headers = forwarded("198.51.100.7");
+        let client = client(&[], "203.0.113.9", &headers);
+        assert_eq!(
+            extract_key(RateLimitStrategy::Ip, &headers, client),
+            "ip:203.0.113.9"
+        );
+    }
+
+    #[test]
+    fn test_ip_key_uses_forwarded_client_behind_trusted_proxy() {
+        let headers = forwarded("198.51.100.7");
+        let client = client(&["10.0.0.0/8"], "10.0.0.2", &headers);
+        assert_eq!(
+            extract_key(RateLimitStrategy::Ip, &headers, client),
+            "ip:198.51.100.7"
+        );
+    }
+
+    #[test]
+    fn test_user_key_falls_back_to_client_ip() {
+        let mut headers = HeaderMap::new();
+        let peer = client(&[], "203.0.113.9", &headers);
+        assert_eq!(
+            extract_key(RateLimitStrategy::User, &headers, peer),
+            "ip:203.0.113.9"
+        );
+        headers.insert(header::COOKIE, HeaderValue::from_static("sid=abc"));
+        assert_eq!(
+            extract_key(RateLimitStrategy::User, &headers, peer),
+            "user:abc"
+        );
+        assert_eq!(
+            extract_key(RateLimitStrategy::Ip, &HeaderMap::new(), None),
+            "ip:unknown"
+        );
+    }
+}
@@ -0,0 +1,109 @@
+//! 503 responses while the module is still initializing.
+//!
+//! `start_server` binds its listener before loading the WASM module and
+//! serves [`StartupGate::router`] on it. Until [`StartupGate::open`] installs
+//! the app, every request is answered `503 Service Unavailable` with
+//! `Retry-After`, rather than hitting a router with no routes registered yet.
+//! [`StartupGate::replace`] later swaps in a reloaded app on the same
+//! listener.
+
+use std::sync::Arc;
+
+use axum::{
+    Router,
+    extract::{Request, State},
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use parking_lot::RwLock;
+use tower::ServiceExt;
+use tracing::warn;
+
+/// Seconds clients are told to wait before retrying during startup
+pub const RETRY_AFTER_SECS: u64 = 1;
+
+/// Holds requests at 503 until the app serving them is ready.
+#[derive(Clone, Default)]
+pub struct StartupGate {
+    app: Arc<RwLock<Option<Router>>>,
+}
+
+impl StartupGate {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Route every request from now on to `app`. Only the first call counts.
+    pub fn open(&self, app: Router) {
+        let mut current = self.app.write();
+        if current.is_some() {
+            warn!("Startup gate opened twice; keeping the first app");
+        } else {
+            *current = Some(app);
+        }
+    }
+
+    /// Route requests from now on to `app` instead of the open app.
+    /// Requests already forwarded finish on the app they started on.
+    pub fn replace(&self, app: Router) {
+        *self.app.write() = Some(app);
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.app.read().is_some()
+    }
+
+    /// Router answering 503 until `open` is called, then forwarding to the app.
+    pub fn router(&self) -> Router {
+        Router::new()
+            .fallback(gated_request)
+            .with_state(self.clone())
+    }
+}
+
+async fn gated_request(State(gate): State<StartupGate>, req: Request) -> Response {
+    let app = gate.app.read().clone();
+    match app {
+        Some(app) => match app.oneshot(req).await {
+            Ok(response) => response,
+            Err(never) => match never {},
+        },
+        None => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            [(header::RETRY_AFTER, RETRY_AFTER_SECS.to_string())],
+            "Server is starting",
+        )
+            .into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+
+    fn get(path: &str) -> Request {
+        Request::builder().uri(path).body(Body::empty()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn answers_503_until_opened() {
+        let gate = StartupGate::new();
+        let router = gate.router();
+
+        let response = router.clone().oneshot(get("/hello")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "1");
+
+        gate.open(Router::new().route("/hello", axum::routing::get(|| async { "hi" })));
+        assert!(gate.is_open());
+        let response = router.clone().oneshot(get("/hello")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = router.clone().oneshot(get("/missing")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        gate.replace(Router::new().route("/missing", axum::routing::get(|| async { "found" })));
+        let response = router.oneshot(get("/missing")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
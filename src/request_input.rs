@@ -0,0 +1,174 @@
+//! `_req_input(name)`: one lookup over every place a request can carry a
+//! named input.
+//!
+//! Sources are checked in `ServerConfig.input_precedence` order, by default
+//! path parameters, then the query string, then body fields. Body fields come
+//! from a JSON object, a urlencoded form or the text fields of a
+//! multipart/form-data body, chosen by the request's Content-Type.
+
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use crate::wasm::RequestContext;
+
+/// Where `_req_input` looks for a named value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputSource {
+    /// Route parameters such as `:id`
+    Path,
+    /// Query string parameters
+    Query,
+    /// Top-level fields of a JSON, urlencoded or multipart body
+    Body,
+}
+
+/// Lookup order used unless `ServerConfig.input_precedence` says otherwise
+pub const DEFAULT_INPUT_PRECEDENCE: [InputSource; 3] =
+    [InputSource::Path, InputSource::Query, InputSource::Body];
+
+/// Lookup order shared with every request store
+pub type SharedInputPrecedence = Arc<[InputSource]>;
+
+impl FromStr for InputSource {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "path" | "params" => Ok(InputSource::Path),
+            "query" => Ok(InputSource::Query),
+            "body" => Ok(InputSource::Body),
+            other => Err(format!(
+                "unknown input source {:?}: expected path, query or body",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for InputSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            InputSource::Path => "path",
+            InputSource::Query => "query",
+            InputSource::Body => "body",
+        })
+    }
+}
+
+/// Reject an empty precedence or one naming a source twice.
+pub fn validate_precedence(precedence: &[InputSource]) -> Result<(), String> {
+    if precedence.is_empty() {
+        return Err("input precedence must name at least one source".to_string());
+    }
+    for (i, source) in precedence.iter().enumerate() {
+        if precedence[..i].contains(source) {
+            return Err(format!("input source {} is listed twice", source));
+        }
+    }
+    Ok(())
+}
+
+/// The first value named `name` in the `precedence` sources of `ctx`.
+pub fn lookup(ctx: &RequestContext, name: &str, precedence: &[InputSource]) -> Option<String> {
+    precedence.iter().find_map(|source| match source {
+        InputSource::Path => ctx.params.get(name).cloned(),
+        InputSource::Query => ctx.query.get(name).cloned(),
+        InputSource::Body => body_field(ctx, name),
+    })
+}
+
+fn body_field(ctx: &RequestContext, name: &str) -> Option<String> {
+    let content_type = ctx
+        .headers
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case("content-type"))
+        .map(|(_, v)| v.as_str())
+        .unwrap_or_default();
+    let body = match &ctx.body_bytes {
+        Some(b) => b.as_slice(),
+        None => ctx.body.as_bytes(),
+    };
+    let media_type = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+
+    match media_type.as_str() {
+        "application/x-www-form-urlencoded" => url::form_urlencoded::parse(body)
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.into_owned()),
+        "multipart/form-data" => crate::multipart::parse(content_type, body)?
+            .into_iter()
+            .find(|part| part.name == name && part.filename.is_none())
+            .map(|part| String::from_utf8_lossy(part.data).into_owned()),
+        _ => match serde_json::from_slice::<serde_json::Value>(body)
+            .ok()?
+            .get(name)?
+        {
+            serde_json::Value::String(s) => Some(s.clone()),
+            serde_json::Value::Null => None,
+            other => Some(other.to_string()),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wasm::RequestContextBuilder;
+
+    fn request(content_type: &str, body: &str) -> RequestContext {
+        RequestContextBuilder::default()
+            .header("Content-Type", content_type)
+            .body(body)
+            .param("id", "from-path")
+            .query("id", "from-query")
+            .query("page", "2")
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn default_precedence_prefers_path_then_query_then_body() {
+        let ctx = request(
+            "application/json",
+            r#"{"id":"from-body","age":41,"tag":null}"#,
+        );
+        let lookup = |name| lookup(&ctx, name, &DEFAULT_INPUT_PRECEDENCE);
+        assert_eq!(lookup("id").as_deref(), Some("from-path"));
+        assert_eq!(lookup("page").as_deref(), Some("2"));
+        assert_eq!(lookup("age").as_deref(), Some("41"));
+        assert_eq!(lookup("tag"), None);
+        assert_eq!(lookup("missing"), None);
+    }
+
+    #[test]
+    fn body_fields_follow_the_content_type() {
+        let form = request("application/x-www-form-urlencoded", "id=from+form&x=1");
+        assert_eq!(
+            lookup(&form, "id", &[InputSource::Body]).as_deref(),
+            Some("from form")
+        );
+
+        let multipart = request(
+            "multipart/form-data; boundary=B",
+            "--B\r\nContent-Disposition: form-data; name=\"id\"\r\n\r\nfrom-part\r\n--B--\r\n",
+        );
+        assert_eq!(
+            lookup(&multipart, "id", &[InputSource::Body]).as_deref(),
+            Some("from-part")
+        );
+    }
+
+    #[test]
+    fn precedence_parses_and_rejects_duplicates() {
+        assert_eq!("Query".parse::<InputSource>(), Ok(InputSource::Query));
+        assert!("cookie".parse::<InputSource>().is_err());
+        assert!(validate_precedence(&[InputSource::Body, InputSource::Query]).is_ok());
+        assert!(validate_precedence(&[InputSource::Body, InputSource::Body]).is_err());
+        assert!(validate_precedence(&[]).is_err());
+    }
+}
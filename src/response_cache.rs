@@ -0,0 +1,452 @@
+//! In-memory cache for GET responses from WASM handlers.
+//!
+//! Enabled by `ServerConfig::response_cache`. Successful GET responses are
+//! stored under method, path, query and the values of the configured `Vary`
+//! request headers, and served again without calling the handler until they
+//! expire. A handler controls caching through the `Cache-Control` header it
+//! sets: `max-age`/`s-maxage` override `CacheConfig::default_ttl_secs`, and
+//! `no-store`, `no-cache` or `private` keep the response out of the cache.
+//! Responses setting cookies are never cached, and a response is only served
+//! again to requests matching it on every header its own `Vary` names
+//! (`Vary: *` is never cached).
+//!
+//! Requests carrying credentials (`Authorization` or `Cookie`) and requests
+//! for protected routes bypass the cache entirely, so one user's response is
+//! never served to another. Every GET answered through the cache carries
+//! `X-Cache: HIT` or `X-Cache: MISS`; bypassed requests carry neither.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::{
+    body::{Body, Bytes},
+    extract::{Request, State},
+    http::{HeaderMap, HeaderName, HeaderValue, Method, StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use parking_lot::Mutex;
+use tracing::{debug, warn};
+
+use crate::error::{RuntimeError, RuntimeResult};
+use crate::router::{HttpMethod, SharedRouter};
+
+/// Header reporting whether the response came from the cache
+pub const CACHE_STATUS_HEADER: &str = "x-cache";
+
+/// Response cache settings (`ServerConfig::response_cache`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheConfig {
+    /// Most responses kept; the oldest is evicted to make room
+    pub max_entries: usize,
+    /// Lifetime of a response whose `Cache-Control` sets no `max-age`
+    pub default_ttl_secs: u64,
+    /// Request headers whose values are part of the cache key
+    /// (e.g. "Accept-Language")
+    pub vary: Vec<String>,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            max_entries: 1000,
+            default_ttl_secs: 60,
+            vary: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct EntryKey {
+    method: Method,
+    path_and_query: String,
+    vary: Vec<Option<HeaderValue>>,
+}
+
+#[derive(Debug)]
+struct CachedResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+    stored_at: Instant,
+    ttl: Duration,
+    /// Request headers named by the response's `Vary`, with the values the
+    /// response was produced for
+    varied: Vec<(HeaderName, Option<HeaderValue>)>,
+}
+
+impl CachedResponse {
+    fn is_fresh(&self) -> bool {
+        self.stored_at.elapsed() < self.ttl
+    }
+
+    /// Whether the request headers match the ones this response varies on
+    fn matches(&self, headers: &HeaderMap) -> bool {
+        self.varied
+            .iter()
+            .all(|(name, value)| headers.get(name) == value.as_ref())
+    }
+}
+
+pub struct ResponseCache {
+    max_entries: usize,
+    default_ttl: Duration,
+    vary: Vec<HeaderName>,
+    /// Routes of the cached module, to tell protected ones apart
+    router: Option<SharedRouter>,
+    /// Shared with the caches of reloaded modules (see `for_router`)
+    entries: Arc<Mutex<HashMap<EntryKey, CachedResponse>>>,
+}
+
+impl ResponseCache {
+    /// Build a cache from `config`, rejecting invalid `Vary` header names.
+    pub fn new(config: &CacheConfig) -> RuntimeResult<Self> {
+        let vary = config
+            .vary
+            .iter()
+            .map(|name| {
+                HeaderName::from_bytes(name.trim().as_bytes()).map_err(|_| {
+                    RuntimeError::config(format!("Invalid response cache Vary header: {:?}", name))
+                })
+            })
+            .collect::<RuntimeResult<Vec<_>>>()?;
+        Ok(Self {
+            max_entries: config.max_entries,
+            default_ttl: Duration::from_secs(config.default_ttl_secs),
+            vary,
+            router: None,
+            entries: Arc::new(Mutex::new(HashMap::new())),
+        })
+    }
+
+    /// Bypass the cache for protected routes of `router`.
+    pub fn with_router(mut self, router: SharedRouter) -> Self {
+        self.router = Some(router);
+        self
+    }
+
+    /// A cache for the routes of a reloaded module that serves and stores
+    /// the same entries as this one.
+    pub fn for_router(&self, router: SharedRouter) -> Self {
+        Self {
+            max_entries: self.max_entries,
+            default_ttl: self.default_ttl,
+            vary: self.vary.clone(),
+            router: Some(router),
+            entries: self.entries.clone(),
+        }
+    }
+
+    /// Whether `req` must skip the cache: it carries credentials, or it is
+    /// for a protected route.
+    fn bypasses(&self, req: &Request) -> bool {
+        if req.headers().contains_key(header::AUTHORIZATION)
+            || req.headers().contains_key(header::COOKIE)
+        {
+            return true;
+        }
+        self.router.as_ref().is_some_and(|router| {
+            router
+                .find(HttpMethod::GET, req.uri().path())
+                .is_some_and(|(route, _)| route.protected)
+        })
+    }
+
+    fn key(&self, req: &Request) -> EntryKey {
+        EntryKey {
+            method: req.method().clone(),
+            path_and_query: req
+                .uri()
+                .path_and_query()
+                .map(|pq| pq.as_str().to_string())
+                .unwrap_or_else(|| req.uri().path().to_string()),
+            vary: self
+                .vary
+                .iter()
+                .map(|name| req.headers().get(name).cloned())
+                .collect(),
+        }
+    }
+
+    /// The response cached for `key`, if still fresh and produced for the
+    /// same values of the headers it varies on.
+    fn lookup(&self, key: &EntryKey, headers: &HeaderMap) -> Option<Response> {
+        let entries = self.entries.lock();
+        let cached = entries
+            .get(key)
+            .filter(|c| c.is_fresh() && c.matches(headers))?;
+        let mut response = Response::new(Body::from(cached.body.clone()));
+        *response.status_mut() = cached.status;
+        *response.headers_mut() = cached.headers.clone();
+        response
+            .headers_mut()
+            .insert(header::AGE, cached.stored_at.elapsed().as_secs().into());
+        Some(response)
+    }
+
+    /// How long a response with these headers may be cached, or None when it
+    /// must not be.
+    fn ttl_for(&self, headers: &HeaderMap) -> Option<Duration> {
+        if headers.contains_key(header::SET_COOKIE) || varies_on_everything(headers) {
+            return None;
+        }
+        let Some(cache_control) = headers
+            .get(header::CACHE_CONTROL)
+            .and_then(|v| v.to_str().ok())
+        else {
+            return Some(self.default_ttl);
+        };
+
+        let mut max_age = None;
+        let mut s_maxage = None;
+        for directive in cache_control.split(',') {
+            let directive = directive.trim().to_ascii_lowercase();
+            match directive.split_once('=') {
+                Some(("max-age", secs)) => max_age = secs.trim().parse::<u64>().ok(),
+                Some(("s-maxage", secs)) => s_maxage = secs.trim().parse::<u64>().ok(),
+                None if matches!(directive.as_str(), "no-store" | "no-cache" | "private") => {
+                    return None;
+                }
+                _ => {}
+            }
+        }
+        match s_maxage.or(max_age) {
+            Some(0) => None,
+            Some(secs) => Some(Duration::from_secs(secs)),
+            None => Some(self.default_ttl),
+        }
+    }
+
+    /// Cache a response under `key`, making room if the cache is full.
+    /// `request_headers` are those of the request it answered.
+    fn store(
+        &self,
+        key: EntryKey,
+        request_headers: &HeaderMap,
+        status: StatusCode,
+        headers: HeaderMap,
+        body: Bytes,
+        ttl: Duration,
+    ) {
+        if self.max_entries == 0 {
+            return;
+        }
+        let varied = vary_names(&headers)
+            .map(|name| {
+                let value = request_headers.get(&name).cloned();
+                (name, value)
+            })
+            .collect();
+        let mut entries = self.entries.lock();
+        if entries.len() >= self.max_entries && !entries.contains_key(&key) {
+            entries.retain(|_, c| c.is_fresh());
+            if entries.len() >= self.max_entries {
+                let oldest = entries
+                    .iter()
+                    .min_by_key(|(_, c)| c.stored_at)
+                    .map(|(k, _)| k.clone());
+                if let Some(oldest) = oldest {
+                    entries.remove(&oldest);
+                }
+            }
+        }
+        entries.insert(
+            key,
+            CachedResponse {
+                status,
+                headers,
+                body,
+                stored_at: Instant::now(),
+                ttl,
+                varied,
+            },
+        );
+    }
+}
+
+/// Header names listed in the response's `Vary` headers
+fn vary_names(headers: &HeaderMap) -> impl Iterator<Item = HeaderName> + '_ {
+    headers
+        .get_all(header::VARY)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .filter_map(|name| HeaderName::from_bytes(name.trim().as_bytes()).ok())
+}
+
+/// `Vary: *`: the response depends on more than the request headers
+fn varies_on_everything(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(header::VARY)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .any(|name| name.trim() == "*")
+}
+
+pub type SharedResponseCache = Arc<ResponseCache>;
+
+fn with_cache_status(mut response: Response, status: &'static str) -> Response {
+    response
+        .headers_mut()
+        .insert(CACHE_STATUS_HEADER, HeaderValue::from_static(status));
+    response
+}
+
+/// axum middleware serving cached GET responses and caching fresh ones.
+pub async fn response_cache_middleware(
+    State(cache): State<SharedResponseCache>,
+    req: Request,
+    next: Next,
+) -> Response {
+    // WebSocket upgrades are GETs too, but must always reach the handler.
+    if req.method() != Method::GET
+        || req.headers().contains_key(header::UPGRADE)
+        || cache.bypasses(&req)
+    {
+        return next.run(req).await;
+    }
+    let key = cache.key(&req);
+
+    if let Some(response) = cache.lookup(&key, req.headers()) {
+        debug!("Serving cached response for {}", key.path_and_query);
+        return with_cache_status(response, "HIT");
+    }
+
+    let request_headers = req.headers().clone();
+    let response = next.run(req).await;
+    let is_stream = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|ct| ct.starts_with("text/event-stream"));
+    let ttl = cache.ttl_for(response.headers());
+    let Some(ttl) = ttl.filter(|_| response.status().is_success() && !is_stream) else {
+        return with_cache_status(response, "MISS");
+    };
+
+    let (parts, body) = response.into_parts();
+    let body = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(body) => body,
+        Err(e) => {
+            warn!("Failed to buffer response for response cache: {}", e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    cache.store(
+        key,
+        &request_headers,
+        parts.status,
+        parts.headers.clone(),
+        body.clone(),
+        ttl,
+    );
+    with_cache_status(Response::from_parts(parts, Body::from(body)), "MISS")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache(max_entries: usize) -> ResponseCache {
+        ResponseCache::new(&CacheConfig {
+            max_entries,
+            ..CacheConfig::default()
+        })
+        .unwrap()
+    }
+
+    fn key(path: &str) -> EntryKey {
+        EntryKey {
+            method: Method::GET,
+            path_and_query: path.to_string(),
+            vary: Vec::new(),
+        }
+    }
+
+    fn cache_control(value: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::CACHE_CONTROL, HeaderValue::from_static(value));
+        headers
+    }
+
+    #[test]
+    fn ttl_follows_cache_control() {
+        let cache = cache(10);
+        assert_eq!(
+            cache.ttl_for(&HeaderMap::new()),
+            Some(Duration::from_secs(60))
+        );
+        assert_eq!(
+            cache.ttl_for(&cache_control("public, max-age=5")),
+            Some(Duration::from_secs(5))
+        );
+        assert_eq!(
+            cache.ttl_for(&cache_control("max-age=5, s-maxage=30")),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(cache.ttl_for(&cache_control("no-store")), None);
+        assert_eq!(cache.ttl_for(&cache_control("Private")), None);
+        assert_eq!(cache.ttl_for(&cache_control("max-age=0")), None);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::SET_COOKIE, HeaderValue::from_static("sid=1"));
+        assert_eq!(cache.ttl_for(&headers), None);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::VARY, HeaderValue::from_static("Accept, *"));
+        assert_eq!(cache.ttl_for(&headers), None);
+    }
+
+    #[test]
+    fn lookup_honours_response_vary() {
+        let cache = cache(10);
+        let mut response_headers = HeaderMap::new();
+        response_headers.insert(header::VARY, HeaderValue::from_static("X-Tenant"));
+        let mut acme = HeaderMap::new();
+        acme.insert("x-tenant", HeaderValue::from_static("acme"));
+        cache.store(
+            key("/a"),
+            &acme,
+            StatusCode::OK,
+            response_headers,
+            Bytes::from_static(b"x"),
+            Duration::from_secs(60),
+        );
+
+        assert!(cache.lookup(&key("/a"), &acme).is_some());
+        let mut other = HeaderMap::new();
+        other.insert("x-tenant", HeaderValue::from_static("other"));
+        assert!(cache.lookup(&key("/a"), &other).is_none());
+        assert!(cache.lookup(&key("/a"), &HeaderMap::new()).is_none());
+    }
+
+    #[test]
+    fn evicts_oldest_entry_when_full() {
+        let cache = cache(2);
+        for path in ["/a", "/b", "/c"] {
+            cache.store(
+                key(path),
+                &HeaderMap::new(),
+                StatusCode::OK,
+                HeaderMap::new(),
+                Bytes::from_static(b"x"),
+                Duration::from_secs(60),
+            );
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert!(cache.lookup(&key("/a"), &HeaderMap::new()).is_none());
+        assert!(cache.lookup(&key("/b"), &HeaderMap::new()).is_some());
+        assert!(cache.lookup(&key("/c"), &HeaderMap::new()).is_some());
+    }
+
+    #[test]
+    fn rejects_invalid_vary_header() {
+        let config = CacheConfig {
+            vary: vec!["Accept Language".to_string()],
+            ..CacheConfig::default()
+        };
+        assert!(ResponseCache::new(&config).is_err());
+    }
+}
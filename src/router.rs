@@ -1,11 +1,16 @@
 //! HTTP Route Registry
 //!
 //! Manages route registration from WASM modules and matches incoming requests.
+//! Routes are registered while a module initializes; registration is
+//! synchronized, so re-running init (or registering late) cannot corrupt the
+//! table.
 
 use crate::error::{RuntimeError, RuntimeResult};
+use crate::json_schema::JsonSchema;
 use parking_lot::RwLock;
-use std::collections::HashMap;
-use std::sync::Arc;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, LazyLock};
 
 /// Convert Express-style route parameters to matchit syntax
 /// Express uses `:id`, matchit 0.8+ uses `{id}`
@@ -34,6 +39,17 @@ fn convert_express_to_matchit(path: &str) -> String {
     result
 }
 
+/// Extension method names registered through `HttpMethod::register_extension`.
+/// Names are leaked so `HttpMethod` stays `Copy`; only route registration
+/// adds to the set, so it is bounded by the routes modules declare.
+static EXTENSION_METHODS: LazyLock<RwLock<HashSet<&'static str>>> =
+    LazyLock::new(|| RwLock::new(HashSet::new()));
+
+/// `tchar` from RFC 9110 §5.6.2, the characters a method token may contain
+fn is_token_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c)
+}
+
 /// HTTP methods supported by the router
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum HttpMethod {
@@ -44,10 +60,27 @@ pub enum HttpMethod {
     DELETE,
     HEAD,
     OPTIONS,
+    /// Non-standard method a module registered routes for (e.g. WebDAV's
+    /// PROPFIND or MKCOL)
+    Other(&'static str),
 }
 
 impl HttpMethod {
-    /// Parse HTTP method from string
+    /// Every supported method, in the order `Allow` headers list them
+    pub const ALL: [HttpMethod; 7] = [
+        HttpMethod::GET,
+        HttpMethod::HEAD,
+        HttpMethod::POST,
+        HttpMethod::PUT,
+        HttpMethod::PATCH,
+        HttpMethod::DELETE,
+        HttpMethod::OPTIONS,
+    ];
+
+    /// Parse HTTP method from string.
+    ///
+    /// Accepts the standard methods and extension methods already passed to
+    /// [`HttpMethod::register_extension`].
     pub fn parse(s: &str) -> RuntimeResult<Self> {
         match s.to_uppercase().as_str() {
             "GET" => Ok(HttpMethod::GET),
@@ -57,11 +90,34 @@ impl HttpMethod {
             "DELETE" => Ok(HttpMethod::DELETE),
             "HEAD" => Ok(HttpMethod::HEAD),
             "OPTIONS" => Ok(HttpMethod::OPTIONS),
-            other => Err(RuntimeError::route(format!(
-                "Unknown HTTP method: {}",
-                other
-            ))),
+            other => match EXTENSION_METHODS.read().get(other) {
+                Some(name) => Ok(HttpMethod::Other(name)),
+                None => Err(RuntimeError::route(format!(
+                    "Unknown HTTP method: {}",
+                    other
+                ))),
+            },
+        }
+    }
+
+    /// Parse a method a route is being registered for, accepting any valid
+    /// method token. Non-standard names are recorded so later requests using
+    /// them parse.
+    pub fn register_extension(s: &str) -> RuntimeResult<Self> {
+        if let Ok(method) = Self::parse(s) {
+            return Ok(method);
+        }
+        if s.is_empty() || !s.chars().all(is_token_char) {
+            return Err(RuntimeError::route(format!("Invalid HTTP method: {:?}", s)));
+        }
+        let name = s.to_uppercase();
+        let mut methods = EXTENSION_METHODS.write();
+        if let Some(&interned) = methods.get(name.as_str()) {
+            return Ok(HttpMethod::Other(interned));
         }
+        let interned: &'static str = Box::leak(name.into_boxed_str());
+        methods.insert(interned);
+        Ok(HttpMethod::Other(interned))
     }
 
     /// Convert to string representation
@@ -74,6 +130,7 @@ impl HttpMethod {
             HttpMethod::DELETE => "DELETE",
             HttpMethod::HEAD => "HEAD",
             HttpMethod::OPTIONS => "OPTIONS",
+            HttpMethod::Other(name) => name,
         }
     }
 }
@@ -104,6 +161,12 @@ pub struct RouteHandler {
     /// If set, this route is a static redirect: (destination, status_code).
     /// The server returns the redirect immediately without invoking any WASM handler.
     pub redirect_destination: Option<(String, u16)>,
+    /// JSON Schema the request body must satisfy before the handler runs
+    /// (attached via `_http_route_schema`). Failing bodies get 422.
+    pub request_schema: Option<Arc<JsonSchema>>,
+    /// Handler time limit for this route in milliseconds (set via
+    /// `_http_route_timeout`), overriding the server's `handler_timeout_ms`
+    pub timeout_ms: Option<u64>,
 }
 
 /// Key for route lookup
@@ -113,21 +176,58 @@ struct RouteKey {
     path: String,
 }
 
-/// Thread-safe route registry
-pub struct Router {
+impl RouteHandler {
+    /// Whether `other` registers the same route the same way. Settings
+    /// attached after registration (schema, timeout) are not compared.
+    fn same_registration(&self, other: &RouteHandler) -> bool {
+        self.method == other.method
+            && self.path == other.path
+            && self.handler_name == other.handler_name
+            && self.protected == other.protected
+            && self.required_role == other.required_role
+            && self.is_sse == other.is_sse
+            && self.is_ws == other.is_ws
+            && self.redirect_destination == other.redirect_destination
+    }
+}
+
+/// Route handlers and the path matcher over them. Kept behind one lock so
+/// a lookup never sees a route in one but not the other.
+#[derive(Default)]
+struct RouteTable {
     /// Routes indexed by method and path
-    routes: RwLock<HashMap<RouteKey, RouteHandler>>,
+    routes: HashMap<RouteKey, RouteHandler>,
     /// Static path matcher for fast lookups
-    path_matcher: RwLock<matchit::Router<RouteKey>>,
+    matcher: matchit::Router<RouteKey>,
+}
+
+/// Thread-safe route registry
+///
+/// Routes are expected to be registered while the module initializes, but
+/// registration is synchronized and safe from any thread: each call is
+/// applied atomically, repeating an identical registration is a no-op (so
+/// a re-run init keeps schemas and timeouts attached in between), and a
+/// different registration for the same method and path replaces it.
+/// New routes beyond `max_routes` are rejected.
+#[derive(Default)]
+pub struct Router {
+    table: RwLock<RouteTable>,
+    /// Most distinct method/path pairs the table may hold (None: no limit)
+    max_routes: Option<usize>,
+    /// Set once the first registration over `max_routes` has been logged
+    limit_logged: AtomicBool,
 }
 
 impl Router {
     /// Create a new router
     pub fn new() -> Self {
-        Self {
-            routes: RwLock::new(HashMap::new()),
-            path_matcher: RwLock::new(matchit::Router::new()),
-        }
+        Self::default()
+    }
+
+    /// Reject registering more than `max` distinct routes (None: no limit)
+    pub fn with_max_routes(mut self, max: Option<usize>) -> Self {
+        self.max_routes = max;
+        self
     }
 
     /// Register a route handler
@@ -140,41 +240,18 @@ impl Router {
         required_role: Option<String>,
         is_sse: bool,
     ) -> RuntimeResult<()> {
-        let key = RouteKey {
-            method,
-            path: path.clone(),
-        };
-
-        let handler = RouteHandler {
+        self.insert(RouteHandler {
             method,
-            path: path.clone(),
+            path,
             handler_name,
             protected,
             required_role,
             is_sse,
             is_ws: false,
             redirect_destination: None,
-        };
-
-        // Store in routes map
-        {
-            let mut routes = self.routes.write();
-            routes.insert(key.clone(), handler);
-        }
-
-        // Add to path matcher
-        // Convert Express-style :param to matchit-style {param}
-        // matchit 0.8+ uses {id} syntax instead of :id
-        let matchit_path = convert_express_to_matchit(&path);
-
-        {
-            let mut matcher = self.path_matcher.write();
-            // matchit returns an error if the path is already registered,
-            // which we ignore since we're replacing the route
-            let _ = matcher.insert(matchit_path, key);
-        }
-
-        Ok(())
+            request_schema: None,
+            timeout_ms: None,
+        })
     }
 
     /// Register a static redirect route.
@@ -188,34 +265,18 @@ impl Router {
         to_path: String,
         status: u16,
     ) -> RuntimeResult<()> {
-        let key = RouteKey {
+        self.insert(RouteHandler {
             method,
-            path: from_path.clone(),
-        };
-
-        let handler = RouteHandler {
-            method,
-            path: from_path.clone(),
+            path: from_path,
             handler_name: String::new(),
             protected: false,
             required_role: None,
             is_sse: false,
             is_ws: false,
             redirect_destination: Some((to_path, status)),
-        };
-
-        {
-            let mut routes = self.routes.write();
-            routes.insert(key.clone(), handler);
-        }
-
-        let matchit_path = convert_express_to_matchit(&from_path);
-        {
-            let mut matcher = self.path_matcher.write();
-            let _ = matcher.insert(matchit_path, key);
-        }
-
-        Ok(())
+            request_schema: None,
+            timeout_ms: None,
+        })
     }
 
     /// Register a WebSocket (LIVE) route handler.
@@ -224,33 +285,101 @@ impl Router {
     /// server's fallback handler recognises this flag and performs the WebSocket
     /// upgrade instead of calling a regular WASM handler.
     pub fn register_ws(&self, path: String, handler_name: String) -> RuntimeResult<()> {
-        let key = RouteKey {
-            method: HttpMethod::GET,
-            path: path.clone(),
-        };
-
-        let handler = RouteHandler {
+        self.insert(RouteHandler {
             method: HttpMethod::GET,
-            path: path.clone(),
+            path,
             handler_name,
             protected: false,
             required_role: None,
             is_sse: false,
             is_ws: true,
             redirect_destination: None,
+            request_schema: None,
+            timeout_ms: None,
+        })
+    }
+
+    fn insert(&self, handler: RouteHandler) -> RuntimeResult<()> {
+        let key = RouteKey {
+            method: handler.method,
+            path: handler.path.clone(),
         };
+        // Convert Express-style :param to matchit-style {param}
+        // matchit 0.8+ uses {id} syntax instead of :id
+        let matchit_path = convert_express_to_matchit(&handler.path);
 
-        {
-            let mut routes = self.routes.write();
-            routes.insert(key.clone(), handler);
+        let mut table = self.table.write();
+        match table.routes.get(&key) {
+            Some(existing) if existing.same_registration(&handler) => {
+                tracing::debug!("Route {} {} already registered", key.method, key.path);
+                return Ok(());
+            }
+            Some(existing) => tracing::debug!(
+                "Route {} {} re-registered: handler {} replaces {}",
+                key.method,
+                key.path,
+                handler.handler_name,
+                existing.handler_name
+            ),
+            None => {
+                if let Some(max) = self.max_routes
+                    && table.routes.len() >= max
+                {
+                    let message = format!(
+                        "route limit of {} reached; not registering {} {}",
+                        max, key.method, key.path
+                    );
+                    // A runaway module can hit this once per remaining
+                    // registration; say so once
+                    if !self.limit_logged.swap(true, Ordering::Relaxed) {
+                        tracing::error!("{} (further rejections are not logged)", message);
+                    }
+                    return Err(RuntimeError::route(message));
+                }
+            }
         }
+        table.routes.insert(key.clone(), handler);
+        // matchit returns an error if the path is already registered (e.g.
+        // for another method), which we ignore since the path still matches
+        let _ = table.matcher.insert(matchit_path, key);
+        Ok(())
+    }
 
-        let matchit_path = convert_express_to_matchit(&path);
-        {
-            let mut matcher = self.path_matcher.write();
-            let _ = matcher.insert(matchit_path, key);
-        }
+    /// Attach a request body schema to an already registered route.
+    pub fn set_request_schema(
+        &self,
+        method: HttpMethod,
+        path: &str,
+        schema: Arc<JsonSchema>,
+    ) -> RuntimeResult<()> {
+        let key = RouteKey {
+            method,
+            path: path.to_string(),
+        };
+        let mut table = self.table.write();
+        let handler = table.routes.get_mut(&key).ok_or_else(|| {
+            RuntimeError::route(format!("No route registered for {} {}", method, path))
+        })?;
+        handler.request_schema = Some(schema);
+        Ok(())
+    }
 
+    /// Override the handler timeout of an already registered route.
+    pub fn set_route_timeout(
+        &self,
+        method: HttpMethod,
+        path: &str,
+        timeout_ms: u64,
+    ) -> RuntimeResult<()> {
+        let key = RouteKey {
+            method,
+            path: path.to_string(),
+        };
+        let mut table = self.table.write();
+        let handler = table.routes.get_mut(&key).ok_or_else(|| {
+            RuntimeError::route(format!("No route registered for {} {}", method, path))
+        })?;
+        handler.timeout_ms = Some(timeout_ms);
         Ok(())
     }
 
@@ -264,11 +393,10 @@ impl Router {
 
         debug!("Router::find: Looking for {} {}", method.as_str(), path);
 
-        let matcher = self.path_matcher.read();
-        let routes = self.routes.read();
+        let table = self.table.read();
 
         // Try to match the path
-        if let Ok(matched) = matcher.at(path) {
+        if let Ok(matched) = table.matcher.at(path) {
             debug!(
                 "Router::find: matchit matched path '{}' to route '{}'",
                 path, matched.value.path
@@ -280,7 +408,7 @@ impl Router {
                 path: matched.value.path.clone(),
             };
 
-            if let Some(handler) = routes.get(&key) {
+            if let Some(handler) = table.routes.get(&key) {
                 // Extract path parameters
                 let params: HashMap<String, String> = matched
                     .params
@@ -303,6 +431,36 @@ impl Router {
         None
     }
 
+    /// Methods with a route registered for the route pattern `path` matches,
+    /// in [`HttpMethod::ALL`] order followed by extension methods by name.
+    /// Empty when no route matches the path.
+    pub fn allowed_methods(&self, path: &str) -> Vec<HttpMethod> {
+        let table = self.table.read();
+        let Ok(matched) = table.matcher.at(path) else {
+            return Vec::new();
+        };
+        let routes = &table.routes;
+        let mut methods: Vec<HttpMethod> = HttpMethod::ALL
+            .into_iter()
+            .filter(|&method| {
+                routes.contains_key(&RouteKey {
+                    method,
+                    path: matched.value.path.clone(),
+                })
+            })
+            .collect();
+        let mut extensions: Vec<HttpMethod> = routes
+            .keys()
+            .filter(|key| {
+                matches!(key.method, HttpMethod::Other(_)) && key.path == matched.value.path
+            })
+            .map(|key| key.method)
+            .collect();
+        extensions.sort_by_key(|method| method.as_str());
+        methods.extend(extensions);
+        methods
+    }
+
     /// Check if a route exists
     pub fn exists(&self, method: HttpMethod, path: &str) -> bool {
         self.find(method, path).is_some()
@@ -310,32 +468,22 @@ impl Router {
 
     /// Get all registered routes (for debugging)
     pub fn all_routes(&self) -> Vec<RouteHandler> {
-        let routes = self.routes.read();
-        routes.values().cloned().collect()
+        self.table.read().routes.values().cloned().collect()
     }
 
     /// Clear all routes
     pub fn clear(&self) {
-        let mut routes = self.routes.write();
-        let mut matcher = self.path_matcher.write();
-        routes.clear();
-        *matcher = matchit::Router::new();
+        *self.table.write() = RouteTable::default();
     }
 
     /// Get route count
     pub fn len(&self) -> usize {
-        self.routes.read().len()
+        self.table.read().routes.len()
     }
 
     /// Check if router is empty
     pub fn is_empty(&self) -> bool {
-        self.routes.read().is_empty()
-    }
-}
-
-impl Default for Router {
-    fn default() -> Self {
-        Self::new()
+        self.table.read().routes.is_empty()
     }
 }
 
@@ -379,6 +527,21 @@ mod tests {
         assert!(HttpMethod::parse("INVALID").is_err());
     }
 
+    #[test]
+    fn test_extension_method_registration() {
+        assert!(HttpMethod::parse("PROPPATCH").is_err());
+        let method = HttpMethod::register_extension("proppatch").unwrap();
+        assert_eq!(method, HttpMethod::Other("PROPPATCH"));
+        assert_eq!(method.as_str(), "PROPPATCH");
+        assert_eq!(HttpMethod::parse("PROPPATCH").unwrap(), method);
+        assert_eq!(
+            HttpMethod::register_extension("get").unwrap(),
+            HttpMethod::GET
+        );
+        assert!(HttpMethod::register_extension("BAD METHOD").is_err());
+        assert!(HttpMethod::register_extension("").is_err());
+    }
+
     #[test]
     fn test_router_basic() {
         let router = Router::new();
@@ -421,6 +584,29 @@ mod tests {
         assert!(router.find(HttpMethod::GET, "/not-found").is_none());
     }
 
+    #[test]
+    fn allowed_methods_lists_methods_for_the_matched_pattern() {
+        let router = Router::new();
+        for method in [HttpMethod::POST, HttpMethod::GET, HttpMethod::DELETE] {
+            router
+                .register(
+                    method,
+                    "/users/:id".to_string(),
+                    format!("handler_{}", method),
+                    false,
+                    None,
+                    false,
+                )
+                .unwrap();
+        }
+
+        assert_eq!(
+            router.allowed_methods("/users/42"),
+            vec![HttpMethod::GET, HttpMethod::POST, HttpMethod::DELETE]
+        );
+        assert!(router.allowed_methods("/posts").is_empty());
+    }
+
     #[test]
     fn test_router_with_params() {
         let router = Router::new();
@@ -527,6 +713,118 @@ mod tests {
         assert!(router.is_empty());
     }
 
+    #[test]
+    fn test_router_identical_registration_is_idempotent() {
+        let router = Router::new();
+        let register = |handler: &str| {
+            router
+                .register(
+                    HttpMethod::GET,
+                    "/items/:id".to_string(),
+                    handler.to_string(),
+                    false,
+                    None,
+                    false,
+                )
+                .unwrap()
+        };
+
+        register("__route_handler_0");
+        router
+            .set_route_timeout(HttpMethod::GET, "/items/:id", 250)
+            .unwrap();
+        register("__route_handler_0");
+        let (handler, _) = router.find(HttpMethod::GET, "/items/7").unwrap();
+        assert_eq!(handler.timeout_ms, Some(250));
+        assert_eq!(router.len(), 1);
+
+        register("__route_handler_1");
+        let (handler, _) = router.find(HttpMethod::GET, "/items/7").unwrap();
+        assert_eq!(handler.handler_name, "__route_handler_1");
+        assert_eq!(handler.timeout_ms, None);
+        assert_eq!(router.len(), 1);
+    }
+
+    #[test]
+    fn test_router_rejects_routes_beyond_the_limit() {
+        let router = Router::new().with_max_routes(Some(2));
+        let register = |path: &str, handler: &str| {
+            router.register(
+                HttpMethod::GET,
+                path.to_string(),
+                handler.to_string(),
+                false,
+                None,
+                false,
+            )
+        };
+
+        register("/a", "a").unwrap();
+        register("/b", "b").unwrap();
+        assert!(register("/c", "c").is_err());
+        assert!(router.find(HttpMethod::GET, "/c").is_none());
+        assert_eq!(router.len(), 2);
+
+        // Replacing an existing route does not add one
+        register("/b", "b2").unwrap();
+        assert_eq!(router.len(), 2);
+    }
+
+    #[test]
+    fn test_router_concurrent_registration() {
+        const THREADS: usize = 8;
+        const ROUTES: usize = 50;
+        let router = Router::new();
+
+        // Every thread registers the same routes (in a different order) while
+        // also looking them up, as a module re-running init on a worker would.
+        std::thread::scope(|scope| {
+            for t in 0..THREADS {
+                let router = &router;
+                scope.spawn(move || {
+                    for i in 0..ROUTES {
+                        let i = (i + t * 7) % ROUTES;
+                        let method = if i.is_multiple_of(2) {
+                            HttpMethod::GET
+                        } else {
+                            HttpMethod::POST
+                        };
+                        router
+                            .register(
+                                method,
+                                format!("/r{}/:id", i / 2),
+                                format!("__route_handler_{}", i),
+                                false,
+                                None,
+                                false,
+                            )
+                            .unwrap();
+                        let (handler, params) =
+                            router.find(method, &format!("/r{}/x", i / 2)).unwrap();
+                        assert_eq!(handler.handler_name, format!("__route_handler_{}", i));
+                        assert_eq!(params["id"], "x");
+                        router.allowed_methods(&format!("/r{}/x", i / 2));
+                    }
+                });
+            }
+        });
+
+        assert_eq!(router.len(), ROUTES);
+        for i in 0..ROUTES {
+            let method = if i.is_multiple_of(2) {
+                HttpMethod::GET
+            } else {
+                HttpMethod::POST
+            };
+            let (handler, _) = router.find(method, &format!("/r{}/x", i / 2)).unwrap();
+            assert_eq!(handler.handler_name, format!("__route_handler_{}", i));
+        }
+        assert_eq!(
+            router.allowed_methods("/r0/x"),
+            vec![HttpMethod::GET, HttpMethod::POST]
+        );
+    }
+
     #[test]
     fn test_express_to_matchit_conversion() {
         assert_eq!(convert_express_to_matchit("/users"), "/users");
@@ -1,7 +1,8 @@
 //! Runtime configuration populated by `server:` block bridge calls during WASM init.
 //!
 //! Values written here by `_http_listen_on`, `_cors_configure`,
-//! `_rate_limit_configure`, and `_http_set_global_error_handler` outlive the
+//! `_rate_limit_configure`, `_http_set_global_error_handler` and
+//! `_http_middleware` outlive the
 //! transient `WasmState` used for initialization and are read by `start_server`
 //! when building the axum router. Per-request `WasmState`s receive an `Arc`
 //! clone so the same handle is shared everywhere.
@@ -17,6 +18,9 @@ pub struct RuntimeConfig {
     pub cors: Option<CorsConfig>,
     pub rate_limit: Option<RateLimitConfig>,
     pub global_error_handler: Option<String>,
+    /// Exports run, in registration order, before every route handler in
+    /// the same WASM instance (registered via `_http_middleware`)
+    pub middleware: Vec<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -28,7 +32,7 @@ pub struct CorsConfig {
     pub allow_credentials: bool,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct RateLimitConfig {
     pub per_window: u32,
     pub window_secs: u32,
@@ -0,0 +1,169 @@
+//! `clean-server check`: validate a deployment before serving it.
+//!
+//! Runs the checks a misconfigured server would otherwise only fail lazily —
+//! settings, required environment, bearer auth keys, every module
+//! instantiating with its entry point, and the database answering a query —
+//! without binding a port or running the module.
+
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+
+use host_bridge::{DbBridge, DbConfig, redact_url_credentials};
+use tokio::sync::RwLock as TokioRwLock;
+
+use crate::server::{ServerConfig, validate_config};
+use crate::session::{SessionConfig, create_session_store};
+use crate::wasm::{SharedDbBridge, WasmInstance};
+
+/// Time allowed for the database to accept a connection during the check
+const DB_CHECK_TIMEOUT_MS: u64 = 5_000;
+
+/// Outcome of one check: what passed, or why it failed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckResult {
+    pub name: String,
+    pub outcome: Result<String, String>,
+}
+
+/// Results of `run`, printed one line per check
+#[derive(Debug, Clone, Default)]
+pub struct CheckReport {
+    pub results: Vec<CheckResult>,
+}
+
+impl CheckReport {
+    /// Whether every check passed
+    pub fn passed(&self) -> bool {
+        self.results.iter().all(|r| r.outcome.is_ok())
+    }
+
+    fn record(&mut self, name: impl Into<String>, outcome: Result<String, String>) {
+        self.results.push(CheckResult {
+            name: name.into(),
+            outcome,
+        });
+    }
+}
+
+impl fmt::Display for CheckReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for result in &self.results {
+            match &result.outcome {
+                Ok(detail) => writeln!(f, "  ok    {}: {}", result.name, detail)?,
+                Err(message) => writeln!(f, "  FAIL  {}: {}", result.name, message)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Check `wasm_path` (and `config.mounts`) against `config`.
+pub async fn run(wasm_path: &Path, config: &ServerConfig) -> CheckReport {
+    let mut report = CheckReport::default();
+
+    report.record(
+        "config",
+        validate_config(config)
+            .map(|()| "settings are valid".to_string())
+            .map_err(|e| e.to_string()),
+    );
+
+    if let Some(auth) = &config.auth {
+        let sessions = create_session_store(SessionConfig::default());
+        report.record(
+            "auth",
+            auth.provider(&sessions)
+                .map(|_| format!("{:?} loaded", auth))
+                .map_err(|e| e.to_string()),
+        );
+    }
+
+    let mut modules = vec![("/".to_string(), wasm_path.to_path_buf())];
+    modules.extend(
+        config
+            .mounts
+            .iter()
+            .map(|m| (m.prefix.clone(), m.wasm_path.clone())),
+    );
+    for (prefix, path) in modules {
+        report.record(format!("wasm {}", prefix), check_module(&path, config));
+    }
+
+    if let Some(url) = &config.database_url {
+        report.record("database", check_database(url).await);
+    }
+
+    report
+}
+
+fn check_module(path: &Path, config: &ServerConfig) -> Result<String, String> {
+    let db_bridge: SharedDbBridge = Arc::new(TokioRwLock::new(DbBridge::new()));
+    let instance = WasmInstance::load_with_module_cache(
+        path,
+        crate::router::create_shared_router(),
+        db_bridge,
+        create_session_store(SessionConfig::default()),
+        config.effective_memory_limit(),
+        None,
+        config.diag_dir.as_deref(),
+    )
+    .map_err(|e| e.to_string())?;
+    let entry = instance.check_instantiates().map_err(|e| e.to_string())?;
+    Ok(format!("{:?} instantiates (entry point {})", path, entry))
+}
+
+async fn check_database(url: &str) -> Result<String, String> {
+    let redacted = redact_url_credentials(url);
+    let mut bridge = DbBridge::new();
+    bridge
+        .configure(DbConfig {
+            database_url: url.to_string(),
+            max_connections: 1,
+            min_connections: 1,
+            connection_timeout: DB_CHECK_TIMEOUT_MS,
+            eager_connect: true,
+            ..DbConfig::default()
+        })
+        .await
+        .map_err(|e| {
+            format!(
+                "cannot connect to {}: {}",
+                redacted,
+                redact_url_credentials(&e.to_string())
+            )
+        })?;
+    let result = bridge
+        .call(
+            "query",
+            serde_json::json!({ "sql": "SELECT 1", "params": [] }),
+        )
+        .await
+        .map_err(|e| e.to_string())?;
+    if result["ok"] != true {
+        return Err(format!(
+            "{} did not answer SELECT 1: {}",
+            redacted, result["err"]
+        ));
+    }
+    Ok(format!("{} answers queries", redacted))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_fails_if_any_check_fails() {
+        let mut report = CheckReport::default();
+        report.record("config", Ok("settings are valid".to_string()));
+        assert!(report.passed());
+
+        report.record("database", Err("cannot connect".to_string()));
+        assert!(!report.passed());
+        assert_eq!(
+            report.to_string(),
+            "  ok    config: settings are valid\n  FAIL  database: cannot connect\n"
+        );
+    }
+}
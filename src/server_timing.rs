@@ -0,0 +1,59 @@
+//! `Server-Timing` response header, enabled by `ServerConfig.server_timing`.
+//!
+//! Breaks a request into the phases browser devtools show next to the
+//! network timeline:
+//! - `auth`: resolving the session cookie or bearer token
+//! - `wasm`: middleware and route handler, including their database calls
+//! - `db`: time the handler spent in database bridge calls
+
+use std::time::Duration;
+
+use axum::http::HeaderValue;
+
+/// Name of the response header carrying the timings
+pub const SERVER_TIMING_HEADER: &str = "server-timing";
+
+/// Phase durations collected while handling one request. Phases the request
+/// never reached (e.g. `wasm` for a 401) are left out of the header.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ServerTiming {
+    pub auth: Option<Duration>,
+    pub wasm: Option<Duration>,
+    pub db: Option<Duration>,
+}
+
+impl ServerTiming {
+    /// Header value listing each recorded phase in milliseconds, or None
+    /// when no phase was recorded.
+    pub fn header_value(&self) -> Option<HeaderValue> {
+        let metrics: Vec<String> = [("auth", self.auth), ("wasm", self.wasm), ("db", self.db)]
+            .into_iter()
+            .filter_map(|(name, duration)| {
+                duration.map(|d| format!("{};dur={:.3}", name, d.as_secs_f64() * 1000.0))
+            })
+            .collect();
+        if metrics.is_empty() {
+            return None;
+        }
+        HeaderValue::from_str(&metrics.join(", ")).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn header_lists_recorded_phases_in_milliseconds() {
+        let timing = ServerTiming {
+            auth: Some(Duration::from_micros(250)),
+            wasm: Some(Duration::from_millis(12)),
+            db: None,
+        };
+        assert_eq!(
+            timing.header_value().unwrap(),
+            "auth;dur=0.250, wasm;dur=12.000"
+        );
+        assert!(ServerTiming::default().header_value().is_none());
+    }
+}
@@ -4,14 +4,23 @@
 //! - Session creation with claims
 //! - Session retrieval and validation
 //! - Session deletion (logout)
-//! - Automatic expiration
+//! - Automatic expiration (sliding idle timeout plus absolute lifetime)
+//!
+//! `SessionStore::use_cookie_sessions` switches typed sessions to a stateless
+//! backend (`CookieSessionStore`) that keeps the session in an AES-GCM
+//! encrypted cookie instead of server memory.
 //!
 //! Future: Redis/database-backed sessions for horizontal scaling
 
+use crate::error::{RuntimeError, RuntimeResult};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use host_bridge::CryptoBridge;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 use std::sync::{Arc, RwLock};
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tracing::{debug, info};
 use uuid::Uuid;
 
@@ -26,29 +35,60 @@ pub struct SessionData {
     pub role: String,
     /// Additional claims (JSON string)
     pub claims: String,
-    /// Last accessed timestamp
+    /// Creation timestamp (drives the absolute timeout)
+    #[serde(skip)]
+    created_at: Option<Instant>,
+    /// Last accessed timestamp (drives the idle timeout)
     #[serde(skip)]
     last_accessed: Option<Instant>,
 }
 
 impl SessionData {
     pub fn new(user_id: i32, role: String, claims: String) -> Self {
+        let now = Instant::now();
         Self {
             session_id: Uuid::new_v4().to_string(),
             user_id,
             role,
             claims,
-            last_accessed: Some(Instant::now()),
+            created_at: Some(now),
+            last_accessed: Some(now),
         }
     }
 
-    /// Check if session is expired
-    pub fn is_expired(&self, timeout: Duration) -> bool {
-        if let Some(last_accessed) = self.last_accessed {
-            last_accessed.elapsed() > timeout
-        } else {
-            true
+    /// Rebuild a session whose timestamps are known only as ages (e.g. decoded
+    /// from a cookie). Ages that predate the monotonic clock leave the
+    /// timestamp unset, which `is_expired` treats as expired.
+    fn with_ages(mut self, created_age: Duration, idle_age: Duration) -> Self {
+        let now = Instant::now();
+        self.created_at = now.checked_sub(created_age);
+        self.last_accessed = now.checked_sub(idle_age);
+        self
+    }
+
+    /// When the session was created, if known
+    pub fn created_at(&self) -> Option<Instant> {
+        self.created_at
+    }
+
+    /// When the session was last accessed, if known
+    pub fn last_accessed(&self) -> Option<Instant> {
+        self.last_accessed
+    }
+
+    /// Check if session is expired.
+    ///
+    /// A session expires when it has been idle longer than `idle_timeout`, or
+    /// when it is older than `absolute_timeout` regardless of activity.
+    /// Sessions without timestamps (e.g. freshly deserialized) are expired.
+    pub fn is_expired(&self, idle_timeout: Duration, absolute_timeout: Option<Duration>) -> bool {
+        let (Some(created_at), Some(last_accessed)) = (self.created_at, self.last_accessed) else {
+            return true;
+        };
+        if last_accessed.elapsed() > idle_timeout {
+            return true;
         }
+        matches!(absolute_timeout, Some(max) if created_at.elapsed() > max)
     }
 
     /// Update last accessed time
@@ -60,8 +100,11 @@ impl SessionData {
 /// Session store configuration
 #[derive(Debug, Clone)]
 pub struct SessionConfig {
-    /// Session timeout in seconds (default: 3600 = 1 hour)
-    pub timeout_seconds: u64,
+    /// Idle timeout, reset on every access (default: 1 hour)
+    pub idle_timeout: Duration,
+    /// Absolute maximum session lifetime, enforced regardless of activity.
+    /// `None` disables the cap (default: 24 hours)
+    pub absolute_timeout: Option<Duration>,
     /// Cookie name for session ID
     pub cookie_name: String,
     /// Cookie path
@@ -74,10 +117,20 @@ pub struct SessionConfig {
     pub http_only: bool,
 }
 
+impl SessionConfig {
+    /// Cookie `Max-Age` in seconds. The cookie has to outlive idle periods
+    /// because it is not re-issued on every access, so it follows the
+    /// absolute lifetime when one is configured.
+    pub fn cookie_max_age(&self) -> u64 {
+        self.absolute_timeout.unwrap_or(self.idle_timeout).as_secs()
+    }
+}
+
 impl Default for SessionConfig {
     fn default() -> Self {
         Self {
-            timeout_seconds: 3600,
+            idle_timeout: Duration::from_secs(3600),
+            absolute_timeout: Some(Duration::from_secs(24 * 3600)),
             cookie_name: "session".to_string(),
             cookie_path: "/".to_string(),
             same_site: "Lax".to_string(),
@@ -91,8 +144,9 @@ impl Default for SessionConfig {
 pub struct SessionStore {
     /// Sessions indexed by session ID (typed sessions for auth flow)
     sessions: HashMap<String, SessionData>,
-    /// Raw key-value session storage (for plugin API: _session_store/_session_get)
-    raw_data: HashMap<String, (String, Instant)>,
+    /// Raw key-value session storage (for plugin API: _session_store/_session_get).
+    /// Value is `(data, created_at, last_accessed)`.
+    raw_data: HashMap<String, (String, Instant, Instant)>,
     /// CSRF tokens indexed by session ID
     csrf_tokens: HashMap<String, String>,
     /// Consumed JWT ids (jti) with the instant at which they expire from the
@@ -104,6 +158,9 @@ pub struct SessionStore {
     /// `(user_id, expires_at)`. Consumed atomically via `consume_reset_token()`
     /// under the SessionStore write lock.
     password_resets: HashMap<String, (i32, Instant)>,
+    /// When set, typed sessions live in encrypted cookies instead of
+    /// `sessions`, and a session's ID is its sealed cookie value
+    cookies: Option<CookieSessionStore>,
     /// Configuration
     config: SessionConfig,
 }
@@ -116,27 +173,60 @@ impl SessionStore {
             csrf_tokens: HashMap::new(),
             consumed_jti: HashMap::new(),
             password_resets: HashMap::new(),
+            cookies: None,
             config,
         }
     }
 
-    /// Create a new session
-    pub fn create(&mut self, user_id: i32, role: &str, claims: &str) -> SessionData {
+    /// Keep typed sessions in cookies encrypted with a key derived from
+    /// `secret` instead of in memory (see `CookieSessionStore`). Raw
+    /// key-value data, CSRF tokens and the other stores stay in memory.
+    pub fn use_cookie_sessions(&mut self, secret: &[u8]) {
+        self.cookies = Some(CookieSessionStore::new(self.config.clone(), secret));
+    }
+
+    /// Whether typed sessions are kept in cookies
+    pub fn uses_cookie_sessions(&self) -> bool {
+        self.cookies.is_some()
+    }
+
+    /// Create a new session.
+    ///
+    /// With cookie sessions the returned `session_id` is the sealed cookie
+    /// value, which fails when it exceeds the cookie size limit.
+    pub fn create(&mut self, user_id: i32, role: &str, claims: &str) -> RuntimeResult<SessionData> {
         let session = SessionData::new(user_id, role.to_string(), claims.to_string());
+        if let Some(cookies) = &self.cookies {
+            info!("Creating cookie session for user {}", user_id);
+            return cookies.seal(session);
+        }
         let session_id = session.session_id.clone();
 
         info!("Creating session {} for user {}", session_id, user_id);
         self.sessions.insert(session_id, session.clone());
 
-        session
+        Ok(session)
     }
 
-    /// Get a session by ID
+    /// Get a session by ID, refreshing its idle timer.
+    /// Sessions past either the idle or the absolute timeout are removed.
+    ///
+    /// With cookie sessions the ID is the cookie value; the returned
+    /// session's ID is the value re-sealed with the refreshed idle timer.
     pub fn get(&mut self, session_id: &str) -> Option<SessionData> {
-        let timeout = Duration::from_secs(self.config.timeout_seconds);
+        if let Some(cookies) = &self.cookies {
+            return cookies.decode(session_id).and_then(|session| {
+                cookies
+                    .seal(session)
+                    .map_err(|e| debug!("Failed to re-seal session cookie: {}", e))
+                    .ok()
+            });
+        }
+        let idle = self.config.idle_timeout;
+        let absolute = self.config.absolute_timeout;
 
         if let Some(session) = self.sessions.get_mut(session_id) {
-            if session.is_expired(timeout) {
+            if session.is_expired(idle, absolute) {
                 debug!("Session {} has expired, removing", session_id);
                 self.sessions.remove(session_id);
                 return None;
@@ -147,8 +237,12 @@ impl SessionStore {
         None
     }
 
-    /// Delete a session
+    /// Delete a session. Cookie sessions are not stored, so deleting one
+    /// only succeeds; the caller clears the cookie with `format_clear_cookie`.
     pub fn delete(&mut self, session_id: &str) -> bool {
+        if self.cookies.is_some() {
+            return true;
+        }
         info!("Deleting session {}", session_id);
         self.sessions.remove(session_id).is_some()
     }
@@ -160,31 +254,12 @@ impl SessionStore {
 
     /// Format a Set-Cookie header for the session
     pub fn format_cookie(&self, session_id: &str) -> String {
-        let mut cookie = format!(
-            "{}={}; Path={}",
-            self.config.cookie_name, session_id, self.config.cookie_path
-        );
-
-        if self.config.http_only {
-            cookie.push_str("; HttpOnly");
-        }
-        if self.config.secure {
-            cookie.push_str("; Secure");
-        }
-        cookie.push_str(&format!("; SameSite={}", self.config.same_site));
-
-        // Add max-age
-        cookie.push_str(&format!("; Max-Age={}", self.config.timeout_seconds));
-
-        cookie
+        format_session_cookie(&self.config, session_id)
     }
 
     /// Format a cookie header that clears the session
     pub fn format_clear_cookie(&self) -> String {
-        format!(
-            "{}=; Path={}; Max-Age=0; HttpOnly",
-            self.config.cookie_name, self.config.cookie_path
-        )
+        format_clear_session_cookie(&self.config)
     }
 
     // =========================================
@@ -194,32 +269,47 @@ impl SessionStore {
     /// Store raw data by session ID (for plugin API)
     pub fn store_raw(&mut self, session_id: &str, data: &str) -> bool {
         debug!("Storing raw session data for {}", session_id);
+        let now = Instant::now();
+        // Overwriting a live session keeps its original creation time so
+        // repeated writes cannot extend it past the absolute timeout; an
+        // expired one is replaced by a new session.
+        let created_at = self
+            .raw_data
+            .get(session_id)
+            .filter(|(_, created_at, last_accessed)| {
+                !raw_is_expired(
+                    *created_at,
+                    *last_accessed,
+                    self.config.idle_timeout,
+                    self.config.absolute_timeout,
+                )
+            })
+            .map(|(_, created_at, _)| *created_at)
+            .unwrap_or(now);
         self.raw_data
-            .insert(session_id.to_string(), (data.to_string(), Instant::now()));
+            .insert(session_id.to_string(), (data.to_string(), created_at, now));
         true
     }
 
     /// Get raw session data by ID (with expiration check)
     pub fn get_raw(&mut self, session_id: &str) -> Option<String> {
-        let timeout = Duration::from_secs(self.config.timeout_seconds);
+        let idle = self.config.idle_timeout;
+        let absolute = self.config.absolute_timeout;
 
-        if let Some((data, last_accessed)) = self.raw_data.get(session_id) {
-            if last_accessed.elapsed() > timeout {
+        if let Some((data, created_at, last_accessed)) = self.raw_data.get_mut(session_id) {
+            if raw_is_expired(*created_at, *last_accessed, idle, absolute) {
                 debug!("Raw session {} expired, removing", session_id);
                 self.raw_data.remove(session_id);
                 return None;
             }
-            let result = data.clone();
             // Touch - update timestamp
-            if let Some(entry) = self.raw_data.get_mut(session_id) {
-                entry.1 = Instant::now();
-            }
-            return Some(result);
+            *last_accessed = Instant::now();
+            return Some(data.clone());
         }
 
         // Also check typed sessions for backward compatibility
         if let Some(session) = self.sessions.get_mut(session_id) {
-            if session.is_expired(timeout) {
+            if session.is_expired(idle, absolute) {
                 self.sessions.remove(session_id);
                 return None;
             }
@@ -334,14 +424,16 @@ impl SessionStore {
 
     /// Cleanup expired sessions (call periodically)
     pub fn cleanup_expired(&mut self) -> usize {
-        let timeout = Duration::from_secs(self.config.timeout_seconds);
+        let idle = self.config.idle_timeout;
+        let absolute = self.config.absolute_timeout;
         let before = self.sessions.len() + self.raw_data.len();
         let now = Instant::now();
 
         self.sessions
-            .retain(|_, session| !session.is_expired(timeout));
-        self.raw_data
-            .retain(|_, (_, last_accessed)| last_accessed.elapsed() <= timeout);
+            .retain(|_, session| !session.is_expired(idle, absolute));
+        self.raw_data.retain(|_, (_, created_at, last_accessed)| {
+            !raw_is_expired(*created_at, *last_accessed, idle, absolute)
+        });
         self.consumed_jti.retain(|_, expires_at| *expires_at > now);
         self.password_resets
             .retain(|_, (_, expires_at)| *expires_at > now);
@@ -365,6 +457,46 @@ impl SessionStore {
     }
 }
 
+/// Build a Set-Cookie header carrying `value` with the configured attributes
+fn format_session_cookie(config: &SessionConfig, value: &str) -> String {
+    let mut cookie = format!(
+        "{}={}; Path={}",
+        config.cookie_name, value, config.cookie_path
+    );
+
+    if config.http_only {
+        cookie.push_str("; HttpOnly");
+    }
+    if config.secure {
+        cookie.push_str("; Secure");
+    }
+    cookie.push_str(&format!("; SameSite={}", config.same_site));
+
+    // Add max-age
+    cookie.push_str(&format!("; Max-Age={}", config.cookie_max_age()));
+
+    cookie
+}
+
+/// Build a Set-Cookie header that clears the session cookie
+fn format_clear_session_cookie(config: &SessionConfig) -> String {
+    format!(
+        "{}=; Path={}; Max-Age=0; HttpOnly",
+        config.cookie_name, config.cookie_path
+    )
+}
+
+/// Expiry check for raw key-value sessions, mirroring `SessionData::is_expired`
+fn raw_is_expired(
+    created_at: Instant,
+    last_accessed: Instant,
+    idle_timeout: Duration,
+    absolute_timeout: Option<Duration>,
+) -> bool {
+    last_accessed.elapsed() > idle_timeout
+        || matches!(absolute_timeout, Some(max) if created_at.elapsed() > max)
+}
+
 /// Shared session store type
 pub type SharedSessionStore = Arc<RwLock<SessionStore>>;
 
@@ -373,25 +505,211 @@ pub fn create_session_store(config: SessionConfig) -> SharedSessionStore {
     Arc::new(RwLock::new(SessionStore::new(config)))
 }
 
+// =========================================
+// COOKIE-BACKED SESSIONS
+// =========================================
+
+/// Default cap on a session cookie (`name=value`). Browsers drop cookies
+/// larger than roughly 4 KB, so an oversized session must fail loudly
+/// instead of silently logging the user out.
+pub const DEFAULT_MAX_COOKIE_SIZE: usize = 4096;
+
+/// Wire format of a cookie session before encryption. `SessionData` skips
+/// its monotonic timestamps when serialized, so wall-clock milliseconds are
+/// carried alongside to keep the idle and absolute timeouts enforceable.
+#[derive(Serialize, Deserialize)]
+struct CookiePayload {
+    #[serde(flatten)]
+    session: SessionData,
+    created_at_ms: u64,
+    last_accessed_ms: u64,
+}
+
+/// Stateless session storage: the whole `SessionData` lives in the cookie.
+///
+/// The session is serialized to JSON and sealed with `CryptoBridge`'s
+/// AES-256-GCM, which both encrypts and authenticates it, so a tampered
+/// cookie fails to decode. The cookie value is
+/// `base64url(nonce || ciphertext || tag)`.
+///
+/// Selected through `SessionStore::use_cookie_sessions`, which the server
+/// calls when `ServerConfig::session_cookie_secret` is set.
+pub struct CookieSessionStore {
+    crypto: CryptoBridge,
+    /// AES-256 key derived from the configured secret
+    key: [u8; 32],
+    /// Maximum size of `name=value` in bytes
+    max_cookie_size: usize,
+    /// Cookie attributes and timeouts
+    config: SessionConfig,
+}
+
+impl CookieSessionStore {
+    /// Create a cookie store keyed by `secret`. The AES key is the SHA-256
+    /// of the secret, so any secret length yields a full-strength key.
+    pub fn new(config: SessionConfig, secret: &[u8]) -> Self {
+        Self {
+            crypto: CryptoBridge::new(),
+            key: Sha256::digest(secret).into(),
+            max_cookie_size: DEFAULT_MAX_COOKIE_SIZE,
+            config,
+        }
+    }
+
+    /// Override the maximum cookie size
+    pub fn with_max_cookie_size(mut self, max_cookie_size: usize) -> Self {
+        self.max_cookie_size = max_cookie_size;
+        self
+    }
+
+    /// Get session configuration
+    pub fn config(&self) -> &SessionConfig {
+        &self.config
+    }
+
+    /// Create a new session. Nothing is stored server-side; send the result
+    /// of `format_cookie` to the client.
+    pub fn create(&self, user_id: i32, role: &str, claims: &str) -> SessionData {
+        SessionData::new(user_id, role.to_string(), claims.to_string())
+    }
+
+    /// Serialize and seal a session into a cookie value
+    pub fn encode(&self, session: &SessionData) -> RuntimeResult<String> {
+        let now_ms = unix_millis();
+        let age_ms = |at: Option<Instant>| {
+            at.map(|t| t.elapsed().as_millis() as u64)
+                .unwrap_or(u64::MAX)
+        };
+        let payload = CookiePayload {
+            session: session.clone(),
+            created_at_ms: now_ms.saturating_sub(age_ms(session.created_at)),
+            last_accessed_ms: now_ms.saturating_sub(age_ms(session.last_accessed)),
+        };
+        let json = serde_json::to_vec(&payload)
+            .map_err(|e| RuntimeError::server(format!("Failed to serialize session: {}", e)))?;
+        let (nonce, ciphertext) = self
+            .crypto
+            .encrypt_aes(&self.key, &json)
+            .map_err(|e| RuntimeError::server(format!("Failed to encrypt session: {}", e)))?;
+
+        let mut sealed = Vec::with_capacity(nonce.len() + ciphertext.len());
+        sealed.extend_from_slice(&nonce);
+        sealed.extend_from_slice(&ciphertext);
+        let value = URL_SAFE_NO_PAD.encode(sealed);
+
+        let size = self.config.cookie_name.len() + 1 + value.len();
+        if size > self.max_cookie_size {
+            return Err(RuntimeError::server(format!(
+                "Session cookie is {} bytes, exceeding the {} byte limit; store less data in the session",
+                size, self.max_cookie_size
+            )));
+        }
+        Ok(value)
+    }
+
+    /// Open a cookie value and return the session, refreshing its idle timer.
+    ///
+    /// Returns `None` for malformed, tampered, or expired cookies. Callers
+    /// re-issue the cookie via `format_cookie` to persist the refreshed
+    /// idle timer on the client.
+    pub fn decode(&self, value: &str) -> Option<SessionData> {
+        let sealed = URL_SAFE_NO_PAD.decode(value.as_bytes()).ok()?;
+        if sealed.len() < 12 {
+            return None;
+        }
+        let (nonce, ciphertext) = sealed.split_at(12);
+        let json = match self.crypto.decrypt_aes(&self.key, nonce, ciphertext) {
+            Ok(json) => json,
+            Err(_) => {
+                debug!("Rejected session cookie that failed authentication");
+                return None;
+            }
+        };
+        let payload: CookiePayload = serde_json::from_slice(&json).ok()?;
+
+        let now_ms = unix_millis();
+        let mut session = payload.session.with_ages(
+            Duration::from_millis(now_ms.saturating_sub(payload.created_at_ms)),
+            Duration::from_millis(now_ms.saturating_sub(payload.last_accessed_ms)),
+        );
+        if session.is_expired(self.config.idle_timeout, self.config.absolute_timeout) {
+            debug!("Session cookie for {} has expired", session.session_id);
+            return None;
+        }
+        session.touch();
+        Some(session)
+    }
+
+    /// Seal `session` and return it with the sealed value as its ID, the
+    /// form `SessionStore` hands out
+    fn seal(&self, session: SessionData) -> RuntimeResult<SessionData> {
+        let value = self.encode(&session)?;
+        Ok(SessionData {
+            session_id: value,
+            ..session
+        })
+    }
+
+    /// Look up the session carried by a Cookie request header
+    pub fn get_from_header(&self, cookie_header: &str) -> Option<SessionData> {
+        let cookies = parse_cookies(cookie_header);
+        self.decode(cookies.get(&self.config.cookie_name)?)
+    }
+
+    /// Format a Set-Cookie header carrying the sealed session
+    pub fn format_cookie(&self, session: &SessionData) -> RuntimeResult<String> {
+        Ok(format_session_cookie(&self.config, &self.encode(session)?))
+    }
+
+    /// Format a cookie header that clears the session
+    pub fn format_clear_cookie(&self) -> String {
+        format_clear_session_cookie(&self.config)
+    }
+}
+
+/// Milliseconds since the Unix epoch
+fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
 /// Parse cookies from a Cookie header value
 /// Returns a HashMap of cookie name -> value
+///
+/// Parsing follows RFC 6265 §5.4, leniently: segments are split on `;`,
+/// optional whitespace (spaces and tabs) around names and values is trimmed,
+/// one pair of surrounding double quotes is removed from a value, and
+/// `name=` yields an empty value. Segments without `=` or with an empty name
+/// are skipped. When a name repeats, the last occurrence wins.
 pub fn parse_cookies(cookie_header: &str) -> HashMap<String, String> {
     let mut cookies = HashMap::new();
 
-    for part in cookie_header.split(';') {
-        let part = part.trim();
-        if let Some(eq_pos) = part.find('=') {
-            let name = part[..eq_pos].trim();
-            let value = part[eq_pos + 1..].trim();
-            // Remove surrounding quotes if present
-            let value = value.trim_matches('"');
-            cookies.insert(name.to_string(), value.to_string());
+    for segment in cookie_header.split(';') {
+        let Some((name, value)) = segment.split_once('=') else {
+            continue;
+        };
+        let name = name.trim_matches(is_ows);
+        if name.is_empty() {
+            continue;
         }
+        let value = value.trim_matches(is_ows);
+        let value = value
+            .strip_prefix('"')
+            .and_then(|v| v.strip_suffix('"'))
+            .unwrap_or(value);
+        cookies.insert(name.to_string(), value.to_string());
     }
 
     cookies
 }
 
+/// Optional whitespace as defined by RFC 7230 (SP / HTAB)
+fn is_ows(c: char) -> bool {
+    c == ' ' || c == '\t'
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -401,7 +719,7 @@ mod tests {
         let config = SessionConfig::default();
         let mut store = SessionStore::new(config);
 
-        let session = store.create(1, "user", r#"{"email":"test@example.com"}"#);
+        let session = store.create(1, "user", r#"{"email":"test@example.com"}"#).unwrap();
         assert_eq!(session.user_id, 1);
         assert_eq!(session.role, "user");
         assert!(!session.session_id.is_empty());
@@ -412,7 +730,7 @@ mod tests {
         let config = SessionConfig::default();
         let mut store = SessionStore::new(config);
 
-        let session = store.create(1, "admin", "{}");
+        let session = store.create(1, "admin", "{}").unwrap();
         let session_id = session.session_id.clone();
 
         let retrieved = store.get(&session_id);
@@ -425,7 +743,7 @@ mod tests {
         let config = SessionConfig::default();
         let mut store = SessionStore::new(config);
 
-        let session = store.create(1, "user", "{}");
+        let session = store.create(1, "user", "{}").unwrap();
         let session_id = session.session_id.clone();
 
         assert!(store.delete(&session_id));
@@ -435,12 +753,12 @@ mod tests {
     #[test]
     fn test_session_expiry() {
         let config = SessionConfig {
-            timeout_seconds: 0, // Immediate expiry
+            idle_timeout: Duration::ZERO, // Immediate expiry
             ..SessionConfig::default()
         };
         let mut store = SessionStore::new(config);
 
-        let session = store.create(1, "user", "{}");
+        let session = store.create(1, "user", "{}").unwrap();
         let session_id = session.session_id.clone();
 
         // Session should be expired immediately
@@ -448,6 +766,79 @@ mod tests {
         assert!(store.get(&session_id).is_none());
     }
 
+    #[test]
+    fn test_idle_timeout_resets_on_access() {
+        let config = SessionConfig {
+            idle_timeout: Duration::from_millis(150),
+            absolute_timeout: None,
+            ..SessionConfig::default()
+        };
+        let mut store = SessionStore::new(config);
+        let session_id = store.create(1, "user", "{}").unwrap().session_id;
+
+        // Each access lands inside the idle window and pushes it forward, so
+        // the session outlives several idle periods in total.
+        for _ in 0..4 {
+            std::thread::sleep(Duration::from_millis(60));
+            assert!(store.get(&session_id).is_some());
+        }
+
+        // Once left idle past the window it is gone.
+        std::thread::sleep(Duration::from_millis(200));
+        assert!(store.get(&session_id).is_none());
+    }
+
+    #[test]
+    fn test_absolute_timeout_fires_despite_activity() {
+        let config = SessionConfig {
+            idle_timeout: Duration::from_secs(3600),
+            absolute_timeout: Some(Duration::from_millis(100)),
+            ..SessionConfig::default()
+        };
+        let mut store = SessionStore::new(config);
+        let session_id = store.create(1, "user", "{}").unwrap().session_id;
+        store.store_raw("raw-1", "{}");
+
+        assert!(store.get(&session_id).is_some());
+        assert!(store.get_raw("raw-1").is_some());
+
+        // Keep touching the sessions; activity must not extend the lifetime.
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(store.get(&session_id).is_some());
+        store.store_raw("raw-1", "{\"v\":2}");
+        std::thread::sleep(Duration::from_millis(60));
+
+        assert!(store.get(&session_id).is_none());
+        assert!(store.get_raw("raw-1").is_none());
+    }
+
+    #[test]
+    fn test_overwriting_an_expired_raw_session_starts_a_new_one() {
+        let config = SessionConfig {
+            idle_timeout: Duration::from_secs(3600),
+            absolute_timeout: Some(Duration::from_millis(50)),
+            ..SessionConfig::default()
+        };
+        let mut store = SessionStore::new(config);
+        store.store_raw("raw-1", "{}");
+        std::thread::sleep(Duration::from_millis(60));
+
+        store.store_raw("raw-1", "{\"v\":2}");
+        assert_eq!(store.get_raw("raw-1").as_deref(), Some("{\"v\":2}"));
+    }
+
+    #[test]
+    fn test_session_tracks_created_and_last_accessed() {
+        let mut store = SessionStore::new(SessionConfig::default());
+        let session = store.create(1, "user", "{}").unwrap();
+        let created = session.created_at().unwrap();
+
+        std::thread::sleep(Duration::from_millis(5));
+        let fetched = store.get(&session.session_id).unwrap();
+        assert_eq!(fetched.created_at(), Some(created));
+        assert!(fetched.last_accessed().unwrap() > created);
+    }
+
     #[test]
     fn test_parse_cookies() {
         let cookies = parse_cookies("session=abc123; theme=dark; lang=en");
@@ -457,6 +848,129 @@ mod tests {
         assert_eq!(cookies.get("lang"), Some(&"en".to_string()));
     }
 
+    #[test]
+    fn test_cookie_session_round_trip() {
+        let store = CookieSessionStore::new(SessionConfig::default(), b"test-secret");
+        let session = store.create(7, "admin", r#"{"email":"a@example.com"}"#);
+
+        let header = store.format_cookie(&session).unwrap();
+        assert!(header.starts_with("session="));
+        assert!(header.contains("HttpOnly"));
+
+        let value = store.encode(&session).unwrap();
+        let decoded = store
+            .get_from_header(&format!("theme=dark; session={}", value))
+            .unwrap();
+        assert_eq!(decoded.session_id, session.session_id);
+        assert_eq!(decoded.user_id, 7);
+        assert_eq!(decoded.role, "admin");
+        assert_eq!(decoded.claims, session.claims);
+    }
+
+    #[test]
+    fn test_cookie_session_rejects_tampering() {
+        let store = CookieSessionStore::new(SessionConfig::default(), b"test-secret");
+        let value = store.encode(&store.create(1, "user", "{}")).unwrap();
+
+        let mut bytes = URL_SAFE_NO_PAD.decode(&value).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0x01;
+        assert!(store.decode(&URL_SAFE_NO_PAD.encode(&bytes)).is_none());
+
+        // Wrong key, truncated, and garbage values are all rejected.
+        let other = CookieSessionStore::new(SessionConfig::default(), b"other-secret");
+        assert!(other.decode(&value).is_none());
+        assert!(store.decode(&value[..10]).is_none());
+        assert!(store.decode("not base64 !!").is_none());
+    }
+
+    #[test]
+    fn test_cookie_session_rejects_oversized() {
+        let store = CookieSessionStore::new(SessionConfig::default(), b"test-secret");
+        let big_claims = format!(r#"{{"blob":"{}"}}"#, "x".repeat(8 * 1024));
+        let session = store.create(1, "user", &big_claims);
+        assert!(store.encode(&session).is_err());
+        assert!(store.format_cookie(&session).is_err());
+
+        let roomy = CookieSessionStore::new(SessionConfig::default(), b"test-secret")
+            .with_max_cookie_size(64 * 1024);
+        assert!(roomy.encode(&session).is_ok());
+    }
+
+    #[test]
+    fn test_cookie_session_expires() {
+        let config = SessionConfig {
+            idle_timeout: Duration::from_secs(3600),
+            absolute_timeout: Some(Duration::from_millis(50)),
+            ..SessionConfig::default()
+        };
+        let store = CookieSessionStore::new(config, b"test-secret");
+        let value = store.encode(&store.create(1, "user", "{}")).unwrap();
+        assert!(store.decode(&value).is_some());
+
+        std::thread::sleep(Duration::from_millis(80));
+        assert!(store.decode(&value).is_none());
+    }
+
+    #[test]
+    fn test_session_store_cookie_backend() {
+        let mut store = SessionStore::new(SessionConfig::default());
+        store.use_cookie_sessions(b"test-secret");
+        let session = store.create(3, "editor", "{}").unwrap();
+        assert!(store.is_empty());
+
+        let found = store.get(&session.session_id).unwrap();
+        assert_eq!(found.user_id, 3);
+        assert_eq!(found.role, "editor");
+        // The refreshed value opens the same session
+        assert_eq!(store.get(&found.session_id).unwrap().user_id, 3);
+        assert!(store.get("forged").is_none());
+
+        let mut other = SessionStore::new(SessionConfig::default());
+        other.use_cookie_sessions(b"other-secret");
+        assert!(other.get(&session.session_id).is_none());
+    }
+
+    #[test]
+    fn test_parse_cookies_quoted_values() {
+        let cookies = parse_cookies(r#"a="quoted value"; b=""; c="unbalanced; d=x"y"#);
+
+        assert_eq!(cookies.get("a"), Some(&"quoted value".to_string()));
+        assert_eq!(cookies.get("b"), Some(&String::new()));
+        // Only a matching pair of quotes is stripped.
+        assert_eq!(cookies.get("c"), Some(&"\"unbalanced".to_string()));
+        assert_eq!(cookies.get("d"), Some(&"x\"y".to_string()));
+    }
+
+    #[test]
+    fn test_parse_cookies_whitespace_and_empty_segments() {
+        let cookies = parse_cookies(" session = abc ;\ttheme=dark\t;; ;lang=;flag;=orphan;");
+
+        assert_eq!(cookies.get("session"), Some(&"abc".to_string()));
+        assert_eq!(cookies.get("theme"), Some(&"dark".to_string()));
+        assert_eq!(cookies.get("lang"), Some(&String::new()));
+        // Segments without `=` or without a name are ignored.
+        assert!(!cookies.contains_key("flag"));
+        assert!(!cookies.contains_key(""));
+        assert_eq!(cookies.len(), 3);
+    }
+
+    #[test]
+    fn test_parse_cookies_value_may_contain_equals() {
+        let cookies = parse_cookies("token=YWJj==; q=a=b");
+
+        assert_eq!(cookies.get("token"), Some(&"YWJj==".to_string()));
+        assert_eq!(cookies.get("q"), Some(&"a=b".to_string()));
+    }
+
+    #[test]
+    fn test_parse_cookies_duplicate_names_last_wins() {
+        let cookies = parse_cookies("session=first; other=1; session=second");
+
+        assert_eq!(cookies.get("session"), Some(&"second".to_string()));
+        assert_eq!(cookies.len(), 2);
+    }
+
     #[test]
     fn test_jti_first_consumption_succeeds_replay_fails() {
         let mut store = SessionStore::new(SessionConfig::default());
@@ -511,7 +1025,8 @@ mod tests {
             same_site: "Strict".to_string(),
             secure: true,
             http_only: true,
-            timeout_seconds: 3600,
+            idle_timeout: Duration::from_secs(3600),
+            absolute_timeout: None,
         };
         let store = SessionStore::new(config);
 
@@ -0,0 +1,169 @@
+//! Detached tasks spawned by handlers with `_task_spawn`.
+//!
+//! A task names a WASM export and carries a payload. Tasks queued during a
+//! request are held back until the response body has been sent (or the
+//! client went away), then run on a bounded pool, each in a fresh instance
+//! with the payload as its request body. Failures are logged; the client has
+//! already received its response.
+
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::task::{Context, Poll};
+
+use axum::body::{Body, Bytes};
+use http_body::{Frame, SizeHint};
+use tokio::sync::Semaphore;
+use tracing::{debug, error, warn};
+
+use crate::wasm::{RequestContext, SharedWasmInstance};
+
+/// Tasks run concurrently per module unless `ServerConfig.task_workers` says otherwise
+pub const DEFAULT_TASK_WORKERS: usize = 4;
+
+/// Tasks waiting for a worker beyond this are dropped with a warning
+pub const TASK_QUEUE_LIMIT: usize = 1024;
+
+/// A task queued by `_task_spawn`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpawnedTask {
+    /// WASM export to run
+    pub handler: String,
+    /// Payload handed to the task as its request body
+    pub payload: String,
+}
+
+/// Bounded pool running spawned tasks.
+#[derive(Clone)]
+pub struct TaskPool {
+    workers: Arc<Semaphore>,
+    queued: Arc<AtomicUsize>,
+}
+
+impl Default for TaskPool {
+    fn default() -> Self {
+        Self::new(DEFAULT_TASK_WORKERS)
+    }
+}
+
+impl TaskPool {
+    /// Pool running at most `workers` tasks at a time (at least one).
+    pub fn new(workers: usize) -> Self {
+        Self {
+            workers: Arc::new(Semaphore::new(workers.max(1))),
+            queued: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Run `tasks` against `wasm` once `body` has been sent or dropped.
+    pub fn run_after(&self, body: Body, wasm: SharedWasmInstance, tasks: Vec<SpawnedTask>) -> Body {
+        if tasks.is_empty() {
+            return body;
+        }
+        let pool = self.clone();
+        Body::new(OnDropBody {
+            inner: body,
+            on_drop: Some(Box::new(move || pool.spawn(wasm, tasks))),
+        })
+    }
+
+    /// Queue `tasks` on the pool now.
+    pub fn spawn(&self, wasm: SharedWasmInstance, tasks: Vec<SpawnedTask>) {
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            warn!("Dropping {} spawned task(s): no async runtime", tasks.len());
+            return;
+        };
+        for task in tasks {
+            if self.queued.fetch_add(1, Ordering::Relaxed) >= TASK_QUEUE_LIMIT {
+                self.queued.fetch_sub(1, Ordering::Relaxed);
+                warn!("Task queue full; dropping task {}", task.handler);
+                continue;
+            }
+            let pool = self.clone();
+            let wasm = wasm.clone();
+            runtime.spawn(async move {
+                let _permit = pool.workers.acquire().await;
+                pool.queued.fetch_sub(1, Ordering::Relaxed);
+                let handler = task.handler.clone();
+                let result = tokio::task::spawn_blocking(move || run_task(&wasm, task)).await;
+                match result {
+                    Ok(Ok(())) => debug!("Task {} finished", handler),
+                    Ok(Err(e)) => error!("Task {} failed: {}", handler, e),
+                    Err(e) => error!("Task {} panicked: {}", handler, e),
+                }
+            });
+        }
+    }
+}
+
+fn run_task(wasm: &SharedWasmInstance, task: SpawnedTask) -> crate::error::RuntimeResult<()> {
+    let request = RequestContext {
+        method: "TASK".to_string(),
+        path: String::new(),
+        headers: Vec::new(),
+        body: task.payload,
+        body_bytes: None,
+        params: Default::default(),
+        query: Default::default(),
+        raw_query: String::new(),
+        client: None,
+        route_pattern: None,
+    };
+    wasm.call_handler_job(&task.handler, request, None)
+}
+
+/// Response body running `on_drop` when it is dropped, which hyper does
+/// after the last frame is written or the connection closes.
+struct OnDropBody {
+    inner: Body,
+    on_drop: Option<Box<dyn FnOnce() + Send>>,
+}
+
+impl http_body::Body for OnDropBody {
+    type Data = Bytes;
+    type Error = axum::Error;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        Pin::new(&mut self.inner).poll_frame(cx)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+impl Drop for OnDropBody {
+    fn drop(&mut self) {
+        if let Some(on_drop) = self.on_drop.take() {
+            on_drop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicBool;
+
+    #[tokio::test]
+    async fn deferred_work_runs_only_after_the_body_is_sent() {
+        let ran = Arc::new(AtomicBool::new(false));
+        let flag = ran.clone();
+        let body = Body::new(OnDropBody {
+            inner: Body::from("ok"),
+            on_drop: Some(Box::new(move || flag.store(true, Ordering::SeqCst))),
+        });
+        assert!(!ran.load(Ordering::SeqCst));
+
+        let bytes = axum::body::to_bytes(body, usize::MAX).await.unwrap();
+        assert_eq!(&bytes[..], b"ok");
+        assert!(ran.load(Ordering::SeqCst));
+    }
+}
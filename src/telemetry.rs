@@ -0,0 +1,246 @@
+//! OpenTelemetry request tracing.
+//!
+//! Enabled by `ServerConfig.otlp_endpoint`. `server::handle_request` opens an
+//! `http.request` span per request (continuing the caller's trace when a W3C
+//! `traceparent` header is present, see [`set_remote_parent`]), the WASM
+//! handler call runs in a child `wasm.handler` span, and host-bridge database
+//! calls open `db.*` spans beneath that. [`layer`] bridges those `tracing`
+//! spans to OpenTelemetry through `tracing-opentelemetry`, and
+//! [`otlp_tracer_provider`] ships them to a collector over OTLP/HTTP.
+//!
+//! The usual `tracing-opentelemetry` span fields apply: `otel.name` overrides
+//! the span name, `otel.kind` sets the span kind and `otel.status_code` the
+//! status. Only spans from this crate and `host-bridge` are meant to be
+//! exported; filter the layer with [`is_traced_target`].
+
+use axum::http::HeaderMap;
+use opentelemetry::propagation::{Extractor, TextMapPropagator};
+use opentelemetry::trace::{TraceContextExt, TracerProvider};
+use opentelemetry_otlp::{ExporterBuildError, WithExportConfig};
+use opentelemetry_sdk::Resource;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::{SdkTracerProvider, Tracer};
+use tracing::Subscriber;
+use tracing_opentelemetry::{OpenTelemetryLayer, OpenTelemetrySpanExt};
+use tracing_subscriber::registry::LookupSpan;
+
+/// `service.name` resource attribute reported to the collector.
+pub const SERVICE_NAME: &str = "clean-server";
+
+/// Span targets exported to the collector.
+const TRACED_TARGETS: &[&str] = &["clean_server", "host_bridge"];
+
+/// Whether spans with this `tracing` target are exported.
+pub fn is_traced_target(target: &str) -> bool {
+    TRACED_TARGETS
+        .iter()
+        .any(|prefix| target == *prefix || target.starts_with(&format!("{}::", prefix)))
+}
+
+/// Tracer provider that batches finished spans and POSTs them to the
+/// OTLP/HTTP collector at `endpoint`, e.g. `http://localhost:4318`.
+///
+/// `/v1/traces` is appended unless the endpoint already ends with it. Call
+/// `shutdown` on the provider before exiting to flush pending spans.
+pub fn otlp_tracer_provider(endpoint: &str) -> Result<SdkTracerProvider, ExporterBuildError> {
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_http()
+        .with_endpoint(traces_url(endpoint))
+        .build()?;
+    Ok(SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(Resource::builder().with_service_name(SERVICE_NAME).build())
+        .build())
+}
+
+/// `tracing_subscriber` layer exporting spans through `provider`.
+pub fn layer<S>(provider: &SdkTracerProvider) -> OpenTelemetryLayer<S, Tracer>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    tracing_opentelemetry::layer().with_tracer(provider.tracer(SERVICE_NAME))
+}
+
+/// Make `span` a child of the remote span named by the request's W3C
+/// `traceparent` header.
+///
+/// Must be called before `span` is first entered. Malformed or missing
+/// headers leave `span` as the root of a new trace.
+pub fn set_remote_parent(span: &tracing::Span, headers: &HeaderMap) {
+    let cx = TraceContextPropagator::new().extract(&HeaderExtractor(headers));
+    if cx.span().span_context().is_valid() {
+        // Fails only when no OpenTelemetry layer is installed
+        let _ = span.set_parent(cx);
+    }
+}
+
+/// Reads propagation headers from an axum `HeaderMap`.
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|name| name.as_str()).collect()
+    }
+}
+
+fn traces_url(endpoint: &str) -> String {
+    let endpoint = endpoint.trim_end_matches('/');
+    if endpoint.ends_with("/v1/traces") {
+        endpoint.to_string()
+    } else {
+        format!("{}/v1/traces", endpoint)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::Value;
+    use opentelemetry::trace::{SpanId, SpanKind, TraceId};
+    use opentelemetry_sdk::trace::{InMemorySpanExporter, SpanData};
+    use tracing_subscriber::Registry;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    const TRACEPARENT: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+    /// Run `f` with an OpenTelemetry layer installed and return the finished
+    /// spans in close order.
+    fn capture<F: FnOnce()>(f: F) -> Vec<SpanData> {
+        let exporter = InMemorySpanExporter::default();
+        let provider = SdkTracerProvider::builder()
+            .with_simple_exporter(exporter.clone())
+            .build();
+        let subscriber = Registry::default().with(layer(&provider));
+        tracing::subscriber::with_default(subscriber, f);
+        exporter.get_finished_spans().unwrap()
+    }
+
+    fn attribute<'a>(span: &'a SpanData, key: &str) -> Option<&'a Value> {
+        span.attributes
+            .iter()
+            .find(|kv| kv.key.as_str() == key)
+            .map(|kv| &kv.value)
+    }
+
+    #[test]
+    fn test_remote_parent_links_children_to_traceparent() {
+        let spans = capture(|| {
+            let root = tracing::info_span!(
+                "http.request",
+                otel.name = "GET /users",
+                otel.kind = "server",
+                http.status_code = tracing::field::Empty
+            );
+            let mut headers = HeaderMap::new();
+            headers.insert("traceparent", TRACEPARENT.parse().unwrap());
+            set_remote_parent(&root, &headers);
+
+            let _guard = root.enter();
+            tracing::debug_span!("wasm.handler").in_scope(|| {});
+            root.record("http.status_code", 200);
+        });
+
+        assert_eq!(spans.len(), 2);
+        let child = &spans[0];
+        let root = &spans[1];
+        let trace_id = TraceId::from_hex("4bf92f3577b34da6a3ce929d0e0e4736").unwrap();
+
+        assert_eq!(root.name, "GET /users");
+        assert_eq!(root.span_kind, SpanKind::Server);
+        assert_eq!(root.span_context.trace_id(), trace_id);
+        assert_eq!(
+            root.parent_span_id,
+            SpanId::from_hex("00f067aa0ba902b7").unwrap()
+        );
+        assert_eq!(attribute(root, "http.status_code"), Some(&Value::I64(200)));
+
+        assert_eq!(child.name, "wasm.handler");
+        assert_eq!(child.span_kind, SpanKind::Internal);
+        assert_eq!(child.span_context.trace_id(), trace_id);
+        assert_eq!(child.parent_span_id, root.span_context.span_id());
+    }
+
+    #[test]
+    fn test_malformed_traceparent_starts_new_trace() {
+        let spans = capture(|| {
+            let span = tracing::info_span!("http.request");
+            let mut headers = HeaderMap::new();
+            headers.insert("traceparent", "garbage".parse().unwrap());
+            set_remote_parent(&span, &headers);
+            span.in_scope(|| {});
+        });
+
+        assert_eq!(spans.len(), 1);
+        assert_eq!(spans[0].parent_span_id, SpanId::INVALID);
+        assert_ne!(spans[0].span_context.trace_id(), TraceId::INVALID);
+    }
+
+    #[test]
+    fn test_is_traced_target() {
+        assert!(is_traced_target("clean_server"));
+        assert!(is_traced_target("clean_server::server"));
+        assert!(is_traced_target("host_bridge::db"));
+        assert!(!is_traced_target("hyper::proto"));
+        assert!(!is_traced_target("clean_server_extra"));
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[tokio::test]
+    async fn test_db_query_span_is_child_of_handler_span() {
+        use serde_json::json;
+        use tracing::Instrument;
+
+        let exporter = InMemorySpanExporter::default();
+        let provider = SdkTracerProvider::builder()
+            .with_simple_exporter(exporter.clone())
+            .build();
+        let subscriber = Registry::default().with(layer(&provider));
+        let _default = tracing::subscriber::set_default(subscriber);
+
+        let handler = tracing::debug_span!("wasm.handler");
+        async {
+            let mut db = host_bridge::DbBridge::new();
+            db.call("configure", json!({ "database_url": "sqlite::memory:" }))
+                .await
+                .unwrap();
+            let result = db
+                .call("query", json!({ "sql": "SELECT 1 AS one", "params": [] }))
+                .await
+                .unwrap();
+            assert_eq!(result["ok"], json!(true));
+        }
+        .instrument(handler)
+        .await;
+
+        let spans = exporter.get_finished_spans().unwrap();
+        let handler = spans.iter().find(|s| s.name == "wasm.handler").unwrap();
+        let query = spans.iter().find(|s| s.name == "db.query").unwrap();
+        assert_eq!(query.span_kind, SpanKind::Client);
+        assert_eq!(
+            query.span_context.trace_id(),
+            handler.span_context.trace_id()
+        );
+        assert_eq!(query.parent_span_id, handler.span_context.span_id());
+        assert_eq!(
+            attribute(query, "db.statement"),
+            Some(&Value::from("SELECT 1 AS one"))
+        );
+        assert_eq!(attribute(query, "db.system"), Some(&Value::from("sqlite")));
+    }
+
+    #[test]
+    fn test_traces_url() {
+        assert_eq!(
+            traces_url("http://collector:4318/"),
+            "http://collector:4318/v1/traces"
+        );
+        assert_eq!(
+            traces_url("http://collector:4318/v1/traces"),
+            "http://collector:4318/v1/traces"
+        );
+    }
+}
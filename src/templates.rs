@@ -0,0 +1,211 @@
+//! Server-side HTML templates for `_res_render`.
+//!
+//! Templates live in `ServerConfig::templates_dir` and are Handlebars
+//! templates (`{{user.name}}`, `{{#each items}}...{{/each}}`, `{{#if}}`).
+//! `{{value}}` HTML-escapes on substitution; `{{{value}}}` inserts raw
+//! markup. Each template is compiled and registered with the store's
+//! Handlebars registry on first use, under its name; in watch mode (enabled
+//! by the server in dev mode, `CLEAN_DEV=1`) a registered template is
+//! recompiled and re-registered whenever its file modification time
+//! changes, so edits show up without a restart.
+
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use handlebars::Handlebars;
+use parking_lot::RwLock;
+use serde_json::Value;
+
+use crate::error::{RuntimeError, RuntimeResult};
+
+#[derive(Debug, Default)]
+struct Registry {
+    handlebars: Handlebars<'static>,
+    /// Modification time of each registered template's file when it was
+    /// compiled
+    modified: HashMap<String, Option<SystemTime>>,
+}
+
+#[derive(Debug, Default)]
+pub struct TemplateStore {
+    dir: Option<PathBuf>,
+    watch: bool,
+    registry: RwLock<Registry>,
+}
+
+pub type SharedTemplateStore = Arc<TemplateStore>;
+
+impl TemplateStore {
+    /// A store reading templates from `dir`; `None` disables rendering.
+    pub fn new(dir: Option<PathBuf>) -> Self {
+        Self {
+            dir,
+            watch: false,
+            registry: RwLock::new(Registry::default()),
+        }
+    }
+
+    /// Recompile registered templates whose file modification time has
+    /// changed, checked on every render.
+    pub fn with_watch(mut self, watch: bool) -> Self {
+        self.watch = watch;
+        self
+    }
+
+    pub fn dir(&self) -> Option<&Path> {
+        self.dir.as_deref()
+    }
+
+    /// Render template `name` (a path relative to the templates directory)
+    /// with `data`.
+    pub fn render(&self, name: &str, data: &Value) -> RuntimeResult<String> {
+        let path = self.resolve(name)?;
+        {
+            let registry = self.registry.read();
+            if let Some(compiled_at) = registry.modified.get(name)
+                && (!self.watch || modified(&path) == *compiled_at)
+            {
+                return render_registered(&registry, name, data);
+            }
+        }
+
+        let mut registry = self.registry.write();
+        let compiled_at = modified(&path);
+        let source = std::fs::read_to_string(&path)
+            .map_err(|e| RuntimeError::config(format!("Cannot read template '{}': {}", name, e)))?;
+        registry
+            .handlebars
+            .register_template_string(name, source)
+            .map_err(|e| RuntimeError::config(format!("Invalid template '{}': {}", name, e)))?;
+        registry.modified.insert(name.to_string(), compiled_at);
+        render_registered(&registry, name, data)
+    }
+
+    /// Join `name` onto the templates directory, refusing absolute paths and
+    /// `..` so a template name cannot escape it.
+    fn resolve(&self, name: &str) -> RuntimeResult<PathBuf> {
+        let dir = self
+            .dir
+            .as_ref()
+            .ok_or_else(|| RuntimeError::config("No templates directory configured"))?;
+        let relative = Path::new(name);
+        let confined = !name.is_empty()
+            && relative
+                .components()
+                .all(|c| matches!(c, Component::Normal(_) | Component::CurDir));
+        if !confined {
+            return Err(RuntimeError::config(format!(
+                "Invalid template name: {:?}",
+                name
+            )));
+        }
+        Ok(dir.join(relative))
+    }
+}
+
+fn render_registered(registry: &Registry, name: &str, data: &Value) -> RuntimeResult<String> {
+    registry
+        .handlebars
+        .render(name, data)
+        .map_err(|e| RuntimeError::config(format!("Cannot render template '{}': {}", name, e)))
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn renders_escaped_variables_and_caches() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("hello.html"),
+            "<h1>Hello {{user.name}}</h1><p>{{count}} new</p>",
+        )
+        .unwrap();
+        let store = TemplateStore::new(Some(dir.path().to_path_buf()));
+
+        let html = store
+            .render(
+                "hello.html",
+                &json!({"user": {"name": "<Ada & co>"}, "count": 3}),
+            )
+            .unwrap();
+        assert_eq!(html, "<h1>Hello &lt;Ada &amp; co&gt;</h1><p>3 new</p>");
+
+        // Without watch mode the compiled template stays registered.
+        std::fs::write(dir.path().join("hello.html"), "changed").unwrap();
+        assert_eq!(
+            store.render("hello.html", &json!({})).unwrap(),
+            "<h1>Hello </h1><p> new</p>"
+        );
+    }
+
+    #[test]
+    fn blocks_and_raw_output() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("list.html"),
+            "{{#if items}}<ul>{{#each items}}<li>{{this}}</li>{{/each}}</ul>{{else}}none{{/if}}{{{footer}}}",
+        )
+        .unwrap();
+        let store = TemplateStore::new(Some(dir.path().to_path_buf()));
+
+        assert_eq!(
+            store
+                .render(
+                    "list.html",
+                    &json!({"items": ["a", "<b>"], "footer": "<hr>"})
+                )
+                .unwrap(),
+            "<ul><li>a</li><li>&lt;b&gt;</li></ul><hr>"
+        );
+        assert_eq!(
+            store.render("list.html", &json!({"items": []})).unwrap(),
+            "none"
+        );
+    }
+
+    #[test]
+    fn watch_mode_reregisters_modified_templates() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("page.html");
+        std::fs::write(&path, "v1 {{x}}").unwrap();
+        let store = TemplateStore::new(Some(dir.path().to_path_buf())).with_watch(true);
+        assert_eq!(store.render("page.html", &json!({"x": 1})).unwrap(), "v1 1");
+
+        std::fs::write(&path, "v2 {{x}}").unwrap();
+        // Make the change visible even on filesystems with coarse mtimes.
+        let later = std::time::SystemTime::now() + std::time::Duration::from_secs(5);
+        std::fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(later)
+            .unwrap();
+        assert_eq!(store.render("page.html", &json!({"x": 1})).unwrap(), "v2 1");
+    }
+
+    #[test]
+    fn missing_malformed_or_escaping_templates_are_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("broken.html"), "{{#if x}}unclosed").unwrap();
+        let store = TemplateStore::new(Some(dir.path().to_path_buf()));
+
+        let err = store.render("missing.html", &json!({})).unwrap_err();
+        assert!(err.to_string().contains("missing.html"), "{}", err);
+        let err = store.render("broken.html", &json!({})).unwrap_err();
+        assert!(err.to_string().contains("broken.html"), "{}", err);
+        assert!(store.render("../secret.html", &json!({})).is_err());
+        assert!(store.render("/etc/passwd", &json!({})).is_err());
+
+        let unconfigured = TemplateStore::default();
+        assert!(unconfigured.render("hello.html", &json!({})).is_err());
+    }
+}
@@ -0,0 +1,248 @@
+//! In-process test harness.
+//!
+//! [`TestServer`] loads a WASM module exactly as `start_server` does and
+//! drives requests through the full Axum dispatch path (middleware, routing,
+//! sessions, handler invocation) without binding a TCP port:
+//!
+//! ```no_run
+//! # async fn example() -> clean_server::RuntimeResult<()> {
+//! use clean_server::testing::TestServer;
+//!
+//! let server = TestServer::new("app.wasm").await?;
+//! let response = server.get("/users/42").await?;
+//! assert_eq!(response.status, 200);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Fixtures can also be loaded straight from WAT with
+//! [`TestServer::from_wat`]; [`with_malloc`] adds the bump allocator that
+//! string-returning bridges need.
+//!
+//! Module initialization uses `block_in_place`, so tests must run on a
+//! multi-threaded runtime (`#[tokio::test(flavor = "multi_thread")]`).
+
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+
+use axum::Router;
+use axum::body::{Body, Bytes};
+use axum::extract::ConnectInfo;
+use axum::http::{HeaderMap, HeaderName, HeaderValue, Method, Request, StatusCode};
+use tower::ServiceExt;
+
+use crate::error::{RuntimeError, RuntimeResult};
+use crate::server::ServerConfig;
+use crate::wasm::SharedWasmInstance;
+
+/// Bump allocator exporting `malloc` and `__heap_ptr`, starting at 64 KiB.
+///
+/// Bridges that return strings call `malloc` to place them in guest memory,
+/// so the module must export a memory of at least two pages.
+pub const MALLOC_WAT: &str = r#"
+  (global $__heap (mut i32) (i32.const 65536))
+  (global $__heap_ptr (export "__heap_ptr") (mut i32) (i32.const 65536))
+  (func (export "malloc") (param $size i32) (result i32)
+    (local $ptr i32)
+    (local.set $ptr (global.get $__heap))
+    (global.set $__heap
+      (i32.and
+        (i32.add (i32.add (global.get $__heap) (local.get $size)) (i32.const 7))
+        (i32.const -8)))
+    (global.set $__heap_ptr (global.get $__heap))
+    (local.get $ptr))
+"#;
+
+/// `module` with [`MALLOC_WAT`] appended to its fields.
+///
+/// `module` is a complete `(module ...)`; the allocator goes after its last
+/// field, so it must not already export `malloc` or `__heap_ptr`.
+pub fn with_malloc(module: &str) -> String {
+    let body = module
+        .trim_end()
+        .strip_suffix(')')
+        .expect("WAT module should end with ')'");
+    format!("{}{})", body, MALLOC_WAT)
+}
+
+/// A WASM app served in-memory
+pub struct TestServer {
+    app: Router,
+    wasm: SharedWasmInstance,
+    /// Directory holding the module written by [`TestServer::from_bytes`],
+    /// removed on drop
+    module_dir: Option<ModuleDir>,
+}
+
+/// Temporary directory removed when dropped
+struct ModuleDir(PathBuf);
+
+impl Drop for ModuleDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Response captured from a [`TestServer`] request
+#[derive(Debug, Clone)]
+pub struct TestResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Bytes,
+}
+
+impl TestResponse {
+    /// Body decoded as UTF-8 (lossy)
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+
+    /// Body parsed as JSON
+    pub fn json(&self) -> RuntimeResult<serde_json::Value> {
+        serde_json::from_slice(&self.body)
+            .map_err(|e| RuntimeError::server(format!("Response body is not JSON: {}", e)))
+    }
+
+    /// First value of a response header, if present and valid UTF-8
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).and_then(|v| v.to_str().ok())
+    }
+}
+
+impl TestServer {
+    /// Load `wasm_path` with the default server configuration.
+    ///
+    /// Like `start_server`, the default configuration honors `DATABASE_URL`
+    /// and the memory tier environment variables.
+    pub async fn new(wasm_path: impl AsRef<Path>) -> RuntimeResult<Self> {
+        Self::with_config(wasm_path, ServerConfig::default()).await
+    }
+
+    /// Load `wasm_path` with an explicit server configuration.
+    ///
+    /// Host and port are ignored. Background tasks (WebSocket heartbeat, job
+    /// worker, cron scheduler) are not started.
+    pub async fn with_config(
+        wasm_path: impl AsRef<Path>,
+        mut config: ServerConfig,
+    ) -> RuntimeResult<Self> {
+        let (app, wasm) = crate::server::build_app(wasm_path.as_ref(), &mut config).await?;
+        Ok(Self {
+            app,
+            wasm,
+            module_dir: None,
+        })
+    }
+
+    /// Compile WAT source text and load it like [`TestServer::from_bytes`].
+    pub async fn from_wat(wat: &str, config: ServerConfig) -> RuntimeResult<Self> {
+        let bytes = wat::parse_str(wat)
+            .map_err(|e| RuntimeError::wasm(format!("Invalid WAT fixture: {}", e)))?;
+        Self::from_bytes(&bytes, config).await
+    }
+
+    /// Load a module from its binary with an explicit server configuration.
+    ///
+    /// The module is written to a fresh temporary directory as `app.wasm`,
+    /// which is removed when the server is dropped. Nothing else is placed
+    /// next to it, so there is no build manifest or static directory.
+    pub async fn from_bytes(wasm: &[u8], config: ServerConfig) -> RuntimeResult<Self> {
+        let dir = ModuleDir(
+            std::env::temp_dir().join(format!("clean-server-test-{}", uuid::Uuid::new_v4())),
+        );
+        std::fs::create_dir_all(&dir.0)
+            .map_err(|e| RuntimeError::server(format!("Failed to create module dir: {}", e)))?;
+        let wasm_path = dir.0.join("app.wasm");
+        std::fs::write(&wasm_path, wasm)
+            .map_err(|e| RuntimeError::server(format!("Failed to write module: {}", e)))?;
+
+        let mut server = Self::with_config(&wasm_path, config).await?;
+        server.module_dir = Some(dir);
+        Ok(server)
+    }
+
+    /// Serve only the `config.mounts` modules, each under its prefix;
+    /// requests matching no prefix get 404.
+    ///
+    /// [`TestServer::wasm`] returns the first mounted module.
+    pub async fn with_mounts(mut config: ServerConfig) -> RuntimeResult<Self> {
+        let (app, instances) = crate::server::build_mounted_app(None, &mut config).await?;
+        let wasm = instances
+            .into_iter()
+            .next()
+            .ok_or_else(|| RuntimeError::config("No WASM modules mounted"))?;
+        Ok(Self {
+            app,
+            wasm,
+            module_dir: None,
+        })
+    }
+
+    /// The loaded WASM instance, for inspecting routes or shared state
+    pub fn wasm(&self) -> &SharedWasmInstance {
+        &self.wasm
+    }
+
+    /// Dispatch one request and collect the full response.
+    ///
+    /// `path` may include a query string.
+    pub async fn request(
+        &self,
+        method: Method,
+        path: &str,
+        headers: &[(&str, &str)],
+        body: impl Into<Bytes>,
+    ) -> RuntimeResult<TestResponse> {
+        let mut request = Request::builder()
+            .method(method)
+            .uri(path)
+            .body(Body::from(body.into()))
+            .map_err(|e| RuntimeError::server(format!("Invalid test request: {}", e)))?;
+
+        for (name, value) in headers {
+            let name = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|e| RuntimeError::server(format!("Invalid header name: {}", e)))?;
+            let value = HeaderValue::from_str(value)
+                .map_err(|e| RuntimeError::server(format!("Invalid header value: {}", e)))?;
+            request.headers_mut().append(name, value);
+        }
+        // Requests appear to come from a loopback client, as they would
+        // from a local client of `start_server`.
+        request
+            .extensions_mut()
+            .insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 0))));
+
+        let response = match self.app.clone().oneshot(request).await {
+            Ok(response) => response,
+            Err(never) => match never {},
+        };
+
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .map_err(|e| RuntimeError::server(format!("Failed to read response body: {}", e)))?;
+
+        Ok(TestResponse {
+            status,
+            headers,
+            body,
+        })
+    }
+
+    /// `GET path` with no headers
+    pub async fn get(&self, path: &str) -> RuntimeResult<TestResponse> {
+        self.request(Method::GET, path, &[], Bytes::new()).await
+    }
+
+    /// `POST path` with a body and content type
+    pub async fn post(
+        &self,
+        path: &str,
+        content_type: &str,
+        body: impl Into<Bytes>,
+    ) -> RuntimeResult<TestResponse> {
+        self.request(Method::POST, path, &[("content-type", content_type)], body)
+            .await
+    }
+}
@@ -0,0 +1,118 @@
+//! Strict percent-decoding of request paths and query strings.
+//!
+//! `url::form_urlencoded` and the router accept `%zz` or sequences that
+//! decode to invalid UTF-8 and pass them on mangled. The server decodes
+//! with these functions instead and answers 400 when they fail.
+
+use std::collections::HashMap;
+use std::fmt;
+
+/// Why a path or query component could not be decoded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    /// `%` not followed by two hex digits
+    InvalidEscape(String),
+    /// The decoded bytes are not UTF-8
+    InvalidUtf8(String),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::InvalidEscape(s) => write!(f, "Malformed percent-encoding in '{}'", s),
+            DecodeError::InvalidUtf8(s) => {
+                write!(f, "Percent-encoded bytes in '{}' are not valid UTF-8", s)
+            }
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// Decode one path segment or query component. With `plus_as_space`
+/// (query strings), `+` decodes to a space.
+pub fn decode_component(input: &str, plus_as_space: bool) -> Result<String, DecodeError> {
+    if !(input.contains('%') || plus_as_space && input.contains('+')) {
+        return Ok(input.to_string());
+    }
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' => {
+                let byte = bytes
+                    .get(i + 1..i + 3)
+                    .filter(|hex| hex.iter().all(u8::is_ascii_hexdigit))
+                    .and_then(|hex| std::str::from_utf8(hex).ok())
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                    .ok_or_else(|| DecodeError::InvalidEscape(input.to_string()))?;
+                decoded.push(byte);
+                i += 3;
+            }
+            b'+' if plus_as_space => {
+                decoded.push(b' ');
+                i += 1;
+            }
+            other => {
+                decoded.push(other);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(decoded).map_err(|_| DecodeError::InvalidUtf8(input.to_string()))
+}
+
+/// Check that every escape in a request path decodes to UTF-8.
+pub fn validate_path(path: &str) -> Result<(), DecodeError> {
+    decode_component(path, false).map(|_| ())
+}
+
+/// Parse an `a=1&b=2` query string. Later duplicates win, as with
+/// `form_urlencoded`.
+pub fn parse_query(query: &str) -> Result<HashMap<String, String>, DecodeError> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            Ok((decode_component(key, true)?, decode_component(value, true)?))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_escapes_and_plus() {
+        assert_eq!(decode_component("caf%C3%A9", false).unwrap(), "café");
+        assert_eq!(decode_component("a+b", false).unwrap(), "a+b");
+        assert_eq!(decode_component("a+b%2B", true).unwrap(), "a b+");
+
+        let query = parse_query("q=hello+world&empty=&flag&q2=%26").unwrap();
+        assert_eq!(query["q"], "hello world");
+        assert_eq!(query["empty"], "");
+        assert_eq!(query["flag"], "");
+        assert_eq!(query["q2"], "&");
+    }
+
+    #[test]
+    fn rejects_bad_escapes_and_invalid_utf8() {
+        assert!(matches!(
+            decode_component("%zz", true),
+            Err(DecodeError::InvalidEscape(_))
+        ));
+        assert!(matches!(
+            decode_component("abc%4", true),
+            Err(DecodeError::InvalidEscape(_))
+        ));
+        assert!(matches!(
+            decode_component("%C3%28", true),
+            Err(DecodeError::InvalidUtf8(_))
+        ));
+        assert!(parse_query("a=%zz").is_err());
+        assert!(validate_path("/files/%FF").is_err());
+    }
+}
@@ -12,9 +12,12 @@ use host_bridge::{DbBridge, WasmMemory, WasmStateCore};
 use std::collections::HashMap;
 use std::path::Path;
 use std::sync::{Arc, Condvar, Mutex, RwLock};
+use std::time::Duration;
 use tokio::sync::RwLock as TokioRwLock;
 use tracing::{debug, info, warn};
-use wasmtime::{Engine, Instance, Module, Store, StoreLimits, StoreLimitsBuilder, Trap};
+use wasmtime::{
+    Engine, Extern, ExternType, Instance, Module, Store, StoreLimits, StoreLimitsBuilder, Trap,
+};
 
 /// Shared database bridge type
 pub type SharedDbBridge = Arc<TokioRwLock<DbBridge>>;
@@ -232,6 +235,8 @@ pub struct WasmState {
     pub pending_status: Option<u16>,
     /// Pending response body (for _http_respond)
     pub pending_body: Option<String>,
+    /// Handler output is raw bytes rather than UTF-8 text (set by `_res_set_binary`)
+    pub pending_binary: bool,
     /// Current transaction ID (for implicit commit/rollback)
     pub current_tx_id: Option<String>,
     /// Cached last_insert_id from the most recent INSERT in this request.
@@ -254,6 +259,22 @@ pub struct WasmState {
     /// declare any (e.g. older compiler / no v2 plugins loaded). See
     /// `foundation/spec/plugins/contracts/bridge-host-classes.md` §4.
     pub callbacks: Arc<Vec<crate::build_manifest::CallbackContract>>,
+    /// HTML templates for `_res_render`. Installed by
+    /// `WasmInstance::set_templates` and copied into each fresh state.
+    pub templates: crate::templates::SharedTemplateStore,
+    /// Audit sink for security-sensitive bridge calls. Installed by
+    /// `WasmInstance::set_audit_log` and copied into each fresh state.
+    pub audit_log: Option<crate::audit::SharedAuditLog>,
+    /// Role ordering for `_auth_require_role` and `_auth_require_any_role`.
+    /// Installed by `WasmInstance::set_role_hierarchy` and copied into each
+    /// fresh state.
+    pub role_hierarchy: crate::auth::SharedRoleHierarchy,
+    /// Sources `_req_input` checks, in order. Installed by
+    /// `WasmInstance::set_input_precedence` and copied into each fresh state.
+    pub input_precedence: crate::request_input::SharedInputPrecedence,
+    /// Identifier of the request being handled, written to audit entries:
+    /// the client's `X-Request-Id` header, or a generated UUID.
+    pub request_id: Option<String>,
     /// JSON-encoded attribute map for the custom component tag currently
     /// being dispatched by `_ui_render_page`. Set by the host immediately
     /// before calling `<tagname>_render` and cleared afterwards so a future
@@ -293,6 +314,29 @@ pub struct WasmState {
     /// when building the axum router so WASM-declared host/port/CORS/rate-limit
     /// values are honored.
     pub runtime_config: crate::runtime_config::SharedRuntimeConfig,
+    /// Request-scoped key/value pairs written by `_ctx_set` and read by
+    /// `_ctx_get`, so middleware can hand data to the route handler.
+    /// Cleared whenever the request context is set or cleared.
+    pub context_store: std::collections::HashMap<String, String>,
+    /// Tasks queued by `_task_spawn`, run after the response is sent
+    pub spawned_tasks: Vec<crate::tasks::SpawnedTask>,
+    /// Time spent in database bridge calls while handling the request
+    pub db_time: Duration,
+    /// The instance's `WasmInstance::reentry_token` while a request handler
+    /// runs; sent with its outbound `http_*` requests to `server_addr`
+    pub reentry_token: Option<Arc<str>>,
+    /// The instance's `WasmInstance::server_addr` while a request handler runs
+    pub server_addr: Option<std::net::SocketAddr>,
+    /// Clock the `_time_*` bridges read. Installed by
+    /// `WasmInstance::set_clock` and copied into each fresh state.
+    pub clock: crate::clock::Clock,
+    /// Where `print`/`printl` write. Installed by
+    /// `WasmInstance::set_print_output` and copied into each fresh state.
+    pub print_output: host_bridge::PrintOutput,
+    /// Zone `time_format`/`time_now_local` use when a call names none.
+    /// Installed by `WasmInstance::set_default_timezone` and copied into
+    /// each fresh state.
+    pub default_timezone: chrono_tz::Tz,
 }
 
 /// Request context passed to handlers
@@ -315,6 +359,185 @@ pub struct RequestContext {
     pub body_bytes: Option<Vec<u8>>,
     pub params: std::collections::HashMap<String, String>,
     pub query: std::collections::HashMap<String, String>,
+    /// Query string exactly as received: undecoded, in its original order
+    /// and without the leading `?` (empty when there is none). For
+    /// `_req_raw_query`, e.g. to verify a signature over the exact query.
+    pub raw_query: String,
+    /// Client address and scheme resolved by the HTTP entry point from the
+    /// socket peer (or a trusted proxy's forwarded headers). `None` for
+    /// requests that did not arrive over a connection (jobs, cron, tests).
+    pub client: Option<ClientInfo>,
+    /// Pattern of the route the router matched (e.g. `/users/:id`), for
+    /// `_req_route_pattern`. `None` when the handler was not reached through
+    /// a route (jobs, cron, tasks, WebSocket events).
+    pub route_pattern: Option<String>,
+}
+
+/// Client as seen by the server, for `_req_client_ip` / `_req_scheme`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientInfo {
+    pub ip: String,
+    /// `"http"` or `"https"`
+    pub scheme: String,
+}
+
+impl RequestContext {
+    /// Start building a request context (defaults to `GET /`).
+    pub fn builder() -> RequestContextBuilder {
+        RequestContextBuilder::default()
+    }
+}
+
+/// Fluent builder for [`RequestContext`], for tests and in-process dispatch.
+///
+/// ```
+/// use clean_server::wasm::RequestContext;
+///
+/// let ctx = RequestContext::builder()
+///     .method("POST")
+///     .path("/users/42")
+///     .param("id", "42")
+///     .query("expand", "posts")
+///     .header("Content-Type", "application/json")
+///     .body(r#"{"name":"Ada"}"#)
+///     .build()
+///     .unwrap();
+/// assert_eq!(ctx.params.get("id").map(String::as_str), Some("42"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct RequestContextBuilder {
+    method: String,
+    path: String,
+    headers: Vec<(String, String)>,
+    body: String,
+    params: std::collections::HashMap<String, String>,
+    query: std::collections::HashMap<String, String>,
+    raw_query: String,
+    client: Option<ClientInfo>,
+    route_pattern: Option<String>,
+}
+
+impl Default for RequestContextBuilder {
+    fn default() -> Self {
+        Self {
+            method: "GET".to_string(),
+            path: "/".to_string(),
+            headers: Vec::new(),
+            body: String::new(),
+            params: std::collections::HashMap::new(),
+            query: std::collections::HashMap::new(),
+            raw_query: String::new(),
+            client: None,
+            route_pattern: None,
+        }
+    }
+}
+
+impl RequestContextBuilder {
+    pub fn method(mut self, method: impl Into<String>) -> Self {
+        self.method = method.into();
+        self
+    }
+
+    pub fn path(mut self, path: impl Into<String>) -> Self {
+        self.path = path.into();
+        self
+    }
+
+    /// Set a path parameter (as extracted from the route pattern)
+    pub fn param(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.params.insert(name.into(), value.into());
+        self
+    }
+
+    /// Set a query string parameter
+    pub fn query(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.query.insert(name.into(), value.into());
+        self
+    }
+
+    /// Set the raw query string `_req_raw_query` returns. Independent of
+    /// [`Self::query`], which sets the parsed values.
+    pub fn raw_query(mut self, raw_query: impl Into<String>) -> Self {
+        self.raw_query = raw_query.into();
+        self
+    }
+
+    /// Set the route pattern `_req_route_pattern` returns, e.g. `/users/:id`
+    pub fn route_pattern(mut self, pattern: impl Into<String>) -> Self {
+        self.route_pattern = Some(pattern.into());
+        self
+    }
+
+    /// Append a header; repeated names are kept, as on the wire
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    pub fn body(mut self, body: impl Into<String>) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    /// Set the resolved client address and scheme
+    pub fn client(mut self, ip: impl Into<String>, scheme: impl Into<String>) -> Self {
+        self.client = Some(ClientInfo {
+            ip: ip.into(),
+            scheme: scheme.into(),
+        });
+        self
+    }
+
+    /// Validate and build the context.
+    ///
+    /// Fails when the method is not a supported HTTP method, the path is not
+    /// absolute or carries a query string (use [`Self::query`] instead), or a
+    /// header name or value is not valid HTTP.
+    pub fn build(self) -> RuntimeResult<RequestContext> {
+        let method = crate::router::HttpMethod::parse(&self.method)?;
+
+        if !self.path.starts_with('/') {
+            return Err(RuntimeError::route(format!(
+                "Request path must start with '/': {}",
+                self.path
+            )));
+        }
+        if self.path.contains('?') {
+            return Err(RuntimeError::route(format!(
+                "Request path must not contain a query string: {}",
+                self.path
+            )));
+        }
+
+        for (name, value) in &self.headers {
+            if http::HeaderName::from_bytes(name.as_bytes()).is_err() {
+                return Err(RuntimeError::route(format!(
+                    "Invalid header name: {:?}",
+                    name
+                )));
+            }
+            if http::HeaderValue::from_str(value).is_err() {
+                return Err(RuntimeError::route(format!(
+                    "Invalid value for header '{}'",
+                    name
+                )));
+            }
+        }
+
+        Ok(RequestContext {
+            method: method.as_str().to_string(),
+            path: self.path,
+            headers: self.headers,
+            body: self.body,
+            body_bytes: None,
+            params: self.params,
+            query: self.query,
+            raw_query: self.raw_query,
+            client: self.client,
+            route_pattern: self.route_pattern,
+        })
+    }
 }
 
 /// Authentication context
@@ -323,6 +546,9 @@ pub struct AuthContext {
     pub user_id: i32,
     pub role: String,
     pub session_id: Option<String>,
+    /// Permissions granted to this user directly, independent of the role
+    /// (see `crate::auth::can`)
+    pub permissions: Vec<String>,
 }
 
 /// Response captured from a `_test_http_request` in-process dispatch
@@ -347,13 +573,24 @@ pub struct HandlerResponse {
     pub status: Option<u16>,
     /// Stylesheet hrefs to inject into the response <head> as <link> tags
     pub head_links: Vec<String>,
+    /// Raw body bytes when the handler called `_res_set_binary`. Sent
+    /// verbatim instead of `body`, which is left empty.
+    pub binary_body: Option<Vec<u8>>,
+    /// Tasks the handler queued with `_task_spawn`
+    pub tasks: Vec<crate::tasks::SpawnedTask>,
+    /// Time the handler (and middleware) spent in database bridge calls
+    pub db_time: Duration,
 }
 
-/// Build StoreLimits from a memory limit in bytes
+/// Build StoreLimits from a memory limit in bytes.
+///
+/// Growth past the limit does not trap: `memory.grow` returns -1 inside the
+/// module, which its allocator reports as an allocation failure. The host
+/// never grows memory on the guest's behalf beyond the cap.
 fn build_store_limits(memory_limit: usize) -> StoreLimits {
     StoreLimitsBuilder::new()
         .memory_size(memory_limit)
-        .trap_on_grow_failure(true)
+        .trap_on_grow_failure(false)
         .instances(1)
         .tables(10)
         .memories(1)
@@ -394,15 +631,17 @@ fn is_memory_limit_trap(err: &wasmtime::Error) -> bool {
 }
 
 /// Wrap a wasmtime handler error with friendlier context when the trap is
-/// caused by the per-instance memory limit. Lets operators see "raise
-/// CLEAN_SERVER_MEMORY_LIMIT_MB" instead of the raw wasmtime backtrace.
-fn classify_handler_error(handler_name: &str, err: wasmtime::Error) -> RuntimeError {
+/// caused by the per-instance memory limit (`memory_limit` bytes). Lets
+/// operators see "raise CLEAN_SERVER_MEMORY_LIMIT_MB" instead of the raw
+/// wasmtime backtrace.
+fn classify_handler_error(
+    handler_name: &str,
+    memory_limit: usize,
+    err: wasmtime::Error,
+) -> RuntimeError {
     let formatted = format_wasm_error(&err);
     if is_memory_limit_trap(&err) {
-        let limit_mb = std::env::var("CLEAN_SERVER_MEMORY_LIMIT_MB")
-            .ok()
-            .and_then(|s| s.trim().parse::<usize>().ok())
-            .unwrap_or(DEFAULT_MEMORY_LIMIT / (1024 * 1024));
+        let limit_mb = memory_limit / (1024 * 1024);
         RuntimeError::wasm(format!(
             "Handler {} hit the {} MB WASM memory limit. \
              Raise it with CLEAN_SERVER_MEMORY_LIMIT_MB=<MB> if the request is \
@@ -412,11 +651,37 @@ fn classify_handler_error(handler_name: &str, err: wasmtime::Error) -> RuntimeEr
              Original error: {}",
             handler_name, limit_mb, formatted
         ))
+    } else if err.downcast_ref::<Trap>() == Some(&Trap::Interrupt) {
+        RuntimeError::wasm(format!(
+            "Handler {} timed out and was interrupted",
+            handler_name
+        ))
     } else {
         RuntimeError::wasm(format!("Handler {} failed: {}", handler_name, formatted))
     }
 }
 
+/// Run a call into WASM, turning a panic in a host function it reaches into
+/// an error instead of unwinding into the Tokio worker. The panicking call's
+/// store is per-request and is dropped by the caller afterwards.
+fn catch_host_panic<R>(
+    handler_name: &str,
+    call: impl FnOnce() -> wasmtime::Result<R>,
+) -> wasmtime::Result<R> {
+    std::panic::catch_unwind(std::panic::AssertUnwindSafe(call)).unwrap_or_else(|payload| {
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "non-string panic payload".to_string());
+        tracing::error!("Host function panicked in {}: {}", handler_name, message);
+        Err(wasmtime::Error::msg(format!(
+            "host function panicked: {}",
+            message
+        )))
+    })
+}
+
 /// Default memory limit: 128 MB. Raised from 32 MB because realistic
 /// SSR handlers (page rendering with iterate + string.split + string.trim
 /// chains, design-doc validators, etc.) exhaust 32 MB inside a single
@@ -431,6 +696,17 @@ fn classify_handler_error(handler_name: &str, err: wasmtime::Error) -> RuntimeEr
 /// e.g. `CLEAN_SERVER_MEMORY_LIMIT_MB=512`.
 const DEFAULT_MEMORY_LIMIT: usize = 128 * 1024 * 1024;
 
+/// How often the engine epoch advances once a handler timeout is in use.
+/// Timeouts are enforced with this granularity.
+const EPOCH_TICK: Duration = Duration::from_millis(10);
+
+/// Epoch deadline for stores without a timeout. Far enough ahead never to be
+/// reached, but leaves headroom so `set_epoch_deadline` cannot overflow.
+const NO_EPOCH_DEADLINE: u64 = u64::MAX / 2;
+
+/// Exports `initialize` calls to register routes, in order of preference
+const ENTRY_POINTS: [&str; 4] = ["main", "_start", "start", "init"];
+
 /// Read `CLEAN_SERVER_MEMORY_LIMIT_MB` from the environment and convert
 /// to bytes. Returns `DEFAULT_MEMORY_LIMIT` when the env var is unset or
 /// invalid.
@@ -460,6 +736,7 @@ impl WasmState {
             pending_redirect: None,
             pending_status: None,
             pending_body: None,
+            pending_binary: false,
             current_tx_id: None,
             last_insert_id: None,
             roles_store: Arc::new(RwLock::new(RolesStore::new())),
@@ -467,6 +744,11 @@ impl WasmState {
             islands_store: create_shared_islands_store(),
             component_registry: create_shared_component_registry(),
             callbacks: Arc::new(Vec::new()),
+            templates: Default::default(),
+            audit_log: None,
+            role_hierarchy: Default::default(),
+            input_precedence: Arc::new(crate::request_input::DEFAULT_INPUT_PRECEDENCE),
+            request_id: None,
             pending_component_attrs: None,
             permission_gate: PermissionGate::allow_all(),
             limits: build_store_limits(DEFAULT_MEMORY_LIMIT),
@@ -480,6 +762,14 @@ impl WasmState {
             jobs_state: crate::jobs::create_shared_jobs_state(),
             locale_state: crate::locale::create_shared_locale_state(),
             runtime_config: crate::runtime_config::create_shared_runtime_config(),
+            context_store: std::collections::HashMap::new(),
+            spawned_tasks: Vec::new(),
+            db_time: Duration::ZERO,
+            reentry_token: None,
+            server_addr: None,
+            clock: crate::clock::Clock::System,
+            print_output: host_bridge::PrintOutput::RawStdout,
+            default_timezone: chrono_tz::Tz::UTC,
         }
     }
 
@@ -498,6 +788,7 @@ impl WasmState {
             pending_redirect: None,
             pending_status: None,
             pending_body: None,
+            pending_binary: false,
             current_tx_id: None,
             last_insert_id: None,
             roles_store: Arc::new(RwLock::new(RolesStore::new())),
@@ -505,6 +796,11 @@ impl WasmState {
             islands_store: create_shared_islands_store(),
             component_registry: create_shared_component_registry(),
             callbacks: Arc::new(Vec::new()),
+            templates: Default::default(),
+            audit_log: None,
+            role_hierarchy: Default::default(),
+            input_precedence: Arc::new(crate::request_input::DEFAULT_INPUT_PRECEDENCE),
+            request_id: None,
             pending_component_attrs: None,
             permission_gate: PermissionGate::allow_all(),
             limits: build_store_limits(DEFAULT_MEMORY_LIMIT),
@@ -518,6 +814,14 @@ impl WasmState {
             jobs_state: crate::jobs::create_shared_jobs_state(),
             locale_state: crate::locale::create_shared_locale_state(),
             runtime_config: crate::runtime_config::create_shared_runtime_config(),
+            context_store: std::collections::HashMap::new(),
+            spawned_tasks: Vec::new(),
+            db_time: Duration::ZERO,
+            reentry_token: None,
+            server_addr: None,
+            clock: crate::clock::Clock::System,
+            print_output: host_bridge::PrintOutput::RawStdout,
+            default_timezone: chrono_tz::Tz::UTC,
         }
     }
 
@@ -546,6 +850,7 @@ impl WasmState {
             pending_redirect: None,
             pending_status: None,
             pending_body: None,
+            pending_binary: false,
             current_tx_id: None,
             last_insert_id: None,
             roles_store: Arc::new(RwLock::new(RolesStore::new())),
@@ -553,6 +858,11 @@ impl WasmState {
             islands_store,
             component_registry,
             callbacks: Arc::new(Vec::new()),
+            templates: Default::default(),
+            audit_log: None,
+            role_hierarchy: Default::default(),
+            input_precedence: Arc::new(crate::request_input::DEFAULT_INPUT_PRECEDENCE),
+            request_id: None,
             pending_component_attrs: None,
             permission_gate,
             limits: build_store_limits(memory_limit),
@@ -566,6 +876,14 @@ impl WasmState {
             jobs_state: crate::jobs::create_shared_jobs_state(),
             locale_state: crate::locale::create_shared_locale_state(),
             runtime_config: crate::runtime_config::create_shared_runtime_config(),
+            context_store: std::collections::HashMap::new(),
+            spawned_tasks: Vec::new(),
+            db_time: Duration::ZERO,
+            reentry_token: None,
+            server_addr: None,
+            clock: crate::clock::Clock::System,
+            print_output: host_bridge::PrintOutput::RawStdout,
+            default_timezone: chrono_tz::Tz::UTC,
         }
     }
 
@@ -576,7 +894,15 @@ impl WasmState {
             ctx.params
         );
         tracing::debug!("WasmState::set_request: Path: {}", ctx.path);
+        self.request_id = Some(
+            ctx.headers
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case("x-request-id"))
+                .map(|(_, v)| v.clone())
+                .unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
+        );
         self.request_context = Some(ctx);
+        self.context_store.clear();
         // Reset memory allocator for new request
         self.memory.reset();
     }
@@ -584,19 +910,28 @@ impl WasmState {
     /// Clear request context
     pub fn clear_request(&mut self) {
         self.request_context = None;
+        self.request_id = None;
         self.auth_context = None;
         self.pending_set_cookie = None;
         self.pending_headers.clear();
         self.pending_redirect = None;
         self.pending_head_links.clear();
+        self.context_store.clear();
     }
 
     /// Set auth context from session
-    pub fn set_auth_from_session(&mut self, user_id: i32, role: String, session_id: String) {
+    pub fn set_auth_from_session(
+        &mut self,
+        user_id: i32,
+        role: String,
+        session_id: String,
+        permissions: Vec<String>,
+    ) {
         self.auth_context = Some(AuthContext {
             user_id,
             role,
             session_id: Some(session_id),
+            permissions,
         });
     }
 
@@ -630,6 +965,11 @@ impl WasmState {
         self.pending_body = Some(body);
     }
 
+    /// Mark the handler's return value as a binary body
+    pub fn set_binary(&mut self) {
+        self.pending_binary = true;
+    }
+
     /// Add a custom response header
     pub fn add_header(&mut self, name: String, value: String) {
         self.pending_headers.push((name, value));
@@ -684,6 +1024,36 @@ impl WasmStateCore for WasmState {
     fn set_last_insert_id(&mut self, id: Option<i64>) {
         self.last_insert_id = id;
     }
+
+    fn audit(&self, namespace: &str, function: &str, params: &serde_json::Value) {
+        if let Some(log) = &self.audit_log {
+            log.record(namespace, function, params, self.request_id.as_deref());
+        }
+    }
+
+    fn record_db_time(&mut self, elapsed: Duration) {
+        self.db_time += elapsed;
+    }
+
+    fn reentry_token(&self) -> Option<&str> {
+        self.reentry_token.as_deref()
+    }
+
+    fn server_addr(&self) -> Option<std::net::SocketAddr> {
+        self.server_addr
+    }
+
+    fn now(&self) -> std::time::SystemTime {
+        self.clock.now()
+    }
+
+    fn print_output(&self) -> host_bridge::PrintOutput {
+        self.print_output
+    }
+
+    fn default_timezone(&self) -> chrono_tz::Tz {
+        self.default_timezone
+    }
 }
 
 /// WASM module instance ready for execution
@@ -712,10 +1082,45 @@ pub struct WasmInstance {
     /// callbacks after the `WasmInstance` is constructed (build manifest is
     /// loaded after the WASM module). See contracts/bridge-host-classes.md §4.
     callbacks: parking_lot::Mutex<Arc<Vec<crate::build_manifest::CallbackContract>>>,
+    /// Template store for `_res_render`, installed via `set_templates` and
+    /// shared with every fresh `WasmState`.
+    templates: parking_lot::Mutex<crate::templates::SharedTemplateStore>,
+    /// Audit sink installed via `set_audit_log`, shared with every fresh
+    /// `WasmState`.
+    audit_log: parking_lot::Mutex<Option<crate::audit::SharedAuditLog>>,
+    /// Role ordering installed via `set_role_hierarchy`, shared with every
+    /// fresh `WasmState`.
+    role_hierarchy: parking_lot::Mutex<crate::auth::SharedRoleHierarchy>,
+    /// `_req_input` lookup order installed via `set_input_precedence`,
+    /// shared with every fresh `WasmState`.
+    input_precedence: parking_lot::Mutex<crate::request_input::SharedInputPrecedence>,
+    /// Time limit for route handler calls, installed via `set_handler_timeout`.
+    /// Routes registered with `_http_route_timeout` override it.
+    handler_timeout: parking_lot::Mutex<Option<Duration>>,
+    /// Clock installed via `set_clock`, copied into every fresh `WasmState`.
+    clock: parking_lot::Mutex<crate::clock::Clock>,
+    /// Print destination installed via `set_print_output`, copied into every
+    /// fresh `WasmState`.
+    print_output: parking_lot::Mutex<host_bridge::PrintOutput>,
+    /// Zone installed via `set_default_timezone`, copied into every fresh
+    /// `WasmState`.
+    default_timezone: parking_lot::Mutex<chrono_tz::Tz>,
+    /// Starts the thread advancing the engine epoch the first time a
+    /// handler runs under a timeout
+    epoch_ticker: std::sync::Once,
+    /// Random per-instance token its request handlers send with outbound
+    /// `http_*` requests, so the server can refuse a handler calling back
+    /// into it (see `host_bridge::REENTRY_HEADER`)
+    reentry_token: Arc<str>,
+    /// Address the server serves this instance on; `None` when it is not
+    /// reachable over TCP (Unix sockets, tests)
+    server_addr: parking_lot::Mutex<Option<std::net::SocketAddr>>,
     /// Bridge function permission gate parsed from the loaded WASM binary
     permission_gate: PermissionGate,
     /// Memory limit in bytes for each Store
     memory_limit: usize,
+    /// Whether the module was compiled at load or taken from the module cache
+    module_source: crate::module_cache::ModuleSource,
     /// Shared WebSocket state (connections, rooms, route registry).
     /// A single instance is shared with the server so bridge functions can
     /// find and update live WebSocket connections.
@@ -753,6 +1158,8 @@ impl WasmInstance {
             session_store,
             Some(wasm_path),
             memory_limit_from_env(),
+            None,
+            None,
         )
     }
 
@@ -771,6 +1178,45 @@ impl WasmInstance {
         router: SharedRouter,
         db_bridge: SharedDbBridge,
         memory_limit: usize,
+    ) -> RuntimeResult<Self> {
+        Self::load_with_module_cache(
+            wasm_path,
+            router,
+            db_bridge,
+            create_session_store(SessionConfig::default()),
+            memory_limit,
+            None,
+            None,
+        )
+    }
+
+    /// Instantiate the module without running it, checking its imports and
+    /// exports, and name the entry point `initialize` would call
+    pub fn check_instantiates(&self) -> RuntimeResult<&'static str> {
+        self.check_imports()?;
+        // The memory export was already checked when the module loaded
+        let (mut store, instance) = self.create_instance()?;
+        ENTRY_POINTS
+            .into_iter()
+            .find(|name| instance.get_typed_func::<(), ()>(&mut store, name).is_ok())
+            .ok_or_else(|| {
+                RuntimeError::wasm(format!(
+                    "Module exports none of the entry points {}",
+                    ENTRY_POINTS.join(", ")
+                ))
+            })
+    }
+
+    /// Load a WASM module from a file, reusing the compiled artifact in
+    /// `module_cache_dir` when one matches (see `module_cache`)
+    pub fn load_with_module_cache(
+        wasm_path: &Path,
+        router: SharedRouter,
+        db_bridge: SharedDbBridge,
+        session_store: SharedSessionStore,
+        memory_limit: usize,
+        module_cache_dir: Option<&Path>,
+        diag_dir: Option<&Path>,
     ) -> RuntimeResult<Self> {
         info!("Loading WASM module from {:?}", wasm_path);
 
@@ -778,7 +1224,6 @@ impl WasmInstance {
             RuntimeError::wasm(format!("Failed to read WASM file {:?}: {}", wasm_path, e))
         })?;
 
-        let session_store = create_session_store(SessionConfig::default());
         Self::from_bytes_inner(
             &wasm_bytes,
             router,
@@ -786,6 +1231,8 @@ impl WasmInstance {
             session_store,
             Some(wasm_path),
             memory_limit,
+            module_cache_dir,
+            diag_dir,
         )
     }
 
@@ -819,6 +1266,8 @@ impl WasmInstance {
             session_store,
             None,
             memory_limit_from_env(),
+            None,
+            None,
         )
     }
 
@@ -826,7 +1275,10 @@ impl WasmInstance {
     ///
     /// Public `from_bytes_*` entry points have no path to report; the
     /// file-based `load*` entry points pass `Some(path)` so that
-    /// `RUNTIME_WASM_PARSE` diagnostics include the originating file.
+    /// `RUNTIME_WASM_PARSE` diagnostics include the originating file. Those
+    /// diagnostics go under `diag_dir`, or `error_reporting::diag_dir()` when
+    /// it is None.
+    #[allow(clippy::too_many_arguments)]
     fn from_bytes_inner(
         wasm_bytes: &[u8],
         router: SharedRouter,
@@ -834,6 +1286,8 @@ impl WasmInstance {
         session_store: SharedSessionStore,
         module_path: Option<&Path>,
         memory_limit: usize,
+        module_cache_dir: Option<&Path>,
+        diag_dir: Option<&Path>,
     ) -> RuntimeResult<Self> {
         // Parse the clean:permissions custom section before compiling so we
         // have the gate available before any bridge function can be called.
@@ -848,16 +1302,28 @@ impl WasmInstance {
             debug!("Module loaded without permission enforcement (no clean:permissions section)");
         }
 
-        // Create engine
-        let engine = Engine::default();
+        // Create engine. Epoch interruption lets handler timeouts stop a
+        // handler stuck in a loop; stores without a timeout get a deadline
+        // that is never reached.
+        let mut engine_config = wasmtime::Config::new();
+        engine_config.epoch_interruption(true);
+        let engine = Engine::new(&engine_config)
+            .map_err(|e| RuntimeError::wasm(format!("Failed to create WASM engine: {}", e)))?;
 
         // Compile module. When wasmtime rejects the bytes, assemble a
         // structured diagnostic bundle (see `error_reporting`) before
         // surfacing the error so the compiler team can reproduce the
         // bug from the on-disk report.
-        let module = Module::new(&engine, wasm_bytes).map_err(|e| {
+        let (module, module_source) = crate::module_cache::load_or_compile(
+            &engine,
+            wasm_bytes,
+            module_cache_dir,
+        )
+        .map_err(|e| {
             let report = WasmParseReport::new(wasm_bytes, &e, module_path);
-            let diag_root = error_reporting::diag_dir();
+            let diag_root = diag_dir
+                .map(Path::to_path_buf)
+                .unwrap_or_else(error_reporting::diag_dir);
             match report.emit(wasm_bytes, &diag_root) {
                 Ok(path) => warn!(
                     "Wrote RUNTIME_WASM_PARSE diagnostic to {:?} (sha={})",
@@ -878,6 +1344,7 @@ impl WasmInstance {
         })?;
 
         debug!("WASM module compiled successfully");
+        check_memory_export(&module)?;
 
         // Stash the raw WASM bytes for `_dev_snapshot()`. This is a one-time
         // copy at load; the ring buffer's read side base64-encodes on demand.
@@ -904,8 +1371,22 @@ impl WasmInstance {
             islands_store: create_shared_islands_store(),
             component_registry: create_shared_component_registry(),
             callbacks: parking_lot::Mutex::new(Arc::new(Vec::new())),
+            templates: parking_lot::Mutex::new(Default::default()),
+            audit_log: parking_lot::Mutex::new(None),
+            role_hierarchy: parking_lot::Mutex::new(Default::default()),
+            input_precedence: parking_lot::Mutex::new(Arc::new(
+                crate::request_input::DEFAULT_INPUT_PRECEDENCE,
+            )),
+            handler_timeout: parking_lot::Mutex::new(None),
+            clock: parking_lot::Mutex::new(crate::clock::Clock::System),
+            print_output: parking_lot::Mutex::new(host_bridge::PrintOutput::RawStdout),
+            default_timezone: parking_lot::Mutex::new(chrono_tz::Tz::UTC),
+            epoch_ticker: std::sync::Once::new(),
+            reentry_token: uuid::Uuid::new_v4().simple().to_string().into(),
+            server_addr: parking_lot::Mutex::new(None),
             permission_gate,
             memory_limit,
+            module_source,
             ws_state: crate::websocket::create_shared_ws_state(),
             jobs_state: crate::jobs::create_shared_jobs_state(),
             locale_state: crate::locale::create_shared_locale_state(),
@@ -921,6 +1402,90 @@ impl WasmInstance {
         *self.callbacks.lock() = callbacks;
     }
 
+    /// Install the template store `_res_render` reads from.
+    pub fn set_templates(&self, templates: crate::templates::SharedTemplateStore) {
+        *self.templates.lock() = templates;
+    }
+
+    /// Install the audit sink security-sensitive bridges report to.
+    pub fn set_audit_log(&self, audit_log: Option<crate::audit::SharedAuditLog>) {
+        *self.audit_log.lock() = audit_log;
+    }
+
+    /// Install the role ordering role checks use.
+    pub fn set_role_hierarchy(&self, hierarchy: crate::auth::SharedRoleHierarchy) {
+        *self.role_hierarchy.lock() = hierarchy;
+    }
+
+    /// Role ordering role checks use (see `set_role_hierarchy`).
+    pub fn role_hierarchy(&self) -> crate::auth::SharedRoleHierarchy {
+        self.role_hierarchy.lock().clone()
+    }
+
+    /// Token this instance's request handlers send in the
+    /// `host_bridge::REENTRY_HEADER` of their outbound requests.
+    pub fn reentry_token(&self) -> &str {
+        &self.reentry_token
+    }
+
+    /// Install the address the server listens on; handlers only send the
+    /// reentry token with requests that reach it.
+    pub fn set_server_addr(&self, addr: Option<std::net::SocketAddr>) {
+        *self.server_addr.lock() = addr;
+    }
+
+    /// Install the order `_req_input` checks request sources in.
+    pub fn set_input_precedence(&self, precedence: crate::request_input::SharedInputPrecedence) {
+        *self.input_precedence.lock() = precedence;
+    }
+
+    /// Install the clock the `_time_*` bridges read.
+    pub fn set_clock(&self, clock: crate::clock::Clock) {
+        *self.clock.lock() = clock;
+    }
+
+    /// Send `print`/`printl` output to stdout or through `tracing`.
+    pub fn set_print_output(&self, output: host_bridge::PrintOutput) {
+        *self.print_output.lock() = output;
+    }
+
+    /// Install the zone `time_format` and `time_now_local` fall back to.
+    pub fn set_default_timezone(&self, zone: chrono_tz::Tz) {
+        *self.default_timezone.lock() = zone;
+    }
+
+    /// Interrupt route handlers running longer than `timeout` (`None`: no limit).
+    pub fn set_handler_timeout(&self, timeout: Option<Duration>) {
+        *self.handler_timeout.lock() = timeout;
+    }
+
+    /// Make calls into `store` trap with `Trap::Interrupt` once `timeout` has
+    /// elapsed, starting the epoch ticker if this is the first timeout.
+    fn set_deadline(&self, store: &mut Store<WasmState>, timeout: Duration) {
+        self.epoch_ticker.call_once(|| {
+            let engine = self.engine.weak();
+            let spawned = std::thread::Builder::new()
+                .name("wasm-epoch-ticker".to_string())
+                .spawn(move || {
+                    // Exits once the WasmInstance owning the engine is dropped.
+                    while let Some(engine) = engine.upgrade() {
+                        engine.increment_epoch();
+                        drop(engine);
+                        std::thread::sleep(EPOCH_TICK);
+                    }
+                });
+            if let Err(e) = spawned {
+                warn!(
+                    "Failed to start epoch ticker, handler timeouts disabled: {}",
+                    e
+                );
+            }
+        });
+        // One extra tick because the current one may be about to end.
+        let ticks = timeout.as_millis().div_ceil(EPOCH_TICK.as_millis()) as u64 + 1;
+        store.set_epoch_deadline(ticks);
+    }
+
     /// Create a fresh WASM instance for request handling
     fn create_instance(&self) -> RuntimeResult<(Store<WasmState>, Instance)> {
         let mut state = WasmState::with_session_store(
@@ -939,9 +1504,17 @@ impl WasmInstance {
         state.runtime_config = self.runtime_config.clone();
         let mut store = Store::new(&self.engine, state);
         store.limiter(|state| &mut state.limits);
+        store.set_epoch_deadline(NO_EPOCH_DEADLINE);
         // Copy the resolved callback contracts into the fresh state so bridge
         // functions like `_ui_render_page` can look up their dispatch rules.
         store.data_mut().callbacks = self.callbacks.lock().clone();
+        store.data_mut().templates = self.templates.lock().clone();
+        store.data_mut().audit_log = self.audit_log.lock().clone();
+        store.data_mut().role_hierarchy = self.role_hierarchy.lock().clone();
+        store.data_mut().input_precedence = self.input_precedence.lock().clone();
+        store.data_mut().clock = *self.clock.lock();
+        store.data_mut().print_output = *self.print_output.lock();
+        store.data_mut().default_timezone = *self.default_timezone.lock();
 
         let instance = self
             .linker
@@ -981,8 +1554,53 @@ impl WasmInstance {
         &self.component_registry
     }
 
+    /// Whether the module was compiled at load or taken from the module cache
+    pub fn module_source(&self) -> crate::module_cache::ModuleSource {
+        self.module_source
+    }
+
+    /// Linker every instance is created from. Embedders can define their own
+    /// host functions on it before the module is initialized.
+    pub fn linker_mut(&mut self) -> &mut wasmtime::Linker<WasmState> {
+        &mut self.linker
+    }
+
+    /// Check that the linker satisfies every import of the module, listing
+    /// all missing or mismatched imports at once instead of failing on the
+    /// first one at instantiation
+    pub fn check_imports(&self) -> RuntimeResult<()> {
+        let mut store = Store::new(&self.engine, WasmState::new(self.router.clone()));
+        let mut problems = Vec::new();
+        for import in self.module.imports() {
+            let name = format!("{}.{}", import.module(), import.name());
+            let expected = import.ty();
+            match (self.linker.get_by_import(&mut store, &import), &expected) {
+                (None, ExternType::Func(ty)) => problems.push(format!("{}: {}", name, ty)),
+                (None, other) => problems.push(format!("{}: {:?}", name, other)),
+                (Some(Extern::Func(func)), ExternType::Func(ty)) => {
+                    let provided = func.ty(&store);
+                    if !provided.matches(ty) {
+                        problems.push(format!("{}: {} (server provides {})", name, ty, provided));
+                    }
+                }
+                (Some(_), _) => {}
+            }
+        }
+
+        if problems.is_empty() {
+            return Ok(());
+        }
+        Err(RuntimeError::wasm(format!(
+            "Module has {} unsatisfied import(s):\n  {}",
+            problems.len(),
+            problems.join("\n  ")
+        )))
+    }
+
     /// Initialize the module (calls main/start function to register routes)
     pub fn initialize(&self) -> RuntimeResult<()> {
+        self.check_imports()?;
+
         // Create an instance specifically for initialization
         let (mut store, instance) = self.create_instance()?;
 
@@ -1000,13 +1618,11 @@ impl WasmInstance {
         store.data_mut().memory.set_offset(heap_ptr);
 
         // Try different entry point names
-        let entry_names = ["main", "_start", "start", "init"];
-
-        for name in entry_names {
+        for name in ENTRY_POINTS {
             if let Ok(func) = instance.get_typed_func::<(), ()>(&mut store, name) {
                 info!("Calling WASM entry point: {}", name);
 
-                func.call(&mut store, ())
+                catch_host_panic(name, || func.call(&mut store, ()))
                     .map_err(|e| RuntimeError::wasm(format!("Failed to call {}: {}", name, e)))?;
 
                 // Run any migrations registered during WASM startup
@@ -1056,9 +1672,8 @@ impl WasmInstance {
         debug!("Calling handler: {}", handler_name);
 
         if let Ok(handler) = instance.get_typed_func::<(), i32>(&mut store, handler_name) {
-            let result_ptr = handler
-                .call(&mut store, ())
-                .map_err(|e| classify_handler_error(handler_name, e))?;
+            let result_ptr = catch_host_panic(handler_name, || handler.call(&mut store, ()))
+                .map_err(|e| classify_handler_error(handler_name, self.memory_limit, e))?;
 
             let result =
                 crate::memory::read_string_from_memory(&store, &memory, result_ptr as u32)?;
@@ -1079,6 +1694,19 @@ impl WasmInstance {
         handler_name: &str,
         request: RequestContext,
         auth_context: Option<AuthContext>,
+    ) -> RuntimeResult<HandlerResponse> {
+        self.call_handler_with_timeout(handler_name, request, auth_context, None)
+    }
+
+    /// Call a route handler like `call_handler_with_auth`, interrupting it
+    /// (and the middleware before it) after `timeout`. `None` falls back to
+    /// the timeout installed with `set_handler_timeout`.
+    pub fn call_handler_with_timeout(
+        &self,
+        handler_name: &str,
+        request: RequestContext,
+        auth_context: Option<AuthContext>,
+        timeout: Option<Duration>,
     ) -> RuntimeResult<HandlerResponse> {
         debug!(
             "call_handler_with_auth: handler={}, path={}, params={:?}",
@@ -1087,6 +1715,9 @@ impl WasmInstance {
 
         // Create a fresh instance for this request
         let (mut store, instance) = self.create_instance()?;
+        if let Some(timeout) = timeout.or(*self.handler_timeout.lock()) {
+            self.set_deadline(&mut store, timeout);
+        }
 
         // Set request context
         debug!(
@@ -1094,6 +1725,8 @@ impl WasmInstance {
             request.params.len()
         );
         store.data_mut().set_request(request);
+        store.data_mut().reentry_token = Some(self.reentry_token.clone());
+        store.data_mut().server_addr = *self.server_addr.lock();
 
         // Verify the params were set correctly
         if let Some(ref ctx) = store.data().request_context {
@@ -1113,13 +1746,25 @@ impl WasmInstance {
             .get_memory(&mut store, "memory")
             .ok_or_else(|| RuntimeError::wasm("Module has no memory export"))?;
 
+        // Run `_http_middleware` exports first, in this same store, so values
+        // they put in the context store are visible to the handler.
+        let middleware = self.runtime_config.read().middleware.clone();
+        for name in &middleware {
+            let func = instance.get_func(&mut store, name).ok_or_else(|| {
+                RuntimeError::wasm(format!("Could not find middleware '{}'", name))
+            })?;
+            let mut results = vec![wasmtime::Val::I32(0); func.ty(&store).results().len()];
+            catch_host_panic(name, || func.call(&mut store, &[], &mut results))
+                .map_err(|e| classify_handler_error(name, self.memory_limit, e))?;
+        }
+
         debug!("Calling handler with auth: {}", handler_name);
 
+        let mut binary_body = None;
         let result =
             if let Ok(handler) = instance.get_typed_func::<(), i32>(&mut store, handler_name) {
-                let result_ptr = handler
-                    .call(&mut store, ())
-                    .map_err(|e| classify_handler_error(handler_name, e))?;
+                let result_ptr = catch_host_panic(handler_name, || handler.call(&mut store, ()))
+                    .map_err(|e| classify_handler_error(handler_name, self.memory_limit, e))?;
 
                 // When the handler signalled a redirect via `_http_redirect` /
                 // `_res_redirect`, its i32 return value is not guaranteed to be a
@@ -1130,6 +1775,20 @@ impl WasmInstance {
                 // turns the intended 302 into a 500.
                 if store.data().pending_redirect.is_some() {
                     String::new()
+                } else if store.data().pending_binary {
+                    // `_res_set_binary`: same length-prefixed layout, but the
+                    // payload is sent as-is without UTF-8 validation.
+                    let len = crate::memory::read_i32(&store, &memory, result_ptr as u32)?;
+                    let len = u32::try_from(len).map_err(|_| {
+                        RuntimeError::memory(format!("Negative binary body length {}", len))
+                    })?;
+                    binary_body = Some(crate::memory::read_bytes_from_memory(
+                        &store,
+                        &memory,
+                        result_ptr as u32 + crate::memory::STRING_LENGTH_PREFIX_SIZE as u32,
+                        len,
+                    )?);
+                    String::new()
                 } else {
                     crate::memory::read_string_from_memory(&store, &memory, result_ptr as u32)?
                 }
@@ -1146,6 +1805,7 @@ impl WasmInstance {
         let redirect = store.data_mut().take_pending_redirect();
         let status = store.data_mut().take_pending_status();
         let head_links = store.data_mut().take_pending_head_links();
+        let tasks = std::mem::take(&mut store.data_mut().spawned_tasks);
 
         Ok(HandlerResponse {
             body: result,
@@ -1154,6 +1814,9 @@ impl WasmInstance {
             redirect,
             status,
             head_links,
+            binary_body,
+            tasks,
+            db_time: store.data().db_time,
         })
     }
 
@@ -1175,13 +1838,15 @@ impl WasmInstance {
     ) -> RuntimeResult<()> {
         let (mut store, instance) = self.create_instance()?;
         store.data_mut().set_request(request);
+        store.data_mut().reentry_token = Some(self.reentry_token.clone());
+        store.data_mut().server_addr = *self.server_addr.lock();
         if let Some(auth) = auth_context {
             store.data_mut().auth_context = Some(auth);
         }
         store.data_mut().sse_sender = Some(sse_tx);
 
         if let Ok(handler) = instance.get_typed_func::<(), i32>(&mut store, handler_name) {
-            let _ = handler.call(&mut store, ()).map_err(|e| {
+            let _ = catch_host_panic(handler_name, || handler.call(&mut store, ())).map_err(|e| {
                 RuntimeError::wasm(format!("SSE handler {} failed: {}", handler_name, e))
             });
         } else {
@@ -1225,17 +1890,18 @@ impl WasmInstance {
         // WebSocket handlers may export as () -> i32 or () -> ().
         // Try both signatures; the return value is discarded.
         if let Ok(handler) = instance.get_typed_func::<(), i32>(&mut store, handler_name) {
-            let _ = handler.call(&mut store, ()).map_err(|e| {
-                crate::error::RuntimeError::wasm(format!(
-                    "WebSocket handler {} failed: {}",
-                    handler_name, e
-                ))
-            })?;
+            let _ =
+                catch_host_panic(handler_name, || handler.call(&mut store, ())).map_err(|e| {
+                    crate::error::RuntimeError::wasm(format!(
+                        "WebSocket handler {} failed: {}",
+                        handler_name, e
+                    ))
+                })?;
             return Ok(());
         }
 
         if let Ok(handler) = instance.get_typed_func::<(), ()>(&mut store, handler_name) {
-            handler.call(&mut store, ()).map_err(|e| {
+            catch_host_panic(handler_name, || handler.call(&mut store, ())).map_err(|e| {
                 crate::error::RuntimeError::wasm(format!(
                     "WebSocket handler {} failed: {}",
                     handler_name, e
@@ -1282,17 +1948,18 @@ impl WasmInstance {
         // Job handlers may export as () -> i32 or () -> ().
         // Try both signatures; the return value is discarded.
         if let Ok(handler) = instance.get_typed_func::<(), i32>(&mut store, handler_name) {
-            let _ = handler.call(&mut store, ()).map_err(|e| {
-                crate::error::RuntimeError::wasm(format!(
-                    "Job handler {} failed: {}",
-                    handler_name, e
-                ))
-            })?;
+            let _ =
+                catch_host_panic(handler_name, || handler.call(&mut store, ())).map_err(|e| {
+                    crate::error::RuntimeError::wasm(format!(
+                        "Job handler {} failed: {}",
+                        handler_name, e
+                    ))
+                })?;
             return Ok(());
         }
 
         if let Ok(handler) = instance.get_typed_func::<(), ()>(&mut store, handler_name) {
-            handler.call(&mut store, ()).map_err(|e| {
+            catch_host_panic(handler_name, || handler.call(&mut store, ())).map_err(|e| {
                 crate::error::RuntimeError::wasm(format!(
                     "Job handler {} failed: {}",
                     handler_name, e
@@ -1321,6 +1988,24 @@ impl WasmInstance {
     }
 }
 
+/// Host functions read strings and write results through the module's
+/// exported `memory`; without it every bridge call would quietly return 0 or
+/// an empty string, so such a module is rejected when it loads.
+fn check_memory_export(module: &Module) -> RuntimeResult<()> {
+    match module.get_export("memory") {
+        Some(ExternType::Memory(_)) => Ok(()),
+        Some(other) => Err(RuntimeError::wasm(format!(
+            "Module exports \"memory\" as {:?}, not a memory. Host functions exchange \
+             strings through the module's linear memory, so it must export it as \"memory\"",
+            other
+        ))),
+        None => Err(RuntimeError::wasm(
+            "Module does not export \"memory\". Host functions exchange strings through \
+             the module's linear memory, so it must export it as \"memory\"",
+        )),
+    }
+}
+
 /// Shared WASM instance wrapped in Arc
 pub type SharedWasmInstance = Arc<WasmInstance>;
 
@@ -1343,15 +2028,26 @@ pub fn create_shared_instance_with_db(
     Ok(Arc::new(instance))
 }
 
-/// Create a shared WASM instance with a database bridge and memory limit
+/// Create a shared WASM instance with a database bridge, session store and
+/// memory limit
 pub fn create_shared_instance_with_config(
     wasm_path: &Path,
     router: SharedRouter,
     db_bridge: SharedDbBridge,
+    session_store: SharedSessionStore,
     memory_limit: usize,
+    module_cache_dir: Option<&Path>,
+    diag_dir: Option<&Path>,
 ) -> RuntimeResult<SharedWasmInstance> {
-    let instance =
-        WasmInstance::load_with_db_and_limit(wasm_path, router, db_bridge, memory_limit)?;
+    let instance = WasmInstance::load_with_module_cache(
+        wasm_path,
+        router,
+        db_bridge,
+        session_store,
+        memory_limit,
+        module_cache_dir,
+        diag_dir,
+    )?;
     Ok(Arc::new(instance))
 }
 
@@ -1375,6 +2071,9 @@ mod tests {
             body_bytes: None,
             params: std::collections::HashMap::new(),
             query: std::collections::HashMap::new(),
+            raw_query: String::new(),
+            client: None,
+            route_pattern: None,
         };
 
         state.set_request(request);
@@ -1400,6 +2099,9 @@ mod tests {
             body_bytes: None,
             params,
             query,
+            raw_query: "page=1".to_string(),
+            client: None,
+            route_pattern: None,
         };
 
         assert_eq!(request.method, "GET");
@@ -1407,6 +2109,181 @@ mod tests {
         assert_eq!(request.query.get("page"), Some(&"1".to_string()));
     }
 
+    #[test]
+    fn test_request_context_builder() {
+        let request = RequestContext::builder()
+            .method("post")
+            .path("/users/123")
+            .param("id", "123")
+            .query("page", "2")
+            .header("Content-Type", "application/json")
+            .body("{}")
+            .build()
+            .unwrap();
+
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/users/123");
+        assert_eq!(request.params.get("id"), Some(&"123".to_string()));
+        assert_eq!(request.query.get("page"), Some(&"2".to_string()));
+        assert_eq!(
+            request.headers,
+            vec![("Content-Type".to_string(), "application/json".to_string())]
+        );
+        assert_eq!(request.body, "{}");
+        assert!(request.body_bytes.is_none());
+
+        let defaults = RequestContext::builder().build().unwrap();
+        assert_eq!(defaults.method, "GET");
+        assert_eq!(defaults.path, "/");
+    }
+
+    #[test]
+    fn test_request_context_builder_rejects_invalid_input() {
+        assert!(RequestContext::builder().method("BREW").build().is_err());
+        assert!(RequestContext::builder().path("users").build().is_err());
+        assert!(
+            RequestContext::builder()
+                .path("/users?page=2")
+                .build()
+                .is_err()
+        );
+        assert!(
+            RequestContext::builder()
+                .header("Bad Header", "x")
+                .build()
+                .is_err()
+        );
+        assert!(
+            RequestContext::builder()
+                .header("X-Ok", "line\nbreak")
+                .build()
+                .is_err()
+        );
+    }
+
+    /// Handlers echo `_req_param("id")` and `_req_query("page")`.
+    const REQ_ECHO_WAT: &str = r#"
+        (module
+          (import "env" "_req_param" (func $param (param i32 i32) (result i32)))
+          (import "env" "_req_query" (func $query (param i32 i32) (result i32)))
+          (memory (export "memory") 2)
+          (global $heap (mut i32) (i32.const 65536))
+          (global (export "__heap_ptr") (mut i32) (i32.const 65536))
+          (data (i32.const 1024) "id")
+          (data (i32.const 1032) "page")
+          (func (export "malloc") (param $size i32) (result i32)
+            (local $ptr i32)
+            (local.set $ptr (global.get $heap))
+            (global.set $heap
+              (i32.and
+                (i32.add (i32.add (global.get $heap) (local.get $size)) (i32.const 7))
+                (i32.const -8)))
+            (global.set 1 (global.get $heap))
+            (local.get $ptr))
+          (func (export "echo_param") (result i32)
+            (call $param (i32.const 1024) (i32.const 2)))
+          (func (export "echo_query") (result i32)
+            (call $query (i32.const 1032) (i32.const 4))))
+    "#;
+
+    #[test]
+    fn test_request_context_builder_feeds_req_host_functions() {
+        let wasm_bytes = wat::parse_str(REQ_ECHO_WAT).unwrap();
+        let instance = WasmInstance::from_bytes(&wasm_bytes, create_shared_router()).unwrap();
+        let request = || {
+            RequestContext::builder()
+                .path("/users/123")
+                .param("id", "123")
+                .query("page", "2")
+                .build()
+                .unwrap()
+        };
+
+        assert_eq!(
+            instance.call_handler("echo_param", request()).unwrap(),
+            "123"
+        );
+        assert_eq!(instance.call_handler("echo_query", request()).unwrap(), "2");
+
+        let empty = RequestContext::builder().build().unwrap();
+        assert_eq!(instance.call_handler("echo_param", empty).unwrap(), "");
+    }
+
+    /// `created` sets status 201 and an `X-Id` header, then echoes the
+    /// `name` path param as the body.
+    const RES_STRUCTURED_WAT: &str = r#"
+        (module
+          (import "env" "_req_param" (func $param (param i32 i32) (result i32)))
+          (import "env" "_res_status" (func $status (param i32)))
+          (import "env" "_res_set_header"
+            (func $set_header (param i32 i32 i32 i32) (result i32)))
+          (memory (export "memory") 2)
+          (global $heap (mut i32) (i32.const 65536))
+          (global (export "__heap_ptr") (mut i32) (i32.const 65536))
+          (data (i32.const 1024) "name")
+          (data (i32.const 1032) "X-Id")
+          (data (i32.const 1040) "42")
+          (func (export "malloc") (param $size i32) (result i32)
+            (local $ptr i32)
+            (local.set $ptr (global.get $heap))
+            (global.set $heap
+              (i32.and
+                (i32.add (i32.add (global.get $heap) (local.get $size)) (i32.const 7))
+                (i32.const -8)))
+            (global.set 1 (global.get $heap))
+            (local.get $ptr))
+          (func (export "created") (result i32)
+            (call $status (i32.const 201))
+            (drop (call $set_header
+              (i32.const 1032) (i32.const 4) (i32.const 1040) (i32.const 2)))
+            (call $param (i32.const 1024) (i32.const 4))))
+    "#;
+
+    #[test]
+    fn test_call_handler_with_auth_returns_structured_response() {
+        let wasm_bytes = wat::parse_str(RES_STRUCTURED_WAT).unwrap();
+        let instance = WasmInstance::from_bytes(&wasm_bytes, create_shared_router()).unwrap();
+        let request = RequestContext::builder()
+            .method("POST")
+            .path("/items/ada")
+            .param("name", "ada")
+            .build()
+            .unwrap();
+
+        let response = instance
+            .call_handler_with_auth("created", request.clone(), None)
+            .unwrap();
+        assert_eq!(response.status, Some(201));
+        assert_eq!(response.body, "ada");
+        assert!(
+            response
+                .headers
+                .iter()
+                .any(|(name, value)| name == "X-Id" && value == "42")
+        );
+        assert!(response.redirect.is_none());
+        assert!(response.binary_body.is_none());
+
+        // Each call starts from a fresh instance with no leftover response
+        // state.
+        let again = instance
+            .call_handler_with_auth("created", request.clone(), None)
+            .unwrap();
+        assert_eq!(again.headers.len(), response.headers.len());
+    }
+
+    #[test]
+    fn test_call_handler_with_auth_unknown_handler_errors() {
+        let wasm_bytes = wat::parse_str(RES_STRUCTURED_WAT).unwrap();
+        let instance = WasmInstance::from_bytes(&wasm_bytes, create_shared_router()).unwrap();
+        let request = RequestContext::builder().build().unwrap();
+
+        let err = instance
+            .call_handler_with_auth("missing", request, None)
+            .unwrap_err();
+        assert!(err.to_string().contains("missing"), "{}", err);
+    }
+
     // Regression: SERVER-TRAP-KIND-STRIPPED. wasmtime::Error's Display strips
     // the Trap kind; format_wasm_error must surface it so operators can tell
     // an OOB apart from a stack overflow without a debugger.
@@ -1441,11 +2318,98 @@ mod tests {
     #[test]
     fn classify_handler_error_includes_trap_kind_for_generic_traps() {
         let err: wasmtime::Error = Trap::UnreachableCodeReached.into();
-        let runtime = classify_handler_error("my_handler", err);
+        let runtime = classify_handler_error("my_handler", DEFAULT_MEMORY_LIMIT, err);
         let msg = format!("{runtime}");
         assert!(
             msg.contains("my_handler") && msg.to_lowercase().contains("unreachable"),
             "expected handler error to name both the handler and the trap kind, got: {msg}"
         );
     }
+
+    #[test]
+    fn classify_handler_error_reports_the_instance_memory_limit() {
+        let err: wasmtime::Error = Trap::MemoryOutOfBounds.into();
+        let runtime = classify_handler_error("my_handler", 3 * 1024 * 1024, err);
+        let msg = format!("{runtime}");
+        assert!(
+            msg.contains("3 MB WASM memory limit"),
+            "expected the configured limit in the error, got: {msg}"
+        );
+    }
+
+    #[test]
+    fn modules_without_a_memory_export_fail_to_load() {
+        let load = |wat: &str| {
+            WasmInstance::from_bytes(&wat::parse_str(wat).unwrap(), create_shared_router())
+                .err()
+                .expect("module should be rejected")
+                .to_string()
+        };
+
+        let msg = load(r#"(module (func (export "main")))"#);
+        assert!(msg.contains("does not export \"memory\""), "{msg}");
+        // Unexported, or exported under another name, is the same to the host
+        let msg = load(r#"(module (memory (export "mem") 1) (func (export "main")))"#);
+        assert!(msg.contains("does not export \"memory\""), "{msg}");
+        let msg = load(r#"(module (global (export "memory") i32 (i32.const 0)))"#);
+        assert!(msg.contains("not a memory"), "{msg}");
+
+        assert!(
+            WasmInstance::from_bytes(
+                &wat::parse_str(r#"(module (memory (export "memory") 1))"#).unwrap(),
+                create_shared_router(),
+            )
+            .is_ok()
+        );
+    }
+
+    #[test]
+    fn check_imports_lists_every_missing_import() {
+        let wasm = WasmInstance::from_bytes(
+            &wat::parse_str(
+                r#"(module
+                  (import "env" "_no_such_one" (func (param i32 i32) (result i32)))
+                  (import "env" "_req_body_len" (func (result i32)))
+                  (import "env" "_no_such_two" (func))
+                  (memory (export "memory") 1))"#,
+            )
+            .unwrap(),
+            create_shared_router(),
+        )
+        .unwrap();
+
+        let msg = wasm.check_imports().unwrap_err().to_string();
+        assert!(msg.contains("2 unsatisfied import"), "{msg}");
+        assert!(
+            msg.contains("env._no_such_one: (type (func (param i32 i32) (result i32)))"),
+            "{msg}"
+        );
+        assert!(msg.contains("env._no_such_two: (type (func))"), "{msg}");
+        assert!(!msg.contains("_req_body_len"), "{msg}");
+        // `initialize` runs the same check before instantiating.
+        assert!(wasm.initialize().is_err());
+    }
+
+    #[test]
+    fn check_imports_reports_signature_mismatches() {
+        let wasm = WasmInstance::from_bytes(
+            &wat::parse_str(
+                r#"(module
+                  (import "env" "_req_body_len" (func (param i32) (result i32)))
+                  (memory (export "memory") 1))"#,
+            )
+            .unwrap(),
+            create_shared_router(),
+        )
+        .unwrap();
+
+        let msg = wasm.check_imports().unwrap_err().to_string();
+        assert!(
+            msg.contains(
+                "env._req_body_len: (type (func (param i32) (result i32))) \
+                 (server provides (type (func (result i32))))"
+            ),
+            "{msg}"
+        );
+    }
 }
@@ -418,7 +418,10 @@ const ACTIVITY_DEADLINE: Duration = Duration::from_secs(60);
 /// - After 60 seconds of no inbound activity (Text, Ping, or Pong), the
 ///   client is closed with code 1001 (Going Away) and its `onClose` handler
 ///   is invoked.
-pub fn start_heartbeat_task(ws_state: SharedWsState, wasm: Arc<WasmInstance>) {
+pub fn start_heartbeat_task(
+    ws_state: SharedWsState,
+    wasm: Arc<WasmInstance>,
+) -> tokio::task::JoinHandle<()> {
     tokio::spawn(async move {
         let mut interval = tokio::time::interval(PING_INTERVAL);
         // The first tick fires immediately — skip it so we do not ping before
@@ -494,6 +497,9 @@ pub fn start_heartbeat_task(ws_state: SharedWsState, wasm: Arc<WasmInstance>) {
                                     body_bytes: None,
                                     params: std::collections::HashMap::new(),
                                     query: std::collections::HashMap::new(),
+                                    raw_query: String::new(),
+                                    client: None,
+                                    route_pattern: None,
                                 };
                                 let _ = wasm_clone.call_handler_ws(&h_name, req, None, client_id);
                             })
@@ -502,7 +508,7 @@ pub fn start_heartbeat_task(ws_state: SharedWsState, wasm: Arc<WasmInstance>) {
                 }
             }
         }
-    });
+    })
 }
 
 // ---------------------------------------------------------------------------
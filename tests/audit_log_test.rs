@@ -0,0 +1,121 @@
+//! `ServerConfig::audit_log` audit trail.
+//!
+//! The fixture's handler performs two `_db_execute` writes (one binding a
+//! secret value) and a `_crypto_hash_password`; the audit file must record
+//! each with the request id and without the secrets.
+
+#![cfg(feature = "sqlite")]
+
+use axum::http::Method;
+use clean_server::ServerConfig;
+use clean_server::testing::{TestServer, with_malloc};
+use serde_json::Value;
+
+/// `POST /signup` -> `signup`: creates `accounts`, inserts `["s3cret-value"]`
+/// and hashes the password `hunter2`, then returns "ok".
+const FIXTURE_WAT: &str = r#"
+(module
+  (import "env" "_http_route"
+    (func $route (param i32 i32 i32 i32 i32 i32) (result i32)))
+  (import "env" "_db_execute" (func $execute (param i32 i32 i32 i32) (result i32)))
+  (import "env" "_crypto_hash_password" (func $hash (param i32 i32) (result i32)))
+  (memory (export "memory") 2)
+  (data (i32.const 1024) "\02\00\00\00ok")
+  (data (i32.const 2048) "POST")
+  (data (i32.const 2056) "/signup")
+  (data (i32.const 2064) "signup")
+  (data (i32.const 2072) "hunter2")
+  (data (i32.const 2080) "[\22s3cret-value\22]")
+  (data (i32.const 2112) "CREATE TABLE accounts (api_token TEXT)")
+  (data (i32.const 2160) "INSERT INTO accounts (api_token) VALUES (?)")
+  (func (export "main")
+    (drop (call $route (i32.const 2048) (i32.const 4)
+      (i32.const 2056) (i32.const 7) (i32.const 2064) (i32.const 6))))
+  (func (export "signup") (result i32)
+    (drop (call $execute (i32.const 2112) (i32.const 38) (i32.const 0) (i32.const 0)))
+    (drop (call $execute (i32.const 2160) (i32.const 43) (i32.const 2080) (i32.const 16)))
+    (drop (call $hash (i32.const 2072) (i32.const 7)))
+    (i32.const 1024)))
+"#;
+
+fn read_entries(path: &std::path::Path) -> Vec<Value> {
+    std::fs::read_to_string(path)
+        .expect("audit log should exist")
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("audit line should be JSON"))
+        .collect()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn db_execute_and_password_hash_are_audited_with_secrets_redacted() {
+    let audit_dir = tempfile::tempdir().unwrap();
+    let audit_path = audit_dir.path().join("audit.log");
+    let config = ServerConfig {
+        database_url: Some("sqlite::memory:".to_string()),
+        ..ServerConfig::default()
+    }
+    .with_audit_log(&audit_path);
+    let server = TestServer::from_wat(&with_malloc(FIXTURE_WAT), config)
+        .await
+        .unwrap();
+
+    let response = server
+        .request(Method::POST, "/signup", &[("x-request-id", "req-42")], "")
+        .await
+        .unwrap();
+    assert_eq!(response.status, 200);
+
+    let raw = std::fs::read_to_string(&audit_path).unwrap();
+    assert!(!raw.contains("hunter2"), "password leaked: {}", raw);
+    assert!(!raw.contains("s3cret-value"), "bound value leaked: {}", raw);
+
+    let entries = read_entries(&audit_path);
+    let ops: Vec<String> = entries
+        .iter()
+        .map(|e| format!("{}.{}", e["namespace"], e["function"]).replace('"', ""))
+        .collect();
+    assert_eq!(
+        ops,
+        vec!["db.execute", "db.execute", "crypto.hash_password"]
+    );
+    for entry in &entries {
+        assert_eq!(entry["request_id"], "req-42");
+        assert!(entry["ts"].as_str().is_some_and(|ts| !ts.is_empty()));
+    }
+
+    assert_eq!(
+        entries[1]["params"]["sql"],
+        "INSERT INTO accounts (api_token) VALUES (?)"
+    );
+    assert_eq!(entries[1]["params"]["param_count"], 1);
+    assert_eq!(entries[2]["params"]["password"], "[REDACTED]");
+    assert_eq!(entries[2]["params"]["algorithm"], "bcrypt");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn only_configured_operations_are_audited() {
+    let audit_dir = tempfile::tempdir().unwrap();
+    let audit_path = audit_dir.path().join("audit.log");
+    let config = ServerConfig {
+        database_url: Some("sqlite::memory:".to_string()),
+        ..ServerConfig::default()
+    }
+    .with_audit_log(&audit_path)
+    .with_audit_operations(vec!["crypto.*".to_string()]);
+    let server = TestServer::from_wat(&with_malloc(FIXTURE_WAT), config)
+        .await
+        .unwrap();
+
+    let response = server.post("/signup", "text/plain", "").await.unwrap();
+    assert_eq!(response.status, 200);
+
+    let entries = read_entries(&audit_path);
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0]["function"], "hash_password");
+    // Without an X-Request-Id header the server generates one.
+    assert!(
+        entries[0]["request_id"]
+            .as_str()
+            .is_some_and(|id| id.len() == 36)
+    );
+}
@@ -0,0 +1,100 @@
+//! `_auth_can` checks the permissions the auth provider put on the
+//! `AuthContext`, not the user's role name.
+
+use clean_server::ServerConfig;
+use clean_server::auth::AuthConfig;
+use clean_server::testing::{TestResponse, TestServer, with_malloc};
+use jsonwebtoken::{EncodingKey, Header};
+
+const SECRET: &str = "test-secret";
+
+/// Routes:
+/// - `GET /can` -> `can`: "yes" if `_auth_can("posts.write")`, else "no"
+const FIXTURE_WAT: &str = r#"
+(module
+  (import "env" "_http_route"
+    (func $route (param i32 i32 i32 i32 i32 i32) (result i32)))
+  (import "env" "_auth_can" (func $can (param i32 i32) (result i32)))
+  (memory (export "memory") 2)
+  (data (i32.const 1024) "\03\00\00\00yes")
+  (data (i32.const 1040) "\02\00\00\00no")
+  (data (i32.const 2048) "GET")
+  (data (i32.const 2056) "/can")
+  (data (i32.const 2064) "can")
+  (data (i32.const 2072) "posts.write")
+  (func (export "main")
+    (drop (call $route (i32.const 2048) (i32.const 3)
+      (i32.const 2056) (i32.const 4) (i32.const 2064) (i32.const 3))))
+  (func (export "can") (result i32)
+    (select (i32.const 1024) (i32.const 1040)
+      (call $can (i32.const 2072) (i32.const 11)))))
+"#;
+
+fn jwt_config() -> ServerConfig {
+    ServerConfig {
+        database_url: None,
+        ..ServerConfig::default()
+    }
+    .with_auth(AuthConfig::JwtSecret(SECRET.to_string()))
+}
+
+async fn can_as(server: &TestServer, role: &str, permissions: &[&str]) -> TestResponse {
+    let claims = serde_json::json!({
+        "sub": 1,
+        "role": role,
+        "permissions": permissions,
+        "exp": chrono::Utc::now().timestamp() + 3600,
+    });
+    let token = jsonwebtoken::encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(SECRET.as_bytes()),
+    )
+    .unwrap();
+    server
+        .request(
+            axum::http::Method::GET,
+            "/can",
+            &[("authorization", &format!("Bearer {}", token))],
+            "",
+        )
+        .await
+        .unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn explicit_permission_passes() {
+    let server = TestServer::from_wat(&with_malloc(FIXTURE_WAT), jwt_config())
+        .await
+        .unwrap();
+
+    let response = can_as(&server, "member", &["posts.read", "posts.write"]).await;
+    assert_eq!(response.status, 200, "body: {}", response.text());
+    assert_eq!(response.text(), "yes");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn missing_permission_fails_regardless_of_role() {
+    let server = TestServer::from_wat(&with_malloc(FIXTURE_WAT), jwt_config())
+        .await
+        .unwrap();
+
+    assert_eq!(
+        can_as(&server, "member", &["posts.read"]).await.text(),
+        "no"
+    );
+    assert_eq!(can_as(&server, "admin", &[]).await.text(), "no");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn unauthenticated_request_cannot() {
+    let server = TestServer::from_wat(&with_malloc(FIXTURE_WAT), jwt_config())
+        .await
+        .unwrap();
+
+    let response = server
+        .request(axum::http::Method::GET, "/can", &[], "")
+        .await
+        .unwrap();
+    assert_eq!(response.text(), "no");
+}
@@ -0,0 +1,123 @@
+//! Automatic `OPTIONS` responses.
+//!
+//! For a path with registered routes and no OPTIONS route of its own, the
+//! server answers 204 with an `Allow` header listing the registered methods.
+//! A module-registered OPTIONS handler takes precedence.
+
+use axum::http::Method;
+use clean_server::ServerConfig;
+use clean_server::testing::TestServer;
+
+/// Routes:
+/// - `GET /items`, `POST /items` -> `ok`
+/// - `GET /items/:id`            -> `ok`
+/// - `GET /custom`               -> `ok`
+/// - `OPTIONS /custom`           -> `custom`: returns "custom options"
+const FIXTURE_WAT: &str = r#"
+(module
+  (import "env" "_http_route"
+    (func $route (param i32 i32 i32 i32 i32 i32) (result i32)))
+  (memory (export "memory") 2)
+  (global (export "__heap_ptr") i32 (i32.const 65536))
+  (data (i32.const 1024) "\02\00\00\00ok")
+  (data (i32.const 1040) "\0e\00\00\00custom options")
+  (data (i32.const 2048) "GET")
+  (data (i32.const 2056) "POST")
+  (data (i32.const 2064) "OPTIONS")
+  (data (i32.const 2080) "/items")
+  (data (i32.const 2096) "/items/:id")
+  (data (i32.const 2112) "/custom")
+  (data (i32.const 2128) "ok")
+  (data (i32.const 2136) "custom")
+  (func (export "main")
+    (drop (call $route (i32.const 2048) (i32.const 3)
+      (i32.const 2080) (i32.const 6) (i32.const 2128) (i32.const 2)))
+    (drop (call $route (i32.const 2056) (i32.const 4)
+      (i32.const 2080) (i32.const 6) (i32.const 2128) (i32.const 2)))
+    (drop (call $route (i32.const 2048) (i32.const 3)
+      (i32.const 2096) (i32.const 10) (i32.const 2128) (i32.const 2)))
+    (drop (call $route (i32.const 2048) (i32.const 3)
+      (i32.const 2112) (i32.const 7) (i32.const 2128) (i32.const 2)))
+    (drop (call $route (i32.const 2064) (i32.const 7)
+      (i32.const 2112) (i32.const 7) (i32.const 2136) (i32.const 6))))
+  (func (export "ok") (result i32)
+    (i32.const 1024))
+  (func (export "custom") (result i32)
+    (i32.const 1040)))
+"#;
+
+fn fixture_config() -> ServerConfig {
+    ServerConfig {
+        database_url: None,
+        ..ServerConfig::default()
+    }
+}
+
+async fn options(server: &TestServer, path: &str) -> clean_server::testing::TestResponse {
+    server
+        .request(Method::OPTIONS, path, &[], "")
+        .await
+        .unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn options_reports_registered_methods() {
+    let server = TestServer::from_wat(FIXTURE_WAT, fixture_config())
+        .await
+        .unwrap();
+
+    let response = options(&server, "/items").await;
+    assert_eq!(response.status, 204);
+    assert_eq!(response.header("allow"), Some("GET, POST, OPTIONS"));
+    assert!(response.body.is_empty());
+
+    let response = options(&server, "/items/7").await;
+    assert_eq!(response.status, 204);
+    assert_eq!(response.header("allow"), Some("GET, OPTIONS"));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn options_for_unknown_path_is_404() {
+    let server = TestServer::from_wat(FIXTURE_WAT, fixture_config())
+        .await
+        .unwrap();
+
+    let response = options(&server, "/missing").await;
+    assert_eq!(response.status, 404);
+    assert_eq!(response.header("allow"), None);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn explicit_options_handler_takes_precedence() {
+    let server = TestServer::from_wat(FIXTURE_WAT, fixture_config())
+        .await
+        .unwrap();
+
+    let response = options(&server, "/custom").await;
+    assert_eq!(response.status, 200);
+    assert_eq!(response.text(), "custom options");
+    assert_eq!(response.header("allow"), None);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn cors_preflight_is_still_answered_by_cors() {
+    let server = TestServer::from_wat(FIXTURE_WAT, fixture_config())
+        .await
+        .unwrap();
+
+    let response = server
+        .request(
+            Method::OPTIONS,
+            "/items",
+            &[
+                ("origin", "https://example.com"),
+                ("access-control-request-method", "POST"),
+            ],
+            "",
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status, 200);
+    assert_eq!(response.header("access-control-allow-origin"), Some("*"));
+    assert_eq!(response.header("allow"), None);
+}
@@ -0,0 +1,95 @@
+//! `ServerConfig.auth`: bearer tokens verified by the configured provider
+//! authenticate the request before dispatch.
+
+use clean_server::ServerConfig;
+use clean_server::auth::AuthConfig;
+use clean_server::testing::{TestResponse, TestServer, with_malloc};
+use jsonwebtoken::{EncodingKey, Header};
+
+const SECRET: &str = "test-secret";
+
+/// Routes:
+/// - `GET /me` -> `me` (protected): returns `_auth_get_session`
+const FIXTURE_WAT: &str = r#"
+(module
+  (import "env" "_http_route_protected"
+    (func $route (param i32 i32 i32 i32 i32 i32 i32 i32) (result i32)))
+  (import "env" "_auth_get_session" (func $session (result i32)))
+  (memory (export "memory") 2)
+  (data (i32.const 2048) "GET")
+  (data (i32.const 2056) "/me")
+  (data (i32.const 2064) "me")
+  (func (export "main")
+    (drop (call $route (i32.const 2048) (i32.const 3)
+      (i32.const 2056) (i32.const 3) (i32.const 2064) (i32.const 2)
+      (i32.const 0) (i32.const 0))))
+  (func (export "me") (result i32)
+    (call $session)))
+"#;
+
+fn jwt_config() -> ServerConfig {
+    ServerConfig {
+        database_url: None,
+        ..ServerConfig::default()
+    }
+    .with_auth(AuthConfig::JwtSecret(SECRET.to_string()))
+}
+
+fn token(sub: i32, exp_offset_secs: i64) -> String {
+    let claims = serde_json::json!({
+        "sub": sub.to_string(),
+        "role": "admin",
+        "exp": chrono::Utc::now().timestamp() + exp_offset_secs,
+    });
+    jsonwebtoken::encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(SECRET.as_bytes()),
+    )
+    .unwrap()
+}
+
+async fn get_me(server: &TestServer, authorization: &str) -> TestResponse {
+    server
+        .request(
+            axum::http::Method::GET,
+            "/me",
+            &[("authorization", authorization)],
+            "",
+        )
+        .await
+        .unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn valid_jwt_authenticates_the_request() {
+    let server = TestServer::from_wat(&with_malloc(FIXTURE_WAT), jwt_config())
+        .await
+        .unwrap();
+
+    let response = get_me(&server, &format!("Bearer {}", token(42, 3600))).await;
+    assert_eq!(response.status, 200, "body: {}", response.text());
+    let session = response.json().expect("session JSON");
+    assert_eq!(session["user_id"], 42);
+    assert_eq!(session["role"], "admin");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn invalid_or_expired_jwt_is_401() {
+    let server = TestServer::from_wat(&with_malloc(FIXTURE_WAT), jwt_config())
+        .await
+        .unwrap();
+
+    let expired = format!("Bearer {}", token(42, -3600));
+    let mut tampered = token(42, 3600);
+    tampered.push('x');
+    for authorization in [
+        expired.as_str(),
+        &format!("Bearer {}", tampered),
+        "Bearer not-a-jwt",
+        "Basic YWRhOnNlY3JldA==",
+    ] {
+        let response = get_me(&server, authorization).await;
+        assert_eq!(response.status, 401, "authorization: {}", authorization);
+    }
+}
@@ -0,0 +1,72 @@
+//! `ServerConfig.clock`: a fixed clock pins the time the `_time_*` bridges
+//! report, so time-dependent handlers are deterministic.
+
+use clean_server::ServerConfig;
+use clean_server::clock::Clock;
+use clean_server::testing::{TestServer, with_malloc};
+
+/// Routes:
+/// - `GET /now` -> `now`: returns `_time_now()` as text
+/// - `GET /iso` -> `iso`: returns `_time_iso()`
+const FIXTURE_WAT: &str = r#"
+(module
+  (import "env" "_http_route"
+    (func $route (param i32 i32 i32 i32 i32 i32) (result i32)))
+  (import "env" "_time_now" (func $time_now (result i64)))
+  (import "env" "_time_iso" (func $time_iso (result i32)))
+  (import "env" "int_to_string" (func $to_string (param i32) (result i32)))
+  (memory (export "memory") 2)
+  (data (i32.const 2048) "GET")
+  (data (i32.const 2056) "/now")
+  (data (i32.const 2064) "now")
+  (data (i32.const 2072) "/iso")
+  (data (i32.const 2080) "iso")
+  (func (export "main")
+    (drop (call $route (i32.const 2048) (i32.const 3)
+      (i32.const 2056) (i32.const 4) (i32.const 2064) (i32.const 3)))
+    (drop (call $route (i32.const 2048) (i32.const 3)
+      (i32.const 2072) (i32.const 4) (i32.const 2080) (i32.const 3))))
+  (func (export "now") (result i32)
+    (call $to_string (i32.wrap_i64 (call $time_now))))
+  (func (export "iso") (result i32)
+    (call $time_iso)))
+"#;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn fixed_clock_pins_time_now() {
+    // 2023-11-14T22:13:20Z
+    let config = ServerConfig {
+        database_url: None,
+        ..ServerConfig::default()
+    }
+    .with_clock(Clock::fixed_at_unix_secs(1_700_000_000));
+    let server = TestServer::from_wat(&with_malloc(FIXTURE_WAT), config)
+        .await
+        .unwrap();
+
+    for _ in 0..2 {
+        let response = server.get("/now").await.unwrap();
+        assert_eq!(response.status, 200, "body: {}", response.text());
+        assert_eq!(response.text(), "1700000000");
+    }
+    let response = server.get("/iso").await.unwrap();
+    assert_eq!(response.text(), "2023-11-14T22:13:20.000Z");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn system_clock_is_the_default() {
+    let config = ServerConfig {
+        database_url: None,
+        ..ServerConfig::default()
+    };
+    let server = TestServer::from_wat(&with_malloc(FIXTURE_WAT), config)
+        .await
+        .unwrap();
+
+    let before = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let now: u64 = server.get("/now").await.unwrap().text().parse().unwrap();
+    assert!(now >= before && now <= before + 5, "{now} vs {before}");
+}
@@ -0,0 +1,90 @@
+//! `ServerConfig.allowed_request_content_types`: request bodies of any other
+//! media type are answered 415 before the WASM handler runs.
+
+use clean_server::ServerConfig;
+use clean_server::testing::{TestResponse, TestServer, with_malloc};
+
+/// Routes:
+/// - `POST /echo` -> `echo`: returns the request body
+/// - `GET /echo`  -> `echo`
+const FIXTURE_WAT: &str = r#"
+(module
+  (import "env" "_http_route"
+    (func $route (param i32 i32 i32 i32 i32 i32) (result i32)))
+  (import "env" "_req_body" (func $req_body (result i32)))
+  (memory (export "memory") 2)
+  (data (i32.const 2048) "POST")
+  (data (i32.const 2056) "GET")
+  (data (i32.const 2064) "/echo")
+  (data (i32.const 2072) "echo")
+  (func (export "main")
+    (drop (call $route (i32.const 2048) (i32.const 4)
+      (i32.const 2064) (i32.const 5) (i32.const 2072) (i32.const 4)))
+    (drop (call $route (i32.const 2056) (i32.const 3)
+      (i32.const 2064) (i32.const 5) (i32.const 2072) (i32.const 4))))
+  (func (export "echo") (result i32)
+    (call $req_body)))
+"#;
+
+fn fixture_config() -> ServerConfig {
+    ServerConfig {
+        database_url: None,
+        ..ServerConfig::default()
+    }
+    .with_allowed_request_content_types(vec![
+        "application/json".to_string(),
+        "application/x-www-form-urlencoded".to_string(),
+    ])
+}
+
+async fn post(server: &TestServer, content_type: &str, body: &'static str) -> TestResponse {
+    server
+        .request(
+            axum::http::Method::POST,
+            "/echo",
+            &[("content-type", content_type)],
+            body,
+        )
+        .await
+        .unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn allowed_content_type_reaches_the_handler() {
+    let server = TestServer::from_wat(&with_malloc(FIXTURE_WAT), fixture_config())
+        .await
+        .unwrap();
+
+    let response = post(&server, "application/json; charset=utf-8", r#"{"a":1}"#).await;
+    assert_eq!(response.status, 200);
+    assert_eq!(response.text(), r#"{"a":1}"#);
+
+    let response = post(&server, "application/x-www-form-urlencoded", "a=1").await;
+    assert_eq!(response.status, 200);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn disallowed_content_type_is_415() {
+    let server = TestServer::from_wat(&with_malloc(FIXTURE_WAT), fixture_config())
+        .await
+        .unwrap();
+
+    let response = post(&server, "text/xml", "<a/>").await;
+    assert_eq!(response.status, 415);
+    let body = response.json().expect("error envelope is JSON");
+    assert_eq!(body["ok"], false);
+    assert_eq!(body["error"]["code"], 415);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn requests_without_a_body_are_exempt() {
+    let server = TestServer::from_wat(&with_malloc(FIXTURE_WAT), fixture_config())
+        .await
+        .unwrap();
+
+    let response = server
+        .request(axum::http::Method::GET, "/echo", &[], "")
+        .await
+        .unwrap();
+    assert_eq!(response.status, 200);
+}
@@ -0,0 +1,133 @@
+//! `ServerConfig.session_cookie_secret`: sessions live in encrypted cookies,
+//! so any server configured with the same secret accepts them, and every
+//! authenticated request sends back a cookie with a refreshed idle timer.
+
+use std::time::Duration;
+
+use clean_server::ServerConfig;
+use clean_server::session::{SessionConfig, SessionStore};
+use clean_server::testing::{TestResponse, TestServer, with_malloc};
+
+const SECRET: &str = "cookie-secret";
+
+/// Routes:
+/// - `POST /login` -> `login`: starts a session for user 7, returns `_auth_get_session`
+/// - `GET /me` -> `me` (protected): returns `_auth_get_session`
+const FIXTURE_WAT: &str = r#"
+(module
+  (import "env" "_http_route"
+    (func $route (param i32 i32 i32 i32 i32 i32) (result i32)))
+  (import "env" "_http_route_protected"
+    (func $protected (param i32 i32 i32 i32 i32 i32 i32 i32) (result i32)))
+  (import "env" "_auth_set_session" (func $set_session (param i32 i32) (result i32)))
+  (import "env" "_auth_get_session" (func $session (result i32)))
+  (memory (export "memory") 2)
+  (data (i32.const 2048) "POST")
+  (data (i32.const 2056) "GET")
+  (data (i32.const 2064) "/login")
+  (data (i32.const 2072) "login")
+  (data (i32.const 2080) "/me")
+  (data (i32.const 2088) "me")
+  (data (i32.const 2096) "{\"user_id\":7,\"role\":\"editor\"}")
+  (func (export "main")
+    (drop (call $route (i32.const 2048) (i32.const 4)
+      (i32.const 2064) (i32.const 6) (i32.const 2072) (i32.const 5)))
+    (drop (call $protected (i32.const 2056) (i32.const 3)
+      (i32.const 2080) (i32.const 3) (i32.const 2088) (i32.const 2)
+      (i32.const 0) (i32.const 0))))
+  (func (export "login") (result i32)
+    (drop (call $set_session (i32.const 2096) (i32.const 29)))
+    (call $session))
+  (func (export "me") (result i32)
+    (call $session)))
+"#;
+
+fn cookie_config(secret: &str) -> ServerConfig {
+    ServerConfig {
+        database_url: None,
+        ..ServerConfig::default()
+    }
+    .with_cookie_sessions(secret)
+}
+
+async fn get_me(server: &TestServer, cookie: &str) -> TestResponse {
+    server
+        .request(axum::http::Method::GET, "/me", &[("cookie", cookie)], "")
+        .await
+        .unwrap()
+}
+
+/// The `name=value` part of the login response's Set-Cookie header
+async fn login(server: &TestServer) -> String {
+    let response = server.post("/login", "text/plain", "").await.unwrap();
+    assert_eq!(response.status, 200, "body: {}", response.text());
+    let set_cookie = response.header("set-cookie").expect("session cookie");
+    set_cookie.split(';').next().unwrap().to_string()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn session_cookie_authenticates_on_another_server_with_the_same_secret() {
+    let server = TestServer::from_wat(&with_malloc(FIXTURE_WAT), cookie_config(SECRET))
+        .await
+        .unwrap();
+    let cookie = login(&server).await;
+    assert!(server.wasm().session_store().read().unwrap().is_empty());
+
+    // A server with no memory of the login accepts the cookie
+    let restarted = TestServer::from_wat(&with_malloc(FIXTURE_WAT), cookie_config(SECRET))
+        .await
+        .unwrap();
+    let response = get_me(&restarted, &cookie).await;
+    assert_eq!(response.status, 200, "body: {}", response.text());
+    let session = response.json().expect("session JSON");
+    assert_eq!(session["user_id"], 7);
+    assert_eq!(session["role"], "editor");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn tampered_or_foreign_session_cookie_is_401() {
+    let server = TestServer::from_wat(&with_malloc(FIXTURE_WAT), cookie_config(SECRET))
+        .await
+        .unwrap();
+    let cookie = login(&server).await;
+
+    let mut tampered = cookie.clone();
+    let last = tampered.pop().unwrap();
+    tampered.push(if last == 'A' { 'B' } else { 'A' });
+    assert_eq!(get_me(&server, &tampered).await.status, 401);
+
+    let other = TestServer::from_wat(&with_malloc(FIXTURE_WAT), cookie_config("another-secret"))
+        .await
+        .unwrap();
+    assert_eq!(get_me(&other, &cookie).await.status, 401);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn reading_a_session_sends_back_a_refreshed_cookie() {
+    let server = TestServer::from_wat(&with_malloc(FIXTURE_WAT), cookie_config(SECRET))
+        .await
+        .unwrap();
+    let idle = Duration::from_secs(2);
+    {
+        let mut store = server.wasm().session_store().write().unwrap();
+        *store = SessionStore::new(SessionConfig {
+            idle_timeout: idle,
+            ..SessionConfig::default()
+        });
+        store.use_cookie_sessions(SECRET.as_bytes());
+    }
+    let original = login(&server).await;
+
+    tokio::time::sleep(Duration::from_millis(1500)).await;
+    let response = get_me(&server, &original).await;
+    assert_eq!(response.status, 200, "body: {}", response.text());
+    let set_cookie = response.header("set-cookie").expect("refreshed cookie");
+    let refreshed = set_cookie.split(';').next().unwrap().to_string();
+    assert_ne!(refreshed, original);
+
+    // Past the original cookie's idle window, only the refreshed one works
+    tokio::time::sleep(Duration::from_millis(1500)).await;
+    assert_eq!(get_me(&server, &original).await.status, 401);
+    let response = get_me(&server, &refreshed).await;
+    assert_eq!(response.status, 200, "body: {}", response.text());
+}
@@ -0,0 +1,141 @@
+//! `ServerConfig.cors_allow_credentials`: credentialed CORS echoes the
+//! allowlisted request Origin instead of `*`, and allowing credentials for
+//! any origin is rejected as a misconfiguration.
+
+use axum::http::Method;
+use clean_server::ServerConfig;
+use clean_server::server::validate_config;
+use clean_server::testing::{TestResponse, TestServer};
+
+const APP_ORIGIN: &str = "https://app.example.com";
+
+/// Routes:
+/// - `GET /items`, `POST /items` -> `ok`: returns "ok"
+const FIXTURE_WAT: &str = r#"
+(module
+  (import "env" "_http_route"
+    (func $route (param i32 i32 i32 i32 i32 i32) (result i32)))
+  (memory (export "memory") 2)
+  (global (export "__heap_ptr") i32 (i32.const 65536))
+  (data (i32.const 1024) "\02\00\00\00ok")
+  (data (i32.const 2048) "GET")
+  (data (i32.const 2056) "POST")
+  (data (i32.const 2080) "/items")
+  (data (i32.const 2128) "ok")
+  (func (export "main")
+    (drop (call $route (i32.const 2048) (i32.const 3)
+      (i32.const 2080) (i32.const 6) (i32.const 2128) (i32.const 2)))
+    (drop (call $route (i32.const 2056) (i32.const 4)
+      (i32.const 2080) (i32.const 6) (i32.const 2128) (i32.const 2))))
+  (func (export "ok") (result i32)
+    (i32.const 1024)))
+"#;
+
+fn credentialed_config() -> ServerConfig {
+    ServerConfig {
+        database_url: None,
+        ..ServerConfig::default()
+    }
+    .with_cors_origins(vec![APP_ORIGIN.to_string()])
+    .with_cors_allow_credentials(true)
+}
+
+async fn preflight(server: &TestServer, origin: &str) -> TestResponse {
+    server
+        .request(
+            Method::OPTIONS,
+            "/items",
+            &[
+                ("Origin", origin),
+                ("Access-Control-Request-Method", "POST"),
+                (
+                    "Access-Control-Request-Headers",
+                    "content-type,x-csrf-token",
+                ),
+            ],
+            "",
+        )
+        .await
+        .unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn credentialed_preflight_echoes_the_allowed_origin() {
+    let server = TestServer::from_wat(FIXTURE_WAT, credentialed_config())
+        .await
+        .unwrap();
+
+    let response = preflight(&server, APP_ORIGIN).await;
+    assert_eq!(response.status, 200);
+    assert_eq!(
+        response.header("access-control-allow-origin"),
+        Some(APP_ORIGIN)
+    );
+    assert_eq!(
+        response.header("access-control-allow-credentials"),
+        Some("true")
+    );
+    // Wildcards are not honored on credentialed requests, so the requested
+    // method and headers are echoed
+    assert_eq!(
+        response.header("access-control-allow-methods"),
+        Some("POST")
+    );
+    assert_eq!(
+        response.header("access-control-allow-headers"),
+        Some("content-type,x-csrf-token")
+    );
+
+    let response = server
+        .request(Method::GET, "/items", &[("Origin", APP_ORIGIN)], "")
+        .await
+        .unwrap();
+    assert_eq!(response.text(), "ok");
+    assert_eq!(
+        response.header("access-control-allow-origin"),
+        Some(APP_ORIGIN)
+    );
+    assert_eq!(
+        response.header("access-control-allow-credentials"),
+        Some("true")
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn other_origins_get_no_cors_grant() {
+    let server = TestServer::from_wat(FIXTURE_WAT, credentialed_config())
+        .await
+        .unwrap();
+
+    let response = preflight(&server, "https://evil.example.com").await;
+    assert_eq!(response.header("access-control-allow-origin"), None);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn without_credentials_any_origin_is_allowed() {
+    let config = ServerConfig {
+        database_url: None,
+        ..ServerConfig::default()
+    };
+    let server = TestServer::from_wat(FIXTURE_WAT, config).await.unwrap();
+
+    let response = preflight(&server, APP_ORIGIN).await;
+    assert_eq!(response.header("access-control-allow-origin"), Some("*"));
+    assert_eq!(response.header("access-control-allow-credentials"), None);
+}
+
+#[test]
+fn credentials_with_any_origin_are_rejected() {
+    for origins in [vec![], vec!["*".to_string()]] {
+        let config = ServerConfig::default()
+            .with_cors_origins(origins.clone())
+            .with_cors_allow_credentials(true);
+        assert!(validate_config(&config).is_err(), "{:?}", origins);
+    }
+    assert!(validate_config(&credentialed_config()).is_ok());
+
+    // Credentials are irrelevant while CORS is off
+    let mut disabled = ServerConfig::default().with_cors_allow_credentials(true);
+    disabled.cors_enabled = false;
+    assert!(validate_config(&disabled).is_ok());
+}
@@ -0,0 +1,117 @@
+//! `ServerConfig.db_reload_endpoint`: posting a new `database_url` swaps the
+//! database pool at runtime, so later queries hit the new database.
+
+#![cfg(feature = "sqlite")]
+
+use axum::http::Method;
+use clean_server::ServerConfig;
+use clean_server::testing::{TestResponse, TestServer, with_malloc};
+
+const TOKEN: &str = "rotate-me";
+
+/// Routes:
+/// - `GET /origin` -> `origin`: returns the `_db_query("SELECT name FROM origin")` result
+const FIXTURE_WAT: &str = r#"
+(module
+  (import "env" "_http_route"
+    (func $route (param i32 i32 i32 i32 i32 i32) (result i32)))
+  (import "env" "_db_query" (func $query (param i32 i32 i32 i32) (result i32)))
+  (memory (export "memory") 2)
+  (data (i32.const 2048) "GET")
+  (data (i32.const 2056) "/origin")
+  (data (i32.const 2064) "origin")
+  (data (i32.const 2072) "SELECT name FROM origin")
+  (func (export "main")
+    (drop (call $route (i32.const 2048) (i32.const 3)
+      (i32.const 2056) (i32.const 7) (i32.const 2064) (i32.const 6))))
+  (func (export "origin") (result i32)
+    (call $query (i32.const 2072) (i32.const 23) (i32.const 0) (i32.const 0))))
+"#;
+
+/// A SQLite URL for `name` in `dir` whose `origin` table holds `name`.
+async fn seeded_database(dir: &std::path::Path, name: &str) -> String {
+    use sqlx::Connection;
+
+    let url = format!("sqlite://{}?mode=rwc", dir.join(name).display());
+    let mut conn = sqlx::SqliteConnection::connect(&url)
+        .await
+        .expect("connect");
+    sqlx::query("CREATE TABLE origin (name TEXT)")
+        .execute(&mut conn)
+        .await
+        .expect("create");
+    sqlx::query("INSERT INTO origin VALUES (?)")
+        .bind(name)
+        .execute(&mut conn)
+        .await
+        .expect("insert");
+    url
+}
+
+async fn origin(server: &TestServer) -> serde_json::Value {
+    let result = server.get("/origin").await.unwrap().json().expect("JSON");
+    assert_eq!(result["ok"], true, "{}", result);
+    result["data"]["rows"][0]["name"].clone()
+}
+
+async fn reload(server: &TestServer, token: &str, body: &str) -> TestResponse {
+    server
+        .request(
+            Method::POST,
+            "/admin/db-reload",
+            &[
+                ("Authorization", &format!("Bearer {}", token)),
+                ("Content-Type", "application/json"),
+            ],
+            body.to_string(),
+        )
+        .await
+        .unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn reload_switches_queries_to_the_new_database() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let first = seeded_database(temp.path(), "first.db").await;
+    let second = seeded_database(temp.path(), "second.db").await;
+    let config = ServerConfig {
+        database_url: Some(first),
+        ..ServerConfig::default()
+    }
+    .with_db_reload_endpoint("/admin/db-reload", TOKEN);
+    let server = TestServer::from_wat(&with_malloc(FIXTURE_WAT), config)
+        .await
+        .unwrap();
+    assert_eq!(origin(&server).await, "first.db");
+
+    let body = serde_json::json!({ "database_url": second }).to_string();
+    let response = reload(&server, "wrong", &body).await;
+    assert_eq!(response.status, 401);
+    assert_eq!(origin(&server).await, "first.db");
+
+    let response = reload(&server, TOKEN, &body).await;
+    assert_eq!(response.status, 200, "{}", response.text());
+    assert_eq!(origin(&server).await, "second.db");
+
+    // A database that cannot be opened leaves the current pool serving
+    let missing = format!(
+        "sqlite://{}?mode=ro",
+        temp.path().join("missing.db").display()
+    );
+    let body = serde_json::json!({ "database_url": missing }).to_string();
+    let response = reload(&server, TOKEN, &body).await;
+    assert_eq!(response.status, 502, "{}", response.text());
+    assert_eq!(origin(&server).await, "second.db");
+
+    let response = reload(&server, TOKEN, "{}").await;
+    assert_eq!(response.status, 400);
+}
+
+#[tokio::test]
+async fn reload_endpoint_requires_a_token() {
+    let config = ServerConfig {
+        db_reload_endpoint: Some("/admin/db-reload".to_string()),
+        ..ServerConfig::default()
+    };
+    assert!(clean_server::server::validate_config(&config).is_err());
+}
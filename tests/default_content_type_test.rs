@@ -0,0 +1,98 @@
+//! `ServerConfig.default_content_type`.
+//!
+//! Handlers that set no Content-Type and return a body that is not
+//! recognizably JSON or HTML get the configured default; a type set through
+//! `_res_set_header` always wins.
+
+use clean_server::ServerConfig;
+use clean_server::testing::TestServer;
+
+/// Routes:
+/// - `GET /plain` -> `plain`: returns "hello" with no Content-Type
+/// - `GET /typed` -> `typed`: sets `Content-Type: application/xml`, returns "hello"
+const FIXTURE_WAT: &str = r#"
+(module
+  (import "env" "_http_route"
+    (func $route (param i32 i32 i32 i32 i32 i32) (result i32)))
+  (import "env" "_res_set_header" (func $header (param i32 i32 i32 i32) (result i32)))
+  (memory (export "memory") 2)
+  (global (export "__heap_ptr") i32 (i32.const 65536))
+  (data (i32.const 1024) "\05\00\00\00hello")
+  (data (i32.const 2048) "GET")
+  (data (i32.const 2056) "/plain")
+  (data (i32.const 2064) "plain")
+  (data (i32.const 2072) "/typed")
+  (data (i32.const 2080) "typed")
+  (data (i32.const 2088) "Content-Type")
+  (data (i32.const 2104) "application/xml")
+  (func (export "main")
+    (drop (call $route (i32.const 2048) (i32.const 3)
+      (i32.const 2056) (i32.const 6) (i32.const 2064) (i32.const 5)))
+    (drop (call $route (i32.const 2048) (i32.const 3)
+      (i32.const 2072) (i32.const 6) (i32.const 2080) (i32.const 5))))
+  (func (export "plain") (result i32)
+    (i32.const 1024))
+  (func (export "typed") (result i32)
+    (drop (call $header (i32.const 2088) (i32.const 12) (i32.const 2104) (i32.const 15)))
+    (i32.const 1024)))
+"#;
+
+fn base_config() -> ServerConfig {
+    ServerConfig {
+        database_url: None,
+        ..ServerConfig::default()
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn builtin_default_is_plain_text() {
+    let server = TestServer::from_wat(FIXTURE_WAT, base_config())
+        .await
+        .unwrap();
+
+    let response = server.get("/plain").await.unwrap();
+    assert_eq!(response.status, 200);
+    assert_eq!(
+        response.header("content-type"),
+        Some("text/plain; charset=utf-8")
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn configured_default_is_applied() {
+    let config = base_config().with_default_content_type("text/html; charset=utf-8");
+    let server = TestServer::from_wat(FIXTURE_WAT, config).await.unwrap();
+
+    let response = server.get("/plain").await.unwrap();
+    assert_eq!(
+        response.header("content-type"),
+        Some("text/html; charset=utf-8")
+    );
+    assert_eq!(response.text(), "hello");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn handler_content_type_overrides_default() {
+    for config in [
+        base_config(),
+        base_config().with_default_content_type("text/html; charset=utf-8"),
+    ] {
+        let server = TestServer::from_wat(FIXTURE_WAT, config).await.unwrap();
+        let response = server.get("/typed").await.unwrap();
+        assert_eq!(response.header("content-type"), Some("application/xml"));
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn invalid_default_is_rejected_at_startup() {
+    let config = base_config().with_default_content_type("text/plain\n");
+    let err = TestServer::from_wat(FIXTURE_WAT, config)
+        .await
+        .err()
+        .expect("load should fail");
+    assert!(
+        err.to_string().contains("Invalid default Content-Type"),
+        "{}",
+        err
+    );
+}
@@ -0,0 +1,94 @@
+//! `ServerConfig.default_response_headers`: added to handler responses and
+//! server-generated errors alike, unless the response sets the header itself.
+
+use clean_server::ServerConfig;
+use clean_server::testing::TestServer;
+
+/// Routes:
+/// - `GET /plain` -> `plain`: returns "hello"
+/// - `GET /framed` -> `framed`: sets `X-Frame-Options: SAMEORIGIN`, returns "hello"
+const FIXTURE_WAT: &str = r#"
+(module
+  (import "env" "_http_route"
+    (func $route (param i32 i32 i32 i32 i32 i32) (result i32)))
+  (import "env" "_res_set_header" (func $header (param i32 i32 i32 i32) (result i32)))
+  (memory (export "memory") 2)
+  (global (export "__heap_ptr") i32 (i32.const 65536))
+  (data (i32.const 1024) "\05\00\00\00hello")
+  (data (i32.const 2048) "GET")
+  (data (i32.const 2056) "/plain")
+  (data (i32.const 2064) "plain")
+  (data (i32.const 2072) "/framed")
+  (data (i32.const 2080) "framed")
+  (data (i32.const 2088) "X-Frame-Options")
+  (data (i32.const 2104) "SAMEORIGIN")
+  (func (export "main")
+    (drop (call $route (i32.const 2048) (i32.const 3)
+      (i32.const 2056) (i32.const 6) (i32.const 2064) (i32.const 5)))
+    (drop (call $route (i32.const 2048) (i32.const 3)
+      (i32.const 2072) (i32.const 7) (i32.const 2080) (i32.const 6))))
+  (func (export "plain") (result i32)
+    (i32.const 1024))
+  (func (export "framed") (result i32)
+    (drop (call $header (i32.const 2088) (i32.const 15) (i32.const 2104) (i32.const 10)))
+    (i32.const 1024)))
+"#;
+
+fn config(headers: &[(&str, &str)]) -> ServerConfig {
+    ServerConfig {
+        database_url: None,
+        ..ServerConfig::default()
+    }
+    .with_default_response_headers(
+        headers
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect(),
+    )
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn default_headers_reach_handler_responses_and_errors() {
+    let server = TestServer::from_wat(
+        FIXTURE_WAT,
+        config(&[
+            ("X-Content-Type-Options", "nosniff"),
+            ("X-Frame-Options", "DENY"),
+        ]),
+    )
+    .await
+    .unwrap();
+
+    let response = server.get("/plain").await.unwrap();
+    assert_eq!(response.status, 200);
+    assert_eq!(response.header("x-content-type-options"), Some("nosniff"));
+    assert_eq!(response.header("x-frame-options"), Some("DENY"));
+
+    let response = server.get("/missing").await.unwrap();
+    assert_eq!(response.status, 404);
+    assert_eq!(response.header("x-content-type-options"), Some("nosniff"));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn handler_set_header_wins() {
+    let server = TestServer::from_wat(FIXTURE_WAT, config(&[("X-Frame-Options", "DENY")]))
+        .await
+        .unwrap();
+
+    let response = server.get("/framed").await.unwrap();
+    assert_eq!(response.header("x-frame-options"), Some("SAMEORIGIN"));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn invalid_header_is_rejected_at_startup() {
+    assert!(
+        TestServer::from_wat(FIXTURE_WAT, config(&[("Bad Header", "x")]))
+            .await
+            .is_err()
+    );
+    assert!(
+        TestServer::from_wat(FIXTURE_WAT, config(&[("X-Ok", "line\nbreak")]))
+            .await
+            .is_err()
+    );
+}
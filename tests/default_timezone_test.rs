@@ -0,0 +1,75 @@
+//! `ServerConfig.default_timezone`: `time_format` calls naming no zone format
+//! in the configured IANA zone, an explicit zone still wins, and unknown
+//! zone names are rejected at startup.
+
+use clean_server::ServerConfig;
+use clean_server::server::validate_config;
+use clean_server::testing::{TestServer, with_malloc};
+
+/// Routes (each formats 2023-11-14T22:13:20Z as "%Y-%m-%d %H:%M %z"):
+/// - `GET /default` -> `default_zone`: no zone, so the server default
+/// - `GET /utc` -> `utc`: zone "UTC"
+const FIXTURE_WAT: &str = r#"
+(module
+  (import "env" "_http_route"
+    (func $route (param i32 i32 i32 i32 i32 i32) (result i32)))
+  (import "env" "time_format"
+    (func $format (param i32 i32 i64 i32 i32) (result i32)))
+  (memory (export "memory") 2)
+  (data (i32.const 2048) "GET")
+  (data (i32.const 2056) "/default")
+  (data (i32.const 2072) "/utc")
+  (data (i32.const 2080) "default_zone")
+  (data (i32.const 2096) "utc")
+  (data (i32.const 2112) "%Y-%m-%d %H:%M %z")
+  (data (i32.const 2144) "UTC")
+  (func (export "main")
+    (drop (call $route (i32.const 2048) (i32.const 3)
+      (i32.const 2056) (i32.const 8) (i32.const 2080) (i32.const 12)))
+    (drop (call $route (i32.const 2048) (i32.const 3)
+      (i32.const 2072) (i32.const 4) (i32.const 2096) (i32.const 3))))
+  (func (export "default_zone") (result i32)
+    (call $format (i32.const 2112) (i32.const 17) (i64.const 1700000000000)
+      (i32.const 0) (i32.const 0)))
+  (func (export "utc") (result i32)
+    (call $format (i32.const 2112) (i32.const 17) (i64.const 1700000000000)
+      (i32.const 2144) (i32.const 3))))
+"#;
+
+fn base_config() -> ServerConfig {
+    ServerConfig {
+        database_url: None,
+        ..ServerConfig::default()
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn time_format_uses_the_configured_default_timezone() {
+    let config = base_config().with_default_timezone("America/New_York");
+    let server = TestServer::from_wat(&with_malloc(FIXTURE_WAT), config)
+        .await
+        .unwrap();
+
+    let response = server.get("/default").await.unwrap();
+    assert_eq!(response.text(), "2023-11-14 17:13 -0500");
+    let response = server.get("/utc").await.unwrap();
+    assert_eq!(response.text(), "2023-11-14 22:13 +0000");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn without_a_default_timezone_time_format_uses_utc() {
+    let server = TestServer::from_wat(&with_malloc(FIXTURE_WAT), base_config())
+        .await
+        .unwrap();
+
+    let response = server.get("/default").await.unwrap();
+    assert_eq!(response.text(), "2023-11-14 22:13 +0000");
+}
+
+#[test]
+fn unknown_timezone_names_are_rejected() {
+    let config = ServerConfig::default().with_default_timezone("Mars/Olympus");
+    assert!(validate_config(&config).is_err());
+    let config = ServerConfig::default().with_default_timezone("Europe/Berlin");
+    assert!(validate_config(&config).is_ok());
+}
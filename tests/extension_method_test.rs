@@ -0,0 +1,83 @@
+//! Routes for non-standard (extension) HTTP methods such as WebDAV's PROPFIND.
+//!
+//! A module registering a route for any valid method token gets requests
+//! using that method dispatched to its handler. Extension methods no module
+//! registered are still answered 405.
+
+use axum::http::Method;
+use clean_server::ServerConfig;
+use clean_server::testing::{TestResponse, TestServer};
+
+/// Routes:
+/// - `PROPFIND /dav` -> `propfind`: returns "multistatus"
+/// - `GET /dav`      -> `get`: returns "file"
+const FIXTURE_WAT: &str = r#"
+(module
+  (import "env" "_http_route"
+    (func $route (param i32 i32 i32 i32 i32 i32) (result i32)))
+  (memory (export "memory") 2)
+  (global (export "__heap_ptr") i32 (i32.const 65536))
+  (data (i32.const 1024) "\0b\00\00\00multistatus")
+  (data (i32.const 1040) "\04\00\00\00file")
+  (data (i32.const 2048) "PROPFIND")
+  (data (i32.const 2064) "GET")
+  (data (i32.const 2072) "/dav")
+  (data (i32.const 2080) "propfind")
+  (data (i32.const 2096) "get")
+  (func (export "main")
+    (drop (call $route (i32.const 2048) (i32.const 8)
+      (i32.const 2072) (i32.const 4) (i32.const 2080) (i32.const 8)))
+    (drop (call $route (i32.const 2064) (i32.const 3)
+      (i32.const 2072) (i32.const 4) (i32.const 2096) (i32.const 3))))
+  (func (export "propfind") (result i32)
+    (i32.const 1024))
+  (func (export "get") (result i32)
+    (i32.const 1040)))
+"#;
+
+fn fixture_config() -> ServerConfig {
+    ServerConfig {
+        database_url: None,
+        ..ServerConfig::default()
+    }
+}
+
+async fn send(server: &TestServer, method: &str, path: &str) -> TestResponse {
+    let method = Method::from_bytes(method.as_bytes()).unwrap();
+    server.request(method, path, &[], "").await.unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn propfind_route_is_dispatched() {
+    let server = TestServer::from_wat(FIXTURE_WAT, fixture_config())
+        .await
+        .unwrap();
+
+    let response = send(&server, "PROPFIND", "/dav").await;
+    assert_eq!(response.status, 200);
+    assert_eq!(response.text(), "multistatus");
+
+    let response = send(&server, "GET", "/dav").await;
+    assert_eq!(response.text(), "file");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn unregistered_extension_method_is_405() {
+    let server = TestServer::from_wat(FIXTURE_WAT, fixture_config())
+        .await
+        .unwrap();
+
+    let response = send(&server, "MKCOL", "/dav").await;
+    assert_eq!(response.status, 405);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn options_lists_extension_methods() {
+    let server = TestServer::from_wat(FIXTURE_WAT, fixture_config())
+        .await
+        .unwrap();
+
+    let response = send(&server, "OPTIONS", "/dav").await;
+    assert_eq!(response.status, 204);
+    assert_eq!(response.header("allow"), Some("GET, PROPFIND, OPTIONS"));
+}
@@ -0,0 +1,102 @@
+//! A handler calling back into its own server through `http_get` gets a
+//! 508 error for the inner request instead of deadlocking the server.
+//!
+//! Outbound requests to loopback addresses are refused by the HTTP bridge,
+//! so the handler reaches its server the way a deployed one would: through
+//! a public name, here routed back by pointing `http_proxy` at the server.
+
+use std::net::{Ipv4Addr, SocketAddr};
+use std::time::Duration;
+
+use clean_server::ServerConfig;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+/// Routes:
+/// - `GET /loop` -> `loop`: returns `http_get("{url}")`
+fn fixture_wat(url: &str) -> String {
+    format!(
+        r#"
+(module
+  (import "env" "_http_route"
+    (func $route (param i32 i32 i32 i32 i32 i32) (result i32)))
+  (import "env" "http_get" (func $http_get (param i32 i32) (result i32)))
+  (memory (export "memory") 2)
+  (global $heap (mut i32) (i32.const 65536))
+  (global (export "__heap_ptr") (mut i32) (i32.const 65536))
+  (data (i32.const 2048) "GET")
+  (data (i32.const 2056) "/loop")
+  (data (i32.const 2064) "loop")
+  (data (i32.const 2080) "{url}")
+  (func (export "malloc") (param $size i32) (result i32)
+    (local $ptr i32)
+    (local.set $ptr (global.get $heap))
+    (global.set $heap
+      (i32.and
+        (i32.add (i32.add (global.get $heap) (local.get $size)) (i32.const 7))
+        (i32.const -8)))
+    (global.set 1 (global.get $heap))
+    (local.get $ptr))
+  (func (export "main")
+    (drop (call $route (i32.const 2048) (i32.const 3)
+      (i32.const 2056) (i32.const 5) (i32.const 2064) (i32.const 4))))
+  (func (export "loop") (result i32)
+    (call $http_get (i32.const 2080) (i32.const {len}))))
+"#,
+        len = url.len()
+    )
+}
+
+/// Raw HTTP/1.1 response to `GET /loop` from `addr`.
+async fn get(addr: SocketAddr) -> std::io::Result<String> {
+    let mut stream = tokio::net::TcpStream::connect(addr).await?;
+    stream
+        .write_all(b"GET /loop HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+        .await?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await?;
+    Ok(response)
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn handler_calling_its_own_server_fails_fast() {
+    let port = std::net::TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+    // SAFETY: this is the binary's only test, and nothing reads the
+    // environment until the server starts below
+    unsafe {
+        std::env::set_var("http_proxy", format!("http://{addr}"));
+        std::env::remove_var("no_proxy");
+        std::env::remove_var("NO_PROXY");
+    }
+    let wat = fixture_wat("http://app.example.com/loop");
+    let wasm_bytes = wat::parse_str(&wat).expect("fixture WAT should compile");
+    let temp = tempfile::tempdir().expect("tempdir");
+    let wasm_path = temp.path().join("app.wasm");
+    std::fs::write(&wasm_path, &wasm_bytes).expect("write wasm");
+    let config = ServerConfig {
+        database_url: None,
+        ..ServerConfig::default()
+    }
+    .with_host("127.0.0.1")
+    .with_port(port);
+    let server = tokio::spawn(clean_server::server::start_server(wasm_path, config));
+
+    let response = tokio::time::timeout(Duration::from_secs(20), async {
+        loop {
+            match get(addr).await {
+                Ok(response) if response.starts_with("HTTP/1.1 200") => return response,
+                _ => tokio::time::sleep(Duration::from_millis(50)).await,
+            }
+        }
+    })
+    .await
+    .expect("the re-entrant call should fail instead of hanging");
+
+    // The outer handler sees the inner request's error body
+    assert!(response.contains("own server"), "{response}");
+    server.abort();
+}
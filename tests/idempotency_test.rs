@@ -0,0 +1,147 @@
+//! `ServerConfig::idempotency_header` response replay.
+//!
+//! Every fixture handler returns a fresh `_crypto_uuid()`, so two identical
+//! bodies mean the second response was replayed rather than produced by a
+//! second handler call.
+
+use axum::http::Method;
+use clean_server::ServerConfig;
+use clean_server::testing::{TestResponse, TestServer, with_malloc};
+
+/// Routes (all return a new UUID):
+/// - `POST /pay`  -> `pay`
+/// - `GET /pay`   -> `pay`
+/// - `POST /fail` -> `fail`: status 503
+const FIXTURE_WAT: &str = r#"
+(module
+  (import "env" "_http_route"
+    (func $route (param i32 i32 i32 i32 i32 i32) (result i32)))
+  (import "env" "_crypto_uuid" (func $uuid (result i32)))
+  (import "env" "_res_status" (func $status (param i32)))
+  (memory (export "memory") 2)
+  (data (i32.const 2048) "POST")
+  (data (i32.const 2056) "GET")
+  (data (i32.const 2064) "/pay")
+  (data (i32.const 2072) "pay")
+  (data (i32.const 2080) "/fail")
+  (data (i32.const 2088) "fail")
+  (func (export "main")
+    (drop (call $route (i32.const 2048) (i32.const 4)
+      (i32.const 2064) (i32.const 4) (i32.const 2072) (i32.const 3)))
+    (drop (call $route (i32.const 2056) (i32.const 3)
+      (i32.const 2064) (i32.const 4) (i32.const 2072) (i32.const 3)))
+    (drop (call $route (i32.const 2048) (i32.const 4)
+      (i32.const 2080) (i32.const 5) (i32.const 2088) (i32.const 4))))
+  (func (export "pay") (result i32)
+    (call $uuid))
+  (func (export "fail") (result i32)
+    (call $status (i32.const 503))
+    (call $uuid)))
+"#;
+
+fn fixture_config() -> ServerConfig {
+    ServerConfig {
+        database_url: None,
+        ..ServerConfig::default()
+    }
+    .with_idempotency_header("Idempotency-Key")
+}
+
+async fn send(server: &TestServer, method: Method, path: &str, key: Option<&str>) -> TestResponse {
+    let headers: Vec<(&str, &str)> = key.map(|k| ("idempotency-key", k)).into_iter().collect();
+    server.request(method, path, &headers, "{}").await.unwrap()
+}
+
+async fn pay_as(server: &TestServer, authorization: &str) -> TestResponse {
+    let headers = [
+        ("idempotency-key", "shared"),
+        ("authorization", authorization),
+    ];
+    server
+        .request(Method::POST, "/pay", &headers, "{}")
+        .await
+        .unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn retried_request_gets_the_cached_response() {
+    let server = TestServer::from_wat(&with_malloc(FIXTURE_WAT), fixture_config())
+        .await
+        .unwrap();
+
+    let first = send(&server, Method::POST, "/pay", Some("key-1")).await;
+    assert_eq!(first.status, 200);
+    assert_eq!(first.header("idempotent-replayed"), None);
+
+    let retry = send(&server, Method::POST, "/pay", Some("key-1")).await;
+    assert_eq!(retry.status, first.status);
+    assert_eq!(retry.body, first.body);
+    assert_eq!(retry.header("content-type"), first.header("content-type"));
+    assert_eq!(retry.header("idempotent-replayed"), Some("true"));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn different_keys_and_keyless_requests_run_the_handler() {
+    let server = TestServer::from_wat(&with_malloc(FIXTURE_WAT), fixture_config())
+        .await
+        .unwrap();
+
+    let a = send(&server, Method::POST, "/pay", Some("key-a")).await;
+    let b = send(&server, Method::POST, "/pay", Some("key-b")).await;
+    assert_ne!(a.body, b.body);
+
+    let c = send(&server, Method::POST, "/pay", None).await;
+    let d = send(&server, Method::POST, "/pay", None).await;
+    assert_ne!(c.body, d.body);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn get_and_failed_responses_are_not_cached() {
+    let server = TestServer::from_wat(&with_malloc(FIXTURE_WAT), fixture_config())
+        .await
+        .unwrap();
+
+    let first = send(&server, Method::GET, "/pay", Some("key-get")).await;
+    let second = send(&server, Method::GET, "/pay", Some("key-get")).await;
+    assert_ne!(first.body, second.body);
+
+    let first = send(&server, Method::POST, "/fail", Some("key-fail")).await;
+    assert_eq!(first.status, 503);
+    let second = send(&server, Method::POST, "/fail", Some("key-fail")).await;
+    assert_eq!(second.status, 503);
+    assert_ne!(first.body, second.body);
+    assert_eq!(second.header("idempotent-replayed"), None);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn keys_are_scoped_to_the_callers_credentials() {
+    let server = TestServer::from_wat(&with_malloc(FIXTURE_WAT), fixture_config())
+        .await
+        .unwrap();
+    let alice = pay_as(&server, "Bearer alice").await;
+    let bob = pay_as(&server, "Bearer bob").await;
+    assert_ne!(bob.body, alice.body);
+    assert_eq!(bob.header("idempotent-replayed"), None);
+
+    let alice_retry = pay_as(&server, "Bearer alice").await;
+    assert_eq!(alice_retry.body, alice.body);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn concurrent_duplicates_share_one_handler_run() {
+    let server = TestServer::from_wat(&with_malloc(FIXTURE_WAT), fixture_config())
+        .await
+        .unwrap();
+
+    let responses = futures::future::join_all(
+        (0..4).map(|_| send(&server, Method::POST, "/pay", Some("key-burst"))),
+    )
+    .await;
+    assert!(responses.iter().all(|r| r.status == 200));
+    assert!(responses.iter().all(|r| r.body == responses[0].body));
+    let replayed = responses
+        .iter()
+        .filter(|r| r.header("idempotent-replayed").is_some())
+        .count();
+    assert_eq!(replayed, 3);
+}
@@ -0,0 +1,69 @@
+//! `ServerConfig.instantiation_timeout_ms`: bounds compiling, instantiating
+//! and initializing the module. A module that fits loads as usual; one that
+//! overruns fails startup with a `RuntimeError` instead of stalling it.
+
+use clean_server::ServerConfig;
+use clean_server::testing::TestServer;
+
+/// Routes:
+/// - `GET /ping` -> `ping`: returns `pong`
+const FIXTURE_WAT: &str = r#"
+(module
+  (import "env" "_http_route"
+    (func $route (param i32 i32 i32 i32 i32 i32) (result i32)))
+  (memory (export "memory") 2)
+  (global (export "__heap_ptr") i32 (i32.const 65536))
+  (data (i32.const 1024) "\04\00\00\00pong")
+  (data (i32.const 2048) "GET")
+  (data (i32.const 2056) "/ping")
+  (data (i32.const 2064) "ping")
+  (func (export "main")
+    (drop (call $route (i32.const 2048) (i32.const 3)
+      (i32.const 2056) (i32.const 5) (i32.const 2064) (i32.const 4))))
+  (func (export "ping") (result i32)
+    (i32.const 1024))
+  ;; FILLER
+)
+"#;
+
+fn config(timeout_ms: u64) -> ServerConfig {
+    ServerConfig {
+        database_url: None,
+        ..ServerConfig::default()
+    }
+    .with_instantiation_timeout_ms(timeout_ms)
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn module_loading_within_the_timeout_serves_requests() {
+    let server = TestServer::from_wat(FIXTURE_WAT, config(10_000))
+        .await
+        .expect("fixture should load within the timeout");
+    let response = server.get("/ping").await.unwrap();
+    assert_eq!(response.status, 200);
+    assert_eq!(response.text(), "pong");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn module_overrunning_the_timeout_fails_to_load() {
+    // Hundreds of functions take far longer than 1 ms to compile
+    let filler: String = (0..500)
+        .map(|i| {
+            format!(
+                "(func (export \"f{i}\") (param i64) (result i64) \
+                 (i64.mul (i64.add (local.get 0) (i64.const {i})) (local.get 0)))"
+            )
+        })
+        .collect();
+    let wat = FIXTURE_WAT.replace(";; FILLER", &filler);
+
+    let err = TestServer::from_wat(&wat, config(1))
+        .await
+        .err()
+        .expect("loading should time out");
+    assert!(
+        err.to_string().contains("1 ms instantiation timeout"),
+        "{}",
+        err
+    );
+}
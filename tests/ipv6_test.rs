@@ -0,0 +1,120 @@
+//! IPv6 hosts and `ServerConfig.dual_stack`: `start_server` binds IPv6
+//! addresses, IPv6-only unless dual-stack is enabled.
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
+
+use clean_server::ServerConfig;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+/// Routes:
+/// - `GET /hello` -> `hello`: returns "hi"
+const FIXTURE_WAT: &str = r#"
+(module
+  (import "env" "_http_route"
+    (func $route (param i32 i32 i32 i32 i32 i32) (result i32)))
+  (memory (export "memory") 2)
+  (global (export "__heap_ptr") i32 (i32.const 65536))
+  (data (i32.const 1024) "\02\00\00\00hi")
+  (data (i32.const 2048) "GET")
+  (data (i32.const 2056) "/hello")
+  (data (i32.const 2064) "hello")
+  (func (export "main")
+    (drop (call $route (i32.const 2048) (i32.const 3)
+      (i32.const 2056) (i32.const 6) (i32.const 2064) (i32.const 5))))
+  (func (export "hello") (result i32)
+    (i32.const 1024)))
+"#;
+
+/// A port nothing is listening on, for both IPv4 and IPv6 loopback.
+fn free_port() -> u16 {
+    std::net::TcpListener::bind((Ipv6Addr::LOCALHOST, 0))
+        .expect("IPv6 loopback")
+        .local_addr()
+        .unwrap()
+        .port()
+}
+
+/// Start the fixture on `host:port`; the returned tempdir holds the module.
+fn spawn_server(
+    host: &str,
+    port: u16,
+    dual_stack: bool,
+) -> (tokio::task::JoinHandle<()>, tempfile::TempDir) {
+    let wasm_bytes = wat::parse_str(FIXTURE_WAT).expect("fixture WAT should compile");
+    let temp = tempfile::tempdir().expect("tempdir");
+    let wasm_path = temp.path().join("app.wasm");
+    std::fs::write(&wasm_path, &wasm_bytes).expect("write wasm");
+    let config = ServerConfig {
+        database_url: None,
+        ..ServerConfig::default()
+    }
+    .with_host(host)
+    .with_port(port)
+    .with_dual_stack(dual_stack);
+    let server = tokio::spawn(async move {
+        clean_server::server::start_server(wasm_path, config)
+            .await
+            .expect("server should start");
+    });
+    (server, temp)
+}
+
+/// Raw HTTP/1.1 response to `GET /hello` from `addr`.
+async fn get(addr: SocketAddr) -> std::io::Result<String> {
+    let mut stream = tokio::net::TcpStream::connect(addr).await?;
+    stream
+        .write_all(b"GET /hello HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+        .await?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await?;
+    Ok(response)
+}
+
+/// Retry until the module has loaded and `/hello` answers 200.
+async fn get_when_ready(addr: SocketAddr) -> String {
+    for _ in 0..100 {
+        if let Ok(response) = get(addr).await
+            && response.starts_with("HTTP/1.1 200")
+        {
+            return response;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    panic!("no 200 response from {addr}");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn serves_requests_over_ipv6_loopback() {
+    let port = free_port();
+    let (server, _temp) = spawn_server("::1", port, false);
+
+    let response = get_when_ready(SocketAddr::new(IpAddr::V6(Ipv6Addr::LOCALHOST), port)).await;
+    assert!(response.ends_with("hi"), "{response}");
+    server.abort();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn dual_stack_also_accepts_ipv4() {
+    let port = free_port();
+    let (server, _temp) = spawn_server("::", port, true);
+
+    get_when_ready(SocketAddr::new(IpAddr::V6(Ipv6Addr::LOCALHOST), port)).await;
+    let response = get_when_ready(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), port)).await;
+    assert!(response.ends_with("hi"), "{response}");
+    server.abort();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn ipv6_host_is_ipv6_only_without_dual_stack() {
+    let port = free_port();
+    let (server, _temp) = spawn_server("::", port, false);
+
+    get_when_ready(SocketAddr::new(IpAddr::V6(Ipv6Addr::LOCALHOST), port)).await;
+    assert!(
+        get(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), port))
+            .await
+            .is_err()
+    );
+    server.abort();
+}
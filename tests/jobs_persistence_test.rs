@@ -3,6 +3,8 @@
 //! Each test uses an in-memory SQLite database (`:memory:`) to exercise the
 //! persistence layer in isolation without touching the filesystem.
 
+#![cfg(feature = "sqlite")]
+
 use clean_server::jobs::{
     BackoffStrategy, JobStatus, create_shared_jobs_state, enqueue_job, ensure_jobs_table,
     init_persistence, job_result, job_status, now_ms, recover_from_disk_with_pool, register_job,
@@ -0,0 +1,93 @@
+//! Malformed percent-encoding in the path or query string is answered 400
+//! with the standard error envelope instead of reaching the handler mangled.
+
+use clean_server::ServerConfig;
+use clean_server::testing::{TestResponse, TestServer, with_malloc};
+
+/// Routes:
+/// - `GET /x`         -> `query_a`: returns query parameter `a`
+/// - `GET /items/:id` -> `item`: returns path parameter `id`
+const FIXTURE_WAT: &str = r#"
+(module
+  (import "env" "_http_route"
+    (func $route (param i32 i32 i32 i32 i32 i32) (result i32)))
+  (import "env" "_req_query" (func $query (param i32 i32) (result i32)))
+  (import "env" "_req_param" (func $param (param i32 i32) (result i32)))
+  (memory (export "memory") 2)
+  (data (i32.const 2048) "GET")
+  (data (i32.const 2056) "/x")
+  (data (i32.const 2064) "query_a")
+  (data (i32.const 2072) "/items/:id")
+  (data (i32.const 2088) "item")
+  (data (i32.const 2096) "a")
+  (data (i32.const 2104) "id")
+  (func (export "main")
+    (drop (call $route (i32.const 2048) (i32.const 3)
+      (i32.const 2056) (i32.const 2) (i32.const 2064) (i32.const 7)))
+    (drop (call $route (i32.const 2048) (i32.const 3)
+      (i32.const 2072) (i32.const 10) (i32.const 2088) (i32.const 4))))
+  (func (export "query_a") (result i32)
+    (call $query (i32.const 2096) (i32.const 1)))
+  (func (export "item") (result i32)
+    (call $param (i32.const 2104) (i32.const 2))))
+"#;
+
+fn fixture_config() -> ServerConfig {
+    ServerConfig {
+        database_url: None,
+        ..ServerConfig::default()
+    }
+}
+
+async fn get(server: &TestServer, path: &str) -> TestResponse {
+    server
+        .request(axum::http::Method::GET, path, &[], "")
+        .await
+        .unwrap()
+}
+
+fn assert_bad_request(response: &TestResponse) {
+    assert_eq!(response.status, 400, "body: {}", response.text());
+    let body = response.json().expect("error envelope is JSON");
+    assert_eq!(body["ok"], false);
+    assert_eq!(body["error"]["code"], 400);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn invalid_escape_in_query_is_400() {
+    let server = TestServer::from_wat(&with_malloc(FIXTURE_WAT), fixture_config())
+        .await
+        .unwrap();
+    assert_bad_request(&get(&server, "/x?a=%zz").await);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn invalid_utf8_in_query_is_400() {
+    let server = TestServer::from_wat(&with_malloc(FIXTURE_WAT), fixture_config())
+        .await
+        .unwrap();
+    assert_bad_request(&get(&server, "/x?a=%C3%28").await);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn invalid_utf8_in_path_is_400() {
+    let server = TestServer::from_wat(&with_malloc(FIXTURE_WAT), fixture_config())
+        .await
+        .unwrap();
+    assert_bad_request(&get(&server, "/items/%FF").await);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn well_formed_encoding_is_decoded() {
+    let server = TestServer::from_wat(&with_malloc(FIXTURE_WAT), fixture_config())
+        .await
+        .unwrap();
+
+    let response = get(&server, "/x?a=caf%C3%A9+au+lait").await;
+    assert_eq!(response.status, 200);
+    assert_eq!(response.text(), "café au lait");
+
+    let response = get(&server, "/items/a%2Fb").await;
+    assert_eq!(response.status, 200);
+    assert_eq!(response.text(), "a/b");
+}
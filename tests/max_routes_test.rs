@@ -0,0 +1,62 @@
+//! `ServerConfig.max_routes`: registrations beyond the limit fail, the
+//! routes registered before it keep working.
+
+use clean_server::ServerConfig;
+use clean_server::testing::TestServer;
+
+/// Routes, registered in this order:
+/// - `GET /a`, `GET /b`, `GET /c` -> `ok`: returns "ok"
+const FIXTURE_WAT: &str = r#"
+(module
+  (import "env" "_http_route"
+    (func $route (param i32 i32 i32 i32 i32 i32) (result i32)))
+  (memory (export "memory") 2)
+  (global (export "__heap_ptr") i32 (i32.const 65536))
+  (data (i32.const 1024) "\02\00\00\00ok")
+  (data (i32.const 2048) "GET")
+  (data (i32.const 2056) "/a")
+  (data (i32.const 2064) "/b")
+  (data (i32.const 2072) "/c")
+  (data (i32.const 2080) "ok")
+  (func $register (param $path i32)
+    (drop (call $route (i32.const 2048) (i32.const 3)
+      (local.get $path) (i32.const 2) (i32.const 2080) (i32.const 2))))
+  (func (export "main")
+    (call $register (i32.const 2056))
+    (call $register (i32.const 2064))
+    (call $register (i32.const 2072)))
+  (func (export "ok") (result i32)
+    (i32.const 1024)))
+"#;
+
+fn routes_config(max_routes: usize) -> ServerConfig {
+    ServerConfig {
+        database_url: None,
+        ..ServerConfig::default()
+    }
+    .with_max_routes(max_routes)
+}
+
+async fn status(server: &TestServer, path: &str) -> axum::http::StatusCode {
+    server.get(path).await.unwrap().status
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn routes_up_to_the_limit_are_registered() {
+    let server = TestServer::from_wat(FIXTURE_WAT, routes_config(3))
+        .await
+        .unwrap();
+    for path in ["/a", "/b", "/c"] {
+        assert_eq!(status(&server, path).await, 200, "{path}");
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn routes_beyond_the_limit_are_rejected() {
+    let server = TestServer::from_wat(FIXTURE_WAT, routes_config(2))
+        .await
+        .unwrap();
+    assert_eq!(status(&server, "/a").await, 200);
+    assert_eq!(status(&server, "/b").await, 200);
+    assert_eq!(status(&server, "/c").await, 404);
+}
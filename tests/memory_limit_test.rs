@@ -0,0 +1,84 @@
+//! `ServerConfig::max_instance_memory_bytes` cap.
+//!
+//! Growing linear memory past the cap fails inside the module (`memory.grow`
+//! returns -1) rather than trapping the host, and a handler that aborts on
+//! that allocation failure produces a 500 while the server keeps serving.
+
+use clean_server::ServerConfig;
+use clean_server::testing::TestServer;
+
+/// 1 MiB: sixteen 64 KiB pages
+const CAP_BYTES: usize = 1024 * 1024;
+
+/// Routes:
+/// - `GET /probe` -> `probe`: one 4 MiB `memory.grow`, returns "denied" on -1
+/// - `GET /hog`   -> `hog`:   grows a page at a time until refused, then
+///   hits `unreachable` the way an allocator aborts on failure
+/// - `GET /ok`    -> `ok`:    returns "ok"
+const FIXTURE_WAT: &str = r#"
+(module
+  (import "env" "_http_route"
+    (func $route (param i32 i32 i32 i32 i32 i32) (result i32)))
+  (memory (export "memory") 2)
+  (global (export "__heap_ptr") i32 (i32.const 65536))
+  (data (i32.const 1024) "\06\00\00\00denied")
+  (data (i32.const 1040) "\05\00\00\00grown")
+  (data (i32.const 1056) "\02\00\00\00ok")
+  (data (i32.const 2048) "GET")
+  (data (i32.const 2056) "/probe")
+  (data (i32.const 2064) "probe")
+  (data (i32.const 2072) "/hog")
+  (data (i32.const 2080) "hog")
+  (data (i32.const 2088) "/ok")
+  (data (i32.const 2096) "ok")
+  (func (export "main")
+    (drop (call $route (i32.const 2048) (i32.const 3)
+      (i32.const 2056) (i32.const 6) (i32.const 2064) (i32.const 5)))
+    (drop (call $route (i32.const 2048) (i32.const 3)
+      (i32.const 2072) (i32.const 4) (i32.const 2080) (i32.const 3)))
+    (drop (call $route (i32.const 2048) (i32.const 3)
+      (i32.const 2088) (i32.const 3) (i32.const 2096) (i32.const 2))))
+  (func (export "probe") (result i32)
+    (if (result i32) (i32.eq (memory.grow (i32.const 64)) (i32.const -1))
+      (then (i32.const 1024))
+      (else (i32.const 1040))))
+  (func (export "hog") (result i32)
+    (loop $grow
+      (br_if $grow (i32.ne (memory.grow (i32.const 1)) (i32.const -1))))
+    unreachable)
+  (func (export "ok") (result i32)
+    (i32.const 1056)))
+"#;
+
+fn fixture_config() -> ServerConfig {
+    ServerConfig {
+        database_url: None,
+        ..ServerConfig::default()
+    }
+    .with_max_instance_memory_bytes(CAP_BYTES)
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn grow_past_cap_returns_failure_inside_wasm() {
+    let server = TestServer::from_wat(FIXTURE_WAT, fixture_config())
+        .await
+        .unwrap();
+
+    let response = server.get("/probe").await.unwrap();
+    assert_eq!(response.status, 200);
+    assert_eq!(response.text(), "denied");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn memory_hungry_handler_gets_500_and_server_keeps_serving() {
+    let server = TestServer::from_wat(FIXTURE_WAT, fixture_config())
+        .await
+        .unwrap();
+
+    let response = server.get("/hog").await.unwrap();
+    assert_eq!(response.status, 500);
+
+    let response = server.get("/ok").await.unwrap();
+    assert_eq!(response.status, 200);
+    assert_eq!(response.text(), "ok");
+}
@@ -0,0 +1,88 @@
+//! `ServerConfig::module_cache_dir` compiled-module cache.
+//!
+//! The first start compiles the module and writes the artifact to the cache
+//! directory; the next start with the same bytes loads it from there and
+//! serves requests the same way.
+
+use clean_server::ServerConfig;
+use clean_server::module_cache::{CACHE_EXTENSION, ModuleSource};
+use clean_server::testing::TestServer;
+
+/// `GET /ping` -> `ping`: returns "pong"
+const FIXTURE_WAT: &str = r#"
+(module
+  (import "env" "_http_route"
+    (func $route (param i32 i32 i32 i32 i32 i32) (result i32)))
+  (memory (export "memory") 2)
+  (global (export "__heap_ptr") i32 (i32.const 65536))
+  (data (i32.const 1024) "\04\00\00\00pong")
+  (data (i32.const 2048) "GET")
+  (data (i32.const 2056) "/ping")
+  (data (i32.const 2064) "ping")
+  (func (export "main")
+    (drop (call $route (i32.const 2048) (i32.const 3)
+      (i32.const 2056) (i32.const 5) (i32.const 2064) (i32.const 4))))
+  (func (export "ping") (result i32)
+    (i32.const 1024)))
+"#;
+
+async fn start(wasm_path: &std::path::Path, cache_dir: &std::path::Path) -> TestServer {
+    let config = ServerConfig {
+        database_url: None,
+        ..ServerConfig::default()
+    }
+    .with_module_cache_dir(cache_dir);
+    TestServer::with_config(wasm_path, config)
+        .await
+        .expect("fixture should load")
+}
+
+fn cache_files(dir: &std::path::Path) -> Vec<std::path::PathBuf> {
+    std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .map(|e| e.unwrap().path())
+                .filter(|p| p.extension().is_some_and(|ext| ext == CACHE_EXTENSION))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn restart_loads_module_from_cache() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let wasm_path = temp.path().join("app.wasm");
+    std::fs::write(&wasm_path, wat::parse_str(FIXTURE_WAT).unwrap()).expect("write wasm");
+    let cache_dir = temp.path().join("cache");
+
+    let first = start(&wasm_path, &cache_dir).await;
+    assert_eq!(first.wasm().module_source(), ModuleSource::Compiled);
+    assert_eq!(cache_files(&cache_dir).len(), 1);
+
+    let second = start(&wasm_path, &cache_dir).await;
+    assert_eq!(second.wasm().module_source(), ModuleSource::Cached);
+    assert_eq!(cache_files(&cache_dir).len(), 1);
+
+    let response = second.get("/ping").await.unwrap();
+    assert_eq!(response.status, 200);
+    assert_eq!(response.text(), "pong");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn changed_module_misses_the_cache() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let wasm_path = temp.path().join("app.wasm");
+    let cache_dir = temp.path().join("cache");
+
+    std::fs::write(&wasm_path, wat::parse_str(FIXTURE_WAT).unwrap()).expect("write wasm");
+    start(&wasm_path, &cache_dir).await;
+
+    let changed = FIXTURE_WAT.replace("pong", "PONG");
+    std::fs::write(&wasm_path, wat::parse_str(&changed).unwrap()).expect("write wasm");
+    let server = start(&wasm_path, &cache_dir).await;
+    assert_eq!(server.wasm().module_source(), ModuleSource::Compiled);
+    assert_eq!(cache_files(&cache_dir).len(), 2);
+
+    let response = server.get("/ping").await.unwrap();
+    assert_eq!(response.text(), "PONG");
+}
@@ -0,0 +1,95 @@
+//! `ServerConfig::mounts`: several WASM modules behind one server.
+//!
+//! Each fixture module answers `GET /` and `GET /users` with a body naming
+//! the module, so a response shows which module handled it and which path
+//! it saw after the prefix was stripped.
+
+use clean_server::ServerConfig;
+use clean_server::testing::TestServer;
+
+/// Fixture module whose two handlers return "`<name>` root" and
+/// "`<name>` users". `name` must be exactly five bytes.
+fn fixture_wat(name: &str) -> String {
+    assert_eq!(name.len(), 5);
+    format!(
+        r#"
+(module
+  (import "env" "_http_route"
+    (func $route (param i32 i32 i32 i32 i32 i32) (result i32)))
+  (memory (export "memory") 2)
+  (global (export "__heap_ptr") i32 (i32.const 65536))
+  (data (i32.const 1024) "\0a\00\00\00{name} root")
+  (data (i32.const 1040) "\0b\00\00\00{name} users")
+  (data (i32.const 2048) "GET")
+  (data (i32.const 2056) "/")
+  (data (i32.const 2064) "root")
+  (data (i32.const 2072) "/users")
+  (data (i32.const 2080) "users")
+  (func (export "main")
+    (drop (call $route (i32.const 2048) (i32.const 3)
+      (i32.const 2056) (i32.const 1) (i32.const 2064) (i32.const 4)))
+    (drop (call $route (i32.const 2048) (i32.const 3)
+      (i32.const 2072) (i32.const 6) (i32.const 2080) (i32.const 5))))
+  (func (export "root") (result i32)
+    (i32.const 1024))
+  (func (export "users") (result i32)
+    (i32.const 1040)))
+"#
+    )
+}
+
+async fn mounted_server() -> (TestServer, tempfile::TempDir) {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let mut config = ServerConfig {
+        database_url: None,
+        ..ServerConfig::default()
+    };
+    for (prefix, name) in [("/api", "api-1"), ("/admin", "admin"), ("/api/v2", "api-2")] {
+        let wasm_path = temp.path().join(format!("{}.wasm", name));
+        let wasm_bytes = wat::parse_str(fixture_wat(name)).expect("fixture WAT should compile");
+        std::fs::write(&wasm_path, wasm_bytes).expect("write wasm");
+        config = config.with_mount(prefix, wasm_path);
+    }
+    let server = TestServer::with_mounts(config)
+        .await
+        .expect("fixtures should load");
+    (server, temp)
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn requests_reach_the_module_mounted_at_their_prefix() {
+    let (server, _temp) = mounted_server().await;
+
+    for (path, expected) in [
+        ("/api", "api-1 root"),
+        ("/api/users", "api-1 users"),
+        ("/admin/", "admin root"),
+        ("/admin/users?page=2", "admin users"),
+    ] {
+        let response = server.get(path).await.unwrap();
+        assert_eq!(response.status, 200, "GET {}", path);
+        assert_eq!(response.text(), expected, "GET {}", path);
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn longest_matching_prefix_wins() {
+    let (server, _temp) = mounted_server().await;
+
+    let response = server.get("/api/v2/users").await.unwrap();
+    assert_eq!(response.status, 200);
+    assert_eq!(response.text(), "api-2 users");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn unmatched_prefixes_get_404() {
+    let (server, _temp) = mounted_server().await;
+
+    for path in ["/", "/users", "/apiary", "/other/users"] {
+        let response = server.get(path).await.unwrap();
+        assert_eq!(response.status, 404, "GET {}", path);
+    }
+    // Inside a mount, the module's own router decides.
+    let response = server.get("/admin/missing").await.unwrap();
+    assert_eq!(response.status, 404);
+}
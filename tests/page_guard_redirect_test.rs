@@ -80,6 +80,9 @@ fn build_request() -> RequestContext {
         body_bytes: None,
         params: Default::default(),
         query: Default::default(),
+        raw_query: String::new(),
+        client: None,
+        route_pattern: None,
     }
 }
 
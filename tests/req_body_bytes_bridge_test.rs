@@ -13,14 +13,19 @@
 //! - `_req_body` still returns the string surface when both are populated
 //!   (additive contract, no regression).
 //!
+//! - Over HTTP, a POSTed binary body reaches the handler unchanged and
+//!   `_req_body_len` agrees with the buffer length.
+//!
 //! The tests build a minimal WASM host module (via WAT) that exports `memory`
 //! and `malloc`, imports `_req_body_bytes`, and exposes a call helper. Each
 //! test seeds `state.request_context.body_bytes`, invokes the helper, then
 //! reads back the [4-byte LE length][bytes] LP buffer from the module's
 //! linear memory.
 
+use clean_server::ServerConfig;
 use clean_server::bridge::create_linker;
 use clean_server::router::Router;
+use clean_server::testing::{TestServer, with_malloc};
 use clean_server::wasm::{RequestContext, WasmState};
 use std::sync::Arc;
 use wasmtime::{Engine, Instance, Module, Store, TypedFunc};
@@ -90,6 +95,9 @@ impl BridgeHarness {
             body_bytes: Some(bytes),
             params: Default::default(),
             query: Default::default(),
+            raw_query: String::new(),
+            client: None,
+            route_pattern: None,
         });
     }
 
@@ -102,6 +110,9 @@ impl BridgeHarness {
             body_bytes: None,
             params: Default::default(),
             query: Default::default(),
+            raw_query: String::new(),
+            client: None,
+            route_pattern: None,
         });
     }
 
@@ -114,6 +125,9 @@ impl BridgeHarness {
             body_bytes: Some(bytes),
             params: Default::default(),
             query: Default::default(),
+            raw_query: String::new(),
+            client: None,
+            route_pattern: None,
         });
     }
 
@@ -194,8 +208,8 @@ fn preserves_gzip_magic_and_tar_gz_shaped_payload() {
     gz[0] = 0x1f;
     gz[1] = 0x8b;
     gz[2] = 0x08;
-    for i in 3..gz.len() {
-        gz[i] = ((i * 37) & 0xff) as u8;
+    for (i, byte) in gz.iter_mut().enumerate().skip(3) {
+        *byte = ((i * 37) & 0xff) as u8;
     }
     h.set_body_bytes(gz.clone());
     let (_ptr, out) = h.invoke_and_read();
@@ -308,3 +322,57 @@ fn no_request_context_returns_empty_lp_buffer_not_null() {
     assert!(ptr > 0);
     assert!(out.is_empty());
 }
+
+// ---------------------------------------------------------------------------
+// End-to-end through the HTTP entry point
+// ---------------------------------------------------------------------------
+
+/// `POST /echo` -> `echo`: answers with the raw request body as a binary
+/// response, and with status 500 if `_req_body_len` disagrees with the
+/// `_req_body_bytes` length prefix.
+const ECHO_WAT: &str = r#"
+(module
+  (import "env" "_http_route"
+    (func $route (param i32 i32 i32 i32 i32 i32) (result i32)))
+  (import "env" "_req_body_bytes" (func $body_bytes (result i32)))
+  (import "env" "_req_body_len" (func $body_len (result i32)))
+  (import "env" "_res_set_binary" (func $binary))
+  (import "env" "_res_status" (func $status (param i32)))
+  (memory (export "memory") 2)
+  (data (i32.const 2048) "POST")
+  (data (i32.const 2056) "/echo")
+  (data (i32.const 2064) "echo")
+  (func (export "main")
+    (drop (call $route (i32.const 2048) (i32.const 4)
+      (i32.const 2056) (i32.const 5) (i32.const 2064) (i32.const 4))))
+  (func (export "echo") (result i32)
+    (local $buf i32)
+    (call $binary)
+    (local.set $buf (call $body_bytes))
+    (if (i32.ne (i32.load (local.get $buf)) (call $body_len))
+      (then (call $status (i32.const 500))))
+    (local.get $buf)))
+"#;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn posted_binary_body_reaches_handler_unchanged() {
+    let config = ServerConfig {
+        database_url: None,
+        ..ServerConfig::default()
+    };
+    let server = TestServer::from_wat(&with_malloc(ECHO_WAT), config)
+        .await
+        .unwrap();
+
+    // Protobuf-style varints plus every byte value, none of it valid UTF-8
+    // as a whole.
+    let mut payload = vec![0x08, 0x96, 0x01, 0x12, 0x80, 0xff];
+    payload.extend(0u8..=255);
+
+    let response = server
+        .post("/echo", "application/octet-stream", payload.clone())
+        .await
+        .unwrap();
+    assert_eq!(response.status, 200);
+    assert_eq!(response.body.as_ref(), payload.as_slice());
+}
@@ -0,0 +1,104 @@
+//! `_req_client_ip` / `_req_scheme` bridges.
+//!
+//! `TestServer` requests arrive from a loopback peer, so `X-Forwarded-For` and
+//! `X-Forwarded-Proto` must be ignored unless `127.0.0.1` is configured as a
+//! trusted proxy.
+
+use clean_server::ServerConfig;
+use clean_server::ip_filter::parse_net;
+use clean_server::testing::{TestServer, with_malloc};
+
+/// Registers `GET /ip` (returns `_req_client_ip`) and `GET /scheme` (returns
+/// `_req_scheme`) from `main`.
+const FIXTURE_WAT: &str = r#"
+(module
+  (import "env" "_http_route"
+    (func $route (param i32 i32 i32 i32 i32 i32) (result i32)))
+  (import "env" "_req_client_ip" (func $client_ip (result i32)))
+  (import "env" "_req_scheme" (func $scheme (result i32)))
+  (memory (export "memory") 2)
+  (data (i32.const 1024) "GET")
+  (data (i32.const 1032) "/ip")
+  (data (i32.const 1040) "ip")
+  (data (i32.const 1048) "/scheme")
+  (data (i32.const 1056) "scheme")
+  (func (export "main")
+    (drop (call $route
+      (i32.const 1024) (i32.const 3)
+      (i32.const 1032) (i32.const 3)
+      (i32.const 1040) (i32.const 2)))
+    (drop (call $route
+      (i32.const 1024) (i32.const 3)
+      (i32.const 1048) (i32.const 7)
+      (i32.const 1056) (i32.const 6))))
+  (func (export "ip") (result i32)
+    (call $client_ip))
+  (func (export "scheme") (result i32)
+    (call $scheme)))
+"#;
+
+const FORWARDED: &[(&str, &str)] = &[
+    ("X-Forwarded-For", "203.0.113.9"),
+    ("X-Forwarded-Proto", "https"),
+];
+
+fn proxy_config(trusted_proxies: &[&str]) -> ServerConfig {
+    ServerConfig {
+        database_url: None,
+        ..ServerConfig::default()
+    }
+    .with_trusted_proxies(
+        trusted_proxies
+            .iter()
+            .map(|net| parse_net(net).unwrap())
+            .collect(),
+    )
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn untrusted_peer_forwarded_headers_are_ignored() {
+    let server = TestServer::from_wat(&with_malloc(FIXTURE_WAT), proxy_config(&[]))
+        .await
+        .unwrap();
+
+    let ip = server
+        .request(axum::http::Method::GET, "/ip", FORWARDED, "")
+        .await
+        .unwrap();
+    assert_eq!(ip.text(), "127.0.0.1");
+
+    let scheme = server
+        .request(axum::http::Method::GET, "/scheme", FORWARDED, "")
+        .await
+        .unwrap();
+    assert_eq!(scheme.text(), "http");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn trusted_proxy_forwarded_headers_identify_client() {
+    let server = TestServer::from_wat(&with_malloc(FIXTURE_WAT), proxy_config(&["127.0.0.1"]))
+        .await
+        .unwrap();
+
+    let ip = server
+        .request(axum::http::Method::GET, "/ip", FORWARDED, "")
+        .await
+        .unwrap();
+    assert_eq!(ip.text(), "203.0.113.9");
+
+    let scheme = server
+        .request(axum::http::Method::GET, "/scheme", FORWARDED, "")
+        .await
+        .unwrap();
+    assert_eq!(scheme.text(), "https");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn trusted_proxy_without_forwarded_headers_reports_peer() {
+    let server = TestServer::from_wat(&with_malloc(FIXTURE_WAT), proxy_config(&["127.0.0.1"]))
+        .await
+        .unwrap();
+
+    assert_eq!(server.get("/ip").await.unwrap().text(), "127.0.0.1");
+    assert_eq!(server.get("/scheme").await.unwrap().text(), "http");
+}
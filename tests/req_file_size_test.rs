@@ -0,0 +1,101 @@
+//! `_req_file_size(name)`: size of an uploaded multipart file, read without
+//! copying the file into WASM memory.
+
+use clean_server::ServerConfig;
+use clean_server::testing::{TestResponse, TestServer, with_malloc};
+
+const BOUNDARY: &str = "----clean-test-boundary";
+
+/// Routes:
+/// - `POST /upload` -> `upload`: returns `_req_file_size("avatar")` as text
+const FIXTURE_WAT: &str = r#"
+(module
+  (import "env" "_http_route"
+    (func $route (param i32 i32 i32 i32 i32 i32) (result i32)))
+  (import "env" "_req_file_size" (func $file_size (param i32 i32) (result i32)))
+  (import "env" "int_to_string" (func $to_string (param i32) (result i32)))
+  (memory (export "memory") 2)
+  (data (i32.const 2048) "POST")
+  (data (i32.const 2056) "/upload")
+  (data (i32.const 2064) "upload")
+  (data (i32.const 2072) "avatar")
+  (func (export "main")
+    (drop (call $route (i32.const 2048) (i32.const 4)
+      (i32.const 2056) (i32.const 7) (i32.const 2064) (i32.const 6))))
+  (func (export "upload") (result i32)
+    (call $to_string (call $file_size (i32.const 2072) (i32.const 6)))))
+"#;
+
+fn fixture_config() -> ServerConfig {
+    ServerConfig {
+        database_url: None,
+        ..ServerConfig::default()
+    }
+}
+
+/// Multipart body with a `title` text field and, if given, a file part
+/// named `file_field`.
+fn multipart_body(file_field: Option<(&str, &[u8])>) -> Vec<u8> {
+    let mut body =
+        format!("--{BOUNDARY}\r\nContent-Disposition: form-data; name=\"title\"\r\n\r\nme\r\n")
+            .into_bytes();
+    if let Some((name, contents)) = file_field {
+        body.extend_from_slice(
+            format!(
+                "--{BOUNDARY}\r\nContent-Disposition: form-data; name=\"{name}\"; \
+                 filename=\"me.png\"\r\nContent-Type: image/png\r\n\r\n"
+            )
+            .as_bytes(),
+        );
+        body.extend_from_slice(contents);
+        body.extend_from_slice(b"\r\n");
+    }
+    body.extend_from_slice(format!("--{BOUNDARY}--\r\n").as_bytes());
+    body
+}
+
+async fn upload(server: &TestServer, body: Vec<u8>) -> TestResponse {
+    server
+        .post(
+            "/upload",
+            &format!("multipart/form-data; boundary={}", BOUNDARY),
+            body,
+        )
+        .await
+        .unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn reports_the_size_of_an_uploaded_file() {
+    let server = TestServer::from_wat(&with_malloc(FIXTURE_WAT), fixture_config())
+        .await
+        .unwrap();
+
+    // Binary contents that include CRLFs and a partial boundary
+    let mut contents = b"\x89PNG\r\n\x1a\n\r\n------clean".to_vec();
+    contents.resize(4321, 0xAB);
+    let response = upload(&server, multipart_body(Some(("avatar", &contents)))).await;
+    assert_eq!(response.status, 200, "body: {}", response.text());
+    assert_eq!(response.text(), "4321");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn missing_file_is_minus_one() {
+    let server = TestServer::from_wat(&with_malloc(FIXTURE_WAT), fixture_config())
+        .await
+        .unwrap();
+
+    let other_field = upload(&server, multipart_body(Some(("cover", b"abc")))).await;
+    assert_eq!(other_field.text(), "-1");
+    assert_eq!(upload(&server, multipart_body(None)).await.text(), "-1");
+
+    let not_multipart = server
+        .post(
+            "/upload",
+            "application/json",
+            b"{\"avatar\":\"abc\"}".to_vec(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(not_multipart.text(), "-1");
+}
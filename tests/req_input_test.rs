@@ -0,0 +1,111 @@
+//! `_req_input(name)`: reads a value from path params, query or body, in
+//! `ServerConfig.input_precedence` order.
+
+use clean_server::ServerConfig;
+use clean_server::request_input::InputSource;
+use clean_server::testing::{TestResponse, TestServer, with_malloc};
+
+/// Routes:
+/// - `POST /items/:id` -> `input`: returns `_req_input("name")`
+const FIXTURE_WAT: &str = r#"
+(module
+  (import "env" "_http_route"
+    (func $route (param i32 i32 i32 i32 i32 i32) (result i32)))
+  (import "env" "_req_input" (func $input (param i32 i32) (result i32)))
+  (memory (export "memory") 2)
+  (data (i32.const 2048) "POST")
+  (data (i32.const 2056) "/items/:id")
+  (data (i32.const 2072) "input")
+  (data (i32.const 2080) "name")
+  (func (export "main")
+    (drop (call $route (i32.const 2048) (i32.const 4)
+      (i32.const 2056) (i32.const 10) (i32.const 2072) (i32.const 5))))
+  (func (export "input") (result i32)
+    (call $input (i32.const 2080) (i32.const 4))))
+"#;
+
+async fn post(server: &TestServer, path: &str, content_type: &str, body: &str) -> TestResponse {
+    server
+        .post(path, content_type, body.as_bytes().to_vec())
+        .await
+        .unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn query_wins_over_body_by_default() {
+    let config = ServerConfig {
+        database_url: None,
+        ..ServerConfig::default()
+    };
+    let server = TestServer::from_wat(&with_malloc(FIXTURE_WAT), config)
+        .await
+        .unwrap();
+
+    let response = post(
+        &server,
+        "/items/1?name=from-query",
+        "application/json",
+        r#"{"name":"from-body"}"#,
+    )
+    .await;
+    assert_eq!(response.status, 200, "body: {}", response.text());
+    assert_eq!(response.text(), "from-query");
+
+    // Falls through to the body when the query does not have it
+    let response = post(
+        &server,
+        "/items/1",
+        "application/x-www-form-urlencoded",
+        "name=from+form",
+    )
+    .await;
+    assert_eq!(response.text(), "from form");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn configured_precedence_lets_body_win() {
+    let config = ServerConfig {
+        database_url: None,
+        ..ServerConfig::default()
+    }
+    .with_input_precedence(vec![
+        InputSource::Body,
+        InputSource::Query,
+        InputSource::Path,
+    ]);
+    let server = TestServer::from_wat(&with_malloc(FIXTURE_WAT), config)
+        .await
+        .unwrap();
+
+    let response = post(
+        &server,
+        "/items/1?name=from-query",
+        "application/json",
+        r#"{"name":"from-body"}"#,
+    )
+    .await;
+    assert_eq!(response.text(), "from-body");
+
+    let response = post(
+        &server,
+        "/items/1?name=from-query",
+        "application/json",
+        "{}",
+    )
+    .await;
+    assert_eq!(response.text(), "from-query");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn duplicate_sources_are_rejected_at_startup() {
+    let config = ServerConfig {
+        database_url: None,
+        ..ServerConfig::default()
+    }
+    .with_input_precedence(vec![InputSource::Query, InputSource::Query]);
+    assert!(
+        TestServer::from_wat(&with_malloc(FIXTURE_WAT), config)
+            .await
+            .is_err()
+    );
+}
@@ -0,0 +1,48 @@
+//! `_req_raw_query()`: the query string exactly as received, undecoded and
+//! in its original order.
+
+use clean_server::ServerConfig;
+use clean_server::testing::{TestServer, with_malloc};
+
+/// Routes:
+/// - `GET /webhook` -> `raw_query`: returns `_req_raw_query()`
+const FIXTURE_WAT: &str = r#"
+(module
+  (import "env" "_http_route"
+    (func $route (param i32 i32 i32 i32 i32 i32) (result i32)))
+  (import "env" "_req_raw_query" (func $raw_query (result i32)))
+  (memory (export "memory") 2)
+  (data (i32.const 2048) "GET")
+  (data (i32.const 2056) "/webhook")
+  (data (i32.const 2072) "raw_query")
+  (func (export "main")
+    (drop (call $route (i32.const 2048) (i32.const 3)
+      (i32.const 2056) (i32.const 8) (i32.const 2072) (i32.const 9))))
+  (func (export "raw_query") (result i32)
+    (call $raw_query)))
+"#;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn raw_query_comes_back_verbatim() {
+    let config = ServerConfig {
+        database_url: None,
+        ..ServerConfig::default()
+    };
+    let server = TestServer::from_wat(&with_malloc(FIXTURE_WAT), config)
+        .await
+        .unwrap();
+
+    let response = server.get("/webhook?b=2&a=1").await.unwrap();
+    assert_eq!(response.status, 200, "body: {}", response.text());
+    assert_eq!(response.text(), "b=2&a=1");
+
+    // Percent-encoding and repeated names are left as sent
+    let response = server
+        .get("/webhook?sig=a%2Bb%3D&tag=x&tag=y+z")
+        .await
+        .unwrap();
+    assert_eq!(response.text(), "sig=a%2Bb%3D&tag=x&tag=y+z");
+
+    let response = server.get("/webhook").await.unwrap();
+    assert_eq!(response.text(), "");
+}
@@ -0,0 +1,53 @@
+//! `_req_route_pattern()`: the pattern of the route the router matched,
+//! rather than the concrete request path.
+
+use clean_server::ServerConfig;
+use clean_server::testing::{TestServer, with_malloc};
+
+/// Routes (all -> `pattern`, which returns `_req_route_pattern()`):
+/// - `GET /users/:id`
+/// - `GET /orgs/:org/users/:id`
+/// - `GET /health`
+const FIXTURE_WAT: &str = r#"
+(module
+  (import "env" "_http_route"
+    (func $route (param i32 i32 i32 i32 i32 i32) (result i32)))
+  (import "env" "_req_route_pattern" (func $route_pattern (result i32)))
+  (memory (export "memory") 2)
+  (data (i32.const 2048) "GET")
+  (data (i32.const 2056) "/users/:id")
+  (data (i32.const 2072) "/orgs/:org/users/:id")
+  (data (i32.const 2096) "/health")
+  (data (i32.const 2112) "pattern")
+  (func (export "main")
+    (drop (call $route (i32.const 2048) (i32.const 3)
+      (i32.const 2056) (i32.const 10) (i32.const 2112) (i32.const 7)))
+    (drop (call $route (i32.const 2048) (i32.const 3)
+      (i32.const 2072) (i32.const 20) (i32.const 2112) (i32.const 7)))
+    (drop (call $route (i32.const 2048) (i32.const 3)
+      (i32.const 2096) (i32.const 7) (i32.const 2112) (i32.const 7))))
+  (func (export "pattern") (result i32)
+    (call $route_pattern)))
+"#;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn handler_reads_the_matched_route_pattern() {
+    let config = ServerConfig {
+        database_url: None,
+        ..ServerConfig::default()
+    };
+    let server = TestServer::from_wat(&with_malloc(FIXTURE_WAT), config)
+        .await
+        .unwrap();
+
+    for (path, pattern) in [
+        ("/users/42", "/users/:id"),
+        ("/users/7?tab=posts", "/users/:id"),
+        ("/orgs/acme/users/42", "/orgs/:org/users/:id"),
+        ("/health", "/health"),
+    ] {
+        let response = server.get(path).await.unwrap();
+        assert_eq!(response.status, 200, "{}: {}", path, response.text());
+        assert_eq!(response.text(), pattern, "{}", path);
+    }
+}
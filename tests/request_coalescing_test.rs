@@ -0,0 +1,128 @@
+//! `ServerConfig.coalesce_requests`: identical concurrent GETs wait for the
+//! one already running its handler and share that response.
+
+#![cfg(feature = "sqlite")]
+
+use std::sync::Arc;
+
+use clean_server::ServerConfig;
+use clean_server::coalesce::COALESCED_HEADER;
+use clean_server::testing::{TestServer, with_malloc};
+
+/// Requests fired at once in each test
+const CLIENTS: usize = 8;
+
+/// Routes:
+/// - `GET /slow` -> `slow`: records a row in `hits`, sleeps 500 ms, then
+///   returns "slow"
+const FIXTURE_WAT: &str = r#"
+(module
+  (import "env" "_http_route"
+    (func $route (param i32 i32 i32 i32 i32 i32) (result i32)))
+  (import "env" "_db_execute" (func $execute (param i32 i32 i32 i32) (result i32)))
+  (import "env" "_server_sleep" (func $sleep (param i64)))
+  (memory (export "memory") 2)
+  (data (i32.const 1024) "\04\00\00\00slow")
+  (data (i32.const 2048) "GET")
+  (data (i32.const 2056) "/slow")
+  (data (i32.const 2064) "slow")
+  (data (i32.const 2072) "INSERT INTO hits VALUES (1)")
+  (func (export "main")
+    (drop (call $route (i32.const 2048) (i32.const 3)
+      (i32.const 2056) (i32.const 5) (i32.const 2064) (i32.const 4))))
+  (func (export "slow") (result i32)
+    (drop (call $execute (i32.const 2072) (i32.const 27) (i32.const 0) (i32.const 0)))
+    (call $sleep (i64.const 500))
+    (i32.const 1024)))
+"#;
+
+struct Fixture {
+    server: Arc<TestServer>,
+    database_url: String,
+    _temp: tempfile::TempDir,
+}
+
+async fn fixture_server(coalesce: bool) -> Fixture {
+    use sqlx::Connection;
+
+    let temp = tempfile::tempdir().expect("tempdir");
+    let database_url = format!("sqlite://{}?mode=rwc", temp.path().join("app.db").display());
+    let mut conn = sqlx::SqliteConnection::connect(&database_url)
+        .await
+        .expect("connect");
+    sqlx::query("CREATE TABLE hits (n INTEGER)")
+        .execute(&mut conn)
+        .await
+        .expect("create");
+
+    let config = ServerConfig {
+        database_url: Some(database_url.clone()),
+        ..ServerConfig::default()
+    }
+    .with_coalesce_requests(coalesce);
+    let server = TestServer::from_wat(&with_malloc(FIXTURE_WAT), config)
+        .await
+        .unwrap();
+    Fixture {
+        server: Arc::new(server),
+        database_url,
+        _temp: temp,
+    }
+}
+
+impl Fixture {
+    /// Number of times the handler ran
+    async fn hits(&self) -> i64 {
+        use sqlx::Connection;
+
+        let mut conn = sqlx::SqliteConnection::connect(&self.database_url)
+            .await
+            .expect("connect");
+        sqlx::query_scalar("SELECT COUNT(*) FROM hits")
+            .fetch_one(&mut conn)
+            .await
+            .expect("count")
+    }
+
+    /// Fire `CLIENTS` identical GETs at once; returns how many were served
+    /// from another request's response
+    async fn stampede(&self) -> usize {
+        let requests: Vec<_> = (0..CLIENTS)
+            .map(|_| {
+                let server = Arc::clone(&self.server);
+                tokio::spawn(async move { server.get("/slow").await.unwrap() })
+            })
+            .collect();
+        let mut coalesced = 0;
+        for request in requests {
+            let response = request.await.expect("request task");
+            assert_eq!(response.status, 200);
+            assert_eq!(response.text(), "slow");
+            if response.header(COALESCED_HEADER) == Some("true") {
+                coalesced += 1;
+            }
+        }
+        coalesced
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn concurrent_identical_gets_run_the_handler_once() {
+    let fixture = fixture_server(true).await;
+
+    assert_eq!(fixture.stampede().await, CLIENTS - 1);
+    assert_eq!(fixture.hits().await, 1);
+
+    // Once the flight has landed, the next request runs the handler again
+    let response = fixture.server.get("/slow").await.unwrap();
+    assert_eq!(response.header(COALESCED_HEADER), None);
+    assert_eq!(fixture.hits().await, 2);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn without_coalescing_every_request_runs_the_handler() {
+    let fixture = fixture_server(false).await;
+
+    assert_eq!(fixture.stampede().await, 0);
+    assert_eq!(fixture.hits().await, CLIENTS as i64);
+}
@@ -0,0 +1,107 @@
+//! Request-scoped context store shared by `_http_middleware` exports and
+//! route handlers through `_ctx_set` / `_ctx_get`.
+
+use clean_server::ServerConfig;
+use clean_server::testing::{TestResponse, TestServer, with_malloc};
+
+/// Middleware (in registration order):
+/// - `copy_user`: copies the `X-User` request header into ctx `user`, if sent
+/// - `mark_first`, `mark_second`: each set ctx `order` to their own name
+///
+/// Routes:
+/// - `GET /whoami` -> `whoami`: returns ctx `user`
+/// - `GET /order`  -> `order`: returns ctx `order`
+const FIXTURE_WAT: &str = r#"
+(module
+  (import "env" "_http_route"
+    (func $route (param i32 i32 i32 i32 i32 i32) (result i32)))
+  (import "env" "_http_middleware" (func $middleware (param i32 i32) (result i32)))
+  (import "env" "_req_header" (func $header (param i32 i32) (result i32)))
+  (import "env" "_ctx_set" (func $ctx_set (param i32 i32 i32 i32) (result i32)))
+  (import "env" "_ctx_get" (func $ctx_get (param i32 i32) (result i32)))
+  (memory (export "memory") 2)
+  (data (i32.const 2048) "GET")
+  (data (i32.const 2056) "/whoami")
+  (data (i32.const 2064) "whoami")
+  (data (i32.const 2072) "/order")
+  (data (i32.const 2080) "order")
+  (data (i32.const 2088) "copy_user")
+  (data (i32.const 2104) "mark_first")
+  (data (i32.const 2120) "mark_second")
+  (data (i32.const 2136) "x-user")
+  (data (i32.const 2144) "user")
+  (func (export "main")
+    (drop (call $middleware (i32.const 2088) (i32.const 9)))
+    (drop (call $middleware (i32.const 2104) (i32.const 10)))
+    (drop (call $middleware (i32.const 2120) (i32.const 11)))
+    (drop (call $route (i32.const 2048) (i32.const 3)
+      (i32.const 2056) (i32.const 7) (i32.const 2064) (i32.const 6)))
+    (drop (call $route (i32.const 2048) (i32.const 3)
+      (i32.const 2072) (i32.const 6) (i32.const 2080) (i32.const 5))))
+  (func (export "copy_user") (result i32)
+    (local $user i32)
+    (local.set $user (call $header (i32.const 2136) (i32.const 6)))
+    (if (i32.gt_s (i32.load (local.get $user)) (i32.const 0))
+      (then
+        (drop (call $ctx_set (i32.const 2144) (i32.const 4)
+          (i32.add (local.get $user) (i32.const 4)) (i32.load (local.get $user))))))
+    (i32.const 0))
+  (func (export "mark_first") (result i32)
+    (call $ctx_set (i32.const 2080) (i32.const 5) (i32.const 2104) (i32.const 10)))
+  (func (export "mark_second") (result i32)
+    (call $ctx_set (i32.const 2080) (i32.const 5) (i32.const 2120) (i32.const 11)))
+  (func (export "whoami") (result i32)
+    (call $ctx_get (i32.const 2144) (i32.const 4)))
+  (func (export "order") (result i32)
+    (call $ctx_get (i32.const 2080) (i32.const 5))))
+"#;
+
+fn fixture_config() -> ServerConfig {
+    ServerConfig {
+        database_url: None,
+        ..ServerConfig::default()
+    }
+}
+
+async fn get(server: &TestServer, path: &str, headers: &[(&str, &str)]) -> TestResponse {
+    server
+        .request(axum::http::Method::GET, path, headers, "")
+        .await
+        .unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn handler_reads_value_set_by_middleware() {
+    let server = TestServer::from_wat(&with_malloc(FIXTURE_WAT), fixture_config())
+        .await
+        .unwrap();
+
+    let response = get(&server, "/whoami", &[("x-user", "alice")]).await;
+    assert_eq!(response.status, 200);
+    assert_eq!(response.text(), "alice");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn values_do_not_leak_into_the_next_request() {
+    let server = TestServer::from_wat(&with_malloc(FIXTURE_WAT), fixture_config())
+        .await
+        .unwrap();
+
+    let first = get(&server, "/whoami", &[("x-user", "alice")]).await;
+    assert_eq!(first.text(), "alice");
+
+    let second = get(&server, "/whoami", &[]).await;
+    assert_eq!(second.status, 200);
+    assert_eq!(second.text(), "");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn middleware_runs_in_registration_order() {
+    let server = TestServer::from_wat(&with_malloc(FIXTURE_WAT), fixture_config())
+        .await
+        .unwrap();
+
+    let response = get(&server, "/order", &[]).await;
+    assert_eq!(response.status, 200);
+    assert_eq!(response.text(), "mark_second");
+}
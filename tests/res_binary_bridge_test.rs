@@ -0,0 +1,97 @@
+//! `_res_set_binary` bridge.
+//!
+//! A handler that calls `_res_set_binary()` has its length-prefixed return
+//! value sent as raw bytes; handlers that don't keep UTF-8 validation.
+
+use clean_server::ServerConfig;
+use clean_server::testing::TestServer;
+
+/// 8-byte PNG signature followed by an IHDR chunk header and bytes that are
+/// invalid UTF-8 (0x89, 0xff, 0xfe, 0x00).
+const PNG_BYTES: &[u8] = b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0dIHDR\xff\xfe\x00\x01";
+
+/// Routes:
+/// - `GET /raw.png`  -> `raw`:  `_res_set_binary()`, default content type
+/// - `GET /logo.png` -> `logo`: `_res_set_binary()` + `Content-Type: image/png`
+/// - `GET /text`     -> `text`: returns the same bytes as a text body
+const FIXTURE_WAT: &str = r#"
+(module
+  (import "env" "_http_route"
+    (func $route (param i32 i32 i32 i32 i32 i32) (result i32)))
+  (import "env" "_res_set_binary" (func $binary))
+  (import "env" "_res_set_header" (func $header (param i32 i32 i32 i32) (result i32)))
+  (memory (export "memory") 2)
+  (global (export "__heap_ptr") i32 (i32.const 65536))
+  ;; length-prefixed PNG payload (20 bytes)
+  (data (i32.const 1024) "\14\00\00\00\89PNG\0d\0a\1a\0a\00\00\00\0dIHDR\ff\fe\00\01")
+  (data (i32.const 2048) "GET")
+  (data (i32.const 2056) "/raw.png")
+  (data (i32.const 2072) "raw")
+  (data (i32.const 2080) "/logo.png")
+  (data (i32.const 2096) "logo")
+  (data (i32.const 2104) "/text")
+  (data (i32.const 2112) "text")
+  (data (i32.const 2120) "Content-Type")
+  (data (i32.const 2136) "image/png")
+  (func (export "main")
+    (drop (call $route (i32.const 2048) (i32.const 3)
+      (i32.const 2056) (i32.const 8) (i32.const 2072) (i32.const 3)))
+    (drop (call $route (i32.const 2048) (i32.const 3)
+      (i32.const 2080) (i32.const 9) (i32.const 2096) (i32.const 4)))
+    (drop (call $route (i32.const 2048) (i32.const 3)
+      (i32.const 2104) (i32.const 5) (i32.const 2112) (i32.const 4))))
+  (func (export "raw") (result i32)
+    (call $binary)
+    (i32.const 1024))
+  (func (export "logo") (result i32)
+    (call $binary)
+    (drop (call $header (i32.const 2120) (i32.const 12) (i32.const 2136) (i32.const 9)))
+    (i32.const 1024))
+  (func (export "text") (result i32)
+    (i32.const 1024)))
+"#;
+
+fn fixture_config() -> ServerConfig {
+    ServerConfig {
+        database_url: None,
+        ..ServerConfig::default()
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn binary_body_arrives_byte_identical() {
+    let server = TestServer::from_wat(FIXTURE_WAT, fixture_config())
+        .await
+        .unwrap();
+
+    let response = server.get("/raw.png").await.unwrap();
+    assert_eq!(response.status, 200);
+    assert_eq!(response.body.as_ref(), PNG_BYTES);
+    assert_eq!(
+        response.header("content-type"),
+        Some("application/octet-stream")
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn binary_body_keeps_explicit_content_type() {
+    let server = TestServer::from_wat(FIXTURE_WAT, fixture_config())
+        .await
+        .unwrap();
+
+    let response = server.get("/logo.png").await.unwrap();
+    assert_eq!(response.status, 200);
+    assert_eq!(response.body.as_ref(), PNG_BYTES);
+    assert_eq!(response.header("content-type"), Some("image/png"));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn text_body_still_rejects_invalid_utf8() {
+    let server = TestServer::from_wat(FIXTURE_WAT, fixture_config())
+        .await
+        .unwrap();
+
+    let response = server.get("/text").await.unwrap();
+    assert_eq!(response.status, 500);
+    assert_ne!(response.body.as_ref(), PNG_BYTES);
+}
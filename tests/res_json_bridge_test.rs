@@ -0,0 +1,73 @@
+//! `_res_json` bridge.
+//!
+//! A handler passing valid JSON to `_res_json` gets it sent with
+//! `Content-Type: application/json`; a payload that does not parse turns the
+//! response into a 500 sent as plain text, even though its body starts with
+//! `{`.
+
+use clean_server::ServerConfig;
+use clean_server::testing::TestServer;
+
+/// Routes (each calls `_res_json` on its length-prefixed payload, then
+/// returns that payload as the body):
+/// - `GET /user` -> `user`: `{"id":7,"name":"Ada"}`
+/// - `GET /broken` -> `broken`: `{"id":` (truncated)
+const FIXTURE_WAT: &str = r#"
+(module
+  (import "env" "_http_route"
+    (func $route (param i32 i32 i32 i32 i32 i32) (result i32)))
+  (import "env" "_res_json" (func $json (param i32 i32)))
+  (memory (export "memory") 2)
+  (global (export "__heap_ptr") i32 (i32.const 65536))
+  (data (i32.const 1024) "\15\00\00\00{\"id\":7,\"name\":\"Ada\"}")
+  (data (i32.const 1056) "\06\00\00\00{\"id\":")
+  (data (i32.const 2048) "GET")
+  (data (i32.const 2056) "/user")
+  (data (i32.const 2064) "user")
+  (data (i32.const 2072) "/broken")
+  (data (i32.const 2080) "broken")
+  (func (export "main")
+    (drop (call $route (i32.const 2048) (i32.const 3)
+      (i32.const 2056) (i32.const 5) (i32.const 2064) (i32.const 4)))
+    (drop (call $route (i32.const 2048) (i32.const 3)
+      (i32.const 2072) (i32.const 7) (i32.const 2080) (i32.const 6))))
+  (func (export "user") (result i32)
+    (call $json (i32.const 1028) (i32.const 21))
+    (i32.const 1024))
+  (func (export "broken") (result i32)
+    (call $json (i32.const 1060) (i32.const 6))
+    (i32.const 1056)))
+"#;
+
+fn fixture_config() -> ServerConfig {
+    ServerConfig {
+        database_url: None,
+        ..ServerConfig::default()
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn valid_json_is_sent_as_application_json() {
+    let server = TestServer::from_wat(FIXTURE_WAT, fixture_config())
+        .await
+        .unwrap();
+
+    let response = server.get("/user").await.unwrap();
+    assert_eq!(response.status, 200);
+    assert_eq!(response.header("content-type"), Some("application/json"));
+    assert_eq!(response.text(), r#"{"id":7,"name":"Ada"}"#);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn invalid_json_fails_the_response() {
+    let server = TestServer::from_wat(FIXTURE_WAT, fixture_config())
+        .await
+        .unwrap();
+
+    let response = server.get("/broken").await.unwrap();
+    assert_eq!(response.status, 500);
+    assert_eq!(
+        response.header("content-type"),
+        Some("text/plain; charset=utf-8")
+    );
+}
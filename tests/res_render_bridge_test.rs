@@ -0,0 +1,88 @@
+//! `_res_render` server-side templates.
+//!
+//! Templates are read from `ServerConfig::templates_dir`; a missing template
+//! turns the response into a 500 with a `TEMPLATE_ERROR` JSON body.
+
+use clean_server::ServerConfig;
+use clean_server::testing::{TestServer, with_malloc};
+
+/// Routes:
+/// - `GET /hello` -> `hello`: renders `hello.html` with
+///   `{"name":"<Ada>","count":3}`
+/// - `GET /missing` -> `missing`: renders `missing.html`, which does not exist
+const FIXTURE_WAT: &str = r#"
+(module
+  (import "env" "_http_route"
+    (func $route (param i32 i32 i32 i32 i32 i32) (result i32)))
+  (import "env" "_res_render" (func $render (param i32 i32 i32 i32) (result i32)))
+  (memory (export "memory") 2)
+  (data (i32.const 2048) "GET")
+  (data (i32.const 2056) "/hello")
+  (data (i32.const 2064) "hello")
+  (data (i32.const 2072) "/missing")
+  (data (i32.const 2088) "missing")
+  (data (i32.const 2104) "hello.html")
+  (data (i32.const 2120) "missing.html")
+  (data (i32.const 2136) "{\22name\22:\22<Ada>\22,\22count\22:3}")
+  (func (export "main")
+    (drop (call $route (i32.const 2048) (i32.const 3)
+      (i32.const 2056) (i32.const 6) (i32.const 2064) (i32.const 5)))
+    (drop (call $route (i32.const 2048) (i32.const 3)
+      (i32.const 2072) (i32.const 8) (i32.const 2088) (i32.const 7))))
+  (func (export "hello") (result i32)
+    (call $render (i32.const 2104) (i32.const 10) (i32.const 2136) (i32.const 26)))
+  (func (export "missing") (result i32)
+    (call $render (i32.const 2120) (i32.const 12) (i32.const 0) (i32.const 0))))
+"#;
+
+async fn fixture_server() -> (TestServer, tempfile::TempDir) {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let templates = temp.path().join("templates");
+    std::fs::create_dir(&templates).expect("templates dir");
+    std::fs::write(
+        templates.join("hello.html"),
+        "<h1>Hello {{name}}</h1><p>{{count}} new</p>",
+    )
+    .expect("write template");
+
+    let config = ServerConfig {
+        database_url: None,
+        ..ServerConfig::default()
+    }
+    .with_templates_dir(templates);
+    let server = TestServer::from_wat(&with_malloc(FIXTURE_WAT), config)
+        .await
+        .unwrap();
+    (server, temp)
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn renders_template_with_escaped_variables() {
+    let (server, _temp) = fixture_server().await;
+
+    let response = server.get("/hello").await.unwrap();
+    assert_eq!(response.status, 200);
+    assert_eq!(
+        response.header("content-type"),
+        Some("text/html; charset=utf-8")
+    );
+    assert_eq!(response.text(), "<h1>Hello &lt;Ada&gt;</h1><p>3 new</p>");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn missing_template_is_a_500_json_error() {
+    let (server, _temp) = fixture_server().await;
+
+    let response = server.get("/missing").await.unwrap();
+    assert_eq!(response.status, 500);
+    assert_eq!(response.header("content-type"), Some("application/json"));
+    let json = response.json().unwrap();
+    assert_eq!(json["ok"], false);
+    assert_eq!(json["err"]["code"], "TEMPLATE_ERROR");
+    assert!(
+        json["err"]["message"]
+            .as_str()
+            .unwrap()
+            .contains("missing.html")
+    );
+}
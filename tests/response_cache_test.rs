@@ -0,0 +1,193 @@
+//! `ServerConfig::response_cache` GET response caching.
+//!
+//! Every fixture handler returns a fresh `_crypto_uuid()`, so two identical
+//! bodies mean the second response came from the cache rather than a second
+//! handler call.
+
+use clean_server::ServerConfig;
+use clean_server::response_cache::CacheConfig;
+use clean_server::testing::{TestResponse, TestServer, with_malloc};
+
+/// Routes (all return a new UUID):
+/// - `GET /fresh`   -> `fresh`
+/// - `GET /nostore` -> `nostore`: sets `Cache-Control: no-store`
+const FIXTURE_WAT: &str = r#"
+(module
+  (import "env" "_http_route"
+    (func $route (param i32 i32 i32 i32 i32 i32) (result i32)))
+  (import "env" "_crypto_uuid" (func $uuid (result i32)))
+  (import "env" "_res_set_header" (func $header (param i32 i32 i32 i32) (result i32)))
+  (memory (export "memory") 2)
+  (data (i32.const 2048) "GET")
+  (data (i32.const 2056) "/fresh")
+  (data (i32.const 2064) "fresh")
+  (data (i32.const 2072) "/nostore")
+  (data (i32.const 2088) "nostore")
+  (data (i32.const 2104) "Cache-Control")
+  (data (i32.const 2120) "no-store")
+  (func (export "main")
+    (drop (call $route (i32.const 2048) (i32.const 3)
+      (i32.const 2056) (i32.const 6) (i32.const 2064) (i32.const 5)))
+    (drop (call $route (i32.const 2048) (i32.const 3)
+      (i32.const 2072) (i32.const 8) (i32.const 2088) (i32.const 7))))
+  (func (export "fresh") (result i32)
+    (call $uuid))
+  (func (export "nostore") (result i32)
+    (drop (call $header (i32.const 2104) (i32.const 13) (i32.const 2120) (i32.const 8)))
+    (call $uuid)))
+"#;
+
+/// Routes:
+/// - `POST /login`  -> `login`: starts a session from the JSON request body
+/// - `GET /me`      -> `me` (protected): returns `_auth_get_session`
+/// - `GET /whoami`  -> `me`: the same, on an unprotected route
+const SESSION_WAT: &str = r#"
+(module
+  (import "env" "_http_route"
+    (func $route (param i32 i32 i32 i32 i32 i32) (result i32)))
+  (import "env" "_http_route_protected"
+    (func $protected (param i32 i32 i32 i32 i32 i32 i32 i32) (result i32)))
+  (import "env" "_req_body" (func $req_body (result i32)))
+  (import "env" "_auth_set_session" (func $set_session (param i32 i32) (result i32)))
+  (import "env" "_auth_get_session" (func $session (result i32)))
+  (memory (export "memory") 2)
+  (data (i32.const 2048) "POST")
+  (data (i32.const 2056) "GET")
+  (data (i32.const 2064) "/login")
+  (data (i32.const 2072) "login")
+  (data (i32.const 2080) "/me")
+  (data (i32.const 2088) "me")
+  (data (i32.const 2096) "/whoami")
+  (func (export "main")
+    (drop (call $route (i32.const 2048) (i32.const 4)
+      (i32.const 2064) (i32.const 6) (i32.const 2072) (i32.const 5)))
+    (drop (call $protected (i32.const 2056) (i32.const 3)
+      (i32.const 2080) (i32.const 3) (i32.const 2088) (i32.const 2)
+      (i32.const 0) (i32.const 0)))
+    (drop (call $route (i32.const 2056) (i32.const 3)
+      (i32.const 2096) (i32.const 7) (i32.const 2088) (i32.const 2))))
+  (func (export "login") (result i32)
+    (local $body i32)
+    (local.set $body (call $req_body))
+    (drop (call $set_session
+      (i32.add (local.get $body) (i32.const 4)) (i32.load (local.get $body))))
+    (call $session))
+  (func (export "me") (result i32)
+    (call $session)))
+"#;
+
+fn cache_config(cache: CacheConfig) -> ServerConfig {
+    ServerConfig {
+        database_url: None,
+        ..ServerConfig::default()
+    }
+    .with_response_cache(cache)
+}
+
+async fn get(server: &TestServer, path: &str, headers: &[(&str, &str)]) -> TestResponse {
+    server
+        .request(axum::http::Method::GET, path, headers, "")
+        .await
+        .unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn repeated_get_is_served_from_cache() {
+    let server = TestServer::from_wat(
+        &with_malloc(FIXTURE_WAT),
+        cache_config(CacheConfig::default()),
+    )
+    .await
+    .unwrap();
+
+    let first = get(&server, "/fresh", &[]).await;
+    assert_eq!(first.status, 200);
+    assert_eq!(first.header("x-cache"), Some("MISS"));
+
+    let second = get(&server, "/fresh", &[]).await;
+    assert_eq!(second.status, 200);
+    assert_eq!(second.header("x-cache"), Some("HIT"));
+    assert_eq!(second.body, first.body);
+    assert_eq!(second.header("content-type"), first.header("content-type"));
+
+    // The query string is part of the key.
+    let other = get(&server, "/fresh?page=2", &[]).await;
+    assert_eq!(other.header("x-cache"), Some("MISS"));
+    assert_ne!(other.body, first.body);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn no_store_response_is_not_cached() {
+    let server = TestServer::from_wat(
+        &with_malloc(FIXTURE_WAT),
+        cache_config(CacheConfig::default()),
+    )
+    .await
+    .unwrap();
+
+    let first = get(&server, "/nostore", &[]).await;
+    let second = get(&server, "/nostore", &[]).await;
+    assert_eq!(first.status, 200);
+    assert_eq!(second.header("cache-control"), Some("no-store"));
+    assert_eq!(second.header("x-cache"), Some("MISS"));
+    assert_ne!(second.body, first.body);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn vary_headers_split_the_cache() {
+    let config = cache_config(CacheConfig {
+        vary: vec!["Accept-Language".to_string()],
+        ..CacheConfig::default()
+    });
+    let server = TestServer::from_wat(&with_malloc(FIXTURE_WAT), config)
+        .await
+        .unwrap();
+
+    let en = get(&server, "/fresh", &[("accept-language", "en")]).await;
+    let fr = get(&server, "/fresh", &[("accept-language", "fr")]).await;
+    assert_eq!(fr.header("x-cache"), Some("MISS"));
+    assert_ne!(fr.body, en.body);
+
+    let en_again = get(&server, "/fresh", &[("accept-language", "en")]).await;
+    assert_eq!(en_again.header("x-cache"), Some("HIT"));
+    assert_eq!(en_again.body, en.body);
+}
+
+/// `Cookie` header value for a new session of `user_id`
+async fn login(server: &TestServer, user_id: u32) -> String {
+    let body = format!(r#"{{"user_id":{},"role":"user"}}"#, user_id);
+    let response = server
+        .post("/login", "application/json", body)
+        .await
+        .unwrap();
+    assert_eq!(response.status, 200, "body: {}", response.text());
+    let set_cookie = response.header("set-cookie").expect("session cookie");
+    set_cookie.split(';').next().unwrap().to_string()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn one_users_response_is_never_served_to_another() {
+    let config = cache_config(CacheConfig::default()).with_cookie_sessions("cache-secret");
+    let server = TestServer::from_wat(&with_malloc(SESSION_WAT), config)
+        .await
+        .unwrap();
+    let alice = login(&server, 1).await;
+    let bob = login(&server, 2).await;
+
+    for path in ["/me", "/whoami"] {
+        let first = get(&server, path, &[("cookie", &alice)]).await;
+        assert_eq!(first.status, 200, "{path}: {}", first.text());
+        assert_eq!(first.json().unwrap()["user_id"], 1, "{path}");
+        let again = get(&server, path, &[("cookie", &alice)]).await;
+        assert_eq!(again.header("x-cache"), None, "{path}");
+
+        let other = get(&server, path, &[("cookie", &bob)]).await;
+        assert_eq!(other.json().unwrap()["user_id"], 2, "{path}");
+    }
+
+    // Anonymous requests get neither user's response
+    assert_eq!(get(&server, "/me", &[]).await.status, 401);
+    let anonymous = get(&server, "/whoami", &[]).await;
+    assert_eq!(anonymous.header("x-cache"), Some("MISS"));
+    assert_eq!(anonymous.text(), "null");
+}
@@ -0,0 +1,126 @@
+//! `ServerConfig.role_hierarchy`: a role passes route role requirements at
+//! or below it in the hierarchy.
+
+use clean_server::ServerConfig;
+use clean_server::auth::AuthConfig;
+use clean_server::testing::{TestResponse, TestServer, with_malloc};
+use jsonwebtoken::{EncodingKey, Header};
+
+const SECRET: &str = "test-secret";
+
+/// Routes:
+/// - `GET /reports` -> `ok`, requires `viewer`
+/// - `GET /users`   -> `ok`, requires `admin`
+/// - `GET /editors` -> `editors`, requires `viewer`: "yes" when
+///   `_auth_has_any_role(["editor"])` passes, otherwise "no"
+const FIXTURE_WAT: &str = r#"
+(module
+  (import "env" "_http_route_protected"
+    (func $route (param i32 i32 i32 i32 i32 i32 i32 i32) (result i32)))
+  (import "env" "_auth_has_any_role" (func $has_any_role (param i32 i32) (result i32)))
+  (memory (export "memory") 2)
+  (data (i32.const 1024) "\02\00\00\00ok")
+  (data (i32.const 1040) "\03\00\00\00yes")
+  (data (i32.const 1056) "\02\00\00\00no")
+  (data (i32.const 2048) "GET")
+  (data (i32.const 2056) "/reports")
+  (data (i32.const 2072) "/users")
+  (data (i32.const 2080) "ok")
+  (data (i32.const 2088) "viewer")
+  (data (i32.const 2096) "admin")
+  (data (i32.const 2104) "/editors")
+  (data (i32.const 2112) "editors")
+  (data (i32.const 2120) "[\22editor\22]")
+  (func (export "main")
+    (drop (call $route (i32.const 2048) (i32.const 3)
+      (i32.const 2056) (i32.const 8) (i32.const 2080) (i32.const 2)
+      (i32.const 2088) (i32.const 6)))
+    (drop (call $route (i32.const 2048) (i32.const 3)
+      (i32.const 2072) (i32.const 6) (i32.const 2080) (i32.const 2)
+      (i32.const 2096) (i32.const 5)))
+    (drop (call $route (i32.const 2048) (i32.const 3)
+      (i32.const 2104) (i32.const 8) (i32.const 2112) (i32.const 7)
+      (i32.const 2088) (i32.const 6))))
+  (func (export "ok") (result i32)
+    (i32.const 1024))
+  (func (export "editors") (result i32)
+    (select (i32.const 1040) (i32.const 1056)
+      (call $has_any_role (i32.const 2120) (i32.const 10)))))
+"#;
+
+fn fixture_config() -> ServerConfig {
+    ServerConfig {
+        database_url: None,
+        ..ServerConfig::default()
+    }
+    .with_auth(AuthConfig::JwtSecret(SECRET.to_string()))
+    .with_role_hierarchy(
+        ["owner", "admin", "editor", "viewer"]
+            .map(String::from)
+            .to_vec(),
+    )
+}
+
+async fn get_as(server: &TestServer, path: &str, role: &str) -> TestResponse {
+    let claims = serde_json::json!({
+        "sub": 1,
+        "role": role,
+        "exp": chrono::Utc::now().timestamp() + 3600,
+    });
+    let token = jsonwebtoken::encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(SECRET.as_bytes()),
+    )
+    .unwrap();
+    server
+        .request(
+            axum::http::Method::GET,
+            path,
+            &[("authorization", &format!("Bearer {}", token))],
+            "",
+        )
+        .await
+        .unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn editor_passes_a_viewer_requirement() {
+    let server = TestServer::from_wat(&with_malloc(FIXTURE_WAT), fixture_config())
+        .await
+        .unwrap();
+
+    let response = get_as(&server, "/reports", "editor").await;
+    assert_eq!(response.status, 200, "body: {}", response.text());
+    assert_eq!(response.text(), "ok");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn editor_fails_an_admin_requirement() {
+    let server = TestServer::from_wat(&with_malloc(FIXTURE_WAT), fixture_config())
+        .await
+        .unwrap();
+
+    assert_eq!(get_as(&server, "/users", "editor").await.status, 403);
+    assert_eq!(get_as(&server, "/users", "owner").await.status, 200);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn roles_outside_the_hierarchy_only_match_themselves() {
+    let server = TestServer::from_wat(&with_malloc(FIXTURE_WAT), fixture_config())
+        .await
+        .unwrap();
+
+    assert_eq!(get_as(&server, "/reports", "guest").await.status, 403);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn has_any_role_applies_the_hierarchy() {
+    let server = TestServer::from_wat(&with_malloc(FIXTURE_WAT), fixture_config())
+        .await
+        .unwrap();
+
+    assert_eq!(get_as(&server, "/editors", "admin").await.text(), "yes");
+    assert_eq!(get_as(&server, "/editors", "editor").await.text(), "yes");
+    assert_eq!(get_as(&server, "/editors", "viewer").await.text(), "no");
+}
@@ -0,0 +1,146 @@
+//! `_http_route_schema` request body validation.
+//!
+//! The fixture registers `POST /users` with a JSON Schema; its handler echoes
+//! the body, so a 200 proves the handler ran and a 422 proves it did not.
+
+use clean_server::ServerConfig;
+use clean_server::testing::{TestServer, with_malloc};
+
+const SCHEMA: &str = r#"{"type":"object","required":["name"],"additionalProperties":false,"properties":{"name":{"type":"string","minLength":1},"age":{"type":"integer","minimum":0}}}"#;
+
+/// `main` registers `POST /users` -> `create` with `SCHEMA`; `create` returns
+/// `_req_body`.
+fn fixture_wat() -> String {
+    format!(
+        r#"
+(module
+  (import "env" "_http_route_schema"
+    (func $route_schema (param i32 i32 i32 i32 i32 i32 i32 i32) (result i32)))
+  (import "env" "_req_body" (func $body (result i32)))
+  (memory (export "memory") 2)
+  (data (i32.const 1024) "POST")
+  (data (i32.const 1032) "/users")
+  (data (i32.const 1040) "create")
+  (data (i32.const 2048) "{schema}")
+  (func (export "main")
+    (drop (call $route_schema
+      (i32.const 1024) (i32.const 4)
+      (i32.const 1032) (i32.const 6)
+      (i32.const 1040) (i32.const 6)
+      (i32.const 2048) (i32.const {schema_len}))))
+  (func (export "create") (result i32)
+    (call $body)))
+"#,
+        schema = SCHEMA.replace('"', "\\\""),
+        schema_len = SCHEMA.len(),
+    )
+}
+
+fn fixture_config() -> ServerConfig {
+    ServerConfig {
+        database_url: None,
+        ..ServerConfig::default()
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn valid_body_reaches_handler() {
+    let server = TestServer::from_wat(&with_malloc(&fixture_wat()), fixture_config())
+        .await
+        .unwrap();
+
+    let body = r#"{"name":"Ada","age":36}"#;
+    let response = server
+        .post("/users", "application/json", body)
+        .await
+        .unwrap();
+    assert_eq!(response.status, 200);
+    assert_eq!(response.text(), body);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn invalid_body_gets_422_with_field_errors() {
+    let server = TestServer::from_wat(&with_malloc(&fixture_wat()), fixture_config())
+        .await
+        .unwrap();
+
+    let response = server
+        .post("/users", "application/json", r#"{"age":-1,"admin":true}"#)
+        .await
+        .unwrap();
+    assert_eq!(response.status, 422);
+
+    let json = response.json().unwrap();
+    assert_eq!(json["ok"], false);
+    assert_eq!(json["error"]["code"], 422);
+    let mut paths: Vec<String> = json["error"]["details"]["errors"]
+        .as_array()
+        .expect("details should list errors")
+        .iter()
+        .map(|e| e["path"].as_str().unwrap().to_string())
+        .collect();
+    paths.sort();
+    assert_eq!(paths, vec!["/admin", "/age", "/name"]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn every_violation_is_reported_with_path_and_message() {
+    let server = TestServer::from_wat(&with_malloc(&fixture_wat()), fixture_config())
+        .await
+        .unwrap();
+
+    let response = server
+        .post(
+            "/users",
+            "application/json",
+            r#"{"name":"","age":-1,"admin":true}"#,
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status, 422);
+    let json = response.json().unwrap();
+    let mut errors: Vec<(String, String)> = json["error"]["details"]["errors"]
+        .as_array()
+        .expect("details.errors should list every violation")
+        .iter()
+        .map(|e| {
+            (
+                e["path"].as_str().unwrap().to_string(),
+                e["message"].as_str().unwrap().to_string(),
+            )
+        })
+        .collect();
+    errors.sort();
+    assert_eq!(
+        errors,
+        vec![
+            (
+                "/admin".to_string(),
+                "Additional properties are not allowed ('admin' was unexpected)".to_string()
+            ),
+            (
+                "/age".to_string(),
+                "-1 is less than the minimum of 0".to_string()
+            ),
+            (
+                "/name".to_string(),
+                r#""" is shorter than 1 character"#.to_string()
+            ),
+        ],
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn non_json_body_gets_422() {
+    let server = TestServer::from_wat(&with_malloc(&fixture_wat()), fixture_config())
+        .await
+        .unwrap();
+
+    let response = server
+        .post("/users", "application/json", "name=Ada")
+        .await
+        .unwrap();
+    assert_eq!(response.status, 422);
+    let json = response.json().unwrap();
+    assert_eq!(json["error"]["details"]["errors"][0]["path"], "");
+}
@@ -0,0 +1,90 @@
+//! Handler timeouts: `ServerConfig.handler_timeout_ms` interrupts handlers
+//! that run too long, and routes registered with `_http_route_timeout` use
+//! their own limit instead.
+
+use clean_server::ServerConfig;
+use clean_server::testing::{TestResponse, TestServer};
+
+/// Global handler timeout used by the fixture server
+const GLOBAL_TIMEOUT_MS: u64 = 50;
+
+/// Routes:
+/// - `GET /report` -> `report` (5 s route timeout): busy-loops well past the
+///   global timeout, then returns "done"
+/// - `GET /stuck`  -> `stuck` (global timeout): never returns
+const FIXTURE_WAT: &str = r#"
+(module
+  (import "env" "_http_route"
+    (func $route (param i32 i32 i32 i32 i32 i32) (result i32)))
+  (import "env" "_http_route_timeout"
+    (func $route_timeout (param i32 i32 i32 i32 i32 i32 i32) (result i32)))
+  (memory (export "memory") 2)
+  (global (export "__heap_ptr") i32 (i32.const 65536))
+  (data (i32.const 1024) "\04\00\00\00done")
+  (data (i32.const 2048) "GET")
+  (data (i32.const 2056) "/report")
+  (data (i32.const 2064) "report")
+  (data (i32.const 2072) "/stuck")
+  (data (i32.const 2080) "stuck")
+  (func (export "main")
+    (drop (call $route_timeout (i32.const 2048) (i32.const 3)
+      (i32.const 2056) (i32.const 7) (i32.const 2064) (i32.const 6) (i32.const 5000)))
+    (drop (call $route (i32.const 2048) (i32.const 3)
+      (i32.const 2072) (i32.const 6) (i32.const 2080) (i32.const 5))))
+  (func (export "report") (result i32)
+    (local $i i32)
+    (local.set $i (i32.const 400000000))
+    (loop $busy
+      (local.set $i (i32.sub (local.get $i) (i32.const 1)))
+      (br_if $busy (i32.ne (local.get $i) (i32.const 0))))
+    (i32.const 1024))
+  (func (export "stuck") (result i32)
+    (loop $forever (br $forever))
+    (i32.const 1024)))
+"#;
+
+fn fixture_config() -> ServerConfig {
+    ServerConfig {
+        database_url: None,
+        ..ServerConfig::default()
+    }
+    .with_handler_timeout_ms(GLOBAL_TIMEOUT_MS)
+}
+
+async fn get(server: &TestServer, path: &str) -> TestResponse {
+    server
+        .request(axum::http::Method::GET, path, &[], "")
+        .await
+        .unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn route_timeout_overrides_the_global_timeout() {
+    let server = TestServer::from_wat(FIXTURE_WAT, fixture_config())
+        .await
+        .unwrap();
+
+    let started = std::time::Instant::now();
+    let response = get(&server, "/report").await;
+    assert_eq!(response.status, 200, "body: {}", response.text());
+    assert_eq!(response.text(), "done");
+    assert!(
+        started.elapsed().as_millis() > GLOBAL_TIMEOUT_MS as u128,
+        "handler should outlive the global timeout to prove the override"
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn default_route_is_interrupted_by_the_global_timeout() {
+    let server = TestServer::from_wat(FIXTURE_WAT, fixture_config())
+        .await
+        .unwrap();
+
+    let response = get(&server, "/stuck").await;
+    assert_eq!(response.status, 500);
+    assert!(response.text().contains("timed out"), "{}", response.text());
+
+    // The interrupted instance does not affect later requests.
+    let response = get(&server, "/report").await;
+    assert_eq!(response.status, 200);
+}
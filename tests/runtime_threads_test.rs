@@ -0,0 +1,59 @@
+//! `ServerConfig.worker_threads` / `max_blocking_threads`: the runtime built
+//! by `build_runtime` uses the configured sizes and still serves requests.
+
+use clean_server::ServerConfig;
+use clean_server::server::build_runtime;
+use clean_server::testing::TestServer;
+
+/// Routes:
+/// - `GET /ping` -> `ping`: returns the 4-byte `pong`
+const FIXTURE_WAT: &str = r#"
+(module
+  (import "env" "_http_route"
+    (func $route (param i32 i32 i32 i32 i32 i32) (result i32)))
+  (memory (export "memory") 2)
+  (global (export "__heap_ptr") i32 (i32.const 65536))
+  (data (i32.const 1024) "\04\00\00\00pong")
+  (data (i32.const 2048) "GET")
+  (data (i32.const 2056) "/ping")
+  (data (i32.const 2064) "ping")
+  (func (export "main")
+    (drop (call $route (i32.const 2048) (i32.const 3)
+      (i32.const 2056) (i32.const 5) (i32.const 2064) (i32.const 4))))
+  (func (export "ping") (result i32)
+    (i32.const 1024)))
+"#;
+
+#[test]
+fn server_runs_on_a_runtime_with_custom_thread_counts() {
+    let config = ServerConfig {
+        database_url: None,
+        ..ServerConfig::default()
+    }
+    .with_worker_threads(2)
+    .with_max_blocking_threads(4);
+
+    let runtime = build_runtime(&config).expect("runtime should build");
+    runtime.block_on(async {
+        assert_eq!(tokio::runtime::Handle::current().metrics().num_workers(), 2);
+
+        let server = TestServer::from_wat(FIXTURE_WAT, config)
+            .await
+            .expect("fixture should load");
+        for _ in 0..3 {
+            let response = server.get("/ping").await.unwrap();
+            assert_eq!(response.status, 200);
+            assert_eq!(response.text(), "pong");
+        }
+    });
+}
+
+#[test]
+fn zero_thread_counts_keep_tokio_defaults() {
+    let runtime = build_runtime(&ServerConfig::default()).expect("runtime should build");
+    let workers = runtime.metrics().num_workers();
+    assert_eq!(
+        workers,
+        std::thread::available_parallelism().map_or(1, |n| n.get())
+    );
+}
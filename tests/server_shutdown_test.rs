@@ -0,0 +1,62 @@
+//! `server::shutdown` closes the database pool: a handler querying after it
+//! gets a `CONNECTION_ERROR` back instead of waiting for a connection.
+
+#![cfg(feature = "sqlite")]
+
+use std::time::Duration;
+
+use clean_server::ServerConfig;
+use clean_server::testing::{TestResponse, TestServer, with_malloc};
+
+/// Routes:
+/// - `GET /query` -> `query`: returns the `_db_query("SELECT 1")` result
+const FIXTURE_WAT: &str = r#"
+(module
+  (import "env" "_http_route"
+    (func $route (param i32 i32 i32 i32 i32 i32) (result i32)))
+  (import "env" "_db_query" (func $query (param i32 i32 i32 i32) (result i32)))
+  (memory (export "memory") 2)
+  (data (i32.const 2048) "GET")
+  (data (i32.const 2056) "/query")
+  (data (i32.const 2064) "query")
+  (data (i32.const 2072) "SELECT 1")
+  (func (export "main")
+    (drop (call $route (i32.const 2048) (i32.const 3)
+      (i32.const 2056) (i32.const 6) (i32.const 2064) (i32.const 5))))
+  (func (export "query") (result i32)
+    (call $query (i32.const 2072) (i32.const 8) (i32.const 0) (i32.const 0))))
+"#;
+
+async fn query(server: &TestServer) -> TestResponse {
+    tokio::time::timeout(
+        Duration::from_secs(10),
+        server.request(axum::http::Method::GET, "/query", &[], ""),
+    )
+    .await
+    .expect("query should not hang")
+    .unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn queries_after_shutdown_fail_with_connection_error() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let config = ServerConfig {
+        database_url: Some(format!(
+            "sqlite://{}?mode=rwc",
+            temp.path().join("app.db").display()
+        )),
+        ..ServerConfig::default()
+    };
+    let server = TestServer::from_wat(&with_malloc(FIXTURE_WAT), config)
+        .await
+        .unwrap();
+
+    let before = query(&server).await.json().expect("query result JSON");
+    assert_eq!(before["ok"], true, "{}", before);
+
+    clean_server::server::shutdown(std::slice::from_ref(server.wasm())).await;
+
+    let after = query(&server).await.json().expect("query result JSON");
+    assert_eq!(after["ok"], false, "{}", after);
+    assert_eq!(after["err"]["code"], "CONNECTION_ERROR", "{}", after);
+}
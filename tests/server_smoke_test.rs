@@ -75,6 +75,9 @@ fn server_boots_serves_and_shuts_down() {
     // failure without polluting normal test output.
     let stdout_log = temp.path().join("server.out");
     let stderr_log = temp.path().join("server.err");
+    // Every exit path below kills and reaps the child; clippy cannot see
+    // through the `try_wait` polling loop.
+    #[allow(clippy::zombie_processes)]
     let mut child = Command::new(&server_bin)
         .arg(&wasm_path)
         .args(["--port", &port.to_string(), "--host", "127.0.0.1"])
@@ -0,0 +1,77 @@
+//! `ServerConfig.server_timing`: responses carry a `Server-Timing` header
+//! breaking the request into auth, handler and database time.
+
+#![cfg(feature = "sqlite")]
+
+use clean_server::ServerConfig;
+use clean_server::testing::{TestResponse, TestServer, with_malloc};
+
+/// Routes:
+/// - `GET /report` -> `report`: runs `SELECT 1` through `_db_query`, returns "ok"
+const FIXTURE_WAT: &str = r#"
+(module
+  (import "env" "_http_route"
+    (func $route (param i32 i32 i32 i32 i32 i32) (result i32)))
+  (import "env" "_db_query" (func $query (param i32 i32 i32 i32) (result i32)))
+  (memory (export "memory") 2)
+  (data (i32.const 1024) "\02\00\00\00ok")
+  (data (i32.const 2048) "GET")
+  (data (i32.const 2056) "/report")
+  (data (i32.const 2064) "report")
+  (data (i32.const 2072) "SELECT 1")
+  (func (export "main")
+    (drop (call $route (i32.const 2048) (i32.const 3)
+      (i32.const 2056) (i32.const 7) (i32.const 2064) (i32.const 6))))
+  (func (export "report") (result i32)
+    (drop (call $query (i32.const 2072) (i32.const 8) (i32.const 0) (i32.const 0)))
+    (i32.const 1024)))
+"#;
+
+async fn get_report(config: ServerConfig) -> TestResponse {
+    let config = ServerConfig {
+        database_url: Some("sqlite::memory:".to_string()),
+        ..config
+    };
+    let server = TestServer::from_wat(&with_malloc(FIXTURE_WAT), config)
+        .await
+        .unwrap();
+    server
+        .request(axum::http::Method::GET, "/report", &[], "")
+        .await
+        .unwrap()
+}
+
+/// Duration in milliseconds of the `name` metric in a `Server-Timing` value
+fn metric_ms(server_timing: &str, name: &str) -> Option<f64> {
+    server_timing.split(',').find_map(|metric| {
+        metric
+            .trim()
+            .strip_prefix(name)?
+            .strip_prefix(";dur=")?
+            .parse()
+            .ok()
+    })
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn server_timing_reports_handler_and_db_durations() {
+    let response = get_report(ServerConfig::default().with_server_timing(true)).await;
+    assert_eq!(response.status, 200, "body: {}", response.text());
+    assert_eq!(response.text(), "ok");
+
+    let header = response
+        .header("server-timing")
+        .expect("Server-Timing header");
+    let wasm = metric_ms(header, "wasm").expect("wasm metric");
+    assert!(wasm > 0.0 && wasm < 10_000.0, "{}", header);
+    let db = metric_ms(header, "db").expect("db metric");
+    assert!(db > 0.0 && db <= wasm, "{}", header);
+    assert!(metric_ms(header, "auth").is_some(), "{}", header);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn server_timing_is_off_by_default() {
+    let response = get_report(ServerConfig::default()).await;
+    assert_eq!(response.status, 200);
+    assert!(response.header("server-timing").is_none());
+}
@@ -0,0 +1,210 @@
+//! `ServerConfig.reload_on_sighup`: SIGHUP reloads the WASM module from disk
+//! and requests switch to it on the same listener, even on a connection
+//! opened before the reload. Sessions started before the reload stay valid.
+#![cfg(unix)]
+
+use std::path::Path;
+use std::time::Duration;
+
+use clean_server::ServerConfig;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::UnixStream;
+
+/// Routes:
+/// - `GET /version` -> `version`: returns the 2-byte `VERSION`
+/// - `POST /login` -> `login`: starts a session for user 7, returns "ok"
+/// - `GET /me` -> `me` (protected): returns "ok"
+const FIXTURE_WAT: &str = r#"
+(module
+  (import "env" "_http_route"
+    (func $route (param i32 i32 i32 i32 i32 i32) (result i32)))
+  (import "env" "_http_route_protected"
+    (func $protected (param i32 i32 i32 i32 i32 i32 i32 i32) (result i32)))
+  (import "env" "_auth_set_session" (func $set_session (param i32 i32) (result i32)))
+  (memory (export "memory") 2)
+  (global (export "__heap_ptr") i32 (i32.const 65536))
+  (data (i32.const 1024) "\02\00\00\00VERSION")
+  (data (i32.const 1040) "\02\00\00\00ok")
+  (data (i32.const 2048) "GET")
+  (data (i32.const 2056) "/version")
+  (data (i32.const 2072) "version")
+  (data (i32.const 2080) "POST")
+  (data (i32.const 2088) "/login")
+  (data (i32.const 2096) "login")
+  (data (i32.const 2104) "/me")
+  (data (i32.const 2112) "me")
+  (data (i32.const 2120) "{\"user_id\":7,\"role\":\"editor\"}")
+  (func (export "main")
+    (drop (call $route (i32.const 2048) (i32.const 3)
+      (i32.const 2056) (i32.const 8) (i32.const 2072) (i32.const 7)))
+    (drop (call $route (i32.const 2080) (i32.const 4)
+      (i32.const 2088) (i32.const 6) (i32.const 2096) (i32.const 5)))
+    (drop (call $protected (i32.const 2048) (i32.const 3)
+      (i32.const 2104) (i32.const 3) (i32.const 2112) (i32.const 2)
+      (i32.const 0) (i32.const 0))))
+  (func (export "version") (result i32)
+    (i32.const 1024))
+  (func (export "login") (result i32)
+    (drop (call $set_session (i32.const 2120) (i32.const 29)))
+    (i32.const 1040))
+  (func (export "me") (result i32)
+    (i32.const 1040)))
+"#;
+
+fn write_module(path: &Path, version: &str) {
+    let wat = FIXTURE_WAT.replace("VERSION", version);
+    let wasm_bytes = wat::parse_str(&wat).expect("fixture WAT should compile");
+    std::fs::write(path, wasm_bytes).expect("write wasm");
+}
+
+fn send_sighup() {
+    let status = std::process::Command::new("kill")
+        .args(["-HUP", &std::process::id().to_string()])
+        .status()
+        .expect("run kill");
+    assert!(status.success());
+}
+
+/// Status code, `name=value` of any Set-Cookie header, and body of a
+/// bodiless `method path` request over a kept-alive `stream`.
+async fn send(
+    stream: &mut BufReader<UnixStream>,
+    method: &str,
+    path: &str,
+    cookie: Option<&str>,
+) -> (u16, Option<String>, String) {
+    let cookie_header = cookie
+        .map(|cookie| format!("Cookie: {cookie}\r\n"))
+        .unwrap_or_default();
+    let request = format!(
+        "{method} {path} HTTP/1.1\r\nHost: localhost\r\nContent-Length: 0\r\n{cookie_header}\r\n"
+    );
+    stream
+        .get_mut()
+        .write_all(request.as_bytes())
+        .await
+        .expect("write request");
+    let mut status_line = String::new();
+    stream.read_line(&mut status_line).await.expect("status");
+    let status = status_line.split(' ').nth(1).unwrap().parse().unwrap();
+    let mut content_length = 0;
+    let mut set_cookie = None;
+    loop {
+        let mut line = String::new();
+        stream.read_line(&mut line).await.expect("header");
+        if line == "\r\n" {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().unwrap();
+            } else if name.eq_ignore_ascii_case("set-cookie") {
+                set_cookie = value.trim().split(';').next().map(str::to_string);
+            }
+        }
+    }
+    let mut body = vec![0; content_length];
+    stream.read_exact(&mut body).await.expect("body");
+    (status, set_cookie, String::from_utf8(body).unwrap())
+}
+
+/// Status code and body of `GET /version` over a kept-alive `stream`.
+async fn get_version(stream: &mut BufReader<UnixStream>) -> (u16, String) {
+    let (status, _, body) = send(stream, "GET", "/version", None).await;
+    (status, body)
+}
+
+/// Poll `GET /version` on `stream` until it returns `expected`.
+async fn wait_for_version(stream: &mut BufReader<UnixStream>, expected: &str) {
+    let mut last = (0, String::new());
+    for _ in 0..100 {
+        last = get_version(stream).await;
+        if last == (200, expected.to_string()) {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    panic!("never served {expected:?}, last response: {last:?}");
+}
+
+/// Start a server reloading on SIGHUP for the module at `wasm_path`,
+/// listening on a socket in `dir`, and open a connection to it.
+async fn start(
+    dir: &Path,
+    wasm_path: &Path,
+) -> (
+    tokio::task::JoinHandle<clean_server::RuntimeResult<()>>,
+    BufReader<UnixStream>,
+) {
+    let socket = dir.join("app.sock");
+    let config = ServerConfig {
+        database_url: None,
+        ..ServerConfig::default()
+    }
+    .with_unix_socket(&socket)
+    .with_reload_on_sighup(true)
+    // Keeps the parse report for broken modules out of the tree
+    .with_diag_dir(dir.join("diagnostics"));
+    let server = tokio::spawn(clean_server::server::start_server(
+        wasm_path.to_path_buf(),
+        config,
+    ));
+
+    for _ in 0..100 {
+        if let Ok(stream) = UnixStream::connect(&socket).await {
+            return (server, BufReader::new(stream));
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    panic!("server should listen");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn sighup_swaps_in_the_updated_module_on_the_same_listener() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let wasm_path = temp.path().join("app.wasm");
+    write_module(&wasm_path, "v1");
+    let (server, mut connection) = start(temp.path(), &wasm_path).await;
+    wait_for_version(&mut connection, "v1").await;
+
+    write_module(&wasm_path, "v2");
+    send_sighup();
+    wait_for_version(&mut connection, "v2").await;
+
+    // A module that fails to load leaves the running one serving
+    std::fs::write(&wasm_path, b"not wasm").expect("write wasm");
+    send_sighup();
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert_eq!(get_version(&mut connection).await, (200, "v2".to_string()));
+    assert!(!server.is_finished());
+
+    server.abort();
+    let _ = server.await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn sessions_started_before_sighup_stay_valid() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let wasm_path = temp.path().join("app.wasm");
+    write_module(&wasm_path, "s1");
+    let (server, mut connection) = start(temp.path(), &wasm_path).await;
+    wait_for_version(&mut connection, "s1").await;
+
+    let (status, cookie, _) = send(&mut connection, "POST", "/login", None).await;
+    assert_eq!(status, 200);
+    let cookie = cookie.expect("session cookie");
+    assert_eq!(
+        send(&mut connection, "GET", "/me", Some(&cookie)).await.0,
+        200
+    );
+
+    write_module(&wasm_path, "s2");
+    send_sighup();
+    wait_for_version(&mut connection, "s2").await;
+
+    let (status, _, body) = send(&mut connection, "GET", "/me", Some(&cookie)).await;
+    assert_eq!((status, body.as_str()), (200, "ok"));
+
+    server.abort();
+    let _ = server.await;
+}
@@ -0,0 +1,112 @@
+//! `ServerConfig.spa_fallback`: GET requests matching no route are answered
+//! with the fallback page, except under `ServerConfig.api_prefix` (`/api` by
+//! default) and for other methods.
+
+use clean_server::ServerConfig;
+use clean_server::testing::TestServer;
+
+const INDEX_HTML: &str = "<!doctype html><div id=\"app\"></div>";
+
+/// Routes:
+/// - `GET /api/ping` -> `ping`: returns the 4-byte `pong`
+const FIXTURE_WAT: &str = r#"
+(module
+  (import "env" "_http_route"
+    (func $route (param i32 i32 i32 i32 i32 i32) (result i32)))
+  (memory (export "memory") 2)
+  (global (export "__heap_ptr") i32 (i32.const 65536))
+  (data (i32.const 1024) "\04\00\00\00pong")
+  (data (i32.const 2048) "GET")
+  (data (i32.const 2056) "/api/ping")
+  (data (i32.const 2072) "ping")
+  (func (export "main")
+    (drop (call $route (i32.const 2048) (i32.const 3)
+      (i32.const 2056) (i32.const 9) (i32.const 2072) (i32.const 4))))
+  (func (export "ping") (result i32)
+    (i32.const 1024)))
+"#;
+
+async fn fixture_server(spa_fallback: bool) -> (TestServer, tempfile::TempDir) {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let index_path = temp.path().join("index.html");
+    std::fs::write(&index_path, INDEX_HTML).expect("write index.html");
+
+    let mut config = ServerConfig {
+        database_url: None,
+        ..ServerConfig::default()
+    };
+    if spa_fallback {
+        config = config.with_spa_fallback(&index_path);
+    }
+    let server = TestServer::from_wat(FIXTURE_WAT, config)
+        .await
+        .expect("fixture should load");
+    (server, temp)
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn unmatched_get_returns_the_fallback_page() {
+    let (server, _temp) = fixture_server(true).await;
+
+    let response = server.get("/some/client/route").await.unwrap();
+    assert_eq!(response.status, 200);
+    assert_eq!(
+        response.header("content-type"),
+        Some("text/html; charset=utf-8")
+    );
+    assert_eq!(response.text(), INDEX_HTML);
+
+    // Registered routes still reach their handler
+    let response = server.get("/api/ping").await.unwrap();
+    assert_eq!(response.text(), "pong");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn api_paths_and_other_methods_still_404() {
+    let (server, _temp) = fixture_server(true).await;
+
+    for path in ["/api/x", "/api"] {
+        let response = server.get(path).await.unwrap();
+        assert_eq!(response.status, 404, "{path}");
+    }
+    // Only the `/api` segment is reserved, not every path starting with it
+    let response = server.get("/apiary").await.unwrap();
+    assert_eq!(response.text(), INDEX_HTML);
+
+    let response = server
+        .post("/some/client/route", "application/json", b"{}".to_vec())
+        .await
+        .unwrap();
+    assert_eq!(response.status, 404);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn unmatched_get_404s_without_a_fallback() {
+    let (server, _temp) = fixture_server(false).await;
+
+    let response = server.get("/some/client/route").await.unwrap();
+    assert_eq!(response.status, 404);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn api_prefix_is_configurable() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let index_path = temp.path().join("index.html");
+    std::fs::write(&index_path, INDEX_HTML).expect("write index.html");
+    let config = ServerConfig {
+        database_url: None,
+        ..ServerConfig::default()
+    }
+    .with_spa_fallback(&index_path)
+    .with_api_prefix("/v2/");
+    let server = TestServer::from_wat(FIXTURE_WAT, config)
+        .await
+        .expect("fixture should load");
+
+    for path in ["/v2", "/v2/users"] {
+        let response = server.get(path).await.unwrap();
+        assert_eq!(response.status, 404, "{path}");
+    }
+    let response = server.get("/api/x").await.unwrap();
+    assert_eq!(response.text(), INDEX_HTML);
+}
@@ -0,0 +1,138 @@
+//! Detached tasks queued by handlers with `_task_spawn`.
+//!
+//! The task runs after the response in its own instance, with the payload as
+//! its request body. A task that fails is logged without affecting the
+//! response or later requests.
+
+use std::time::{Duration, Instant};
+
+use clean_server::ServerConfig;
+use clean_server::testing::{TestResponse, TestServer, with_malloc};
+
+/// Routes:
+/// - `POST /signup` -> `signup`: spawns `send_welcome` with `{"user":"ada"}`
+/// - `POST /boom`   -> `boom`: spawns `explode`, which traps
+/// - `POST /bad`    -> `bad`: spawns `missing`, which is not exported
+///
+/// Each route answers "queued" when `_task_spawn` accepted the task and
+/// "rejected" otherwise. `send_welcome` stores its request body in the raw
+/// session `task`.
+const FIXTURE_WAT: &str = r#"
+(module
+  (import "env" "_http_route"
+    (func $route (param i32 i32 i32 i32 i32 i32) (result i32)))
+  (import "env" "_task_spawn" (func $spawn (param i32 i32 i32 i32) (result i32)))
+  (import "env" "_req_body" (func $req_body (result i32)))
+  (import "env" "_session_store"
+    (func $session_store (param i32 i32 i32 i32 i32) (result i32)))
+  (memory (export "memory") 2)
+  (data (i32.const 1024) "\04\00\00\00task")
+  (data (i32.const 1040) "\00\00\00\00")
+  (data (i32.const 1056) "\06\00\00\00queued")
+  (data (i32.const 1072) "\08\00\00\00rejected")
+  (data (i32.const 2048) "POST")
+  (data (i32.const 2056) "/signup")
+  (data (i32.const 2064) "signup")
+  (data (i32.const 2072) "send_welcome")
+  (data (i32.const 2088) "{\"user\":\"ada\"}")
+  (data (i32.const 2112) "/boom")
+  (data (i32.const 2120) "boom")
+  (data (i32.const 2128) "explode")
+  (data (i32.const 2136) "/bad")
+  (data (i32.const 2144) "bad")
+  (data (i32.const 2152) "missing")
+  (func $answer (param $spawned i32) (result i32)
+    (select (i32.const 1056) (i32.const 1072) (i32.eqz (local.get $spawned))))
+  (func (export "main")
+    (drop (call $route (i32.const 2048) (i32.const 4)
+      (i32.const 2056) (i32.const 7) (i32.const 2064) (i32.const 6)))
+    (drop (call $route (i32.const 2048) (i32.const 4)
+      (i32.const 2112) (i32.const 5) (i32.const 2120) (i32.const 4)))
+    (drop (call $route (i32.const 2048) (i32.const 4)
+      (i32.const 2136) (i32.const 4) (i32.const 2144) (i32.const 3))))
+  (func (export "signup") (result i32)
+    (call $answer (call $spawn (i32.const 2072) (i32.const 12) (i32.const 2088) (i32.const 14))))
+  (func (export "boom") (result i32)
+    (call $answer (call $spawn (i32.const 2128) (i32.const 7) (i32.const 2088) (i32.const 0))))
+  (func (export "bad") (result i32)
+    (call $answer (call $spawn (i32.const 2152) (i32.const 7) (i32.const 2088) (i32.const 0))))
+  (func (export "send_welcome") (result i32)
+    (call $session_store (i32.const 1024) (i32.const 1040) (call $req_body)
+      (i32.const 0) (i32.const 0)))
+  (func (export "explode") (result i32)
+    unreachable))
+"#;
+
+fn fixture_config() -> ServerConfig {
+    ServerConfig {
+        database_url: None,
+        ..ServerConfig::default()
+    }
+}
+
+async fn post(server: &TestServer, path: &str) -> TestResponse {
+    server
+        .request(axum::http::Method::POST, path, &[], "")
+        .await
+        .unwrap()
+}
+
+/// What `send_welcome` stored, once it has run.
+async fn stored_by_task(server: &TestServer) -> Option<String> {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while Instant::now() < deadline {
+        let stored = server
+            .wasm()
+            .session_store()
+            .write()
+            .unwrap()
+            .get_raw("task");
+        if stored.is_some() {
+            return stored;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    None
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn spawned_task_runs_with_its_payload_after_the_response() {
+    let server = TestServer::from_wat(&with_malloc(FIXTURE_WAT), fixture_config())
+        .await
+        .unwrap();
+
+    let response = post(&server, "/signup").await;
+    assert_eq!(response.status, 200);
+    assert_eq!(response.text(), "queued");
+
+    assert_eq!(
+        stored_by_task(&server).await.as_deref(),
+        Some(r#"{"user":"ada"}"#)
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn failing_task_does_not_affect_the_response() {
+    let server = TestServer::from_wat(&with_malloc(FIXTURE_WAT), fixture_config())
+        .await
+        .unwrap();
+
+    let response = post(&server, "/boom").await;
+    assert_eq!(response.status, 200);
+    assert_eq!(response.text(), "queued");
+
+    let response = post(&server, "/signup").await;
+    assert_eq!(response.status, 200);
+    assert!(stored_by_task(&server).await.is_some());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn spawning_an_unknown_export_is_rejected() {
+    let server = TestServer::from_wat(&with_malloc(FIXTURE_WAT), fixture_config())
+        .await
+        .unwrap();
+
+    let response = post(&server, "/bad").await;
+    assert_eq!(response.status, 200);
+    assert_eq!(response.text(), "rejected");
+}
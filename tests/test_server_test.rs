@@ -0,0 +1,82 @@
+//! In-process `TestServer` harness.
+//!
+//! Loads a WAT fixture through `TestServer::from_wat` and drives GET and POST
+//! routes through the full Axum dispatch path without a TCP listener.
+
+use clean_server::ServerConfig;
+use clean_server::testing::{TestServer, with_malloc};
+
+/// Registers `GET /hello/:name` (echoes the `name` path param) and
+/// `POST /echo` (echoes the request body) from `main`. Loaded with
+/// `with_malloc` so the request bridges can return length-prefixed strings.
+const FIXTURE_WAT: &str = r#"
+(module
+  (import "env" "_http_route"
+    (func $route (param i32 i32 i32 i32 i32 i32) (result i32)))
+  (import "env" "_req_param" (func $param (param i32 i32) (result i32)))
+  (import "env" "_req_body" (func $body (result i32)))
+  (memory (export "memory") 2)
+  (data (i32.const 1024) "GET")
+  (data (i32.const 1032) "/hello/:name")
+  (data (i32.const 1048) "hello")
+  (data (i32.const 1056) "POST")
+  (data (i32.const 1064) "/echo")
+  (data (i32.const 1072) "echo")
+  (data (i32.const 1080) "name")
+  (func (export "main")
+    (drop (call $route
+      (i32.const 1024) (i32.const 3)
+      (i32.const 1032) (i32.const 12)
+      (i32.const 1048) (i32.const 5)))
+    (drop (call $route
+      (i32.const 1056) (i32.const 4)
+      (i32.const 1064) (i32.const 5)
+      (i32.const 1072) (i32.const 4))))
+  (func (export "hello") (result i32)
+    (call $param (i32.const 1080) (i32.const 4)))
+  (func (export "echo") (result i32)
+    (call $body)))
+"#;
+
+fn fixture_config() -> ServerConfig {
+    ServerConfig {
+        database_url: None,
+        ..ServerConfig::default()
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn get_route_reads_path_param() {
+    let server = TestServer::from_wat(&with_malloc(FIXTURE_WAT), fixture_config())
+        .await
+        .unwrap();
+
+    let response = server.get("/hello/ada").await.unwrap();
+    assert_eq!(response.status, 200);
+    assert_eq!(response.text(), "ada");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn post_route_reads_body() {
+    let server = TestServer::from_wat(&with_malloc(FIXTURE_WAT), fixture_config())
+        .await
+        .unwrap();
+
+    let response = server
+        .post("/echo", "application/json", r#"{"msg":"hi"}"#)
+        .await
+        .unwrap();
+    assert_eq!(response.status, 200);
+    assert_eq!(response.json().unwrap()["msg"], "hi");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn unregistered_route_is_not_found() {
+    let server = TestServer::from_wat(&with_malloc(FIXTURE_WAT), fixture_config())
+        .await
+        .unwrap();
+
+    let response = server.get("/missing").await.unwrap();
+    assert_eq!(response.status, 404);
+    assert_eq!(server.wasm().router().len(), 2);
+}
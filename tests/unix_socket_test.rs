@@ -0,0 +1,100 @@
+//! `ServerConfig.unix_socket`: `start_server` serves over a Unix domain
+//! socket, replacing a stale socket file and removing it on shutdown.
+#![cfg(unix)]
+
+use std::path::Path;
+use std::time::Duration;
+
+use clean_server::ServerConfig;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+/// Routes:
+/// - `GET /hello` -> `hello`: returns "hi"
+const FIXTURE_WAT: &str = r#"
+(module
+  (import "env" "_http_route"
+    (func $route (param i32 i32 i32 i32 i32 i32) (result i32)))
+  (memory (export "memory") 2)
+  (global (export "__heap_ptr") i32 (i32.const 65536))
+  (data (i32.const 1024) "\02\00\00\00hi")
+  (data (i32.const 2048) "GET")
+  (data (i32.const 2056) "/hello")
+  (data (i32.const 2064) "hello")
+  (func (export "main")
+    (drop (call $route (i32.const 2048) (i32.const 3)
+      (i32.const 2056) (i32.const 6) (i32.const 2064) (i32.const 5))))
+  (func (export "hello") (result i32)
+    (i32.const 1024)))
+"#;
+
+/// Raw HTTP/1.1 response to `GET path` over the socket at `socket`.
+async fn get(socket: &Path, path: &str) -> std::io::Result<String> {
+    let mut stream = tokio::net::UnixStream::connect(socket).await?;
+    stream
+        .write_all(
+            format!("GET {path} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+                .as_bytes(),
+        )
+        .await?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await?;
+    Ok(response)
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn serves_routes_over_a_unix_socket() {
+    let wasm_bytes = wat::parse_str(FIXTURE_WAT).expect("fixture WAT should compile");
+    let temp = tempfile::tempdir().expect("tempdir");
+    let wasm_path = temp.path().join("app.wasm");
+    std::fs::write(&wasm_path, &wasm_bytes).expect("write wasm");
+    let socket = temp.path().join("app.sock");
+
+    // A socket file left behind by a server that did not shut down cleanly
+    drop(std::os::unix::net::UnixListener::bind(&socket).expect("stale socket"));
+    assert!(socket.exists());
+
+    let config = ServerConfig {
+        database_url: None,
+        ..ServerConfig::default()
+    }
+    .with_unix_socket(&socket);
+    let server = tokio::spawn(clean_server::server::start_server(wasm_path, config));
+
+    // Requests get a 503 until the module has loaded
+    let mut response = String::new();
+    for _ in 0..100 {
+        match get(&socket, "/hello").await {
+            Ok(r) if r.starts_with("HTTP/1.1 200") => {
+                response = r;
+                break;
+            }
+            _ => tokio::time::sleep(Duration::from_millis(50)).await,
+        }
+    }
+    assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+    assert!(response.ends_with("hi"), "{response}");
+
+    server.abort();
+    let _ = server.await;
+    assert!(
+        !socket.exists(),
+        "socket file should be removed on shutdown"
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn refuses_to_replace_a_regular_file() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let wasm_path = temp.path().join("app.wasm");
+    std::fs::write(&wasm_path, wat::parse_str(FIXTURE_WAT).unwrap()).expect("write wasm");
+    let socket = temp.path().join("app.sock");
+    std::fs::write(&socket, "not a socket").expect("write file");
+
+    let config = ServerConfig::default().with_unix_socket(&socket);
+    assert!(
+        clean_server::server::start_server(wasm_path, config)
+            .await
+            .is_err()
+    );
+    assert_eq!(std::fs::read_to_string(&socket).unwrap(), "not a socket");
+}